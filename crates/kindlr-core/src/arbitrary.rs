@@ -0,0 +1,51 @@
+use chrono::Duration;
+use proptest::prelude::*;
+use proptest::sample::select;
+
+use crate::parser::{Clipping, ClippingType, Location};
+use crate::samples::{AUTHORS, TITLES, WORDS, epoch};
+
+/// Any clipping a Kindle could write
+impl Arbitrary for Clipping {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Clipping>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        let content =
+            proptest::collection::vec(select(WORDS), 1..60).prop_map(|words| words.join(" "));
+
+        (
+            select(
+                &[
+                    ClippingType::Highlight,
+                    ClippingType::Note,
+                    ClippingType::Bookmark,
+                ][..],
+            ),
+            select(TITLES),
+            select(AUTHORS),
+            1..5_000u32,
+            1..200_000u32,
+            proptest::option::of(0..50u32),
+            0..60 * 24 * 365 * 20i64,
+            content,
+        )
+            .prop_map(
+                |(clipping_type, title, author, page, start, length, minutes, content)| {
+                    Clipping::new(
+                        clipping_type,
+                        title.to_string(),
+                        author.to_string(),
+                        Some(page),
+                        Location {
+                            start,
+                            end: length.map(|length| start + length),
+                        },
+                        epoch() + Duration::minutes(minutes),
+                        (clipping_type != ClippingType::Bookmark).then_some(content),
+                    )
+                },
+            )
+            .boxed()
+    }
+}
@@ -0,0 +1,747 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+use crate::parser::{Clipping, ClippingType, Location, Weekday};
+
+/// Every form of each of `N` names a Kindle may write, the first being the
+/// one written back
+///
+/// Languages that decline names list each case used in metadata lines, such
+/// as the genitive and nominative of Czech months.
+pub type Names<const N: usize> = [&'static [&'static str]; N];
+
+/// Weekday names from Monday shared by Norwegian and Danish
+const MANDAG: Names<7> = [
+    &["mandag"],
+    &["tirsdag"],
+    &["onsdag"],
+    &["torsdag"],
+    &["fredag"],
+    &["lørdag"],
+    &["søndag"],
+];
+
+/// How a Kindle set to one language words the metadata line of a clipping
+///
+/// The line is made of three parts, each a template with placeholders:
+/// `heading` holds `{type}` and `{page}`, `location` holds `{location}`
+/// and `added` holds `{weekday}`, `{day}`, `{month}`, `{year}` and
+/// `{time}`, after `{meridiem}` where times are written on a 12-hour clock.
+/// A language that words the location inside the heading puts `{location}`
+/// there and leaves `location` empty. The same templates are used to read
+/// and to write lines, so a language is added by describing its wording here.
+/// Weekday and month names are looked up in `weekdays` and `months`,
+/// ignoring case, both in metadata lines and in datetimes.
+pub struct LanguagePack {
+    /// Words for a highlight, a note and a bookmark, in that order
+    pub types: [&'static str; 3],
+    pub heading: &'static str,
+    pub location: &'static str,
+    pub added: &'static str,
+    /// From Monday
+    pub weekdays: Names<7>,
+    /// From January, the form used after a day first
+    pub months: Names<12>,
+    /// Words before morning and afternoon times, for a 12-hour clock
+    pub meridiems: Option<[&'static str; 2]>,
+    patterns: OnceLock<[Regex; 3]>,
+}
+
+pub static ENGLISH: LanguagePack = LanguagePack {
+    types: ["Highlight", "Note", "Bookmark"],
+    heading: "Your {type} on page {page}",
+    location: "Location {location}",
+    added: "Added on {weekday}, {day} {month} {year} {time}",
+    weekdays: [
+        &["Monday"],
+        &["Tuesday"],
+        &["Wednesday"],
+        &["Thursday"],
+        &["Friday"],
+        &["Saturday"],
+        &["Sunday"],
+    ],
+    months: [
+        &["January", "Jan"],
+        &["February", "Feb"],
+        &["March", "Mar"],
+        &["April", "Apr"],
+        &["May"],
+        &["June", "Jun"],
+        &["July", "Jul"],
+        &["August", "Aug"],
+        &["September", "Sep"],
+        &["October", "Oct"],
+        &["November", "Nov"],
+        &["December", "Dec"],
+    ],
+    meridiems: None,
+    patterns: OnceLock::new(),
+};
+
+pub static HUNGARIAN: LanguagePack = LanguagePack {
+    types: ["Kiemelés", "Jegyzet", "Könyvjelző"],
+    heading: "{type} a(z) {page}. oldalon",
+    location: "Hely: {location}",
+    added: "Hozzáadva: {year}. {month} {day}., {weekday} {time}",
+    weekdays: [
+        &["hétfő"],
+        &["kedd"],
+        &["szerda"],
+        &["csütörtök"],
+        &["péntek"],
+        &["szombat"],
+        &["vasárnap"],
+    ],
+    months: [
+        &["január"],
+        &["február"],
+        &["március"],
+        &["április"],
+        &["május"],
+        &["június"],
+        &["július"],
+        &["augusztus"],
+        &["szeptember"],
+        &["október"],
+        &["november"],
+        &["december"],
+    ],
+    meridiems: None,
+    patterns: OnceLock::new(),
+};
+
+pub static CZECH: LanguagePack = LanguagePack {
+    types: ["Zvýraznění", "Poznámka", "Záložka"],
+    heading: "{type} na stránce {page}",
+    location: "Pozice {location}",
+    added: "Přidáno: {weekday} {day}. {month} {year} {time}",
+    weekdays: [
+        &["pondělí"],
+        &["úterý"],
+        &["středa"],
+        &["čtvrtek"],
+        &["pátek"],
+        &["sobota"],
+        &["neděle"],
+    ],
+    months: [
+        &["ledna", "leden"],
+        &["února", "únor"],
+        &["března", "březen"],
+        &["dubna", "duben"],
+        &["května", "květen"],
+        &["června", "červen"],
+        &["července", "červenec"],
+        &["srpna", "srpen"],
+        &["září"],
+        &["října", "říjen"],
+        &["listopadu", "listopad"],
+        &["prosince", "prosinec"],
+    ],
+    meridiems: None,
+    patterns: OnceLock::new(),
+};
+
+pub static SWEDISH: LanguagePack = LanguagePack {
+    types: ["Din markering", "Din anteckning", "Ditt bokmärke"],
+    heading: "{type} på sidan {page}",
+    location: "plats {location}",
+    added: "Tillagd {weekday} {day} {month} {year} {time}",
+    weekdays: [
+        &["måndag"],
+        &["tisdag"],
+        &["onsdag"],
+        &["torsdag"],
+        &["fredag"],
+        &["lördag"],
+        &["söndag"],
+    ],
+    months: [
+        &["januari"],
+        &["februari"],
+        &["mars"],
+        &["april"],
+        &["maj"],
+        &["juni"],
+        &["juli"],
+        &["augusti"],
+        &["september"],
+        &["oktober"],
+        &["november"],
+        &["december"],
+    ],
+    meridiems: None,
+    patterns: OnceLock::new(),
+};
+
+pub static NORWEGIAN: LanguagePack = LanguagePack {
+    types: ["Din utheving", "Ditt notat", "Ditt bokmerke"],
+    heading: "{type} på side {page}",
+    location: "posisjon {location}",
+    added: "Lagt til {weekday} {day}. {month} {year} {time}",
+    weekdays: MANDAG,
+    months: [
+        &["januar"],
+        &["februar"],
+        &["mars"],
+        &["april"],
+        &["mai"],
+        &["juni"],
+        &["juli"],
+        &["august"],
+        &["september"],
+        &["oktober"],
+        &["november"],
+        &["desember"],
+    ],
+    meridiems: None,
+    patterns: OnceLock::new(),
+};
+
+pub static DANISH: LanguagePack = LanguagePack {
+    types: ["Din markering", "Din note", "Dit bogmærke"],
+    heading: "{type} på side {page}",
+    location: "placering {location}",
+    added: "Tilføjet {weekday} den {day}. {month} {year} {time}",
+    weekdays: MANDAG,
+    months: [
+        &["januar"],
+        &["februar"],
+        &["marts"],
+        &["april"],
+        &["maj"],
+        &["juni"],
+        &["juli"],
+        &["august"],
+        &["september"],
+        &["oktober"],
+        &["november"],
+        &["december"],
+    ],
+    meridiems: None,
+    patterns: OnceLock::new(),
+};
+
+pub static FINNISH: LanguagePack = LanguagePack {
+    types: ["Korostus", "Muistiinpano", "Kirjanmerkki"],
+    heading: "{type} sivulla {page}",
+    location: "sijainti {location}",
+    added: "Lisätty {weekday} {day}. {month} {year} {time}",
+    weekdays: [
+        &["maanantaina", "maanantai"],
+        &["tiistaina", "tiistai"],
+        &["keskiviikkona", "keskiviikko"],
+        &["torstaina", "torstai"],
+        &["perjantaina", "perjantai"],
+        &["lauantaina", "lauantai"],
+        &["sunnuntaina", "sunnuntai"],
+    ],
+    months: [
+        &["tammikuuta", "tammikuu"],
+        &["helmikuuta", "helmikuu"],
+        &["maaliskuuta", "maaliskuu"],
+        &["huhtikuuta", "huhtikuu"],
+        &["toukokuuta", "toukokuu"],
+        &["kesäkuuta", "kesäkuu"],
+        &["heinäkuuta", "heinäkuu"],
+        &["elokuuta", "elokuu"],
+        &["syyskuuta", "syyskuu"],
+        &["lokakuuta", "lokakuu"],
+        &["marraskuuta", "marraskuu"],
+        &["joulukuuta", "joulukuu"],
+    ],
+    meridiems: None,
+    patterns: OnceLock::new(),
+};
+
+pub static ARABIC: LanguagePack = LanguagePack {
+    types: ["تمييزك", "ملاحظتك", "علامتك المرجعية"],
+    heading: "{type} في الصفحة {page}",
+    location: "الموقع {location}",
+    added: "تمت الإضافة يوم {weekday}، {day} {month} {year} {time}",
+    weekdays: [
+        &["الاثنين"],
+        &["الثلاثاء"],
+        &["الأربعاء"],
+        &["الخميس"],
+        &["الجمعة"],
+        &["السبت"],
+        &["الأحد"],
+    ],
+    months: [
+        &["يناير", "كانون الثاني"],
+        &["فبراير", "شباط"],
+        &["مارس", "آذار"],
+        &["أبريل", "نيسان"],
+        &["مايو", "أيار"],
+        &["يونيو", "حزيران"],
+        &["يوليو", "تموز"],
+        &["أغسطس", "آب"],
+        &["سبتمبر", "أيلول"],
+        &["أكتوبر", "تشرين الأول"],
+        &["نوفمبر", "تشرين الثاني"],
+        &["ديسمبر", "كانون الأول"],
+    ],
+    meridiems: None,
+    patterns: OnceLock::new(),
+};
+
+pub static HEBREW: LanguagePack = LanguagePack {
+    types: ["הסימון שלך", "ההערה שלך", "הסימנייה שלך"],
+    heading: "{type} בעמוד {page}",
+    location: "מיקום {location}",
+    added: "נוסף ב{weekday}, {day} ב{month} {year} {time}",
+    weekdays: [
+        &["יום שני"],
+        &["יום שלישי"],
+        &["יום רביעי"],
+        &["יום חמישי"],
+        &["יום שישי"],
+        &["שבת"],
+        &["יום ראשון"],
+    ],
+    months: [
+        &["ינואר"],
+        &["פברואר"],
+        &["מרץ"],
+        &["אפריל"],
+        &["מאי"],
+        &["יוני"],
+        &["יולי"],
+        &["אוגוסט"],
+        &["ספטמבר"],
+        &["אוקטובר"],
+        &["נובמבר"],
+        &["דצמבר"],
+    ],
+    meridiems: None,
+    patterns: OnceLock::new(),
+};
+
+pub static GREEK: LanguagePack = LanguagePack {
+    types: ["Η επισήμανσή σας", "Η σημείωσή σας", "Ο σελιδοδείκτης σας"],
+    heading: "{type} στη σελίδα {page}",
+    location: "Θέση {location}",
+    added: "Προστέθηκε {weekday}, {day} {month} {year} {time}",
+    weekdays: [
+        &["Δευτέρα"],
+        &["Τρίτη"],
+        &["Τετάρτη"],
+        &["Πέμπτη"],
+        &["Παρασκευή"],
+        &["Σάββατο"],
+        &["Κυριακή"],
+    ],
+    months: [
+        &["Ιανουαρίου", "Ιανουάριος"],
+        &["Φεβρουαρίου", "Φεβρουάριος"],
+        &["Μαρτίου", "Μάρτιος"],
+        &["Απριλίου", "Απρίλιος"],
+        &["Μαΐου", "Μάιος"],
+        &["Ιουνίου", "Ιούνιος"],
+        &["Ιουλίου", "Ιούλιος"],
+        &["Αυγούστου", "Αύγουστος"],
+        &["Σεπτεμβρίου", "Σεπτέμβριος"],
+        &["Οκτωβρίου", "Οκτώβριος"],
+        &["Νοεμβρίου", "Νοέμβριος"],
+        &["Δεκεμβρίου", "Δεκέμβριος"],
+    ],
+    meridiems: None,
+    patterns: OnceLock::new(),
+};
+
+pub static TRADITIONAL_CHINESE: LanguagePack = LanguagePack {
+    types: ["標註", "筆記", "書籤"],
+    heading: "您在第 {page} 頁（位置 #{location}）的{type}",
+    location: "",
+    added: "新增於 {year}年{month}{day}日 {weekday} {meridiem}{time}",
+    weekdays: [
+        &["星期一", "週一"],
+        &["星期二", "週二"],
+        &["星期三", "週三"],
+        &["星期四", "週四"],
+        &["星期五", "週五"],
+        &["星期六", "週六"],
+        &["星期日", "週日"],
+    ],
+    months: [
+        &["1月"],
+        &["2月"],
+        &["3月"],
+        &["4月"],
+        &["5月"],
+        &["6月"],
+        &["7月"],
+        &["8月"],
+        &["9月"],
+        &["10月"],
+        &["11月"],
+        &["12月"],
+    ],
+    meridiems: Some(["上午", "下午"]),
+    patterns: OnceLock::new(),
+};
+
+const CLIPPING_TYPES: [ClippingType; 3] = [
+    ClippingType::Highlight,
+    ClippingType::Note,
+    ClippingType::Bookmark,
+];
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Monday,
+    Weekday::Tuesday,
+    Weekday::Wednesday,
+    Weekday::Thursday,
+    Weekday::Friday,
+    Weekday::Saturday,
+    Weekday::Sunday,
+];
+
+/// The parts of a metadata line, as read by `LanguagePack::read`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    pub clipping_type: ClippingType,
+    pub page: u32,
+    pub location: Location,
+    pub weekday: Weekday,
+    /// As written for English, and in the English form `Clipping::timestamp`
+    /// reads otherwise
+    pub datetime: String,
+}
+
+/// Which part of a metadata line failed to read
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Missing {
+    Heading,
+    Location,
+    Added,
+}
+
+impl LanguagePack {
+    /// The heading, location and added patterns
+    fn patterns(&self) -> &[Regex; 3] {
+        self.patterns.get_or_init(|| {
+            [self.heading, self.location, self.added].map(|template| {
+                Regex::new(&self.pattern(template)).expect("valid language pack template")
+            })
+        })
+    }
+
+    /// Regular expression for `template`, its placeholders captured by name
+    fn pattern(&self, template: &str) -> String {
+        let alternatives = |words: &mut dyn Iterator<Item = &&str>| {
+            words
+                .map(|word| regex::escape(word))
+                .collect::<Vec<_>>()
+                .join("|")
+        };
+
+        let mut pattern = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let end = start + rest[start..].find('}').expect("closed placeholder");
+            pattern += &regex::escape(&rest[..start]).replace(' ', r"\s+");
+            pattern += &match &rest[start + 1..end] {
+                "type" => format!("(?P<type>{})", alternatives(&mut self.types.iter())),
+                "page" => r"(?P<page>\d+)".to_string(),
+                "location" => r"(?P<start>\d+)(?:-(?P<end>\d+))?".to_string(),
+                "weekday" => format!(
+                    "(?i:(?P<weekday>{}))",
+                    alternatives(&mut self.weekdays.iter().copied().flatten())
+                ),
+                "day" => r"(?P<day>\d{1,2})".to_string(),
+                "month" => format!(
+                    "(?i:(?P<month>{}))",
+                    alternatives(&mut self.months.iter().copied().flatten())
+                ),
+                "year" => r"(?P<year>\d{4})".to_string(),
+                "time" => r"(?P<time>\d{1,2}:\d{2}:\d{2})".to_string(),
+                "meridiem" => format!(
+                    "(?P<meridiem>{})",
+                    alternatives(&mut self.meridiems.unwrap_or_default().iter())
+                ),
+                other => panic!("unknown placeholder {{{}}}", other),
+            };
+            rest = &rest[end + 1..];
+        }
+        pattern + &regex::escape(rest).replace(' ', r"\s+")
+    }
+
+    /// Whether `line` starts the way this language words a clipping's type
+    /// and page
+    pub fn matches(&self, line: &str) -> bool {
+        self.patterns()[0].is_match(line)
+    }
+
+    /// The parts of a metadata line written in this language
+    pub fn read(&self, line: &str) -> Result<Metadata, Missing> {
+        let [heading, location, added] = self.patterns();
+
+        let caps = heading.captures(line).ok_or(Missing::Heading)?;
+        let clipping_type = CLIPPING_TYPES[position(&self.types, &caps["type"])];
+        let page = caps["page"].parse().map_err(|_| Missing::Heading)?;
+
+        let caps = match caps.name("start") {
+            Some(_) => caps,
+            None => location.captures(line).ok_or(Missing::Location)?,
+        };
+        let number = |name| caps.name(name).map(|number| number.as_str().parse());
+        let location = Location {
+            start: number("start")
+                .and_then(Result::ok)
+                .ok_or(Missing::Location)?,
+            end: number("end").transpose().map_err(|_| Missing::Location)?,
+        };
+
+        let caps = added.captures(line).ok_or(Missing::Added)?;
+        let weekday = self.weekday(&caps["weekday"]).ok_or(Missing::Added)?;
+        let datetime = self.datetime(line, &caps);
+
+        Ok(Metadata {
+            clipping_type,
+            page,
+            location,
+            weekday,
+            datetime,
+        })
+    }
+
+    /// English dates are kept as written, so ids of clippings read before
+    /// other languages were supported don't change
+    fn datetime(&self, line: &str, caps: &Captures) -> String {
+        if std::ptr::eq(self, &ENGLISH)
+            && let (Some(day), Some(time)) = (caps.name("day"), caps.name("time"))
+        {
+            return line[day.start()..time.end()].to_string();
+        }
+
+        // As `Clipping::new` would write it
+        format!(
+            "{} {} {} {:0>8}",
+            caps["day"].trim_start_matches('0'),
+            ENGLISH.months[self.month(&caps["month"]).expect("matched month") as usize - 1][0],
+            &caps["year"],
+            self.time(caps)
+        )
+    }
+
+    /// The time of an added part on a 24-hour clock
+    fn time(&self, caps: &Captures) -> String {
+        let time = &caps["time"];
+        let (Some(meridiems), Some(meridiem)) = (self.meridiems, caps.name("meridiem")) else {
+            return time.to_string();
+        };
+        let Some((hour, rest)) = time
+            .split_once(':')
+            .and_then(|(hour, rest)| Some((hour.parse::<u32>().ok()?, rest)))
+        else {
+            return time.to_string();
+        };
+
+        let afternoon = if meridiem.as_str() == meridiems[1] {
+            12
+        } else {
+            0
+        };
+        format!("{:02}:{}", hour % 12 + afternoon, rest)
+    }
+
+    /// The weekday `name` is a form of
+    pub fn weekday(&self, name: &str) -> Option<Weekday> {
+        lookup(&self.weekdays, name).map(|index| WEEKDAYS[index].clone())
+    }
+
+    /// Number from 1 of the month `name` is a form of
+    pub fn month(&self, name: &str) -> Option<u32> {
+        lookup(&self.months, name).map(|index| index as u32 + 1)
+    }
+
+    /// A datetime written `D Month YYYY HH:MM:SS` with a month of this
+    /// language, as a calendar value
+    pub fn timestamp(&self, datetime: &str) -> Option<NaiveDateTime> {
+        let mut parts = datetime.split_whitespace();
+        let day = parts.next()?.parse().ok()?;
+        let month = self.month(parts.next()?)?;
+        let year = parts.next()?.parse().ok()?;
+        let time = NaiveTime::parse_from_str(parts.next()?, "%H:%M:%S").ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+
+        NaiveDate::from_ymd_opt(year, month, day).map(|date| date.and_time(time))
+    }
+
+    /// The metadata line a Kindle in this language would write for `clipping`
+    pub fn write(&self, clipping: &Clipping) -> String {
+        let type_index = match clipping.clipping_type {
+            ClippingType::Note => 1,
+            ClippingType::Bookmark => 2,
+            ClippingType::Highlight | ClippingType::ArticleClip => 0,
+        };
+        let weekday = WEEKDAYS
+            .iter()
+            .position(|weekday| *weekday == clipping.weekday)
+            .unwrap_or_default();
+
+        // Dates that don't read as English are written as they are
+        let mut parts = clipping.datetime.split_whitespace();
+        let (day, month, year, time) = match (
+            parts.next(),
+            parts.next().and_then(|month| ENGLISH.month(month)),
+            parts.next(),
+            parts.next(),
+        ) {
+            (Some(day), Some(month), Some(year), Some(time)) => {
+                (day, self.months[month as usize - 1][0], year, time)
+            }
+            _ => (clipping.datetime.as_str(), "", "", ""),
+        };
+
+        // Hours 0 and 12 are written as 12 on a 12-hour clock
+        let (meridiem, time) = match (self.meridiems, time.split_once(':')) {
+            (Some(meridiems), Some((hour, rest))) if let Ok(hour) = hour.parse::<u32>() => (
+                meridiems[usize::from(hour >= 12)],
+                format!("{}:{}", (hour + 11) % 12 + 1, rest),
+            ),
+            _ => ("", time.to_string()),
+        };
+
+        let added = self
+            .added
+            .replace("{weekday}", self.weekdays[weekday][0])
+            .replace("{day}", day)
+            .replace("{month}", month)
+            .replace("{year}", year)
+            .replace("{meridiem}", meridiem)
+            .replace("{time}", &time);
+        let location = clipping.location.to_string();
+        let heading = self
+            .heading
+            .replace("{type}", self.types[type_index])
+            .replace("{page}", &clipping.page.unwrap_or(1).to_string())
+            .replace("{location}", &location);
+
+        if self.location.is_empty() {
+            format!("- {} | {}", heading, added)
+        } else {
+            let location = self.location.replace("{location}", &location);
+            format!("- {} | {} | {}", heading, location, added)
+        }
+    }
+}
+
+/// Index of the name `name` is a form of, ignoring case
+fn lookup(names: &[&[&str]], name: &str) -> Option<usize> {
+    fn lowercase(text: &str) -> impl Iterator<Item = char> + '_ {
+        text.chars().flat_map(char::to_lowercase)
+    }
+
+    names
+        .iter()
+        .position(|forms| forms.iter().any(|form| lowercase(form).eq(lowercase(name))))
+}
+
+/// Index of `word` in `words`, which a pattern made from them matched
+fn position(words: &[&str], word: &str) -> usize {
+    words
+        .iter()
+        .position(|candidate| *candidate == word)
+        .expect("matched word is in its table")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Language, parse_clippings};
+
+    #[test]
+    fn test_language_packs() {
+        let samples = [
+            (
+                Language::Swedish,
+                "- Din markering på sidan 12 | plats 150-152 | Tillagd måndag 4 mars 2024 09:05:10",
+            ),
+            (
+                Language::Norwegian,
+                "- Din utheving på side 12 | posisjon 150-152 | Lagt til mandag 4. mars 2024 09:05:10",
+            ),
+            (
+                Language::Danish,
+                "- Din markering på side 12 | placering 150-152 | Tilføjet mandag den 4. marts 2024 09:05:10",
+            ),
+            (
+                Language::Finnish,
+                "- Korostus sivulla 12 | sijainti 150-152 | Lisätty maanantaina 4. maaliskuuta 2024 09:05:10",
+            ),
+            (
+                Language::Greek,
+                "- Η επισήμανσή σας στη σελίδα 12 | Θέση 150-152 | Προστέθηκε Δευτέρα, 4 Μαρτίου 2024 09:05:10",
+            ),
+            (
+                Language::TraditionalChinese,
+                "- 您在第 12 頁（位置 #150-152）的標註 | 新增於 2024年3月4日 星期一 上午9:05:10",
+            ),
+        ];
+
+        for (language, line) in samples {
+            let entry = format!("Kallocain (Karin Boye)\n{}\n\nDet är inget.\n", line);
+            let clippings = parse_clippings(&entry).unwrap();
+            let clipping = &clippings[0];
+            assert_eq!(clipping.clipping_type, ClippingType::Highlight);
+            assert_eq!(clipping.page, Some(12));
+            assert_eq!(clipping.location.end, Some(152));
+            assert_eq!(clipping.weekday, Weekday::Monday);
+            assert_eq!(clipping.datetime, "4 March 2024 09:05:10");
+            assert_eq!(language.pack().write(clipping), line);
+        }
+
+        // Afternoons on a 12-hour clock, noon and midnight included
+        for (written, time) in [
+            ("下午3:15:00", "15:15:00"),
+            ("下午12:00:00", "12:00:00"),
+            ("上午12:30:00", "00:30:00"),
+        ] {
+            let line = format!(
+                "- 您在第 3 頁（位置 #40）的筆記 | 新增於 2024年12月25日 星期三 {}",
+                written
+            );
+            let metadata = TRADITIONAL_CHINESE.read(&line).unwrap();
+            assert_eq!(metadata.clipping_type, ClippingType::Note);
+            assert_eq!(
+                metadata.location,
+                Location {
+                    start: 40,
+                    end: None
+                }
+            );
+            assert_eq!(metadata.datetime, format!("25 December 2024 {}", time));
+        }
+
+        // Swedish is tried before Danish, whose wording it shares in part
+        assert!(SWEDISH.matches("- Din markering på sidan 1"));
+        assert!(!SWEDISH.matches("- Din markering på side 1"));
+    }
+
+    #[test]
+    fn test_names() {
+        // Declined forms and any case read as the same name
+        assert_eq!(CZECH.month("leden"), Some(1));
+        assert_eq!(CZECH.month("Ledna"), Some(1));
+        assert_eq!(GREEK.month("μαρτίου"), Some(3));
+        assert_eq!(FINNISH.weekday("maanantai"), Some(Weekday::Monday));
+        assert_eq!(TRADITIONAL_CHINESE.weekday("週日"), Some(Weekday::Sunday));
+        assert_eq!(ENGLISH.month("Smarch"), None);
+
+        let metadata = CZECH
+            .read("- Zvýraznění na stránce 7 | Pozice 88-90 | Přidáno: středa 3. červenec 2024 18:30:00")
+            .unwrap();
+        assert_eq!(metadata.datetime, "3 July 2024 18:30:00");
+
+        let timestamp = ENGLISH.timestamp("4 Mar 2024 9:05:10").unwrap();
+        assert_eq!(timestamp.to_string(), "2024-03-04 09:05:10");
+        assert_eq!(ENGLISH.timestamp("31 February 2024 09:05:10"), None);
+    }
+}
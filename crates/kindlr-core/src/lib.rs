@@ -0,0 +1,9 @@
+#[cfg(feature = "proptest")]
+mod arbitrary;
+pub mod context;
+pub mod languages;
+pub mod parser;
+#[cfg(feature = "fixtures")]
+pub mod samples;
+#[cfg(feature = "wasm")]
+pub mod wasm;
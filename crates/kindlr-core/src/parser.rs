@@ -0,0 +1,1342 @@
+use chrono::{Datelike, NaiveDateTime};
+use regex::Regex;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, BufRead};
+use std::ops::ControlFlow;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::context::Context;
+use crate::languages::{self, LanguagePack, Metadata, Missing};
+
+const SEPARATOR: &str = "==========";
+
+/// Parse errors
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("Invalid format: {0}")]
+    InvalidFormat(String),
+    #[error("Missing field: {0}")]
+    MissingField(String),
+    #[error("Invalid weekday: {0}")]
+    InvalidWeekday(String),
+    /// A clippings file without entries, as on a Kindle nothing was
+    /// highlighted on yet
+    #[error("{0} has no clippings yet")]
+    EmptyFile(String),
+    /// An entry of a clippings file that failed to parse
+    #[error("Failed to parse clipping #{index} at line {line}: {source}")]
+    Entry {
+        /// Position of the entry in the file, from 1
+        index: usize,
+        /// Line the entry starts on, from 1
+        line: usize,
+        /// Start of the entry's text
+        snippet: String,
+        source: Box<ParseError>,
+    },
+}
+
+impl ParseError {
+    /// Stable identifier of the kind of failure
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::InvalidFormat(_) => "parse.invalid_format",
+            ParseError::MissingField(_) => "parse.missing_field",
+            ParseError::InvalidWeekday(_) => "parse.invalid_weekday",
+            ParseError::EmptyFile(_) => "parse.empty_file",
+            ParseError::Entry { source, .. } => source.code(),
+        }
+    }
+}
+
+// Clipping type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum ClippingType {
+    Highlight,
+    Note,
+    Bookmark,
+    /// A highlight in a web article, from a read-it-later service
+    ArticleClip,
+}
+
+impl ClippingType {
+    /// Highlighted text, from a book or an article
+    pub fn is_highlight(self) -> bool {
+        matches!(self, ClippingType::Highlight | ClippingType::ArticleClip)
+    }
+}
+
+impl fmt::Display for ClippingType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for ClippingType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            // en
+            "Highlight" => Ok(ClippingType::Highlight),
+            "Note" => Ok(ClippingType::Note),
+            "Bookmark" => Ok(ClippingType::Bookmark),
+            // other languages are read through `LanguagePack`
+            _ => Err(format!("Invalid clipping type: {}", s)),
+        }
+    }
+}
+
+/// Color a highlight was made in, for sources that record one such as the
+/// Kindle app's exports; My Clippings.txt doesn't
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightColor {
+    Yellow,
+    Blue,
+    Pink,
+    Orange,
+}
+
+impl fmt::Display for HighlightColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HighlightColor::Yellow => "yellow",
+            HighlightColor::Blue => "blue",
+            HighlightColor::Pink => "pink",
+            HighlightColor::Orange => "orange",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for HighlightColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "yellow" => Ok(HighlightColor::Yellow),
+            "blue" => Ok(HighlightColor::Blue),
+            "pink" => Ok(HighlightColor::Pink),
+            "orange" => Ok(HighlightColor::Orange),
+            _ => Err(format!("Invalid highlight color: {}", s)),
+        }
+    }
+}
+
+/// Location
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct Location {
+    pub start: u32,
+    pub end: Option<u32>,
+}
+
+impl Location {
+    /// Whether the two location ranges share any location
+    pub fn overlaps(&self, other: &Location) -> bool {
+        self.start <= other.end.unwrap_or(other.start)
+            && other.start <= self.end.unwrap_or(self.start)
+    }
+
+    /// Last location in the range
+    pub fn last(&self) -> u32 {
+        self.end.unwrap_or(self.start)
+    }
+
+    /// Approximate percentage through a book of `total` locations, capped at 100
+    pub fn progress(&self, total: u32) -> Option<f64> {
+        (total > 0).then(|| (f64::from(self.last()) / f64::from(total) * 100.0).min(100.0))
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.end {
+            Some(end) => {
+                write!(f, "{}-{}", self.start, end)
+            }
+            None => {
+                write!(f, "{}", self.start)
+            }
+        }
+    }
+}
+
+/// Days of the week
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl fmt::Display for Weekday {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl From<chrono::Weekday> for Weekday {
+    fn from(weekday: chrono::Weekday) -> Self {
+        match weekday {
+            chrono::Weekday::Mon => Weekday::Monday,
+            chrono::Weekday::Tue => Weekday::Tuesday,
+            chrono::Weekday::Wed => Weekday::Wednesday,
+            chrono::Weekday::Thu => Weekday::Thursday,
+            chrono::Weekday::Fri => Weekday::Friday,
+            chrono::Weekday::Sat => Weekday::Saturday,
+            chrono::Weekday::Sun => Weekday::Sunday,
+        }
+    }
+}
+
+impl FromStr for Weekday {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        languages::ENGLISH
+            .weekday(s)
+            .ok_or_else(|| format!("Invalid weekday: {}", s))
+    }
+}
+
+/// A single Kindle clipping
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct Clipping {
+    pub clipping_type: ClippingType,
+    pub book_title: String,
+    pub author: String,
+    pub page: Option<u32>,
+    pub location: Location,
+    pub datetime: String,
+    pub weekday: Weekday,
+    pub content: Option<String>,
+    /// ISO 639-3 code of the content's language, once detected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_language: Option<String>,
+    /// Text of the entry as found in the file, when kept by `ParserOptions::keep_raw`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<String>,
+    /// Kindle the clipping was read from, see `device::identify`; not part of
+    /// the id, so the same highlight read from two devices is kept once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// Datetime as the Kindle wrote it, when `clock::adjust` shifted
+    /// `datetime` or `timezone::annotate` converted it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_datetime: Option<String>,
+    /// Offset of `datetime` from UTC, e.g. "+02:00", when the Kindle's zone
+    /// is configured; see `timezone::annotate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utc_offset: Option<String>,
+    /// Text around a highlight in its book, see `ebook::Ebook::attach_context`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<Context>,
+    /// Color of a highlight, when its source records one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<HighlightColor>,
+}
+
+impl fmt::Display for Clipping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let date = format!("{} ({})", self.datetime, self.weekday);
+        write!(f, "{}", Dated(self, &date))
+    }
+}
+
+/// A clipping shown as `Display` shows it, with its date written another way
+struct Dated<'a>(&'a Clipping, &'a str);
+
+impl fmt::Display for Dated<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Dated(clipping, date) = self;
+        write!(
+            f,
+            "Book: {}\nAuthor: {}\nLocation: {}\nDate: {}\nPage: {}\nContent: {}",
+            clipping.book_title,
+            clipping.author,
+            clipping.location,
+            date,
+            clipping.page.map_or("N/A".to_string(), |p| p.to_string()),
+            clipping.content.as_deref().unwrap_or("N/A")
+        )
+    }
+}
+
+impl Clipping {
+    /// The clipping as `Display` shows it, with `date` for its datetime and
+    /// weekday, e.g. as written by `dates::DateFormat`
+    pub fn display_dated<'a>(&'a self, date: &'a str) -> impl fmt::Display + 'a {
+        Dated(self, date)
+    }
+
+    /// Stable identifier derived from the clipping's metadata, with the
+    /// datetime the Kindle wrote so shifting or converting it keeps the id
+    pub fn id(&self) -> String {
+        let datetime = self.original_datetime.as_deref().unwrap_or(&self.datetime);
+        let key = format!(
+            "{}\0{}\0{}\0{}\0{}",
+            self.book_title, self.author, self.clipping_type, self.location, datetime
+        );
+
+        // FNV-1a, so ids stay the same across runs and Rust versions
+        let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+
+        format!("{:016x}", hash)
+    }
+
+    /// The datetime as a calendar value, if it can be understood
+    pub fn timestamp(&self) -> Option<NaiveDateTime> {
+        languages::ENGLISH.timestamp(&self.datetime)
+    }
+
+    /// Detect the language of the content, storing it in `content_language`
+    #[cfg(feature = "language-detection")]
+    pub fn detect_language(&mut self) {
+        self.content_language = self
+            .content
+            .as_deref()
+            .and_then(whatlang::detect)
+            .map(|info| info.lang().code().to_string());
+    }
+
+    /// A clipping from a source other than a Kindle, dated as Kindle would write it
+    pub fn new(
+        clipping_type: ClippingType,
+        book_title: String,
+        author: String,
+        page: Option<u32>,
+        location: Location,
+        added: NaiveDateTime,
+        content: Option<String>,
+    ) -> Self {
+        Clipping {
+            clipping_type,
+            book_title,
+            author,
+            page,
+            location,
+            datetime: added.format("%-d %B %Y %H:%M:%S").to_string(),
+            weekday: added.weekday().into(),
+            content,
+            content_language: None,
+            raw: None,
+            device: None,
+            original_datetime: None,
+            utc_offset: None,
+            context: None,
+            color: None,
+        }
+    }
+
+    /// Parse a single clipping from text
+    pub fn from_text(text: &str) -> Result<Self, ParseError> {
+        Parser::default().parse_entry(text)
+    }
+
+    fn from_text_with(text: &str, options: &ParserOptions) -> Result<Self, ParseError> {
+        // Lines may end in CRLF, LF or a lone CR, even within one file
+        let mut lines = text
+            .split(['\r', '\n'])
+            .filter(|line| !line.trim().is_empty());
+
+        // Parse first line: book title and author
+        let first_line = lines
+            .next()
+            .ok_or_else(|| ParseError::MissingField("book title and author".to_string()))?;
+
+        // A file starts with a byte order mark, which would change the id
+        let first_line = first_line.trim();
+        let first_line = first_line.strip_prefix('\u{feff}').unwrap_or(first_line);
+        // A mark after the closing parenthesis would hide the author
+        let first_line = first_line.trim().trim_end_matches(is_bidi_control);
+        let (mut book_title, mut author) = Self::parse_title_and_author(first_line)?;
+        if options.normalize_titles {
+            book_title = normalize_title(&book_title);
+        }
+        if options.normalize_authors {
+            author = normalize_author(&author);
+        }
+
+        // Parse second line: metadata
+        let second_line = normalize_metadata(
+            lines
+                .next()
+                .ok_or_else(|| ParseError::MissingField("metadata".to_string()))?
+                .trim(),
+        );
+        let second_line = second_line.as_str();
+
+        // The first language that reads the whole line wins, and a line
+        // none can read is reported as the first language worded like it
+        // failed on it
+        let mut failure = Missing::Heading;
+        let Metadata {
+            clipping_type,
+            page,
+            location,
+            weekday,
+            datetime,
+        } = options
+            .languages
+            .iter()
+            .find_map(|language| match language.pack().read(second_line) {
+                Ok(metadata) => Some(metadata),
+                Err(missing) => {
+                    if failure == Missing::Heading {
+                        failure = missing;
+                    }
+                    None
+                }
+            })
+            .ok_or_else(|| {
+                let part = match failure {
+                    Missing::Heading => "clipping type",
+                    Missing::Location => "location",
+                    Missing::Added => "datetime",
+                };
+                ParseError::InvalidFormat(format!("Failed to parse {}: {}", part, second_line))
+            })?;
+        let page = Some(page);
+
+        // Parse content
+        let content = if clipping_type == ClippingType::Bookmark {
+            None
+        } else {
+            Some(
+                lines
+                    .next()
+                    .ok_or_else(|| ParseError::MissingField("content".to_string()))?
+                    .to_string(),
+            )
+        };
+
+        let clipping = Self {
+            clipping_type,
+            book_title,
+            author,
+            page,
+            location,
+            datetime,
+            weekday,
+            content,
+            content_language: None,
+            raw: options.keep_raw.then(|| text.trim().to_string()),
+            device: None,
+            original_datetime: None,
+            utc_offset: None,
+            context: None,
+            color: None,
+        };
+
+        if options.datetime == DatetimePolicy::Validate && clipping.timestamp().is_none() {
+            return Err(ParseError::InvalidFormat(format!(
+                "Invalid datetime: {}",
+                clipping.datetime
+            )));
+        }
+
+        Ok(clipping)
+    }
+
+    fn parse_title_and_author(line: &str) -> Result<(String, String), ParseError> {
+        // Match pattern: "Title (Author)"
+        let re = Regex::new(r"^(.+?)\s+\((.+)\)$").unwrap();
+
+        re.captures(line)
+            .map(|caps| (caps[1].trim().to_string(), caps[2].trim().to_string()))
+            .ok_or_else(|| {
+                ParseError::InvalidFormat(format!(
+                    "Expected 'Title (Author)' format, got: {}",
+                    line
+                ))
+            })
+    }
+}
+
+/// Canonical form of an author name, turning "Herbert, Frank" into "Frank Herbert"
+/// and collapsing whitespace and dropping bidi controls
+pub fn normalize_author(author: &str) -> String {
+    let author = author
+        .replace(is_bidi_control, "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    match author.split_once(", ") {
+        Some((last, first)) if !first.contains(',') && !author.contains(';') => {
+            format!("{} {}", first, last)
+        }
+        _ => author,
+    }
+}
+
+/// Languages Kindle writes clipping metadata in
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Language {
+    English,
+    Hungarian,
+    Czech,
+    Swedish,
+    Norwegian,
+    Danish,
+    Finnish,
+    Arabic,
+    Hebrew,
+    Greek,
+    TraditionalChinese,
+}
+
+impl Language {
+    /// Every language, English first as most clippings files are in it, and
+    /// Swedish before Danish, which words highlights the same
+    pub const ALL: [Language; 11] = [
+        Language::English,
+        Language::Hungarian,
+        Language::Czech,
+        Language::Swedish,
+        Language::Norwegian,
+        Language::Danish,
+        Language::Finnish,
+        Language::Arabic,
+        Language::Hebrew,
+        Language::Greek,
+        Language::TraditionalChinese,
+    ];
+
+    /// The wording of metadata lines in this language
+    pub fn pack(self) -> &'static LanguagePack {
+        match self {
+            Language::English => &languages::ENGLISH,
+            Language::Hungarian => &languages::HUNGARIAN,
+            Language::Czech => &languages::CZECH,
+            Language::Swedish => &languages::SWEDISH,
+            Language::Norwegian => &languages::NORWEGIAN,
+            Language::Danish => &languages::DANISH,
+            Language::Finnish => &languages::FINNISH,
+            Language::Arabic => &languages::ARABIC,
+            Language::Hebrew => &languages::HEBREW,
+            Language::Greek => &languages::GREEK,
+            Language::TraditionalChinese => &languages::TRADITIONAL_CHINESE,
+        }
+    }
+}
+
+/// What to do with datetimes the parser can't turn into a calendar value
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DatetimePolicy {
+    /// Keep the datetime text as written
+    #[default]
+    AsWritten,
+    /// Reject the entry
+    Validate,
+}
+
+/// How clippings files are parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParserOptions {
+    /// Fail on the first bad entry instead of skipping it
+    pub strict: bool,
+    /// Languages to try for each entry's metadata, in order
+    pub languages: Vec<Language>,
+    /// Turn "Herbert, Frank" into "Frank Herbert", see `normalize_author`
+    pub normalize_authors: bool,
+    /// Collapse whitespace and drop byte order marks and bidi controls in titles
+    pub normalize_titles: bool,
+    /// Keep each entry's text in `Clipping::raw`
+    pub keep_raw: bool,
+    pub datetime: DatetimePolicy,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            strict: true,
+            languages: Language::ALL.to_vec(),
+            normalize_authors: false,
+            normalize_titles: false,
+            keep_raw: false,
+            datetime: DatetimePolicy::AsWritten,
+        }
+    }
+}
+
+/// Parser for clippings files
+#[derive(Debug, Clone, Default)]
+pub struct Parser {
+    options: ParserOptions,
+}
+
+impl Parser {
+    pub fn new(options: ParserOptions) -> Self {
+        Parser { options }
+    }
+
+    pub fn options(&self) -> &ParserOptions {
+        &self.options
+    }
+
+    /// Parse a single entry, without the separator
+    pub fn parse_entry(&self, text: &str) -> Result<Clipping, ParseError> {
+        Clipping::from_text_with(text, &self.options)
+    }
+
+    /// Parse a whole clippings file
+    ///
+    /// In lenient mode entries that fail to parse are skipped.
+    pub fn parse(&self, contents: &str) -> Result<Vec<Clipping>, ParseError> {
+        self.collect(|visit| self.parse_with(contents, visit))
+    }
+
+    /// Like `parse`, for a file's raw bytes such as a memory map
+    pub fn parse_bytes(&self, bytes: &[u8]) -> Result<Vec<Clipping>, ParseError> {
+        self.collect(|visit| self.parse_bytes_with(bytes, visit))
+    }
+
+    /// Gather the clippings `drive` visits, stopping at the first failure in strict mode
+    fn collect<D>(&self, drive: D) -> Result<Vec<Clipping>, ParseError>
+    where
+        D: FnOnce(&mut dyn FnMut(Result<Clipping, ParseIssue>) -> ControlFlow<()>),
+    {
+        let mut clippings = Vec::new();
+        let mut failure = None;
+
+        drive(&mut |result| match result {
+            Ok(clipping) => {
+                clippings.push(clipping);
+                ControlFlow::Continue(())
+            }
+            Err(_) if !self.options.strict => ControlFlow::Continue(()),
+            Err(issue) => {
+                failure = Some(issue);
+                ControlFlow::Break(())
+            }
+        });
+
+        match failure {
+            Some(issue) => Err(issue.into()),
+            None => Ok(clippings),
+        }
+    }
+
+    /// Call `visit` with every entry of a clippings file as it is parsed,
+    /// stopping early when it returns `ControlFlow::Break`
+    ///
+    /// Failed entries are passed on in lenient and strict mode alike.
+    pub fn parse_with<F>(&self, contents: &str, mut visit: F)
+    where
+        F: FnMut(Result<Clipping, ParseIssue>) -> ControlFlow<()>,
+    {
+        let mut cursor = EntryCursor::default();
+        let mut rest = contents;
+
+        loop {
+            let separator = find_separator(rest.as_bytes());
+            let end = separator.map_or(rest.len(), |(start, _)| start);
+            if cursor.visit(self, &rest[..end], &mut visit).is_break() {
+                return;
+            }
+
+            match separator {
+                Some((_, after)) => rest = &rest[after..],
+                None => return,
+            }
+        }
+    }
+
+    /// Like `parse_with`, over a file's raw bytes
+    ///
+    /// Only one entry at a time is decoded, so a memory-mapped file can be
+    /// parsed without reading it into memory. Invalid UTF-8 is replaced.
+    pub fn parse_bytes_with<F>(&self, mut bytes: &[u8], mut visit: F)
+    where
+        F: FnMut(Result<Clipping, ParseIssue>) -> ControlFlow<()>,
+    {
+        let mut cursor = EntryCursor::default();
+
+        loop {
+            let separator = find_separator(bytes);
+            let end = separator.map_or(bytes.len(), |(start, _)| start);
+
+            let text = String::from_utf8_lossy(&bytes[..end]);
+            if cursor.visit(self, &text, &mut visit).is_break() {
+                return;
+            }
+
+            match separator {
+                Some((_, after)) => bytes = &bytes[after..],
+                None => return,
+            }
+        }
+    }
+
+    /// Like `parse_with`, reading the file a line at a time so memory use
+    /// doesn't grow with its size
+    pub fn parse_reader_with<R, F>(&self, mut reader: R, mut visit: F) -> io::Result<()>
+    where
+        R: BufRead,
+        F: FnMut(Result<Clipping, ParseIssue>) -> ControlFlow<()>,
+    {
+        let mut cursor = EntryCursor::default();
+        let mut text = String::new();
+
+        // A file with lone CR line endings reads as one line, so look for
+        // every separator in what has been read so far
+        while reader.read_line(&mut text)? > 0 {
+            while let Some((start, after)) = find_separator(text.as_bytes()) {
+                if cursor.visit(self, &text[..start], &mut visit).is_break() {
+                    return Ok(());
+                }
+                text.drain(..after);
+            }
+        }
+
+        let _ = cursor.visit(self, &text, &mut visit);
+        Ok(())
+    }
+}
+
+/// Start and end of the first separator line in `text`
+///
+/// Besides Kindle's `==========`, a separator may have more `=` and spaces or
+/// tabs around it, as left by editors and tools concatenating files. Only the
+/// separator itself is included, not the line breaks around it.
+fn find_separator(text: &[u8]) -> Option<(usize, usize)> {
+    let is_blank = |byte: &u8| matches!(byte, b' ' | b'\t');
+    let mut line_start = 0;
+
+    loop {
+        let line = &text[line_start..];
+        let indent = line.iter().take_while(|byte| is_blank(byte)).count();
+        let equals = line[indent..]
+            .iter()
+            .take_while(|&&byte| byte == b'=')
+            .count();
+
+        if equals >= SEPARATOR.len() {
+            let end = line_start + indent + equals;
+            let trailing = text[end..].iter().take_while(|byte| is_blank(byte)).count();
+            return Some((line_start, end + trailing));
+        }
+
+        let line_end = line
+            .iter()
+            .position(|&byte| matches!(byte, b'\r' | b'\n'))?;
+        line_start += line_end + 1;
+    }
+}
+
+/// Whether `text` holds nothing but whitespace and `=`, as left between
+/// separators or by a separator cut short
+fn is_blank(text: &str) -> bool {
+    text.chars()
+        .all(|c| c.is_whitespace() || c == '=' || c == '\u{feff}')
+}
+
+/// Whether a clippings file has no entries at all, being empty or holding
+/// only separators and whitespace
+pub fn is_empty_file(contents: &str) -> bool {
+    is_blank(contents)
+}
+
+/// Number of line breaks in `text`, counting CRLF once
+fn line_breaks(text: &str) -> usize {
+    text.matches('\n').count() + text.matches('\r').count() - text.matches("\r\n").count()
+}
+
+/// An entry of a clippings file that failed to parse
+#[derive(Debug)]
+pub struct ParseIssue {
+    /// Position of the entry in the file, from 1
+    pub index: usize,
+    /// Line the entry starts on, from 1
+    pub line: usize,
+    /// Start of the entry's text
+    pub snippet: String,
+    pub error: ParseError,
+}
+
+impl From<ParseIssue> for ParseError {
+    fn from(issue: ParseIssue) -> Self {
+        ParseError::Entry {
+            index: issue.index,
+            line: issue.line,
+            snippet: issue.snippet,
+            source: Box::new(issue.error),
+        }
+    }
+}
+
+/// Position in a clippings file while visiting its entries
+struct EntryCursor {
+    index: usize,
+    line: usize,
+}
+
+impl Default for EntryCursor {
+    fn default() -> Self {
+        EntryCursor { index: 0, line: 1 }
+    }
+}
+
+impl EntryCursor {
+    /// Parse the text between two separators and hand it to `visit`, skipping
+    /// text that is blank or only broken separators
+    fn visit<F>(&mut self, parser: &Parser, text: &str, visit: &mut F) -> ControlFlow<()>
+    where
+        F: FnMut(Result<Clipping, ParseIssue>) -> ControlFlow<()>,
+    {
+        let start = self.line;
+        self.line += line_breaks(text);
+
+        if is_blank(text) {
+            return ControlFlow::Continue(());
+        }
+        self.index += 1;
+
+        let result = parser.parse_entry(text).map_err(|error| {
+            let leading = text.len() - text.trim_start().len();
+            ParseIssue {
+                index: self.index,
+                line: start + line_breaks(&text[..leading]),
+                snippet: text
+                    .trim()
+                    .split(['\r', '\n'])
+                    .next()
+                    .unwrap_or_default()
+                    .chars()
+                    .take(80)
+                    .collect(),
+                error,
+            }
+        });
+
+        visit(result)
+    }
+}
+
+/// Title with whitespace collapsed and byte order marks and bidi controls
+/// removed
+pub fn normalize_title(title: &str) -> String {
+    title
+        .replace(|c| c == '\u{feff}' || is_bidi_control(c), "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether `c` only steers which way text runs, like the marks Kindles set
+/// to Arabic or Hebrew put around numbers and Latin names
+pub fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200e}' | '\u{200f}' | '\u{061c}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+/// A metadata line without bidi controls and with Arabic-Indic digits
+/// written as ASCII digits
+fn normalize_metadata(line: &str) -> String {
+    line.chars()
+        .filter(|&c| !is_bidi_control(c))
+        .map(|c| match c {
+            '\u{0660}'..='\u{0669}' => char::from(b'0' + (c as u32 - 0x0660) as u8),
+            '\u{06f0}'..='\u{06f9}' => char::from(b'0' + (c as u32 - 0x06f0) as u8),
+            c => c,
+        })
+        .collect()
+}
+
+/// Parse a clippings file with the default options
+pub fn parse_clippings(contents: &str) -> Result<Vec<Clipping>, ParseError> {
+    Parser::default().parse(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_location() {
+        let contents = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Broken entry without metadata
+==========";
+
+        match parse_clippings(contents) {
+            Err(
+                ref error @ ParseError::Entry {
+                    index,
+                    line,
+                    ref snippet,
+                    ..
+                },
+            ) => {
+                assert_eq!((index, line), (2, 6));
+                assert_eq!(snippet, "Broken entry without metadata");
+                assert_eq!(error.code(), "parse.invalid_format");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parser_options() {
+        let contents = "\
+\u{feff}Dune  (Herbert, Frank)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Broken entry without metadata
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 31 February 2024 10:05:00
+
+Classic.
+==========";
+
+        assert!(parse_clippings(contents).is_err());
+
+        let parser = Parser::new(ParserOptions {
+            strict: false,
+            normalize_authors: true,
+            normalize_titles: true,
+            keep_raw: true,
+            ..ParserOptions::default()
+        });
+        let clippings = parser.parse(contents).unwrap();
+        assert_eq!(clippings.len(), 2);
+        assert_eq!(clippings[0].book_title, "Dune");
+        assert_eq!(clippings[0].author, "Frank Herbert");
+        assert!(
+            clippings[0]
+                .raw
+                .as_deref()
+                .unwrap()
+                .ends_with("mind-killer.")
+        );
+
+        let parser = Parser::new(ParserOptions {
+            strict: false,
+            datetime: DatetimePolicy::Validate,
+            ..ParserOptions::default()
+        });
+        assert_eq!(parser.parse(contents).unwrap().len(), 1);
+    }
+
+    /// The same two entries as files edited on Windows, old Macs or by hand
+    /// and concatenated, each paired with what went wrong
+    const NASTY: &[(&str, &str)] = &[
+        (
+            "CRLF",
+            "Dune (Frank Herbert)\r\n- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00\r\n\r\nFear is the mind-killer.\r\n==========\r\nDune (Frank Herbert)\r\n- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00\r\n\r\nClassic.\r\n==========\r\n",
+        ),
+        (
+            "lone CR",
+            "Dune (Frank Herbert)\r- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00\r\rFear is the mind-killer.\r==========\rDune (Frank Herbert)\r- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00\r\rClassic.\r==========\r",
+        ),
+        (
+            "mixed",
+            "Dune (Frank Herbert)\r\n- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00\n\rFear is the mind-killer.\r\n==========\nDune (Frank Herbert)\r- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00\n\nClassic.\r\n==========",
+        ),
+        (
+            "padded separators",
+            "Dune (Frank Herbert)\n- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00\n\nFear is the mind-killer.\n  ==========  \t\nDune (Frank Herbert)\n- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00\n\nClassic.\n\t==========\n",
+        ),
+        (
+            "long separators",
+            "Dune (Frank Herbert)\n- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00\n\nFear is the mind-killer.\n=================\nDune (Frank Herbert)\n- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00\n\nClassic.\n===========\n",
+        ),
+        (
+            "trailing whitespace",
+            "Dune (Frank Herbert)  \r\n- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00 \r\n\r\nFear is the mind-killer.\r\n==========\r\n\r\n\r\nDune (Frank Herbert)\t\n- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00\t\n\nClassic.\n==========\n\n",
+        ),
+        (
+            "concatenated",
+            "Dune (Frank Herbert)\r\n- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00\r\n\r\nFear is the mind-killer.\r\n==========\r\n==========\nDune (Frank Herbert)\n- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00\n\nClassic.\n==========Dune (Frank Herbert)\n- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00\n\nClassic.\n==========",
+        ),
+    ];
+
+    #[test]
+    fn test_nasty_line_endings() {
+        for (problem, contents) in NASTY {
+            let parsed =
+                parse_clippings(contents).unwrap_or_else(|error| panic!("{}: {}", problem, error));
+            let summary: Vec<(ClippingType, &str, &str, Option<&str>)> = parsed
+                .iter()
+                .map(|clipping| {
+                    (
+                        clipping.clipping_type,
+                        clipping.book_title.as_str(),
+                        clipping.datetime.as_str(),
+                        clipping.content.as_deref(),
+                    )
+                })
+                .collect();
+
+            assert_eq!(
+                summary[..2],
+                [
+                    (
+                        ClippingType::Highlight,
+                        "Dune",
+                        "1 January 2024 10:00:00",
+                        Some("Fear is the mind-killer.")
+                    ),
+                    (
+                        ClippingType::Note,
+                        "Dune",
+                        "1 January 2024 10:05:00",
+                        Some("Classic.")
+                    ),
+                ],
+                "{}",
+                problem
+            );
+
+            let mut lines = Vec::new();
+            Parser::default()
+                .parse_reader_with(contents.as_bytes(), |result| {
+                    lines.push(result.map(|clipping| clipping.id()).is_ok());
+                    ControlFlow::Continue(())
+                })
+                .unwrap();
+            assert_eq!(lines.len(), parsed.len(), "{} read as a stream", problem);
+            assert_eq!(
+                Parser::default()
+                    .parse_bytes(contents.as_bytes())
+                    .unwrap()
+                    .len(),
+                parsed.len(),
+                "{} read as bytes",
+                problem
+            );
+        }
+
+        let mut lines = Vec::new();
+        Parser::default().parse_with(
+            "Dune (Frank Herbert)\r- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00\r\rFear.\r==========\rBroken\r==========\r",
+            |result| {
+                lines.push(result.map_err(|issue| issue.line));
+                ControlFlow::Continue(())
+            },
+        );
+        assert_eq!(lines[1].as_ref().err(), Some(&6), "lone CRs count as lines");
+    }
+
+    #[test]
+    fn test_empty_files() {
+        for contents in [
+            "",
+            "\u{feff}\r\n",
+            "==========\n\n==========\n",
+            "=====\n  \n",
+        ] {
+            assert!(is_empty_file(contents), "{:?}", contents);
+            assert!(
+                parse_clippings(contents).unwrap().is_empty(),
+                "{:?}",
+                contents
+            );
+        }
+
+        // A separator cut short is skipped rather than failing as an entry
+        let contents = "Dune (Frank Herbert)\n- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00\n\nFear.\n==========\n=====\n==========\n";
+        assert!(!is_empty_file(contents));
+        assert_eq!(parse_clippings(contents).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_with() {
+        let contents = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Broken entry without metadata
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00
+
+Classic.
+==========";
+
+        let mut seen = Vec::new();
+        Parser::default().parse_with(contents, |result| {
+            seen.push(result.map_err(|issue| issue.line));
+            ControlFlow::Continue(())
+        });
+        assert_eq!(seen.len(), 3);
+        assert_eq!(seen[1].as_ref().err(), Some(&6));
+
+        let mut count = 0;
+        Parser::default()
+            .parse_reader_with(contents.as_bytes(), |result| {
+                count += 1;
+                match result {
+                    Ok(_) => ControlFlow::Continue(()),
+                    Err(_) => ControlFlow::Break(()),
+                }
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let mut lines = Vec::new();
+        Parser::default().parse_bytes_with(contents.as_bytes(), |result| {
+            lines.push(result.map_err(|issue| issue.line));
+            ControlFlow::Continue(())
+        });
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1].as_ref().err(), Some(&6));
+        assert!(Parser::default().parse_bytes(contents.as_bytes()).is_err());
+        let lenient = Parser::new(ParserOptions {
+            strict: false,
+            ..ParserOptions::default()
+        });
+        let ids = |clippings: Vec<Clipping>| clippings.iter().map(Clipping::id).collect::<Vec<_>>();
+        assert_eq!(
+            ids(lenient.parse_bytes(contents.as_bytes()).unwrap()),
+            ids(lenient.parse(contents).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_weekday_parsing() {
+        assert_eq!("Monday".parse::<Weekday>().unwrap(), Weekday::Monday);
+        assert_eq!("Sunday".parse::<Weekday>().unwrap(), Weekday::Sunday);
+        assert!("InvalidDay".parse::<Weekday>().is_err());
+    }
+
+    #[test]
+    #[allow(clippy::useless_format)]
+    fn test_clipping_parsing_en() {
+        // Highlight
+        let highlight = "\
+Book Title (Author Name)
+- Your Highlight on page 123 | Location 1234-1235 | Added on Monday, 26 August 2025 12:57:30
+
+Highlighted text content goes here.";
+
+        let result = Clipping::from_text(highlight).unwrap();
+
+        assert_eq!(result.clipping_type, ClippingType::Highlight);
+        assert_eq!(result.book_title, "Book Title");
+        assert_eq!(result.author, "Author Name");
+        assert_eq!(result.page, Some(123));
+        assert_eq!(
+            result.location,
+            Location {
+                start: 1234,
+                end: Some(1235)
+            }
+        );
+        assert_eq!(result.datetime, "26 August 2025 12:57:30");
+        assert_eq!(
+            result.timestamp().unwrap().to_string(),
+            "2025-08-26 12:57:30"
+        );
+        assert_eq!(result.weekday, Weekday::Monday);
+        assert_eq!(
+            result.content,
+            Some(format!("Highlighted text content goes here."))
+        );
+
+        // Bookmark
+        let bookmark = "\
+Book Title (Author Name)
+- Your Bookmark on page 123 | Location 1234 | Added on Monday, 26 August 2025 12:57:30
+
+";
+        let result = Clipping::from_text(bookmark).unwrap();
+
+        assert_eq!(result.clipping_type, ClippingType::Bookmark);
+        assert_eq!(result.content, None);
+        assert_eq!(
+            result.location,
+            Location {
+                start: 1234,
+                end: None
+            }
+        );
+
+        // Note
+        let note = "\
+Book Title (Author Name)
+- Your Note on page 123 | Location 1234 | Added on Monday, 26 August 2025 12:57:30
+
+Your note content goes here.";
+        let result = Clipping::from_text(note).unwrap();
+
+        assert_eq!(result.clipping_type, ClippingType::Note);
+        assert_eq!(
+            result.content,
+            Some(format!("Your note content goes here."))
+        );
+    }
+
+    #[test]
+    fn test_clipping_parsing_hu_cs() {
+        let hungarian = "\
+A Pál utcai fiúk (Molnár Ferenc)
+- Kiemelés a(z) 12. oldalon | Hely: 150-152 | Hozzáadva: 2024. március 4., hétfő 9:05:10
+
+Nemecsek Ernő.
+==========
+A Pál utcai fiúk (Molnár Ferenc)
+- Jegyzet a(z) 12. oldalon | Hely: 152 | Hozzáadva: 2024. március 4., hétfő 9:06:00
+
+Szegény.
+==========
+A Pál utcai fiúk (Molnár Ferenc)
+- Könyvjelző a(z) 30. oldalon | Hely: 401 | Hozzáadva: 2024. december 29., vasárnap 22:00:00
+
+
+==========";
+        let czech = "\
+Válka s mloky (Karel Čapek)
+- Zvýraznění na stránce 7 | Pozice 88-90 | Přidáno: středa 3. července 2024 18:30:00
+
+Ten mlok.
+==========
+Válka s mloky (Karel Čapek)
+- Poznámka na stránce 7 | Pozice 90 | Přidáno: středa 3. července 2024 18:31:00
+
+Mloci!
+==========
+Válka s mloky (Karel Čapek)
+- Záložka na stránce 9 | Pozice 120 | Přidáno: úterý 1. října 2024 7:00:00
+
+
+==========";
+
+        for contents in [hungarian, czech] {
+            let clippings = parse_clippings(contents).unwrap();
+            assert_eq!(
+                clippings
+                    .iter()
+                    .map(|clipping| clipping.clipping_type)
+                    .collect::<Vec<_>>(),
+                [
+                    ClippingType::Highlight,
+                    ClippingType::Note,
+                    ClippingType::Bookmark
+                ]
+            );
+            assert!(
+                clippings
+                    .iter()
+                    .all(|clipping| clipping.timestamp().is_some())
+            );
+        }
+
+        let clippings = parse_clippings(hungarian).unwrap();
+        assert_eq!(clippings[0].page, Some(12));
+        assert_eq!(
+            clippings[0].location,
+            Location {
+                start: 150,
+                end: Some(152)
+            }
+        );
+        assert_eq!(clippings[0].datetime, "4 March 2024 09:05:10");
+        assert_eq!(clippings[2].weekday, Weekday::Sunday);
+
+        let clippings = parse_clippings(czech).unwrap();
+        assert_eq!(clippings[0].datetime, "3 July 2024 18:30:00");
+        assert_eq!(clippings[0].weekday, Weekday::Wednesday);
+        assert_eq!(clippings[2].location.start, 120);
+    }
+
+    #[test]
+    fn test_clipping_parsing_rtl() {
+        // Marks keep numbers and Latin text running left to right
+        let arabic = "\
+\u{200f}ألف ليلة وليلة (مجهول)\u{200f}
+- تمييزك في الصفحة \u{200e}١٢\u{200e} | الموقع \u{200e}150-152\u{200e} | تمت الإضافة يوم الاثنين، 4 مارس 2024 09:05:10
+
+كان يا ما كان.
+==========";
+        let hebrew = "\
+\u{2067}Dune\u{2069} (פרנק הרברט)
+- הסימון שלך בעמוד 12 | מיקום \u{200e}150-152 | נוסף ביום שני, 4 במרץ 2024 09:05:10
+
+הפחד הוא רוצח הדעת.
+==========";
+
+        for contents in [arabic, hebrew] {
+            let clipping = &parse_clippings(contents).unwrap()[0];
+            assert_eq!(clipping.page, Some(12));
+            assert_eq!(clipping.location.end, Some(152));
+            assert_eq!(clipping.weekday, Weekday::Monday);
+            assert_eq!(clipping.datetime, "4 March 2024 09:05:10");
+        }
+
+        let parser = Parser::new(ParserOptions {
+            normalize_titles: true,
+            ..ParserOptions::default()
+        });
+        assert_eq!(
+            parser.parse(arabic).unwrap()[0].book_title,
+            "ألف ليلة وليلة"
+        );
+        assert_eq!(parser.parse(hebrew).unwrap()[0].book_title, "Dune");
+    }
+
+    #[cfg(feature = "language-detection")]
+    #[test]
+    fn test_detect_language() {
+        let mut clipping = Clipping::from_text(
+            "\
+Book (Author)
+- Your Highlight on page 1 | Location 1-2 | Added on Monday, 1 January 2024 10:00:00
+
+Die Grenzen meiner Sprache bedeuten die Grenzen meiner Welt.",
+        )
+        .unwrap();
+
+        assert_eq!(clipping.content_language, None);
+        clipping.detect_language();
+        assert_eq!(clipping.content_language.as_deref(), Some("deu"));
+    }
+
+    #[test]
+    fn test_normalize_author() {
+        assert_eq!(normalize_author("Herbert, Frank"), "Frank Herbert");
+        assert_eq!(normalize_author("  Frank   Herbert "), "Frank Herbert");
+        assert_eq!(
+            normalize_author("Herbert, Frank;Anderson, Kevin J."),
+            "Herbert, Frank;Anderson, Kevin J."
+        );
+    }
+
+    #[test]
+    fn test_missing_content() {
+        let clipping = "\
+Book (Author)
+Location 123 | Added on Monday, 1 January 2025 10:00:00";
+
+        assert!(Clipping::from_text(clipping).is_err());
+    }
+}
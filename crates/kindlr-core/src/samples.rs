@@ -0,0 +1,69 @@
+//! Titles, authors and words to make up clippings from, for kindlr's
+//! fixtures and proptest strategies
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+/// Titles fixtures are made from, without the parentheses that would end a title
+pub const TITLES: &[&str] = &[
+    "Dune",
+    "Meditations",
+    "The Left Hand of Darkness",
+    "Middlemarch",
+    "Invisible Cities",
+    "The Book of Tea",
+    "Pale Fire",
+    "Der Zauberberg",
+    "Cien años de soledad",
+    "吾輩は猫である",
+];
+
+pub const AUTHORS: &[&str] = &[
+    "Frank Herbert",
+    "Marcus Aurelius",
+    "Ursula K. Le Guin",
+    "Eliot, George",
+    "Italo Calvino",
+    "Okakura Kakuzō",
+    "Vladimir Nabokov",
+    "Thomas Mann",
+    "Gabriel García Márquez",
+    "夏目漱石",
+];
+
+pub const WORDS: &[&str] = &[
+    "the",
+    "spice",
+    "must",
+    "flow",
+    "fear",
+    "is",
+    "mind-killer",
+    "and",
+    "of",
+    "light",
+    "darkness",
+    "a",
+    "river",
+    "never",
+    "same",
+    "twice",
+    "café",
+    "naïve",
+    "Übermensch",
+    "soledad",
+    "猫",
+    "tea,",
+    "silence.",
+    "why?",
+    "—",
+    "“quoted”",
+    "'tis",
+    "100%",
+];
+
+/// Oldest date fixtures are added on
+pub fn epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2015, 1, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .expect("valid date")
+}
@@ -0,0 +1,30 @@
+//! JavaScript bindings for parsing clippings in the browser
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::parser::{self, Clipping};
+
+#[derive(Serialize)]
+struct JsClipping<'a> {
+    id: String,
+    #[serde(flatten)]
+    clipping: &'a Clipping,
+}
+
+/// Parse the contents of My Clippings.txt into an array of clipping objects
+#[wasm_bindgen(js_name = parseClippings)]
+pub fn parse_clippings(text: &str) -> Result<JsValue, JsError> {
+    let clippings = parser::parse_clippings(text)?;
+
+    let clippings: Vec<JsClipping> = clippings
+        .iter()
+        .map(|clipping| JsClipping {
+            id: clipping.id(),
+            clipping,
+        })
+        .collect();
+
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    Ok(clippings.serialize(&serializer)?)
+}
@@ -0,0 +1,163 @@
+use chrono::format::StrftimeItems;
+use chrono::{Duration, NaiveDateTime};
+use serde::Deserialize;
+use std::str::FromStr;
+
+use crate::parser::Clipping;
+
+/// Days after which `--relative` shows dates as the Kindle wrote them
+pub const DEFAULT_RELATIVE_DAYS: i64 = 30;
+
+/// How dates are shown in `list` and Markdown
+///
+/// Datetimes that can't be read are always shown as written.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum DateFormat {
+    /// As the Kindle wrote it, e.g. "1 January 2024 10:00:00"
+    #[default]
+    Kindle,
+    /// "2024-01-01 10:00:00"
+    Iso,
+    /// "2024-01-01"
+    Date,
+    /// "3 days ago", see `relative`, or as the Kindle wrote it when more
+    /// than the given number of days ago
+    Relative(Option<i64>),
+    /// A strftime string such as "%d/%m/%Y"
+    Strftime(String),
+}
+
+impl DateFormat {
+    /// The date of `clipping`, shown `now`
+    pub fn format(&self, clipping: &Clipping, now: NaiveDateTime) -> String {
+        let Some(timestamp) = clipping.timestamp() else {
+            return clipping.datetime.clone();
+        };
+        match self {
+            DateFormat::Kindle => clipping.datetime.clone(),
+            DateFormat::Iso => timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+            DateFormat::Date => timestamp.format("%Y-%m-%d").to_string(),
+            DateFormat::Relative(Some(days)) if now - timestamp > Duration::days(*days) => {
+                clipping.datetime.clone()
+            }
+            DateFormat::Relative(_) => relative(timestamp, now),
+            DateFormat::Strftime(format) => timestamp.format(format).to_string(),
+        }
+    }
+}
+
+impl FromStr for DateFormat {
+    type Err = String;
+
+    /// A preset, "kindle", "iso", "date", "relative" or `"relative:<days>"`,
+    /// or a strftime string
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid date format: {}, expected kindle, iso, date, relative, relative:<days> or a strftime string",
+                s
+            )
+        };
+        match s {
+            "kindle" => Ok(DateFormat::Kindle),
+            "iso" => Ok(DateFormat::Iso),
+            "date" => Ok(DateFormat::Date),
+            "relative" => Ok(DateFormat::Relative(None)),
+            _ if let Some(days) = s.strip_prefix("relative:") => days
+                .parse()
+                .ok()
+                .filter(|&days: &i64| days >= 0)
+                .map(|days| DateFormat::Relative(Some(days)))
+                .ok_or_else(invalid),
+            _ if s.contains('%') && StrftimeItems::new(s).parse().is_ok() => {
+                Ok(DateFormat::Strftime(s.to_string()))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl TryFrom<String> for DateFormat {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// How long before `now` `then` was, in the largest whole unit, e.g.
+/// "yesterday" or "3 weeks ago"; times after `now` read "in 2 days"
+pub fn relative(then: NaiveDateTime, now: NaiveDateTime) -> String {
+    let elapsed = now - then;
+    let span = elapsed.abs();
+    let (count, unit) = if span < Duration::minutes(1) {
+        return "just now".to_string();
+    } else if span < Duration::hours(1) {
+        (span.num_minutes(), "minute")
+    } else if span < Duration::days(1) {
+        (span.num_hours(), "hour")
+    } else if span < Duration::days(2) && elapsed > Duration::zero() {
+        return "yesterday".to_string();
+    } else if span < Duration::weeks(2) {
+        (span.num_days(), "day")
+    } else if span < Duration::days(60) {
+        (span.num_weeks(), "week")
+    } else if span < Duration::days(730) {
+        (span.num_days() / 30, "month")
+    } else {
+        (span.num_days() / 365, "year")
+    };
+
+    let plural = if count == 1 { "" } else { "s" };
+    if elapsed < Duration::zero() {
+        format!("in {} {}{}", count, unit, plural)
+    } else {
+        format!("{} {}{} ago", count, unit, plural)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    #[test]
+    fn test_date_format() {
+        let clippings = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========",
+        )
+        .unwrap();
+        let now =
+            NaiveDateTime::parse_from_str("2024-01-04 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let format = |format: &str| {
+            format
+                .parse::<DateFormat>()
+                .unwrap()
+                .format(&clippings[0], now)
+        };
+
+        assert_eq!(format("kindle"), "1 January 2024 10:00:00");
+        assert_eq!(format("iso"), "2024-01-01 10:00:00");
+        assert_eq!(format("date"), "2024-01-01");
+        assert_eq!(format("relative"), "3 days ago");
+        assert_eq!(format("relative:7"), "3 days ago");
+        assert_eq!(format("relative:2"), "1 January 2024 10:00:00");
+        assert!("relative:soon".parse::<DateFormat>().is_err());
+        assert_eq!(format("%d/%m/%Y"), "01/01/2024");
+        assert!("%Q".parse::<DateFormat>().is_err());
+        assert!("fancy".parse::<DateFormat>().is_err());
+
+        let then = now - Duration::hours(30);
+        assert_eq!(relative(then, now), "yesterday");
+        assert_eq!(relative(now - Duration::seconds(5), now), "just now");
+        assert_eq!(relative(now - Duration::days(400), now), "13 months ago");
+        assert_eq!(relative(now + Duration::hours(1), now), "in 1 hour");
+        assert_eq!(relative(now - Duration::days(800), now), "2 years ago");
+    }
+}
@@ -0,0 +1,758 @@
+use chrono::Local;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+#[cfg(feature = "async")]
+use std::path::PathBuf;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+use thiserror::Error;
+
+use crate::dates::DateFormat;
+use crate::goodreads;
+use crate::library::{Book, Library, annotated_highlight};
+use crate::parser::{Clipping, ClippingType, HighlightColor, Language};
+use crate::sections;
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Unknown export format: {name}, expected one of {expected}")]
+    UnknownFormat { name: String, expected: String },
+    /// Adding to the file would leave it no longer reading as one
+    #[error("Can't add to a {0} file, it would no longer read as one")]
+    NotAppendable(String),
+    #[error("Invalid Goodreads CSV: {0}")]
+    Goodreads(String),
+}
+
+/// An export format
+///
+/// Implement this and add it to an `ExporterRegistry` to make a format
+/// available by name.
+pub trait Exporter {
+    /// Name the format is selected by, e.g. "json"
+    fn name(&self) -> &str;
+
+    /// File extension of exported files, without the dot
+    fn extension(&self) -> &str;
+
+    fn export(&self, library: &Library, out: &mut dyn Write) -> Result<(), ExportError>;
+
+    /// Whether an export added to the end of an earlier one still reads as
+    /// one file, as `--since-last --output` needs
+    fn appendable(&self) -> bool {
+        false
+    }
+
+    /// Bytes `export` starts a file with that an export added to the end of
+    /// a file leaves out, such as a byte order mark
+    fn preamble(&self) -> &[u8] {
+        &[]
+    }
+}
+
+/// Export formats by name
+#[derive(Default)]
+pub struct ExporterRegistry {
+    exporters: BTreeMap<String, Box<dyn Exporter>>,
+}
+
+impl ExporterRegistry {
+    /// A registry without any formats
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry with the formats kindlr ships with
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(JsonExporter));
+        registry.register(Box::new(KindleExporter));
+        registry.register(Box::new(MarginaliaExporter));
+        registry.register(Box::new(MarkdownExporter));
+        registry.register(Box::new(YamlExporter));
+        registry
+    }
+
+    /// Add a format, replacing any format of the same name
+    pub fn register(&mut self, exporter: Box<dyn Exporter>) {
+        self.exporters.insert(exporter.name().to_string(), exporter);
+    }
+
+    pub fn get(&self, name: &str) -> Result<&dyn Exporter, ExportError> {
+        self.exporters
+            .get(name)
+            .map(|exporter| exporter.as_ref())
+            .ok_or_else(|| ExportError::UnknownFormat {
+                name: name.to_string(),
+                expected: self.names().collect::<Vec<_>>().join(", "),
+            })
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.exporters.keys().map(String::as_str)
+    }
+
+    /// Names of the formats that can be appended to a file
+    pub fn appendable_names(&self) -> impl Iterator<Item = &str> {
+        self.exporters
+            .values()
+            .filter(|exporter| exporter.appendable())
+            .map(|exporter| exporter.name())
+    }
+}
+
+/// Export `library` to the file at `path`, replacing it or, when `append`,
+/// adding to its end, which only appendable formats allow
+pub fn write_file(
+    exporter: &dyn Exporter,
+    library: &Library,
+    path: &Path,
+    append: bool,
+) -> Result<(), ExportError> {
+    if append && !exporter.appendable() {
+        return Err(ExportError::NotAppendable(exporter.name().to_string()));
+    }
+    let file = if append {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+    } else {
+        fs::File::create(path)?
+    };
+    if file.metadata()?.len() > 0 {
+        let mut out = Vec::new();
+        exporter.export(library, &mut out)?;
+        let out = out.strip_prefix(exporter.preamble()).unwrap_or(&out);
+        return Ok((&file).write_all(out)?);
+    }
+    let mut file = io::BufWriter::new(file);
+    exporter.export(library, &mut file)?;
+    Ok(file.flush()?)
+}
+
+/// Like `write_file`, exporting on tokio's blocking thread pool so large
+/// libraries don't stall other tasks
+#[cfg(feature = "async")]
+pub async fn write_file_async(
+    exporter: Arc<dyn Exporter + Send + Sync>,
+    library: Arc<Library>,
+    path: impl Into<PathBuf>,
+    append: bool,
+) -> Result<(), ExportError> {
+    let path = path.into();
+    tokio::task::spawn_blocking(move || write_file(exporter.as_ref(), &library, &path, append))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// `library` exported by `exporter` into memory, on tokio's blocking thread
+/// pool, e.g. for the body of an HTTP response
+#[cfg(feature = "async")]
+pub async fn export_async(
+    exporter: Arc<dyn Exporter + Send + Sync>,
+    library: Arc<Library>,
+) -> Result<Vec<u8>, ExportError> {
+    tokio::task::spawn_blocking(move || {
+        let mut out = Vec::new();
+        exporter.export(&library, &mut out)?;
+        Ok(out)
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+/// Books and their clippings as a JSON document
+pub struct JsonExporter;
+
+impl Exporter for JsonExporter {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn extension(&self) -> &str {
+        "json"
+    }
+
+    fn export(&self, library: &Library, out: &mut dyn Write) -> Result<(), ExportError> {
+        serde_json::to_writer_pretty(&mut *out, library)
+            .map_err(|error| ExportError::Io(error.into()))?;
+        writeln!(out)?;
+        Ok(())
+    }
+}
+
+/// Books and their clippings as a YAML document, laid out as `JsonExporter`
+/// lays them out
+pub struct YamlExporter;
+
+impl Exporter for YamlExporter {
+    fn name(&self) -> &str {
+        "yaml"
+    }
+
+    fn extension(&self) -> &str {
+        "yaml"
+    }
+
+    fn export(&self, library: &Library, out: &mut dyn Write) -> Result<(), ExportError> {
+        serde_yaml::to_writer(&mut *out, library)
+            .map_err(|error| ExportError::Io(io::Error::other(error)))?;
+        Ok(())
+    }
+}
+
+/// My Clippings.txt as a Kindle writes it, to copy back onto a device
+pub struct KindleExporter;
+
+impl Exporter for KindleExporter {
+    fn name(&self) -> &str {
+        "kindle"
+    }
+
+    fn extension(&self) -> &str {
+        "txt"
+    }
+
+    fn appendable(&self) -> bool {
+        true
+    }
+
+    /// A Kindle's file starts with a byte order mark
+    fn preamble(&self) -> &[u8] {
+        "\u{feff}".as_bytes()
+    }
+
+    fn export(&self, library: &Library, out: &mut dyn Write) -> Result<(), ExportError> {
+        out.write_all(self.preamble())?;
+        for clipping in kindle_order(library) {
+            out.write_all(kindle_entry(clipping, Language::English).as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Clippings of `library` in the order `KindleExporter` writes them
+///
+/// A Kindle appends clippings as they are made, whatever the book.
+pub fn kindle_order(library: &Library) -> Vec<&Clipping> {
+    let mut clippings: Vec<&Clipping> = library.clippings().collect();
+    clippings.sort_by_key(|clipping| clipping.timestamp());
+    clippings
+}
+
+/// The metadata line a Kindle set to `language` writes for `clipping`
+///
+/// Kindle has no article clips, so they are written as highlights.
+pub fn kindle_metadata_line(clipping: &Clipping, language: Language) -> String {
+    language.pack().write(clipping)
+}
+
+/// `clipping` as an entry of My Clippings.txt, followed by its separator,
+/// with CRLF line endings as a Kindle writes them
+pub fn kindle_entry(clipping: &Clipping, language: Language) -> String {
+    format!(
+        "{} ({})\r\n{}\r\n\r\n{}\r\n==========\r\n",
+        clipping.book_title,
+        clipping.author,
+        kindle_metadata_line(clipping, language),
+        clipping.content.as_deref().unwrap_or_default()
+    )
+}
+
+/// A Markdown section per book with highlights as quotes and notes below them,
+/// split into guessed chapters when `Library::section_gap` is set
+pub struct MarkdownExporter;
+
+impl Exporter for MarkdownExporter {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn extension(&self) -> &str {
+        "md"
+    }
+
+    fn appendable(&self) -> bool {
+        true
+    }
+
+    fn export(&self, library: &Library, out: &mut dyn Write) -> Result<(), ExportError> {
+        for (i, book) in library.books.iter().enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            let cover = book
+                .metadata
+                .as_ref()
+                .and_then(|metadata| match &metadata.cover_path {
+                    Some(path) => Some(path.display().to_string()),
+                    None => metadata.cover_url.clone(),
+                });
+            write!(out, "{}", markdown_heading(book, cover.as_deref()))?;
+
+            // Sections only when there's more than one to tell apart
+            let parts: Vec<(Option<usize>, Vec<&Clipping>)> = match library
+                .section_gap
+                .map(|gap| sections::sections(&book.clippings, gap))
+            {
+                Some(sections) if sections.len() > 1 => sections
+                    .into_iter()
+                    .map(|section| (Some(section.number), section.clippings))
+                    .collect(),
+                _ => vec![(None, book.clippings.iter().collect())],
+            };
+            for (number, clippings) in parts {
+                if let Some(number) = number {
+                    write!(out, "\n## Section {}\n", number)?;
+                }
+                for clipping in clippings {
+                    if let Some(entry) = markdown_entry(book, clipping, &library.date_format) {
+                        write!(out, "\n{}", entry)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A Markdown section per book of nothing but notes, each with the highlight
+/// it was made on quoted beneath it; books without notes are left out
+pub struct MarginaliaExporter;
+
+impl Exporter for MarginaliaExporter {
+    fn name(&self) -> &str {
+        "marginalia"
+    }
+
+    fn extension(&self) -> &str {
+        "md"
+    }
+
+    fn appendable(&self) -> bool {
+        true
+    }
+
+    fn export(&self, library: &Library, out: &mut dyn Write) -> Result<(), ExportError> {
+        let books = library.books.iter().filter(|book| {
+            book.clippings
+                .iter()
+                .any(|clipping| clipping.clipping_type == ClippingType::Note)
+        });
+        for (i, book) in books.enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            write!(out, "{}", markdown_heading(book, None))?;
+
+            for note in book
+                .clippings
+                .iter()
+                .filter(|clipping| clipping.clipping_type == ClippingType::Note)
+            {
+                write!(out, "\n{}\n", note.content.as_deref().unwrap_or_default())?;
+                if let Some(highlight) = annotated_highlight(note, &book.clippings) {
+                    write!(
+                        out,
+                        "\n> {}\n>\n> — Location {}\n",
+                        highlight
+                            .content
+                            .as_deref()
+                            .unwrap_or_default()
+                            .replace('\n', "\n> "),
+                        highlight.location
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The title, author, rating and read date heading a book's Markdown, and
+/// the cover image at `cover` when there is one
+pub fn markdown_heading(book: &Book, cover: Option<&str>) -> String {
+    let mut heading = format!("# {}\n\n*{}*", book.title, book.author);
+    if let Some(rating) = book.rating {
+        heading += &format!(" · {}", goodreads::stars(rating));
+    }
+    if let Some(date_read) = book.date_read {
+        heading += &format!(" · read {}", date_read);
+    }
+    if let Some(asin) = &book.asin {
+        heading += &format!(" · [Amazon]({})", store_url(asin));
+    }
+    heading += "\n";
+    if let Some(cover) = cover {
+        // Angle brackets keep paths with spaces in one link
+        let cover = if cover.contains(' ') {
+            format!("<{}>", cover)
+        } else {
+            cover.to_string()
+        };
+        heading += &format!("\n![Cover of {}]({})\n", book.title, cover);
+    }
+    heading
+}
+
+/// Link opening the Kindle app at `location` of the book with `asin`
+pub fn kindle_link(asin: &str, location: u32) -> String {
+    format!(
+        "kindle://book?action=open&asin={}&location={}",
+        asin, location
+    )
+}
+
+/// The book's page in the Amazon store
+pub fn store_url(asin: &str) -> String {
+    format!("https://www.amazon.com/dp/{}", asin)
+}
+
+/// A highlight as a Markdown quote dated in `date_format`, in bold among its
+/// context when it has one, or a note, as written by `MarkdownExporter`;
+/// bookmarks have no entry
+pub fn markdown_entry(
+    book: &Book,
+    clipping: &Clipping,
+    date_format: &DateFormat,
+) -> Option<String> {
+    let content = clipping.content.as_deref().unwrap_or_default();
+    match clipping.clipping_type {
+        ClippingType::Highlight | ClippingType::ArticleClip => {
+            // Articles have no locations, only the order of their highlights
+            let position = if clipping.clipping_type == ClippingType::ArticleClip {
+                format!("Highlight {}", clipping.location)
+            } else {
+                let progress = book
+                    .progress_of(&clipping.location)
+                    .map(|percent| format!(" (~{:.0}% through the book)", percent))
+                    .unwrap_or_default();
+                let location = match &book.asin {
+                    Some(asin) => format!(
+                        "[Location {}]({})",
+                        clipping.location,
+                        kindle_link(asin, clipping.location.start)
+                    ),
+                    None => format!("Location {}", clipping.location),
+                };
+                format!("{}{}", location, progress)
+            };
+            // The highlight in bold among the text around it in the book
+            let quote = match &clipping.context {
+                Some(context) => [
+                    context.before.clone(),
+                    format!("**{}**", content),
+                    context.after.clone(),
+                ]
+                .into_iter()
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "),
+                None => content.to_string(),
+            };
+            let badge = clipping
+                .color
+                .map(|color| format!(" · {}", color_badge(color)))
+                .unwrap_or_default();
+            Some(format!(
+                "> {}\n>\n> — {}, {}{}\n",
+                quote.replace('\n', "\n> "),
+                position,
+                date_format.format(clipping, Local::now().naive_local()),
+                badge
+            ))
+        }
+        ClippingType::Note => Some(format!("**Note:** {}\n", content)),
+        ClippingType::Bookmark => None,
+    }
+}
+
+/// A highlight's color as a heart of that color and its name, which reads
+/// the same wherever the Markdown is rendered
+pub fn color_badge(color: HighlightColor) -> String {
+    let heart = match color {
+        HighlightColor::Yellow => "💛",
+        HighlightColor::Blue => "💙",
+        HighlightColor::Pink => "🩷",
+        HighlightColor::Orange => "🧡",
+    };
+    format!("{} {}", heart, color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    const CLIPPINGS: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00
+
+Classic.
+==========";
+
+    struct CountExporter;
+
+    impl Exporter for CountExporter {
+        fn name(&self) -> &str {
+            "count"
+        }
+
+        fn extension(&self) -> &str {
+            "txt"
+        }
+
+        fn export(&self, library: &Library, out: &mut dyn Write) -> Result<(), ExportError> {
+            write!(out, "{}", library.clippings().count())?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let library = Library::new(parse_clippings(CLIPPINGS).unwrap());
+
+        let mut registry = ExporterRegistry::with_builtin();
+        registry.register(Box::new(CountExporter));
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            vec!["count", "json", "kindle", "marginalia", "markdown", "yaml"]
+        );
+
+        let mut out = Vec::new();
+        registry
+            .get("count")
+            .unwrap()
+            .export(&library, &mut out)
+            .unwrap();
+        assert_eq!(out, b"2");
+        assert!(registry.get("docx").is_err());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_export_async() {
+        let library = Arc::new(Library::new(parse_clippings(CLIPPINGS).unwrap()));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let out = runtime
+            .block_on(export_async(Arc::new(CountExporter), library))
+            .unwrap();
+        assert_eq!(out, b"2");
+    }
+
+    #[test]
+    fn test_write_file() {
+        let first = Library::new(
+            parse_clippings(
+                "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========",
+            )
+            .unwrap(),
+        );
+        let second = Library::new(
+            parse_clippings(
+                "\
+Dune (Frank Herbert)
+- Your Highlight on page 2 | Location 20-22 | Added on Tuesday, 2 January 2024 10:00:00
+
+The spice must flow.
+==========",
+            )
+            .unwrap(),
+        );
+        let path = std::env::temp_dir().join(format!("kindlr-export-{}.txt", std::process::id()));
+
+        // Two runs of --since-last add up to a file that reads as both
+        write_file(&KindleExporter, &first, &path, false).unwrap();
+        write_file(&KindleExporter, &second, &path, true).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(parse_clippings(&text).unwrap().len(), 2);
+
+        write_file(&JsonExporter, &first, &path, false).unwrap();
+        assert!(write_file(&JsonExporter, &second, &path, true).is_err());
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["books"][0]["clippings"].as_array().unwrap().len(), 1);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_yaml_exporter() {
+        let library = Library::new(parse_clippings(CLIPPINGS).unwrap());
+
+        // YAML reads back as the same document as JSON
+        let mut json = Vec::new();
+        let mut yaml = Vec::new();
+        JsonExporter.export(&library, &mut json).unwrap();
+        YamlExporter.export(&library, &mut yaml).unwrap();
+        assert_eq!(
+            serde_yaml::from_slice::<serde_json::Value>(&yaml).unwrap(),
+            serde_json::from_slice::<serde_json::Value>(&json).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_kindle_exporter() {
+        let library = Library::new(
+            parse_clippings(
+                "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========",
+            )
+            .unwrap(),
+        );
+
+        let mut out = Vec::new();
+        KindleExporter.export(&library, &mut out).unwrap();
+        assert_eq!(
+            out,
+            "\u{feff}Dune (Frank Herbert)\r\n- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00\r\n\r\nFear is the mind-killer.\r\n==========\r\n".as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_kindle_export_byte_order_mark() {
+        let clippings = parse_clippings(&format!("\u{feff}{}", CLIPPINGS)).unwrap();
+        let ids: Vec<String> = clippings.iter().map(Clipping::id).collect();
+        assert_eq!(clippings[0].book_title, "Dune");
+
+        // Exporting an export gives the same file and the same clippings
+        let mut first = Vec::new();
+        KindleExporter
+            .export(&Library::new(clippings), &mut first)
+            .unwrap();
+        let reparsed = parse_clippings(std::str::from_utf8(&first).unwrap()).unwrap();
+        let mut second = Vec::new();
+        KindleExporter
+            .export(&Library::new(reparsed.clone()), &mut second)
+            .unwrap();
+        assert_eq!(first, second);
+        assert!(first.starts_with("\u{feff}D".as_bytes()));
+        assert_eq!(reparsed.iter().map(Clipping::id).collect::<Vec<_>>(), ids);
+
+        // Appending leaves out the byte order mark the file already starts with
+        let path =
+            std::env::temp_dir().join(format!("kindlr-export-bom-{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let library = Library::new(reparsed);
+        write_file(&KindleExporter, &library, &path, true).unwrap();
+        write_file(&KindleExporter, &library, &path, true).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(text.matches('\u{feff}').count(), 1);
+        let appended = parse_clippings(&text).unwrap();
+        assert_eq!(
+            appended.iter().map(Clipping::id).collect::<Vec<_>>(),
+            [ids.clone(), ids].concat()
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_kindle_entry_languages() {
+        for (contents, language) in [
+            (
+                "\
+A Pál utcai fiúk (Molnár Ferenc)
+- Kiemelés a(z) 12. oldalon | Hely: 150-152 | Hozzáadva: 2024. március 4., hétfő 9:05:10
+
+Nemecsek Ernő.
+==========",
+                Language::Hungarian,
+            ),
+            (
+                "\
+Válka s mloky (Karel Čapek)
+- Záložka na stránce 9 | Pozice 120 | Přidáno: úterý 1. října 2024 7:00:00
+
+
+==========",
+                Language::Czech,
+            ),
+            (
+                "\
+\u{2067}Dune\u{2069} (פרנק הרברט)
+- הסימון שלך בעמוד 12 | מיקום \u{200e}150-152 | נוסף ביום שני, 4 במרץ 2024 09:05:10
+
+הפחד הוא רוצח הדעת.
+==========",
+                Language::Hebrew,
+            ),
+        ] {
+            // Written back in the same language, they read the same
+            let clipping = &parse_clippings(contents).unwrap()[0];
+            let again = parse_clippings(&kindle_entry(clipping, language)).unwrap();
+            assert_eq!(again[0].id(), clipping.id());
+        }
+    }
+
+    #[test]
+    fn test_markdown_exporter() {
+        let mut library = Library::new(parse_clippings(CLIPPINGS).unwrap());
+
+        let mut out = Vec::new();
+        MarkdownExporter.export(&library, &mut out).unwrap();
+        let markdown = String::from_utf8(out).unwrap();
+        assert!(markdown.starts_with("# Dune\n\n*Frank Herbert*\n\n> Fear is the mind-killer."));
+        assert!(markdown.contains("**Note:** Classic."));
+
+        library.books[0].asin = Some("B00B7NPRY8".to_string());
+        let entry = markdown_entry(
+            &library.books[0],
+            &library.books[0].clippings[0],
+            &DateFormat::Date,
+        )
+        .unwrap();
+        assert!(entry.contains(
+            "— [Location 10-12](kindle://book?action=open&asin=B00B7NPRY8&location=10) (~"
+        ));
+        assert!(entry.ends_with(", 2024-01-01\n"));
+        library.books[0].clippings[0].color = Some(HighlightColor::Pink);
+        let entry = markdown_entry(
+            &library.books[0],
+            &library.books[0].clippings[0],
+            &DateFormat::Date,
+        )
+        .unwrap();
+        assert!(entry.ends_with(", 2024-01-01 · 🩷 pink\n"));
+        assert!(
+            markdown_heading(&library.books[0], Some("/home/me/covers/a b.jpg"))
+                .ends_with("*Frank Herbert* · [Amazon](https://www.amazon.com/dp/B00B7NPRY8)\n\n![Cover of Dune](</home/me/covers/a b.jpg>)\n")
+        );
+    }
+
+    #[test]
+    fn test_marginalia_exporter() {
+        let library = Library::new(parse_clippings(CLIPPINGS).unwrap());
+
+        let mut out = Vec::new();
+        MarginaliaExporter.export(&library, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "# Dune\n\n*Frank Herbert*\n\nClassic.\n\n> Fear is the mind-killer.\n>\n> — Location 10-12\n"
+        );
+    }
+}
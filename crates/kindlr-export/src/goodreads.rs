@@ -0,0 +1,180 @@
+use chrono::NaiveDate;
+use std::fs;
+use std::path::Path;
+
+use crate::export::ExportError;
+use crate::library::Library;
+use crate::parser;
+
+/// A book from a Goodreads library export (My Books > Import and export)
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoodreadsBook {
+    pub title: String,
+    pub author: String,
+    /// 1 to 5 stars, `None` when unrated
+    pub rating: Option<u8>,
+    /// The exclusive shelf (read, currently-reading, ...) followed by any others
+    pub shelves: Vec<String>,
+    pub date_read: Option<NaiveDate>,
+}
+
+/// Read a Goodreads library export CSV
+pub fn read(path: &Path) -> Result<Vec<GoodreadsBook>, ExportError> {
+    let contents = fs::read_to_string(path)?;
+    parse(contents.trim_start_matches('\u{feff}'))
+}
+
+pub fn parse(contents: &str) -> Result<Vec<GoodreadsBook>, ExportError> {
+    let csv_error = |error: csv::Error| ExportError::Goodreads(error.to_string());
+
+    let mut reader = csv::Reader::from_reader(contents.as_bytes());
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let (title, author, rating, date_read, exclusive_shelf, bookshelves) = (
+        column("Title"),
+        column("Author"),
+        column("My Rating"),
+        column("Date Read"),
+        column("Exclusive Shelf"),
+        column("Bookshelves"),
+    );
+
+    if title.is_none() || author.is_none() {
+        return Err(ExportError::Goodreads(
+            "missing Title or Author column".to_string(),
+        ));
+    }
+
+    let mut books = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(csv_error)?;
+        let get = |column: Option<usize>| {
+            column
+                .and_then(|i| record.get(i))
+                .unwrap_or_default()
+                .trim()
+        };
+
+        let mut shelves: Vec<String> = Vec::new();
+        for shelf in std::iter::once(get(exclusive_shelf)).chain(get(bookshelves).split(',')) {
+            let shelf = shelf.trim();
+            if !shelf.is_empty() && !shelves.iter().any(|known| known == shelf) {
+                shelves.push(shelf.to_string());
+            }
+        }
+
+        books.push(GoodreadsBook {
+            title: get(title).to_string(),
+            author: get(author).to_string(),
+            rating: get(rating).parse().ok().filter(|stars| *stars > 0),
+            shelves,
+            date_read: NaiveDate::parse_from_str(get(date_read), "%Y/%m/%d").ok(),
+        });
+    }
+
+    Ok(books)
+}
+
+/// A rating as filled and empty stars, e.g. "★★★★☆"
+pub fn stars(rating: u8) -> String {
+    let rating = usize::from(rating.min(5));
+    format!("{}{}", "★".repeat(rating), "☆".repeat(5 - rating))
+}
+
+/// Attach ratings, shelves and read dates to the matching books in `library`,
+/// returning how many books matched
+///
+/// Titles match ignoring case, punctuation, subtitles and series such as
+/// "(Dune, #1)"; authors match by surname.
+pub fn correlate(library: &mut Library, goodreads: &[GoodreadsBook]) -> usize {
+    let mut matched = 0;
+
+    for book in &mut library.books {
+        let title = match_title(&book.title);
+        let surname = surname(&book.author);
+
+        let found = goodreads.iter().find(|candidate| {
+            match_title(&candidate.title) == title
+                && (surname.is_empty() || surname == self::surname(&candidate.author))
+        });
+
+        if let Some(found) = found {
+            book.rating = found.rating;
+            book.shelves = found.shelves.clone();
+            book.date_read = found.date_read;
+            matched += 1;
+        }
+    }
+
+    matched
+}
+
+fn match_title(title: &str) -> String {
+    let title = title.split([':', '(']).next().unwrap_or_default();
+    words(title)
+}
+
+fn surname(author: &str) -> String {
+    words(&parser::normalize_author(author))
+        .rsplit(' ')
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Lowercase alphanumeric words separated by single spaces
+fn words(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    #[test]
+    fn test_correlate() {
+        let goodreads = parse(
+            "\
+Book Id,Title,Author,Author l-f,My Rating,Date Read,Bookshelves,Exclusive Shelf
+1,\"Dune (Dune, #1)\",Frank Herbert,\"Herbert, Frank\",5,2024/03/01,\"sci-fi, favorites\",read
+2,Meditations,Marcus Aurelius,\"Aurelius, Marcus\",0,,,to-read
+",
+        )
+        .unwrap();
+
+        assert_eq!(goodreads[0].rating, Some(5));
+        assert_eq!(goodreads[0].shelves, vec!["read", "sci-fi", "favorites"]);
+        assert_eq!(goodreads[1].rating, None);
+        assert_eq!(goodreads[1].date_read, None);
+
+        let mut library = Library::new(
+            parse_clippings(
+                "\
+Dune: Deluxe Edition (Herbert, Frank)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Meditations (Seneca)
+- Your Highlight on page 5 | Location 70-71 | Added on Sunday, 5 January 2025 09:00:00
+
+Be one.
+==========",
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(correlate(&mut library, &goodreads), 1);
+        assert_eq!(library.books[0].rating, Some(5));
+        assert_eq!(
+            library.books[0].date_read,
+            NaiveDate::from_ymd_opt(2024, 3, 1)
+        );
+        assert!(library.books[1].shelves.is_empty());
+    }
+}
@@ -0,0 +1,235 @@
+use chrono::Datelike;
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::parser::Clipping;
+
+/// Field to sort clippings by
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Date,
+    Book,
+    /// Where in the book, for the order passages come in it; locations in
+    /// different books can't be compared, so books are kept together
+    Location,
+    Length,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "date" => Ok(SortKey::Date),
+            "book" => Ok(SortKey::Book),
+            "location" => Ok(SortKey::Location),
+            "length" => Ok(SortKey::Length),
+            _ => Err(format!("Invalid sort key: {}", s)),
+        }
+    }
+}
+
+impl SortKey {
+    /// Parse a comma-separated list of sort keys such as "book,location"
+    pub fn parse_list(s: &str) -> Result<Vec<SortKey>, String> {
+        s.split(',').map(|key| key.trim().parse()).collect()
+    }
+
+    pub fn compare(self, a: &Clipping, b: &Clipping) -> Ordering {
+        match self {
+            SortKey::Date => a.timestamp().cmp(&b.timestamp()),
+            SortKey::Book => a
+                .book_title
+                .to_lowercase()
+                .cmp(&b.book_title.to_lowercase()),
+            SortKey::Location => SortKey::Book.compare(a, b).then_with(|| {
+                (a.location.start, a.location.end).cmp(&(b.location.start, b.location.end))
+            }),
+            SortKey::Length => length(a).cmp(&length(b)),
+        }
+    }
+}
+
+fn length(clipping: &Clipping) -> usize {
+    clipping
+        .content
+        .as_deref()
+        .map_or(0, |content| content.chars().count())
+}
+
+/// Compare by each key in turn, moving to the next one on ties
+pub fn compare(a: &Clipping, b: &Clipping, keys: &[SortKey]) -> Ordering {
+    keys.iter()
+        .map(|key| key.compare(a, b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Stable sort by `keys`, so clippings that tie on every key keep their order
+pub fn sort(clippings: &mut [Clipping], keys: &[SortKey]) {
+    clippings.sort_by(|a, b| compare(a, b, keys));
+}
+
+/// Clippings on a page given by `--page` without `--limit`
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+
+/// Part of the sorted clippings to list, from `--offset` and `--limit`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Page {
+    /// Clippings skipped before the page
+    pub offset: usize,
+    /// Clippings on the page, or all that are left
+    pub limit: Option<usize>,
+}
+
+impl Page {
+    /// Page `number`, counting from 1, of pages `size` clippings long
+    pub fn numbered(number: usize, size: usize) -> Self {
+        Page {
+            offset: number.saturating_sub(1).saturating_mul(size),
+            limit: Some(size),
+        }
+    }
+
+    /// Whether the page leaves out any of the clippings
+    pub fn is_partial(&self) -> bool {
+        self.offset > 0 || self.limit.is_some()
+    }
+
+    /// Indices of the page's clippings among `len` clippings, empty past the
+    /// end
+    pub fn range(&self, len: usize) -> Range<usize> {
+        let start = self.offset.min(len);
+        let end = self
+            .limit
+            .map_or(len, |limit| start.saturating_add(limit).min(len));
+        start..end
+    }
+}
+
+/// How to group clippings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    Book,
+    Author,
+    Month,
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "book" => Ok(GroupBy::Book),
+            "author" => Ok(GroupBy::Author),
+            "month" => Ok(GroupBy::Month),
+            _ => Err(format!("Invalid grouping: {}", s)),
+        }
+    }
+}
+
+/// Clippings sharing a book, author or month, in their original order
+#[derive(Debug)]
+pub struct Group<'a> {
+    pub key: String,
+    pub clippings: Vec<&'a Clipping>,
+}
+
+/// Group clippings, ordering groups by key
+pub fn group(clippings: &[Clipping], by: GroupBy) -> Vec<Group<'_>> {
+    let mut groups: BTreeMap<String, Vec<&Clipping>> = BTreeMap::new();
+    for clipping in clippings {
+        let key = match by {
+            GroupBy::Book => clipping.book_title.clone(),
+            GroupBy::Author => clipping.author.clone(),
+            GroupBy::Month => clipping.timestamp().map_or_else(
+                || "Unknown".to_string(),
+                |timestamp| format!("{}-{:02}", timestamp.year(), timestamp.month()),
+            ),
+        };
+        groups.entry(key).or_default().push(clipping);
+    }
+
+    groups
+        .into_iter()
+        .map(|(key, clippings)| Group { key, clippings })
+        .collect()
+}
+
+pub fn group_by_book(clippings: &[Clipping]) -> Vec<Group<'_>> {
+    group(clippings, GroupBy::Book)
+}
+
+pub fn group_by_author(clippings: &[Clipping]) -> Vec<Group<'_>> {
+    group(clippings, GroupBy::Author)
+}
+
+/// Groups keyed "YYYY-MM", with undated clippings under "Unknown"
+pub fn group_by_month(clippings: &[Clipping]) -> Vec<Group<'_>> {
+    group(clippings, GroupBy::Month)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    #[test]
+    fn test_sort_and_group() {
+        let mut clippings = parse_clippings(
+            "\
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Sunday, 5 January 2025 09:00:00
+
+Waste no more time arguing about what a good man should be. Be one.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 2 | Location 20-21 | Added on Tuesday, 2 January 2024 10:00:00
+
+Short.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Wednesday, 3 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========",
+        )
+        .unwrap();
+
+        sort(&mut clippings, &[SortKey::Date]);
+        assert_eq!(clippings[0].location.start, 20);
+
+        sort(&mut clippings, &[SortKey::Book, SortKey::Location]);
+        let starts: Vec<u32> = clippings.iter().map(|c| c.location.start).collect();
+        assert_eq!(starts, vec![10, 20, 70]);
+        sort(&mut clippings, &[SortKey::Date, SortKey::Location]);
+        sort(&mut clippings, &[SortKey::Location]);
+        assert_eq!(clippings[0].location.start, 10);
+
+        sort(&mut clippings, &[SortKey::Length]);
+        assert_eq!(clippings[0].content.as_deref(), Some("Short."));
+        assert_eq!(
+            SortKey::parse_list("book, date"),
+            Ok(vec![SortKey::Book, SortKey::Date])
+        );
+
+        let by_book = group_by_book(&clippings);
+        assert_eq!(by_book.len(), 2);
+        assert_eq!(by_book[0].key, "Dune");
+        assert_eq!(by_book[0].clippings.len(), 2);
+
+        let by_month = group_by_month(&clippings);
+        let keys: Vec<&str> = by_month.iter().map(|group| group.key.as_str()).collect();
+        assert_eq!(keys, vec!["2024-01", "2025-01"]);
+        assert_eq!(group_by_author(&clippings)[1].key, "Marcus Aurelius");
+
+        assert_eq!(Page::default().range(3), 0..3);
+        assert_eq!(Page::numbered(2, 2).range(3), 2..3);
+        assert_eq!(Page::numbered(3, 2).range(3), 3..3);
+        assert!(!Page::default().is_partial());
+    }
+}
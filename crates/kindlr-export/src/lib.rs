@@ -0,0 +1,8 @@
+pub mod dates;
+pub mod export;
+pub mod goodreads;
+pub mod group;
+pub mod library;
+pub mod sections;
+
+use kindlr_core::parser;
@@ -0,0 +1,221 @@
+use chrono::NaiveDate;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::dates::DateFormat;
+use crate::group::{self, SortKey};
+use crate::parser::{Clipping, Location};
+
+/// A book and the clippings made in it
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct Book {
+    pub title: String,
+    pub author: String,
+    /// Number of locations in the book, when known from elsewhere
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<u32>,
+    /// 1 to 5 stars, from `goodreads::correlate`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shelves: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_read: Option<NaiveDate>,
+    /// ISBN, cover and more, from `enrich::Enricher`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+    /// Amazon identifier of the Kindle edition, for links back to the book
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asin: Option<String>,
+    pub clippings: Vec<Clipping>,
+}
+
+impl Book {
+    /// `length`, or else the furthest location clipped
+    pub fn total_locations(&self) -> Option<u32> {
+        self.length.or_else(|| {
+            self.clippings
+                .iter()
+                .map(|clipping| clipping.location.last())
+                .max()
+        })
+    }
+
+    /// Approximate percentage through the book of `location`
+    pub fn progress_of(&self, location: &Location) -> Option<f64> {
+        location.progress(self.total_locations()?)
+    }
+}
+
+/// Details of a book from a catalogue
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isbn: Option<String>,
+    /// Amazon identifier of the Kindle edition
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asin: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_url: Option<String>,
+    /// Downloaded copy of the cover, from `CoverCache`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subjects: Vec<String>,
+}
+
+impl fmt::Display for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let na = || "N/A".to_string();
+        write!(
+            f,
+            "  ISBN: {}\n  Published: {}\n  Cover: {}\n  Subjects: {}",
+            self.isbn.clone().unwrap_or_else(na),
+            self.year.map_or_else(na, |year| year.to_string()),
+            self.cover_url.clone().unwrap_or_else(na),
+            if self.subjects.is_empty() {
+                na()
+            } else {
+                self.subjects.join(", ")
+            }
+        )
+    }
+}
+
+/// Clippings grouped into books, in the order books first appear
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct Library {
+    pub books: Vec<Book>,
+    /// How Markdown shows dates
+    #[serde(skip)]
+    pub date_format: DateFormat,
+    /// Gap in locations splitting books into sections in Markdown, see
+    /// `sections::sections`
+    #[serde(skip)]
+    pub section_gap: Option<u32>,
+}
+
+impl Library {
+    pub fn new(clippings: Vec<Clipping>) -> Self {
+        let mut books: Vec<Book> = Vec::new();
+        let mut index: HashMap<(String, String), usize> = HashMap::new();
+
+        for clipping in clippings {
+            let key = (clipping.book_title.clone(), clipping.author.clone());
+            let i = *index.entry(key).or_insert_with(|| {
+                books.push(Book {
+                    title: clipping.book_title.clone(),
+                    author: clipping.author.clone(),
+                    length: None,
+                    rating: None,
+                    shelves: Vec::new(),
+                    date_read: None,
+                    asin: None,
+                    metadata: None,
+                    clippings: Vec::new(),
+                });
+                books.len() - 1
+            });
+            books[i].clippings.push(clipping);
+        }
+
+        Library {
+            books,
+            date_format: DateFormat::default(),
+            section_gap: None,
+        }
+    }
+
+    /// Stably sort each book's clippings by `keys`, by location for the
+    /// order passages come in the book; books keep their order
+    pub fn sort(&mut self, keys: &[SortKey]) {
+        for book in &mut self.books {
+            group::sort(&mut book.clippings, keys);
+        }
+    }
+
+    /// Set the length in locations of each book in `lengths`, by title
+    pub fn set_lengths(&mut self, lengths: &BTreeMap<String, u32>) {
+        for book in &mut self.books {
+            if let Some(&length) = lengths.get(&book.title) {
+                book.length = Some(length);
+            }
+        }
+    }
+
+    /// Every clipping, book by book
+    pub fn clippings(&self) -> impl Iterator<Item = &Clipping> {
+        self.books.iter().flat_map(|book| &book.clippings)
+    }
+}
+
+/// The highlight a note was made on: the one in the same book ending where
+/// the note sits
+pub fn annotated_highlight<'a>(note: &Clipping, clippings: &'a [Clipping]) -> Option<&'a Clipping> {
+    clippings.iter().find(|clipping| {
+        clipping.clipping_type.is_highlight()
+            && clipping.book_title == note.book_title
+            && clipping.author == note.author
+            && clipping.location.last() == note.location.start
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    #[test]
+    fn test_library() {
+        let clippings = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Sunday, 5 January 2025 09:00:00
+
+Be one.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00
+
+Classic.
+==========",
+        )
+        .unwrap();
+
+        let mut library = Library::new(clippings);
+
+        assert_eq!(library.books.len(), 2);
+        assert_eq!(library.books[0].title, "Dune");
+        assert_eq!(library.books[0].clippings.len(), 2);
+        assert_eq!(library.clippings().count(), 3);
+
+        library.sort(&[SortKey::Location]);
+        assert_eq!(library.books[0].clippings[0].location.start, 10);
+        library.sort(&[SortKey::Date, SortKey::Length]);
+        assert_eq!(library.books[0].clippings[1].location.start, 12);
+        assert_eq!(library.books[1].title, "Meditations");
+
+        let location = Location {
+            start: 6,
+            end: Some(9),
+        };
+        assert_eq!(library.books[0].progress_of(&location), Some(75.0));
+        library.set_lengths(&BTreeMap::from([("Dune".to_string(), 18)]));
+        assert_eq!(library.books[0].progress_of(&location), Some(50.0));
+        assert_eq!(library.books[1].length, None);
+    }
+}
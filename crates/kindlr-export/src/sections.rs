@@ -0,0 +1,127 @@
+use crate::parser::{Clipping, ClippingType};
+
+/// Locations without a highlight after which a new section starts
+pub const DEFAULT_SECTION_GAP: u32 = 300;
+
+/// A stretch of a book read without a long gap or a bookmark, standing in
+/// for a chapter
+#[derive(Debug)]
+pub struct Section<'a> {
+    /// Numbered from 1 in the order of the book
+    pub number: usize,
+    /// Clippings in the order they come in the book
+    pub clippings: Vec<&'a Clipping>,
+}
+
+/// A book's clippings split into sections, guessing at its chapters
+///
+/// A section ends where more than `gap` locations pass without a clipping,
+/// or at a bookmark coming after highlights or notes, as readers tend to
+/// bookmark the start of a chapter. Sections of nothing but bookmarks are
+/// left out, and articles, which have no locations, make a single section.
+pub fn sections(clippings: &[Clipping], gap: u32) -> Vec<Section<'_>> {
+    let mut ordered: Vec<&Clipping> = clippings.iter().collect();
+    if ordered
+        .iter()
+        .any(|clipping| clipping.clipping_type == ClippingType::ArticleClip)
+    {
+        return vec![Section {
+            number: 1,
+            clippings: ordered,
+        }];
+    }
+    ordered.sort_by_key(|clipping| (clipping.location.start, clipping.location.end));
+
+    let mut parts: Vec<Vec<&Clipping>> = Vec::new();
+    let mut end = None;
+    for clipping in ordered {
+        let bookmark = clipping.clipping_type == ClippingType::Bookmark;
+        let starts_section = match (parts.last(), end) {
+            (Some(part), Some(end)) => {
+                clipping.location.start.saturating_sub(end) > gap
+                    || (bookmark && part.iter().any(|clipping| is_read(clipping)))
+            }
+            _ => true,
+        };
+        if starts_section {
+            parts.push(Vec::new());
+        }
+        parts.last_mut().expect("a section").push(clipping);
+
+        let clipping_end = clipping.location.end.unwrap_or(clipping.location.start);
+        end = Some(end.map_or(clipping_end, |end: u32| end.max(clipping_end)));
+    }
+
+    parts
+        .into_iter()
+        .filter(|part| part.iter().any(|clipping| is_read(clipping)))
+        .enumerate()
+        .map(|(i, clippings)| Section {
+            number: i + 1,
+            clippings,
+        })
+        .collect()
+}
+
+/// Whether the clipping is a highlight or note rather than a bookmark
+fn is_read(clipping: &Clipping) -> bool {
+    clipping.clipping_type != ClippingType::Bookmark
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    #[test]
+    fn test_sections() {
+        let clippings = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 40 | Location 900-905 | Added on Tuesday, 2 January 2024 10:00:00
+
+Far along.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00
+
+Classic.
+==========
+Dune (Frank Herbert)
+- Your Bookmark on page 5 | Location 100 | Added on Monday, 1 January 2024 11:00:00
+
+
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 6 | Location 120-125 | Added on Monday, 1 January 2024 11:10:00
+
+The spice must flow.
+==========
+Dune (Frank Herbert)
+- Your Bookmark on page 30 | Location 700 | Added on Monday, 1 January 2024 12:00:00
+
+
+==========",
+        )
+        .unwrap();
+
+        let sections = sections(&clippings, DEFAULT_SECTION_GAP);
+        let starts: Vec<Vec<u32>> = sections
+            .iter()
+            .map(|section| {
+                section
+                    .clippings
+                    .iter()
+                    .map(|clipping| clipping.location.start)
+                    .collect()
+            })
+            .collect();
+        assert_eq!(starts, [vec![10, 12], vec![100, 120], vec![700, 900]]);
+        assert_eq!(sections[2].number, 3);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod store;
+
+use kindlr_core::parser;
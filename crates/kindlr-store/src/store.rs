@@ -0,0 +1,295 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+use crate::parser::Clipping;
+
+#[derive(Error, Debug)]
+pub enum StoreError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    /// Neither `KINDLR_HOME` nor `HOME` is set
+    #[error("Cannot locate home directory, set KINDLR_HOME")]
+    NoHome,
+    #[error("Store error: {0}")]
+    Invalid(String),
+}
+
+const STORE_FILE: &str = "store.json";
+
+/// Directory holding the local store and user configuration
+pub fn home_dir() -> Result<PathBuf, StoreError> {
+    if let Some(dir) = env::var_os("KINDLR_HOME") {
+        return Ok(PathBuf::from(dir));
+    }
+
+    env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join(".kindlr"))
+        .ok_or(StoreError::NoHome)
+}
+
+/// A single edit of a clipping's content
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Revision {
+    pub content: String,
+    /// Seconds since the Unix epoch
+    pub edited_at: u64,
+}
+
+/// Original content of a clipping and every edit made to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditHistory {
+    pub original: Option<String>,
+    pub revisions: Vec<Revision>,
+}
+
+impl EditHistory {
+    /// Content of the latest revision
+    pub fn current(&self) -> Option<&str> {
+        self.revisions
+            .last()
+            .map(|revision| revision.content.as_str())
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoreData {
+    #[serde(default)]
+    edits: BTreeMap<String, EditHistory>,
+    #[serde(default)]
+    favorites: BTreeSet<String>,
+    /// Id each destination gave the clippings pushed to it, by destination name
+    #[serde(default)]
+    pushed: BTreeMap<String, BTreeMap<String, String>>,
+    /// Hash of the content each clipping had when last exported, by export
+    /// destination
+    #[serde(default)]
+    exported: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Local state kept alongside the clippings file, keyed by clipping id
+pub struct Store {
+    path: PathBuf,
+    data: StoreData,
+}
+
+impl Store {
+    /// Open the store in the kindlr home directory
+    pub fn open() -> Result<Self, StoreError> {
+        Self::open_at(home_dir()?.join(STORE_FILE))
+    }
+
+    /// Open the store at a specific path, starting empty if it doesn't exist
+    pub fn open_at(path: impl Into<PathBuf>) -> Result<Self, StoreError> {
+        let path = path.into();
+
+        let data = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|error| StoreError::Invalid(format!("{}: {}", path.display(), error)))?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => StoreData::default(),
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(Store { path, data })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn save(&self) -> Result<(), StoreError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let json = serde_json::to_string_pretty(&self.data)
+            .map_err(|error| StoreError::Invalid(error.to_string()))?;
+        fs::write(&self.path, json)?;
+
+        Ok(())
+    }
+
+    pub fn history(&self, id: &str) -> Option<&EditHistory> {
+        self.data.edits.get(id)
+    }
+
+    /// Record new content for a clipping, keeping its original text on first edit
+    pub fn record_edit(&mut self, clipping: &Clipping, content: String) {
+        let edited_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        self.data
+            .edits
+            .entry(clipping.id())
+            .or_insert_with(|| EditHistory {
+                original: clipping.content.clone(),
+                revisions: Vec::new(),
+            })
+            .revisions
+            .push(Revision { content, edited_at });
+    }
+
+    pub fn is_favorite(&self, id: &str) -> bool {
+        self.data.favorites.contains(id)
+    }
+
+    pub fn favorites(&self) -> impl Iterator<Item = &str> {
+        self.data.favorites.iter().map(String::as_str)
+    }
+
+    /// Star or unstar a clipping, returning whether anything changed
+    pub fn set_favorite(&mut self, id: &str, favorite: bool) -> bool {
+        if favorite {
+            self.data.favorites.insert(id.to_string())
+        } else {
+            self.data.favorites.remove(id)
+        }
+    }
+
+    /// Id `destination` gave the clipping when it was pushed there
+    pub fn remote_id(&self, destination: &str, id: &str) -> Option<&str> {
+        self.data
+            .pushed
+            .get(destination)?
+            .get(id)
+            .map(String::as_str)
+    }
+
+    pub fn record_push(&mut self, destination: &str, id: &str, remote_id: String) {
+        self.data
+            .pushed
+            .entry(destination.to_string())
+            .or_default()
+            .insert(id.to_string(), remote_id);
+    }
+
+    /// Whether the clipping was exported to `destination` as it is now
+    pub fn is_exported(&self, destination: &str, clipping: &Clipping) -> bool {
+        self.data
+            .exported
+            .get(destination)
+            .and_then(|exported| exported.get(&clipping.id()))
+            .is_some_and(|hash| *hash == content_hash(clipping))
+    }
+
+    /// Whether the clipping was exported to `destination` at all, as it is
+    /// now or before it was edited
+    pub fn was_exported(&self, destination: &str, clipping: &Clipping) -> bool {
+        self.data
+            .exported
+            .get(destination)
+            .is_some_and(|exported| exported.contains_key(&clipping.id()))
+    }
+
+    pub fn record_export(&mut self, destination: &str, clipping: &Clipping) {
+        self.data
+            .exported
+            .entry(destination.to_string())
+            .or_default()
+            .insert(clipping.id(), content_hash(clipping));
+    }
+
+    /// Replace the content of edited clippings with their latest revision
+    pub fn apply_edits(&self, clippings: &mut [Clipping]) {
+        for clipping in clippings {
+            if let Some(content) = self.history(&clipping.id()).and_then(EditHistory::current) {
+                clipping.content = Some(content.to_string());
+            }
+        }
+    }
+}
+
+/// FNV-1a hash of the clipping's content, to tell when it was edited
+fn content_hash(clipping: &Clipping) -> String {
+    let content = clipping.content.as_deref().unwrap_or_default();
+    let hash = content.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_clipping() -> Clipping {
+        Clipping::from_text(
+            "\
+Book Title (Author Name)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 26 August 2025 12:57:30
+
+Cut mid-sen",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_edits_keep_original_and_apply_latest() {
+        let path = env::temp_dir().join(format!("kindlr-store-{}.json", std::process::id()));
+        let clipping = sample_clipping();
+
+        let mut store = Store::open_at(&path).unwrap();
+        store.record_edit(&clipping, "Cut mid-sentence.".to_string());
+        store.record_edit(&clipping, "Cut mid-sentence!".to_string());
+        store.save().unwrap();
+
+        let store = Store::open_at(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let history = store.history(&clipping.id()).unwrap();
+        assert_eq!(history.original.as_deref(), Some("Cut mid-sen"));
+        assert_eq!(history.revisions.len(), 2);
+
+        let mut clippings = vec![sample_clipping()];
+        store.apply_edits(&mut clippings);
+        assert_eq!(clippings[0].content.as_deref(), Some("Cut mid-sentence!"));
+    }
+
+    #[test]
+    fn test_favorites() {
+        let path = env::temp_dir().join(format!("kindlr-favorites-{}.json", std::process::id()));
+        let id = sample_clipping().id();
+
+        let mut store = Store::open_at(&path).unwrap();
+        assert!(store.set_favorite(&id, true));
+        assert!(!store.set_favorite(&id, true));
+        store.save().unwrap();
+
+        let mut store = Store::open_at(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(store.is_favorite(&id));
+        assert!(store.set_favorite(&id, false));
+        assert!(!store.is_favorite(&id));
+    }
+
+    #[test]
+    fn test_exported() {
+        let path = env::temp_dir().join(format!("kindlr-exported-{}.json", std::process::id()));
+        let mut clipping = sample_clipping();
+
+        let mut store = Store::open_at(&path).unwrap();
+        assert!(!store.is_exported("markdown:notes.md", &clipping));
+        store.record_export("markdown:notes.md", &clipping);
+        store.save().unwrap();
+
+        let store = Store::open_at(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(store.is_exported("markdown:notes.md", &clipping));
+        assert!(!store.is_exported("json:-", &clipping));
+
+        clipping.content = Some("Cut mid-sentence.".to_string());
+        assert!(
+            !store.is_exported("markdown:notes.md", &clipping),
+            "an edited clipping is exported again"
+        );
+        assert!(store.was_exported("markdown:notes.md", &clipping));
+        assert!(!store.was_exported("json:-", &clipping));
+    }
+}
@@ -0,0 +1,88 @@
+#ifndef KINDLR_H
+#define KINDLR_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Kind of clipping
+ */
+typedef enum KindlrClippingType {
+  KINDLR_CLIPPING_TYPE_HIGHLIGHT = 0,
+  KINDLR_CLIPPING_TYPE_NOTE = 1,
+  KINDLR_CLIPPING_TYPE_BOOKMARK = 2,
+  KINDLR_CLIPPING_TYPE_ARTICLE_CLIP = 3,
+} KindlrClippingType;
+
+/**
+ * Opaque handle to parsed clippings
+ */
+typedef struct KindlrClippings KindlrClippings;
+
+/**
+ * A clipping whose strings are owned by its `KindlrClippings` handle
+ *
+ * `page` and `location_end` are 0 when absent, `content` is null when absent.
+ */
+typedef struct KindlrClipping {
+  const char *id;
+  KindlrClippingType clipping_type;
+  const char *book_title;
+  const char *author;
+  uint32_t page;
+  uint32_t location_start;
+  uint32_t location_end;
+  const char *datetime;
+  const char *content;
+} KindlrClipping;
+
+/**
+ * Parse `len` bytes of UTF-8 clippings text
+ *
+ * Returns null on failure; `kindlr_last_error` then describes the problem.
+ *
+ * # Safety
+ *
+ * `buffer` must point to `len` readable bytes.
+ */
+KindlrClippings *kindlr_parse(const uint8_t *buffer, uintptr_t len);
+
+/**
+ * Number of clippings behind the handle
+ *
+ * # Safety
+ *
+ * `handle` must come from `kindlr_parse` and not have been freed.
+ */
+uintptr_t kindlr_clippings_len(const KindlrClippings *handle);
+
+/**
+ * Clipping at `index`, or null when out of range
+ *
+ * # Safety
+ *
+ * `handle` must come from `kindlr_parse` and not have been freed.
+ */
+const KindlrClipping *kindlr_clippings_get(const KindlrClippings *handle, uintptr_t index);
+
+/**
+ * Free a handle and every string read from it
+ *
+ * # Safety
+ *
+ * `handle` must come from `kindlr_parse` and is invalid afterwards.
+ */
+void kindlr_clippings_free(KindlrClippings *handle);
+
+/**
+ * Message of the last failure on this thread, or null
+ *
+ * Valid until the next failing call on the same thread.
+ */
+const char *kindlr_last_error(void);
+
+#endif  /* KINDLR_H */
@@ -0,0 +1,186 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::analyze::similarity;
+use crate::parser::Clipping;
+
+/// Titles with at least this similarity are suggested as aliases by default
+pub const DEFAULT_ALIAS_THRESHOLD: f64 = 0.6;
+
+/// Give `clipping` the title and author `titles` and `authors` map its own
+/// to, returning whether either changed
+///
+/// Names are matched exactly and mapped once, so an alias of an alias isn't
+/// followed.
+pub fn apply(
+    clipping: &mut Clipping,
+    titles: &BTreeMap<String, String>,
+    authors: &BTreeMap<String, String>,
+) -> bool {
+    let mut changed = false;
+
+    if let Some(title) = titles.get(&clipping.book_title) {
+        clipping.book_title = title.clone();
+        changed = true;
+    }
+    if let Some(author) = authors.get(&clipping.author) {
+        clipping.author = author.clone();
+        changed = true;
+    }
+
+    changed
+}
+
+/// A title that looks like another name for a title with more clippings
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Suggestion {
+    pub alias: String,
+    pub title: String,
+    pub alias_clippings: usize,
+    pub title_clippings: usize,
+    /// 1.0 when the titles only differ after a colon or parenthesis
+    pub similarity: f64,
+}
+
+/// A line to paste under `[aliases]` in config.toml
+impl fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} = {:?}  # {} and {} clippings, {:.2} similar",
+            self.alias, self.title, self.alias_clippings, self.title_clippings, self.similarity
+        )
+    }
+}
+
+/// Pairs of titles likely to be the same book, most similar first
+///
+/// Titles are alike when they match up to a subtitle or edition in
+/// parentheses, as in "Dune" and "Dune: Deluxe Edition", or when their
+/// `analyze::similarity` is at least `threshold`. The title with fewer
+/// clippings is suggested as the alias, and no title is suggested twice.
+pub fn suggest(clippings: &[Clipping], threshold: f64) -> Vec<Suggestion> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for clipping in clippings {
+        *counts.entry(&clipping.book_title).or_default() += 1;
+    }
+    let titles: Vec<(&str, usize)> = counts.into_iter().collect();
+
+    let mut candidates = Vec::new();
+    for (i, &(a, a_count)) in titles.iter().enumerate() {
+        for &(b, b_count) in &titles[i + 1..] {
+            let score = if main_title(a) == main_title(b) {
+                1.0
+            } else {
+                similarity(a, b)
+            };
+            if score < threshold {
+                continue;
+            }
+
+            // Keep the title used most, or the plainer one when used as often
+            let ((alias, alias_count), (title, title_count)) =
+                if (b_count, a.len()) > (a_count, b.len()) {
+                    ((a, a_count), (b, b_count))
+                } else {
+                    ((b, b_count), (a, a_count))
+                };
+            candidates.push(Suggestion {
+                alias: alias.to_string(),
+                title: title.to_string(),
+                alias_clippings: alias_count,
+                title_clippings: title_count,
+                similarity: score,
+            });
+        }
+    }
+    candidates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+
+    let mut aliases = HashSet::new();
+    let mut kept = HashSet::new();
+    candidates
+        .into_iter()
+        .filter(|suggestion| {
+            let unused = !aliases.contains(&suggestion.alias)
+                && !kept.contains(&suggestion.alias)
+                && !aliases.contains(&suggestion.title);
+            if unused {
+                aliases.insert(suggestion.alias.clone());
+                kept.insert(suggestion.title.clone());
+            }
+            unused
+        })
+        .collect()
+}
+
+/// `title` lowercased, without a subtitle or parenthesized edition
+fn main_title(title: &str) -> String {
+    let end = title.find([':', '(', '[']).unwrap_or(title.len());
+    title[..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(['.', ',', '-', '–', '—'])
+        .trim_end()
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{ClippingType, Location};
+    use chrono::NaiveDate;
+
+    fn clipping(title: &str, author: &str) -> Clipping {
+        Clipping::new(
+            ClippingType::Highlight,
+            title.to_string(),
+            author.to_string(),
+            None,
+            Location {
+                start: 1,
+                end: None,
+            },
+            NaiveDate::from_ymd_opt(2024, 1, 1)
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .unwrap(),
+            Some("Fear is the mind-killer.".to_string()),
+        )
+    }
+
+    #[test]
+    fn test_aliases() {
+        let clippings = vec![
+            clipping("Dune", "Frank Herbert"),
+            clipping("Dune", "Frank Herbert"),
+            clipping("Dune: Deluxe Edition", "Herbert, Frank"),
+            clipping("Dune (Penguin Galaxy)", "Frank Herbert"),
+            clipping("Meditations", "Marcus Aurelius"),
+        ];
+
+        let suggestions = suggest(&clippings, DEFAULT_ALIAS_THRESHOLD);
+        let pairs: Vec<(&str, &str)> = suggestions
+            .iter()
+            .map(|suggestion| (suggestion.alias.as_str(), suggestion.title.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("Dune (Penguin Galaxy)", "Dune"),
+                ("Dune: Deluxe Edition", "Dune")
+            ]
+        );
+        assert_eq!(
+            suggestions[1].to_string(),
+            "\"Dune: Deluxe Edition\" = \"Dune\"  # 1 and 2 clippings, 1.00 similar"
+        );
+
+        let titles = BTreeMap::from([("Dune: Deluxe Edition".to_string(), "Dune".to_string())]);
+        let authors = BTreeMap::from([("Herbert, Frank".to_string(), "Frank Herbert".to_string())]);
+        let mut aliased = clippings[2].clone();
+        assert!(apply(&mut aliased, &titles, &authors));
+        assert_eq!(aliased.id(), clippings[0].id());
+        assert!(!apply(&mut aliased, &titles, &authors));
+    }
+}
@@ -0,0 +1,945 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::KindlrError;
+use crate::parser::Clipping;
+
+const STOPWORDS_EN: &str = "\
+a about above after again against all also am an and any are as at be because been before \
+being below between both but by can could did do does doing down during each even ever few \
+for from further had has have having he her here hers herself him himself his how i if in \
+into is it its itself just let like may me might more most much must my myself never no nor \
+not now of off on once one only or other ought our ours ourselves out over own per same shall \
+she should so some such than that the their theirs them themselves then there these they \
+this those through thus to too under until up upon us very was we were what when where \
+whether which while who whom whose why will with within without would yet you your yours \
+yourself yourselves";
+
+/// Languages with a built-in stopword list, by code
+const BUILTIN_STOPWORDS: &[(&str, &str)] = &[("en", STOPWORDS_EN)];
+
+/// Words left out of term counts
+pub struct Stopwords {
+    words: HashSet<String>,
+}
+
+impl Stopwords {
+    /// Built-in list for a language code such as "en", if there is one
+    pub fn builtin(language: &str) -> Option<Self> {
+        let (_, words) = BUILTIN_STOPWORDS
+            .iter()
+            .find(|(code, _)| *code == language)?;
+
+        Some(Stopwords {
+            words: words.split_whitespace().map(str::to_string).collect(),
+        })
+    }
+
+    /// Built-in list extended with `<home>/stopwords/<language>.txt`, one word per line
+    pub fn load(home: &Path, language: &str) -> Result<Self, KindlrError> {
+        let path = home.join("stopwords").join(format!("{}.txt", language));
+
+        let extra = match fs::read_to_string(&path) {
+            Ok(contents) => Some(contents),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut stopwords = match (Self::builtin(language), &extra) {
+            (Some(builtin), _) => builtin,
+            (None, Some(_)) => Stopwords {
+                words: HashSet::new(),
+            },
+            (None, None) => {
+                let builtin: Vec<&str> = BUILTIN_STOPWORDS.iter().map(|(code, _)| *code).collect();
+                return Err(KindlrError::Config(format!(
+                    "No stopwords for language '{}', only for {} unless added to {}",
+                    language,
+                    builtin.join(", "),
+                    path.display()
+                )));
+            }
+        };
+
+        if let Some(extra) = extra {
+            stopwords.words.extend(
+                extra
+                    .lines()
+                    .map(|line| line.trim().to_lowercase())
+                    .filter(|word| !word.is_empty() && !word.starts_with('#')),
+            );
+        }
+
+        Ok(stopwords)
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(word)
+    }
+}
+
+/// Lowercased words of a text, keeping apostrophes and hyphens inside words
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '’' || c == '-'))
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// How often a term occurs
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TermCount {
+    pub term: String,
+    pub count: usize,
+}
+
+/// Most frequent terms and bigrams in a set of highlights
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Analysis {
+    pub terms: Vec<TermCount>,
+    pub bigrams: Vec<TermCount>,
+}
+
+/// Analysis of a single book's highlights
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BookAnalysis {
+    pub book_title: String,
+    pub author: String,
+    #[serde(flatten)]
+    pub analysis: Analysis,
+}
+
+/// Count the `top` most frequent terms and bigrams across all highlights
+///
+/// Stopwords, numbers and single letters are skipped and break bigrams.
+pub fn analyze<'a>(
+    clippings: impl IntoIterator<Item = &'a Clipping>,
+    stopwords: &Stopwords,
+    top: usize,
+) -> Analysis {
+    let mut terms = HashMap::new();
+    let mut bigrams = HashMap::new();
+
+    for content in clippings
+        .into_iter()
+        .filter(|clipping| clipping.clipping_type.is_highlight())
+        .filter_map(|clipping| clipping.content.as_deref())
+    {
+        let mut previous: Option<String> = None;
+
+        for token in tokenize(content) {
+            if !is_meaningful(&token, stopwords) {
+                previous = None;
+                continue;
+            }
+
+            if let Some(previous) = &previous {
+                *bigrams
+                    .entry(format!("{} {}", previous, token))
+                    .or_insert(0) += 1;
+            }
+            *terms.entry(token.clone()).or_insert(0) += 1;
+            previous = Some(token);
+        }
+    }
+
+    Analysis {
+        terms: most_frequent(terms, top),
+        bigrams: most_frequent(bigrams, top),
+    }
+}
+
+/// Run `analyze` separately for every book
+pub fn analyze_by_book(
+    clippings: &[Clipping],
+    stopwords: &Stopwords,
+    top: usize,
+) -> Vec<BookAnalysis> {
+    let mut books: BTreeMap<(&str, &str), Vec<&Clipping>> = BTreeMap::new();
+    for clipping in clippings {
+        books
+            .entry((&clipping.book_title, &clipping.author))
+            .or_default()
+            .push(clipping);
+    }
+
+    books
+        .into_iter()
+        .map(|((book_title, author), clippings)| BookAnalysis {
+            book_title: book_title.to_string(),
+            author: author.to_string(),
+            analysis: analyze(clippings, stopwords, top),
+        })
+        .collect()
+}
+
+/// Whether `token` says something about a text, rather than being a
+/// stopword, a number or a single letter
+fn is_meaningful(token: &str, stopwords: &Stopwords) -> bool {
+    token.chars().count() > 1
+        && !token.chars().all(|c| c.is_numeric())
+        && !stopwords.contains(token)
+}
+
+fn most_frequent(counts: HashMap<String, usize>, top: usize) -> Vec<TermCount> {
+    let mut counts: Vec<TermCount> = counts
+        .into_iter()
+        .map(|(term, count)| TermCount { term, count })
+        .collect();
+
+    // Ties are broken alphabetically so output is deterministic
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.term.cmp(&b.term)));
+    counts.truncate(top);
+    counts
+}
+
+/// Highlights with at least this text similarity are reported as duplicates by default
+pub const DEFAULT_SIMILARITY_THRESHOLD: f64 = 0.8;
+
+/// A text lowercased with runs of whitespace as one space
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Character trigrams of a text, normalized
+fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    let chars: Vec<char> = normalize(text).chars().collect();
+    chars.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+/// Similarity of two texts from 0.0 to 1.0, as the overlap of their character trigrams
+///
+/// Texts too short to have a trigram are 1.0 only when they read the same,
+/// so empty highlights aren't alike.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    Shingles::new(a).similarity(&Shingles::new(b))
+}
+
+/// A text made ready for `similarity`, to compare it with many others
+/// without breaking it into trigrams each time
+struct Shingles {
+    normalized: String,
+    trigrams: HashSet<[char; 3]>,
+}
+
+impl Shingles {
+    fn new(text: &str) -> Self {
+        let normalized = normalize(text);
+        let chars: Vec<char> = normalized.chars().collect();
+        let trigrams = chars.windows(3).map(|w| [w[0], w[1], w[2]]).collect();
+        Shingles {
+            normalized,
+            trigrams,
+        }
+    }
+
+    fn similarity(&self, other: &Shingles) -> f64 {
+        if self.trigrams.is_empty() || other.trigrams.is_empty() {
+            let (a, b) = (&self.normalized, &other.normalized);
+            return if !a.is_empty() && a == b { 1.0 } else { 0.0 };
+        }
+
+        self.trigrams.intersection(&other.trigrams).count() as f64
+            / self.trigrams.union(&other.trigrams).count() as f64
+    }
+}
+
+/// How much of `phrase` is in `text`, from 0.0 to 1.0, as the share of the
+/// phrase's character trigrams the text has too
+///
+/// Unlike `similarity`, a long passage isn't marked down for holding more
+/// than the phrase.
+pub fn containment(phrase: &str, text: &str) -> f64 {
+    let phrase = trigrams(phrase);
+    if phrase.is_empty() {
+        return 0.0;
+    }
+
+    phrase.intersection(&trigrams(text)).count() as f64 / phrase.len() as f64
+}
+
+/// A highlight found for a phrase
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SimilarQuote {
+    pub score: f64,
+    pub id: String,
+    pub book_title: String,
+    pub author: String,
+    pub location: String,
+    pub content: String,
+}
+
+/// The `top` highlights holding most of `phrase`, as remembered rather than
+/// word for word, best first
+///
+/// Highlights holding as much are ranked by how close they are to the
+/// phrase as a whole, so the shortest passage quoting it comes first.
+pub fn find_similar(phrase: &str, clippings: &[Clipping], top: usize) -> Vec<SimilarQuote> {
+    let mut found: Vec<(&Clipping, f64, f64)> = clippings
+        .iter()
+        .filter(|clipping| clipping.clipping_type.is_highlight())
+        .filter_map(|clipping| {
+            let content = clipping.content.as_deref()?;
+            let score = containment(phrase, content);
+            (score > 0.0).then(|| (clipping, score, similarity(phrase, content)))
+        })
+        .collect();
+
+    found.sort_by(|a, b| b.1.total_cmp(&a.1).then(b.2.total_cmp(&a.2)));
+    found
+        .into_iter()
+        .take(top)
+        .map(|(clipping, score, _)| SimilarQuote {
+            score,
+            id: clipping.id(),
+            book_title: clipping.book_title.clone(),
+            author: clipping.author.clone(),
+            location: clipping.location.to_string(),
+            content: clipping.content.clone().unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Search text is considered similar to a clipping from this similarity by default
+pub const DEFAULT_FUZZY_THRESHOLD: f64 = 0.3;
+
+/// Whether `text` is long enough to search for by similarity, having a
+/// character trigram
+pub fn is_searchable(text: &str) -> bool {
+    !trigrams(text).is_empty()
+}
+
+/// Similarity of `content` to `text` searched for, 0.0 when `text` isn't
+/// searchable, so a short search doesn't match every short highlight
+pub fn search_similarity(text: &str, content: &str) -> f64 {
+    if !is_searchable(text) {
+        return 0.0;
+    }
+    similarity(text, content)
+}
+
+/// Clippings whose content has at least `threshold` similarity to `text`,
+/// most similar first, or none when `text` isn't searchable
+pub fn similar_to<'a>(
+    text: &str,
+    clippings: &'a [Clipping],
+    threshold: f64,
+) -> Vec<(&'a Clipping, f64)> {
+    let mut matches: Vec<(&Clipping, f64)> = clippings
+        .iter()
+        .filter_map(|clipping| {
+            let score = search_similarity(text, clipping.content.as_deref()?);
+            (score > 0.0 && score >= threshold).then_some((clipping, score))
+        })
+        .collect();
+
+    matches.sort_by(|a, b| b.1.total_cmp(&a.1));
+    matches
+}
+
+/// Representative of `i`'s set in a union-find forest
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// A highlight that is part of a duplicate cluster
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DuplicateEntry {
+    pub id: String,
+    pub location: String,
+    pub datetime: String,
+    pub content: String,
+}
+
+/// Highlights in one book that overlap or read almost the same
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DuplicateCluster {
+    pub book_title: String,
+    pub author: String,
+    pub entries: Vec<DuplicateEntry>,
+}
+
+/// Find clusters of highlights in the same book whose locations overlap or
+/// whose text similarity is at least `threshold`
+///
+/// Nothing is removed; this only reports what a dedupe would look at.
+pub fn duplicates(clippings: &[Clipping], threshold: f64) -> Vec<DuplicateCluster> {
+    let mut books: BTreeMap<(&str, &str), Vec<&Clipping>> = BTreeMap::new();
+    for clipping in clippings
+        .iter()
+        .filter(|clipping| clipping.clipping_type.is_highlight())
+    {
+        books
+            .entry((&clipping.book_title, &clipping.author))
+            .or_default()
+            .push(clipping);
+    }
+
+    let mut clusters = Vec::new();
+    for ((book_title, author), highlights) in books {
+        // Union-find over highlights linked by overlap or similarity
+        let mut parent: Vec<usize> = (0..highlights.len()).collect();
+        let shingles: Vec<Shingles> = highlights
+            .iter()
+            .map(|highlight| Shingles::new(highlight.content.as_deref().unwrap_or_default()))
+            .collect();
+
+        for i in 0..highlights.len() {
+            for j in i + 1..highlights.len() {
+                let (a, b) = (highlights[i], highlights[j]);
+                let linked = a.location.overlaps(&b.location)
+                    || shingles[i].similarity(&shingles[j]) >= threshold;
+
+                if linked {
+                    let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+                    parent[ri] = rj;
+                }
+            }
+        }
+
+        let mut groups: BTreeMap<usize, Vec<&Clipping>> = BTreeMap::new();
+        for (i, highlight) in highlights.iter().enumerate() {
+            groups
+                .entry(root(&mut parent, i))
+                .or_default()
+                .push(highlight);
+        }
+
+        clusters.extend(
+            groups
+                .into_values()
+                .filter(|group| group.len() > 1)
+                .map(|group| DuplicateCluster {
+                    book_title: book_title.to_string(),
+                    author: author.to_string(),
+                    entries: group
+                        .into_iter()
+                        .map(|clipping| DuplicateEntry {
+                            id: clipping.id(),
+                            location: clipping.location.to_string(),
+                            datetime: clipping.datetime.clone(),
+                            content: clipping.content.clone().unwrap_or_default(),
+                        })
+                        .collect(),
+                }),
+        );
+    }
+
+    clusters
+}
+
+/// Most topics `topics` finds when not told how many to find
+const MAX_TOPICS: usize = 20;
+
+/// Terms naming each topic
+const TOPIC_TERMS: usize = 5;
+
+/// Highlights quoted for each topic, those closest to its center
+const TOPIC_QUOTES: usize = 3;
+
+/// Rounds of k-means before settling for the topics found
+const MAX_ROUNDS: usize = 50;
+
+/// A highlight quoted for a topic
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TopicQuote {
+    pub id: String,
+    pub book_title: String,
+    pub author: String,
+    pub content: String,
+}
+
+/// Highlights from any book that share their words
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Topic {
+    /// Terms weighing most in the topic, most telling first
+    pub terms: Vec<String>,
+    pub highlights: usize,
+    /// The highlights most typical of the topic
+    pub quotes: Vec<TopicQuote>,
+}
+
+/// Weights of a text's terms, scaled to a length of 1
+type Vector = BTreeMap<String, f64>;
+
+fn cosine(a: &Vector, b: &Vector) -> f64 {
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    small
+        .iter()
+        .filter_map(|(term, weight)| Some(weight * large.get(term)?))
+        .sum()
+}
+
+fn normalized(mut vector: Vector) -> Vector {
+    let length = vector
+        .values()
+        .map(|weight| weight * weight)
+        .sum::<f64>()
+        .sqrt();
+    if length > 0.0 {
+        vector.values_mut().for_each(|weight| *weight /= length);
+    }
+    vector
+}
+
+/// Group highlights across books into `count` topics by the words they
+/// share, or into about the square root of half their number, biggest
+/// topic first
+///
+/// Highlights are weighed by TF-IDF, so words found everywhere count for
+/// little, and grouped with k-means on cosine similarity, started from the
+/// highlights least alike so the result is the same on every run.
+pub fn topics<'a>(
+    clippings: impl IntoIterator<Item = &'a Clipping>,
+    stopwords: &Stopwords,
+    count: Option<usize>,
+) -> Vec<Topic> {
+    let mut highlights: Vec<(&Clipping, HashMap<String, usize>)> = Vec::new();
+    for clipping in clippings
+        .into_iter()
+        .filter(|clipping| clipping.clipping_type.is_highlight())
+    {
+        let mut terms = HashMap::new();
+        for token in tokenize(clipping.content.as_deref().unwrap_or_default()) {
+            if is_meaningful(&token, stopwords) {
+                *terms.entry(token).or_insert(0) += 1;
+            }
+        }
+        if !terms.is_empty() {
+            highlights.push((clipping, terms));
+        }
+    }
+    if highlights.is_empty() {
+        return Vec::new();
+    }
+
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for (_, terms) in &highlights {
+        for term in terms.keys() {
+            *document_frequency.entry(term).or_insert(0) += 1;
+        }
+    }
+    let n = highlights.len() as f64;
+    let vectors: Vec<Vector> = highlights
+        .iter()
+        .map(|(_, terms)| {
+            normalized(
+                terms
+                    .iter()
+                    .map(|(term, &count)| {
+                        let idf = (n / document_frequency[term.as_str()] as f64).ln() + 1.0;
+                        (term.clone(), count as f64 * idf)
+                    })
+                    .collect(),
+            )
+        })
+        .collect();
+
+    let count = count
+        .unwrap_or_else(|| ((n / 2.0).sqrt().round() as usize).clamp(1, MAX_TOPICS))
+        .clamp(1, vectors.len());
+
+    // Start from the richest highlight, then the one least like any chosen
+    let first = (0..vectors.len())
+        .max_by_key(|&i| (vectors[i].len(), std::cmp::Reverse(i)))
+        .unwrap_or_default();
+    let mut centers = vec![vectors[first].clone()];
+    while centers.len() < count {
+        let next = (0..vectors.len())
+            .map(|i| {
+                let closest = centers
+                    .iter()
+                    .map(|center| cosine(&vectors[i], center))
+                    .fold(f64::MIN, f64::max);
+                (i, closest)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+            .map_or(0, |(i, _)| i);
+        centers.push(vectors[next].clone());
+    }
+
+    let nearest = |vector: &Vector, centers: &[Vector]| {
+        (0..centers.len())
+            .max_by(|&a, &b| {
+                cosine(vector, &centers[a])
+                    .total_cmp(&cosine(vector, &centers[b]))
+                    .then(b.cmp(&a))
+            })
+            .unwrap_or_default()
+    };
+    let mut assignments: Vec<usize> = vectors.iter().map(|v| nearest(v, &centers)).collect();
+    for _ in 0..MAX_ROUNDS {
+        for (topic, center) in centers.iter_mut().enumerate() {
+            let mut sum = Vector::new();
+            for (vector, _) in vectors
+                .iter()
+                .zip(&assignments)
+                .filter(|(_, assigned)| **assigned == topic)
+            {
+                for (term, weight) in vector {
+                    *sum.entry(term.clone()).or_insert(0.0) += weight;
+                }
+            }
+            // A topic left with no highlights keeps its center
+            if !sum.is_empty() {
+                *center = normalized(sum);
+            }
+        }
+
+        let next: Vec<usize> = vectors.iter().map(|v| nearest(v, &centers)).collect();
+        if next == assignments {
+            break;
+        }
+        assignments = next;
+    }
+
+    let mut topics: Vec<Topic> = centers
+        .iter()
+        .enumerate()
+        .filter_map(|(topic, center)| {
+            let mut members: Vec<(usize, f64)> = (0..vectors.len())
+                .filter(|&i| assignments[i] == topic)
+                .map(|i| (i, cosine(&vectors[i], center)))
+                .collect();
+            if members.is_empty() {
+                return None;
+            }
+            members.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+            let mut terms: Vec<(&String, &f64)> = center.iter().collect();
+            terms.sort_by(|a, b| b.1.total_cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+            Some(Topic {
+                terms: terms
+                    .into_iter()
+                    .take(TOPIC_TERMS)
+                    .map(|(term, _)| term.clone())
+                    .collect(),
+                highlights: members.len(),
+                quotes: members
+                    .iter()
+                    .take(TOPIC_QUOTES)
+                    .map(|&(i, _)| {
+                        let clipping = highlights[i].0;
+                        TopicQuote {
+                            id: clipping.id(),
+                            book_title: clipping.book_title.clone(),
+                            author: clipping.author.clone(),
+                            content: clipping.content.clone().unwrap_or_default(),
+                        }
+                    })
+                    .collect(),
+            })
+        })
+        .collect();
+
+    topics.sort_by(|a, b| {
+        b.highlights
+            .cmp(&a.highlights)
+            .then_with(|| a.terms.cmp(&b.terms))
+    });
+    topics
+}
+
+impl fmt::Display for Topic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} highlights)",
+            self.terms.join(", "),
+            self.highlights
+        )?;
+        for quote in &self.quotes {
+            write!(
+                f,
+                "\n  \"{}\" — {} ({})",
+                quote.content, quote.book_title, quote.author
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for DuplicateCluster {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.book_title, self.author)?;
+        for entry in &self.entries {
+            write!(
+                f,
+                "\n  {} [{}] {}: {}",
+                entry.id, entry.location, entry.datetime, entry.content
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for SimilarQuote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.2}  {} ({}) [{}] {}\n      {}",
+            self.score, self.book_title, self.author, self.location, self.id, self.content
+        )
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Top terms:")?;
+        for term in &self.terms {
+            writeln!(f, "  {:<30} {}", term.term, term.count)?;
+        }
+
+        write!(f, "Top bigrams:")?;
+        for bigram in &self.bigrams {
+            write!(f, "\n  {:<30} {}", bigram.term, bigram.count)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for BookAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({})\n{}",
+            self.book_title, self.author, self.analysis
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("Fear is the mind-killer. Don't—\"fear\" 42!"),
+            vec!["fear", "is", "the", "mind-killer", "don't", "fear", "42"]
+        );
+    }
+
+    #[test]
+    fn test_stopwords_load() {
+        let home = std::env::temp_dir().join(format!("kindlr-stopwords-{}", std::process::id()));
+        fs::create_dir_all(home.join("stopwords")).unwrap();
+
+        let english = Stopwords::load(&home, "en").unwrap();
+        assert!(english.contains("the"));
+
+        // A language without a list of its own is an error, not an empty list
+        assert!(Stopwords::builtin("sv").is_none());
+        let error = Stopwords::load(&home, "sv").err().unwrap().to_string();
+        assert!(error.contains("'sv'") && error.contains("only for en"));
+
+        fs::write(
+            home.join("stopwords").join("sv.txt"),
+            "och\n# comment\natt\n",
+        )
+        .unwrap();
+        let swedish = Stopwords::load(&home, "sv").unwrap();
+        assert!(swedish.contains("och") && swedish.contains("att"));
+        assert!(!swedish.contains("the"));
+
+        fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let clippings = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-14 | Added on Monday, 1 January 2024 10:01:00
+
+Fear is the mind-killer. Fear is the little-death.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 9 | Location 90-91 | Added on Monday, 1 January 2024 11:00:00
+
+fear is the mind killer
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 20 | Location 200-201 | Added on Monday, 1 January 2024 12:00:00
+
+Something else entirely.
+==========",
+        )
+        .unwrap();
+
+        assert_eq!(similarity("abc", "abc"), 1.0);
+        assert_eq!(similarity("abc", "xyz"), 0.0);
+        assert_eq!(similarity("OK", "ok"), 1.0);
+        assert_eq!(similarity("OK", "Hi"), 0.0);
+        assert_eq!(similarity("", ""), 0.0);
+        assert_eq!(similarity("OK", "OK then"), 0.0);
+
+        let clusters = duplicates(&clippings, 0.5);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].entries.len(), 3);
+
+        let clusters = duplicates(&clippings, 1.0);
+        assert_eq!(clusters[0].entries.len(), 2);
+
+        let similar = similar_to("fear is the mind killer", &clippings, 0.5);
+        assert_eq!(similar.len(), 2);
+        assert_eq!(similar[0].0.location.start, 90);
+        assert_eq!(similar[0].1, 1.0);
+        assert!(!is_searchable("Hi"));
+        assert!(similar_to("Hi", &clippings, 0.0).is_empty());
+
+        // Remembered loosely, the shortest passage holding it comes first
+        let found = find_similar("fear is the mind-killer", &clippings, 3);
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[0].location, "10-12");
+        assert_eq!(found[0].score, 1.0);
+        assert_eq!(found[1].location, "10-14");
+        assert!(found[2].score < 1.0);
+        assert_eq!(containment("the mind", "Fear is the mind-killer."), 1.0);
+    }
+
+    #[test]
+    fn test_duplicates_skip_short_highlights() {
+        let clippings = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+1
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 9 | Location 90-91 | Added on Monday, 1 January 2024 11:00:00
+
+…
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 20 | Location 200-201 | Added on Monday, 1 January 2024 12:00:00
+
+OK
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 30 | Location 300-301 | Added on Monday, 1 January 2024 13:00:00
+
+Hi
+==========",
+        )
+        .unwrap();
+
+        assert!(duplicates(&clippings, DEFAULT_SIMILARITY_THRESHOLD).is_empty());
+    }
+
+    #[test]
+    fn test_topics() {
+        let clippings = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+The spice must flow across the desert.
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 3 | Location 30-32 | Added on Monday, 1 January 2024 11:00:00
+
+Virtue is the only good, and reason serves virtue.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 2 | Location 20-22 | Added on Monday, 1 January 2024 10:05:00
+
+The desert sands hide the spice.
+==========
+Letters from a Stoic (Seneca)
+- Your Highlight on page 4 | Location 40-42 | Added on Monday, 1 January 2024 12:00:00
+
+Reason and virtue make a life worth living.
+==========
+Children of Dune (Frank Herbert)
+- Your Highlight on page 5 | Location 50-52 | Added on Monday, 1 January 2024 13:00:00
+
+Worms guard the spice of the deep desert.
+==========",
+        )
+        .unwrap();
+        let stopwords = Stopwords::builtin("en").unwrap();
+
+        let topics = topics(&clippings, &stopwords, Some(2));
+        assert_eq!(topics.len(), 2);
+        assert_eq!(topics[0].highlights, 3);
+        assert!(topics[0].terms.contains(&"spice".to_string()));
+        assert!(
+            topics[0]
+                .quotes
+                .iter()
+                .all(|quote| quote.book_title.contains("Dune"))
+        );
+        assert_eq!(topics[1].highlights, 2);
+        assert_eq!(topics[1].terms[..2], ["virtue", "reason"]);
+        assert_eq!(
+            topics[1].to_string().lines().next(),
+            Some("virtue, reason, good, serves, life (2 highlights)")
+        );
+    }
+
+    #[test]
+    fn test_analyze() {
+        let clippings = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+The spice must flow, and the spice melange extends life.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 2 | Location 20-21 | Added on Monday, 1 January 2024 10:05:00
+
+Spice melange is everything.
+==========
+Dune (Frank Herbert)
+- Your Note on page 2 | Location 21 | Added on Monday, 1 January 2024 10:06:00
+
+Notes aren't counted: spice spice spice.
+==========",
+        )
+        .unwrap();
+
+        let stopwords = Stopwords::builtin("en").unwrap();
+        let analysis = analyze(&clippings, &stopwords, 2);
+
+        assert_eq!(
+            analysis.terms,
+            vec![
+                TermCount {
+                    term: "spice".to_string(),
+                    count: 3
+                },
+                TermCount {
+                    term: "melange".to_string(),
+                    count: 2
+                },
+            ]
+        );
+        assert_eq!(analysis.bigrams[0].term, "spice melange");
+        assert_eq!(analysis.bigrams[0].count, 2);
+    }
+}
@@ -0,0 +1,341 @@
+use flate2::Compression;
+use flate2::Crc;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::KindlrError;
+use crate::cache;
+use crate::enrich;
+use crate::net;
+use crate::remote;
+use crate::unpack;
+use crate::writeback;
+
+const MANIFEST: &str = "manifest.json";
+
+/// Checksums of every file in a backup archive
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    created_at: u64,
+    files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestEntry {
+    path: String,
+    size: u64,
+    crc32: u32,
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+fn corrupted(msg: String) -> KindlrError {
+    KindlrError::Store(format!("Backup is corrupted: {}", msg))
+}
+
+/// Snapshot every file under `home` into a gzipped tar archive, returning the file count
+pub fn create(home: &Path, archive: &Path) -> Result<usize, KindlrError> {
+    if !home.is_dir() {
+        return Err(KindlrError::NotFound(format!(
+            "Nothing to back up in {}",
+            home.display()
+        )));
+    }
+
+    let mut files = Vec::new();
+    collect_files(home, PathBuf::new(), &mut files)?;
+
+    let output = File::create(archive)?;
+    // Don't back up the archive itself when it is written inside the home directory
+    let archive = fs::canonicalize(archive)?;
+    // Parsed clippings, covers, network answers, remote and unpacked files are
+    // cached for speed and can always be parsed, downloaded or unpacked again
+    let parse_cache = cache::dir(home);
+    let covers = enrich::covers_dir(home);
+    let responses = net::cache_dir(home);
+    let remote_files = remote::dir(home);
+    let unpacked = unpack::dir(home);
+    // Files replaced by writing clippings back are copies of a Kindle's own,
+    // kept only to undo a write, and would grow every backup
+    let writeback_backups = writeback::backups_dir(home);
+
+    let mut builder = tar::Builder::new(GzEncoder::new(output, Compression::default()));
+    let mut manifest = Manifest {
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs()),
+        files: Vec::new(),
+    };
+
+    for relative in files {
+        let path = home.join(&relative);
+        if path.starts_with(&parse_cache)
+            || path.starts_with(&covers)
+            || path.starts_with(&responses)
+            || path.starts_with(&remote_files)
+            || path.starts_with(&unpacked)
+            || path.starts_with(&writeback_backups)
+            || fs::canonicalize(&path)? == archive
+        {
+            continue;
+        }
+
+        let data = fs::read(&path)?;
+        let name = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        append(&mut builder, &name, &data)?;
+        manifest.files.push(ManifestEntry {
+            path: name,
+            size: data.len() as u64,
+            crc32: crc32(&data),
+        });
+    }
+
+    let json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|error| KindlrError::Store(error.to_string()))?;
+    append(&mut builder, MANIFEST, &json)?;
+    builder.into_inner()?.finish()?;
+
+    Ok(manifest.files.len())
+}
+
+/// Verify a backup archive and restore its files into `home`, returning the file count
+///
+/// Nothing is written unless every file matches the archive's manifest.
+pub fn restore(archive: &Path, home: &Path) -> Result<usize, KindlrError> {
+    let mut entries = BTreeMap::new();
+    let mut reader = tar::Archive::new(GzDecoder::new(File::open(archive)?));
+
+    for entry in reader.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        entries.insert(name, data);
+    }
+
+    let manifest: Manifest = entries
+        .remove(MANIFEST)
+        .ok_or_else(|| corrupted("missing manifest".to_string()))
+        .and_then(|json| {
+            serde_json::from_slice(&json).map_err(|error| corrupted(error.to_string()))
+        })?;
+
+    let mut files: Vec<(&str, Vec<u8>)> = Vec::with_capacity(manifest.files.len());
+    for file in &manifest.files {
+        if files.iter().any(|(path, _)| *path == file.path) {
+            return Err(corrupted(format!("{} is listed twice", file.path)));
+        }
+        if !is_safe(&file.path) {
+            return Err(corrupted(format!("unsafe path {}", file.path)));
+        }
+
+        let data = entries
+            .remove(&file.path)
+            .ok_or_else(|| corrupted(format!("{} is missing", file.path)))?;
+
+        if data.len() as u64 != file.size || crc32(&data) != file.crc32 {
+            return Err(corrupted(format!("checksum mismatch for {}", file.path)));
+        }
+
+        files.push((&file.path, data));
+    }
+
+    // Every entry left over was never checked against the manifest
+    if let Some(name) = entries.keys().next() {
+        let reason = if is_safe(name) {
+            "is not in the manifest"
+        } else {
+            "is an unsafe path"
+        };
+        return Err(corrupted(format!("{} {}", name, reason)));
+    }
+
+    for (name, data) in &files {
+        let path = home.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, data)?;
+    }
+
+    Ok(files.len())
+}
+
+/// Whether an archive path stays inside the directory it is restored into
+fn is_safe(name: &str) -> bool {
+    let path = Path::new(name);
+    path.components().next().is_some()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn collect_files(root: &Path, relative: PathBuf, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut dir_entries = fs::read_dir(root.join(&relative))?.collect::<Result<Vec<_>, _>>()?;
+    dir_entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in dir_entries {
+        let path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(root, path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn append<W: io::Write>(builder: &mut tar::Builder<W>, name: &str, data: &[u8]) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, name, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("kindlr-backup-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_backup_round_trip() {
+        let home = temp_path("home");
+        let restored = temp_path("restored");
+        let archive = temp_path("archive.tar.gz");
+
+        fs::create_dir_all(home.join("templates")).unwrap();
+        fs::write(home.join("store.json"), "{}").unwrap();
+        fs::write(home.join("templates/book.md"), "# {title}").unwrap();
+        let backups = writeback::backups_dir(&home);
+        fs::create_dir_all(&backups).unwrap();
+        fs::write(backups.join("My Clippings-20240102T030405.txt"), "").unwrap();
+
+        assert_eq!(create(&home, &archive).unwrap(), 2);
+        assert_eq!(restore(&archive, &restored).unwrap(), 2);
+
+        assert_eq!(
+            fs::read_to_string(restored.join("templates/book.md")).unwrap(),
+            "# {title}"
+        );
+
+        for dir in [&home, &restored] {
+            fs::remove_dir_all(dir).unwrap();
+        }
+        fs::remove_file(&archive).unwrap();
+    }
+
+    #[test]
+    fn test_restore_rejects_checksum_mismatch() {
+        let archive = temp_path("tampered.tar.gz");
+        let manifest = Manifest {
+            created_at: 0,
+            files: vec![ManifestEntry {
+                path: "store.json".to_string(),
+                size: 2,
+                crc32: crc32(b"{}"),
+            }],
+        };
+
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&archive).unwrap(),
+            Compression::default(),
+        ));
+        append(&mut builder, "store.json", b"[]").unwrap();
+        append(
+            &mut builder,
+            MANIFEST,
+            &serde_json::to_vec(&manifest).unwrap(),
+        )
+        .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let home = temp_path("untouched");
+        assert!(restore(&archive, &home).is_err());
+        assert!(!home.exists());
+
+        fs::remove_file(&archive).unwrap();
+    }
+
+    #[test]
+    fn test_restore_rejects_entries_outside_the_manifest() {
+        let archive = temp_path("escaping.tar.gz");
+        let entry = || ManifestEntry {
+            path: "a".to_string(),
+            size: 1,
+            crc32: crc32(b"a"),
+        };
+        let manifest = Manifest {
+            created_at: 0,
+            files: vec![entry(), entry()],
+        };
+
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&archive).unwrap(),
+            Compression::default(),
+        ));
+        append(&mut builder, "a", b"a").unwrap();
+        // The tar crate refuses `..` in paths it writes, so set the raw name
+        let mut header = tar::Header::new_gnu();
+        let name = b"x/../../escaped";
+        header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name);
+        header.set_size(1);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, &b"e"[..]).unwrap();
+        append(
+            &mut builder,
+            MANIFEST,
+            &serde_json::to_vec(&manifest).unwrap(),
+        )
+        .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let home = temp_path("escaping-home").join("home");
+        assert!(restore(&archive, &home).is_err());
+        assert!(!home.exists());
+        assert!(!temp_path("escaping-home").join("escaped").exists());
+
+        // Without the duplicate, the stray entry is still refused
+        let mut manifest = manifest;
+        manifest.files.pop();
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&archive).unwrap(),
+            Compression::default(),
+        ));
+        append(&mut builder, "a", b"a").unwrap();
+        builder.append(&header, &b"e"[..]).unwrap();
+        append(
+            &mut builder,
+            MANIFEST,
+            &serde_json::to_vec(&manifest).unwrap(),
+        )
+        .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        assert!(restore(&archive, &home).is_err());
+        assert!(!home.exists());
+
+        fs::remove_file(&archive).unwrap();
+    }
+}
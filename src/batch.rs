@@ -0,0 +1,237 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crate::KindlrError;
+use crate::dedupe;
+use crate::device;
+use crate::import;
+use crate::parser::{Clipping, Parser, ParserOptions};
+
+/// What was read from one file of a batch
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub clippings: usize,
+    /// Entries that failed to parse and were skipped
+    pub issues: usize,
+    /// Entries repeated by the file having been appended to itself, see
+    /// `dedupe::concatenated`, kept once like clippings of several files
+    pub concatenated: usize,
+}
+
+/// Clippings read from several files, with what each file contributed
+#[derive(Debug)]
+pub struct Batch {
+    /// Every clipping once, in the order the files and their entries were read
+    pub clippings: Vec<Clipping>,
+    pub files: Vec<FileReport>,
+}
+
+/// The files at `paths`, with directories replaced by the `*.txt` files
+/// anywhere inside them, in name order
+///
+/// Symlinks to directories inside them aren't followed, as they may loop.
+pub fn discover(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut found = Vec::new();
+            collect_txt(path, &mut found)?;
+            found.sort();
+            files.extend(found);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn collect_txt(dir: &Path, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_txt(&path, found)?;
+        } else if !path.is_dir()
+            && path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("txt"))
+        {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Read the files at `paths`, searching directories, on as many threads as
+/// there are cores
+///
+/// Clippings found in more than one file, as in monthly copies of the same
+/// My Clippings.txt, are kept once, from the first file they're in.
+/// Clippings of files on a mounted Kindle get its serial as their device.
+/// Entries of clippings files that fail to
+/// parse are skipped and counted in the file's report.
+pub fn read(paths: &[PathBuf]) -> Result<Batch, KindlrError> {
+    let files = discover(paths)?;
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(files.len())
+        .max(1);
+    let chunk_size = files.len().div_ceil(workers).max(1);
+
+    let results: Vec<Result<(Vec<Clipping>, usize), KindlrError>> = thread::scope(|scope| {
+        let handles: Vec<_> = files
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(|path| read_file(path)).collect()))
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle: thread::ScopedJoinHandle<'_, Vec<_>>| {
+                handle.join().expect("reading a file panicked")
+            })
+            .collect()
+    });
+
+    let mut seen = HashSet::new();
+    let mut batch = Batch {
+        clippings: Vec::new(),
+        files: Vec::new(),
+    };
+    for (path, result) in files.into_iter().zip(results) {
+        let (mut clippings, issues) = result?;
+        if let Some(serial) = device::identify(&path) {
+            for clipping in &mut clippings {
+                clipping.device = Some(serial.clone());
+            }
+        }
+        batch.files.push(FileReport {
+            path,
+            clippings: clippings.len(),
+            issues,
+            concatenated: dedupe::concatenated(&clippings).len(),
+        });
+        batch.clippings.extend(
+            clippings
+                .into_iter()
+                .filter(|clipping| seen.insert(clipping.id())),
+        );
+    }
+
+    Ok(batch)
+}
+
+/// Clippings and the number of skipped entries in one file
+fn read_file(path: &Path) -> Result<(Vec<Clipping>, usize), KindlrError> {
+    match import::detect_source(path) {
+        Some(importer) if importer.name() != "kindle" => Ok((importer.import(path)?, 0)),
+        _ => {
+            let contents = fs::read_to_string(path)?;
+            let parser = Parser::new(ParserOptions {
+                strict: false,
+                ..ParserOptions::default()
+            });
+
+            let mut clippings = Vec::new();
+            let mut issues = 0;
+            parser.parse_with(&contents, |result| {
+                match result {
+                    Ok(clipping) => clippings.push(clipping),
+                    Err(_) => issues += 1,
+                }
+                ControlFlow::Continue(())
+            });
+            Ok((clippings, issues))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JANUARY: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+";
+
+    const FEBRUARY: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Broken entry without metadata
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Thursday, 1 February 2024 09:00:00
+
+Be one.
+==========
+";
+
+    #[test]
+    fn test_read_directory() {
+        let dir = std::env::temp_dir().join(format!("kindlr-batch-{}", std::process::id()));
+        fs::create_dir_all(dir.join("2024")).unwrap();
+        fs::write(dir.join("2024/01.txt"), JANUARY).unwrap();
+        fs::write(dir.join("2024/02.txt"), FEBRUARY).unwrap();
+        fs::write(dir.join("2024/03.txt"), FEBRUARY.repeat(2)).unwrap();
+        fs::write(dir.join("notes.md"), "not clippings").unwrap();
+
+        let batch = read(std::slice::from_ref(&dir)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            batch.clippings.len(),
+            2,
+            "the shared highlight is kept once"
+        );
+        assert_eq!(
+            batch.files,
+            vec![
+                FileReport {
+                    path: dir.join("2024/01.txt"),
+                    clippings: 1,
+                    issues: 0,
+                    concatenated: 0,
+                },
+                FileReport {
+                    path: dir.join("2024/02.txt"),
+                    clippings: 2,
+                    issues: 1,
+                    concatenated: 0,
+                },
+                FileReport {
+                    path: dir.join("2024/03.txt"),
+                    clippings: 4,
+                    issues: 2,
+                    concatenated: 2,
+                },
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_discover_symlink_loop() {
+        let dir = std::env::temp_dir().join(format!("kindlr-batch-loop-{}", std::process::id()));
+        fs::create_dir_all(dir.join("2024")).unwrap();
+        fs::write(dir.join("2024/01.txt"), JANUARY).unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("2024/loop")).unwrap();
+        std::os::unix::fs::symlink(dir.join("2024"), dir.join("2024/back.txt")).unwrap();
+
+        let files = discover(std::slice::from_ref(&dir)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(files, vec![dir.join("2024/01.txt")]);
+    }
+}
@@ -0,0 +1,146 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::KindlrError;
+use crate::parser::Clipping;
+
+/// Where parsed clippings are cached in the kindlr home directory
+pub fn dir(home: &Path) -> PathBuf {
+    home.join("cache").join("parsed")
+}
+
+/// Clippings parsed from earlier runs, stored by the hash of the file they
+/// came from
+///
+/// A file whose contents changed hashes differently and is parsed again, and
+/// entries written by another version of kindlr are never read, so entries
+/// never need invalidating by hand.
+pub struct ParseCache {
+    dir: PathBuf,
+}
+
+impl ParseCache {
+    /// Entries kept, the least recently written are removed beyond this
+    const MAX_ENTRIES: usize = 16;
+
+    pub fn new(dir: &Path) -> Self {
+        ParseCache {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// The clippings cached for the file at `path`, or those `parse` reads
+    /// from it, which are cached for next time
+    ///
+    /// The cache is only an optimization, so failing to write it is ignored.
+    pub fn read<F>(&self, path: &Path, parse: F) -> Result<Vec<Clipping>, KindlrError>
+    where
+        F: FnOnce(&Path) -> Result<Vec<Clipping>, KindlrError>,
+    {
+        let entry = self.dir.join(format!(
+            "{}-{}.json",
+            env!("CARGO_PKG_VERSION"),
+            content_hash(path)?
+        ));
+
+        if let Some(clippings) = fs::read(&entry)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+        {
+            return Ok(clippings);
+        }
+
+        let clippings = parse(path)?;
+        if fs::create_dir_all(&self.dir).is_ok()
+            && let Ok(json) = serde_json::to_vec(&clippings)
+            && fs::write(&entry, json).is_ok()
+        {
+            let _ = self.prune();
+        }
+
+        Ok(clippings)
+    }
+
+    /// Remove all but the `MAX_ENTRIES` most recently written entries
+    fn prune(&self) -> io::Result<()> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            entries.push((entry.metadata()?.modified()?, entry.path()));
+        }
+
+        entries.sort();
+        let excess = entries.len().saturating_sub(Self::MAX_ENTRIES);
+        for (_, path) in &entries[..excess] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// FNV-1a hash and length of the file at `path`, read in blocks so huge
+/// files needn't fit in memory
+fn content_hash(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut buffer = vec![0; 64 * 1024];
+    let mut hash = 0xcbf29ce484222325u64;
+    let mut len = 0u64;
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        for &byte in &buffer[..read] {
+            hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+        len += read as u64;
+    }
+
+    Ok(format!("{:016x}-{}", hash, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+    use std::cell::Cell;
+
+    const CLIPPINGS: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========";
+
+    #[test]
+    fn test_cache() {
+        let dir = std::env::temp_dir().join(format!("kindlr-cache-{}", std::process::id()));
+        let file = dir.join("My Clippings.txt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&file, CLIPPINGS).unwrap();
+
+        let cache = ParseCache::new(&dir.join("cache"));
+        let parses = Cell::new(0);
+        let parse = |path: &Path| {
+            parses.set(parses.get() + 1);
+            Ok(parse_clippings(&fs::read_to_string(path)?)?)
+        };
+
+        let first = cache.read(&file, parse).unwrap();
+        let second = cache.read(&file, parse).unwrap();
+        assert_eq!(parses.get(), 1, "the second read comes from the cache");
+        assert_eq!(first[0].id(), second[0].id());
+
+        fs::write(&file, CLIPPINGS.replace("mind-killer", "little-death")).unwrap();
+        let changed = cache.read(&file, parse).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(parses.get(), 2, "a changed file is parsed again");
+        assert_eq!(
+            changed[0].content.as_deref(),
+            Some("Fear is the little-death.")
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use chrono::{Datelike, Duration, Months, NaiveDateTime};
+use regex::Regex;
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::parser::Clipping;
+use crate::settings::ClockRule;
+
+/// An ISO 8601 duration such as `P365D`, `P1Y` or `-PT1H`, to shift
+/// datetimes by
+///
+/// Years and months are calendar ones, so `P1Y` moves 1 March 2023 to
+/// 1 March 2024 whether or not a leap day comes between.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Offset {
+    text: String,
+    backwards: bool,
+    months: u32,
+    duration: Duration,
+}
+
+impl Offset {
+    /// `datetime` moved by the offset, unless that leaves the calendar
+    pub fn apply(&self, datetime: NaiveDateTime) -> Option<NaiveDateTime> {
+        let months = Months::new(self.months);
+        if self.backwards {
+            datetime
+                .checked_sub_months(months)?
+                .checked_sub_signed(self.duration)
+        } else {
+            datetime
+                .checked_add_months(months)?
+                .checked_add_signed(self.duration)
+        }
+    }
+}
+
+impl FromStr for Offset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pattern = Regex::new(
+            r"^(-)?P(?:(\d+)Y)?(?:(\d+)M)?(?:(\d+)W)?(?:(\d+)D)?(?:T(?:(\d+)H)?(?:(\d+)M)?(?:(\d+)S)?)?$",
+        )
+        .expect("valid regex");
+        let invalid = || format!("Invalid offset: {}, expected a duration such as P365D", s);
+        let caps = pattern
+            .captures(s)
+            .filter(|_| !s.ends_with(['P', 'T']))
+            .ok_or_else(invalid)?;
+        let number = |i: usize| -> Result<i64, String> {
+            caps.get(i).map_or(Ok(0), |number| {
+                number.as_str().parse().map_err(|_| invalid())
+            })
+        };
+
+        // Each part times its length in the unit, summed without overflowing
+        let total = |parts: &[(usize, i64)]| -> Result<i64, String> {
+            parts.iter().try_fold(0i64, |total, &(i, unit)| {
+                number(i)?
+                    .checked_mul(unit)
+                    .and_then(|part| total.checked_add(part))
+                    .ok_or_else(invalid)
+            })
+        };
+
+        let months = total(&[(2, 12), (3, 1)])?;
+        let seconds = total(&[(4, 7 * 86_400), (5, 86_400), (6, 3_600), (7, 60), (8, 1)])?;
+        Ok(Offset {
+            text: s.to_string(),
+            backwards: caps.get(1).is_some(),
+            months: u32::try_from(months).map_err(|_| invalid())?,
+            duration: Duration::try_seconds(seconds).ok_or_else(invalid)?,
+        })
+    }
+}
+
+impl TryFrom<String> for Offset {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// As written, e.g. `P365D`
+impl fmt::Display for Offset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl ClockRule {
+    /// Whether the rule is about `clipping`, going by its title and the
+    /// datetime the Kindle wrote
+    pub fn matches(&self, clipping: &Clipping) -> bool {
+        let book = self.book.as_ref().is_none_or(|book| {
+            clipping
+                .book_title
+                .to_lowercase()
+                .contains(&book.to_lowercase())
+        });
+        let date = clipping.timestamp().map(|timestamp| timestamp.date());
+        let since = self
+            .since
+            .is_none_or(|since| date.is_some_and(|date| date >= since));
+        let until = self
+            .until
+            .is_none_or(|until| date.is_some_and(|date| date <= until));
+
+        book && since && until
+    }
+}
+
+/// The rule as a `[[clock]]` entry of config.toml
+impl fmt::Display for ClockRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[[clock]]")?;
+        if let Some(book) = &self.book {
+            writeln!(f, "book = {:?}", book)?;
+        }
+        if let Some(since) = self.since {
+            writeln!(f, "since = \"{}\"", since)?;
+        }
+        if let Some(until) = self.until {
+            writeln!(f, "until = \"{}\"", until)?;
+        }
+        write!(f, "offset = {:?}", self.offset.to_string())
+    }
+}
+
+/// Shift `clipping` by the first of `rules` about it, returning whether one
+/// was
+///
+/// The datetime the Kindle wrote is kept in `original_datetime`, so the file
+/// and anything stored by the clipping's original id are left alone.
+pub fn adjust(clipping: &mut Clipping, rules: &[ClockRule]) -> bool {
+    let Some(rule) = rules.iter().find(|rule| rule.matches(clipping)) else {
+        return false;
+    };
+    let Some(shifted) = clipping
+        .timestamp()
+        .and_then(|timestamp| rule.offset.apply(timestamp))
+    else {
+        return false;
+    };
+
+    redate(clipping, shifted);
+    true
+}
+
+/// Write `datetime` into `clipping` as `Clipping::new` would, keeping the
+/// datetime the Kindle wrote in `original_datetime`
+pub(crate) fn redate(clipping: &mut Clipping, datetime: NaiveDateTime) {
+    let original = std::mem::replace(
+        &mut clipping.datetime,
+        datetime.format("%-d %B %Y %H:%M:%S").to_string(),
+    );
+    clipping.original_datetime.get_or_insert(original);
+    clipping.weekday = datetime.weekday().into();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Weekday, parse_clippings};
+    use crate::store::Store;
+
+    #[test]
+    fn test_adjust() {
+        let offset: Offset = "P1Y".parse().unwrap();
+        let datetime = |text| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap();
+        assert_eq!(
+            offset.apply(datetime("2023-03-01 10:00")),
+            Some(datetime("2024-03-01 10:00"))
+        );
+        let offset: Offset = "-P1DT2H30M".parse().unwrap();
+        assert_eq!(
+            offset.apply(datetime("2024-03-01 10:00")),
+            Some(datetime("2024-02-29 07:30"))
+        );
+        for invalid in [
+            "",
+            "P",
+            "365D",
+            "P1DT",
+            "P1.5D",
+            "P99999999999999999W",
+            "P999999999999999999Y",
+            "P9223372036854775807DT1S",
+        ] {
+            assert!(invalid.parse::<Offset>().is_err(), "{}", invalid);
+        }
+
+        let mut clippings = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Sunday, 1 January 2023 10:00:00
+
+Fear is the mind-killer.
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Sunday, 1 January 2023 09:00:00
+
+Be one.
+==========",
+        )
+        .unwrap();
+        let written = clippings[0].id();
+        let rules = [ClockRule {
+            book: Some("dune".to_string()),
+            since: None,
+            until: chrono::NaiveDate::from_ymd_opt(2023, 6, 30),
+            offset: "P365D".parse().unwrap(),
+        }];
+
+        assert!(adjust(&mut clippings[0], &rules));
+        assert!(!adjust(&mut clippings[1], &rules));
+        assert_eq!(clippings[0].datetime, "1 January 2024 10:00:00");
+        assert_eq!(clippings[0].weekday, Weekday::Monday);
+        assert_eq!(
+            clippings[0].original_datetime.as_deref(),
+            Some("1 January 2023 10:00:00")
+        );
+
+        // Starred by the id listed once shifted, and still starred without the rule
+        let path = std::env::temp_dir().join(format!("kindlr-clock-{}.json", std::process::id()));
+        let mut store = Store::open_at(&path).unwrap();
+        store.set_favorite(&clippings[0].id(), true);
+        assert!(store.is_favorite(&written));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(
+            rules[0].to_string(),
+            "[[clock]]\nbook = \"dune\"\nuntil = \"2023-06-30\"\noffset = \"P365D\""
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::KindlrError;
+use crate::aliases;
+use crate::import;
+use crate::parser::{Clipping, ClippingType, Parser, ParserOptions};
+use crate::settings::Settings;
+
+/// What `kindlr count` breaks the total down by
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CountBy {
+    #[default]
+    Type,
+    Book,
+}
+
+impl FromStr for CountBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "type" => Ok(CountBy::Type),
+            "book" => Ok(CountBy::Book),
+            _ => Err(format!("Invalid count: {}, expected type or book", s)),
+        }
+    }
+}
+
+/// Numbers of clippings, tallied one clipping at a time
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Counts {
+    pub total: usize,
+    pub highlights: usize,
+    pub notes: usize,
+    pub bookmarks: usize,
+    /// Entries that couldn't be parsed, left out of the other counts
+    pub skipped: usize,
+    /// Clippings by book title, when counting by book
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub books: Option<BTreeMap<String, usize>>,
+}
+
+impl Counts {
+    pub fn new(by: CountBy) -> Self {
+        Counts {
+            books: (by == CountBy::Book).then(BTreeMap::new),
+            ..Counts::default()
+        }
+    }
+
+    pub fn add(&mut self, clipping: &Clipping) {
+        self.total += 1;
+        match clipping.clipping_type {
+            ClippingType::Highlight | ClippingType::ArticleClip => self.highlights += 1,
+            ClippingType::Note => self.notes += 1,
+            ClippingType::Bookmark => self.bookmarks += 1,
+        }
+        if let Some(books) = &mut self.books {
+            *books.entry(clipping.book_title.clone()).or_default() += 1;
+        }
+    }
+}
+
+/// A count and what it counts per line, tab-separated, books most clipped
+/// first
+impl fmt::Display for Counts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\ttotal", self.total)?;
+        match &self.books {
+            None => write!(
+                f,
+                "\n{}\thighlights\n{}\tnotes\n{}\tbookmarks",
+                self.highlights, self.notes, self.bookmarks
+            )?,
+            Some(books) => {
+                let mut books: Vec<(&String, &usize)> = books.iter().collect();
+                books.sort_by(|a, b| b.1.cmp(a.1));
+                for (title, count) in books {
+                    write!(f, "\n{}\t{}", count, title)?;
+                }
+            }
+        }
+        if self.skipped > 0 {
+            write!(f, "\n{}\tskipped", self.skipped)?;
+        }
+        Ok(())
+    }
+}
+
+/// Count the clippings in `path`, leaving out `ignore_books` and counting
+/// aliased titles as the title they're shown as
+///
+/// My Clippings.txt is read a line at a time and never held in memory, so
+/// huge files are counted quickly; other sources are read as `list` reads
+/// them.
+pub fn count_file(path: &Path, by: CountBy, settings: &Settings) -> Result<Counts, KindlrError> {
+    let mut counts = Counts::new(by);
+    let mut add = |clipping: &mut Clipping| {
+        if by == CountBy::Book {
+            aliases::apply(clipping, &settings.aliases, &settings.author_aliases);
+        }
+        if !settings.is_ignored(&clipping.book_title) {
+            counts.add(clipping);
+        }
+    };
+
+    match import::detect_source(path) {
+        Some(importer) if importer.name() != "kindle" => {
+            for mut clipping in importer.import(path)? {
+                add(&mut clipping);
+            }
+        }
+        _ => {
+            let parser = Parser::new(ParserOptions {
+                strict: false,
+                ..ParserOptions::default()
+            });
+            let mut skipped = 0;
+            parser.parse_reader_with(BufReader::new(File::open(path)?), |result| {
+                match result {
+                    Ok(mut clipping) => add(&mut clipping),
+                    Err(_) => skipped += 1,
+                }
+                ControlFlow::Continue(())
+            })?;
+            counts.skipped = skipped;
+        }
+    }
+
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_file() {
+        let path = std::env::temp_dir().join(format!("kindlr-count-{}.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00
+
+Classic.
+==========
+Meditations (Marcus Aurelius)
+- Your Bookmark on page 5 | Location 70 | Added on Monday, 1 January 2024 11:00:00
+
+
+==========
+Broken entry
+==========
+",
+        )
+        .unwrap();
+
+        let counts = count_file(&path, CountBy::Type, &Settings::default()).unwrap();
+        assert_eq!(
+            (
+                counts.total,
+                counts.highlights,
+                counts.notes,
+                counts.bookmarks
+            ),
+            (3, 1, 1, 1)
+        );
+        assert_eq!(counts.skipped, 1);
+        assert_eq!(
+            counts.to_string(),
+            "3\ttotal\n1\thighlights\n1\tnotes\n1\tbookmarks\n1\tskipped"
+        );
+
+        let counts = count_file(&path, CountBy::Book, &Settings::default()).unwrap();
+        assert_eq!(
+            counts.to_string(),
+            "3\ttotal\n2\tDune\n1\tMeditations\n1\tskipped"
+        );
+        std::fs::remove_file(&path).ok();
+    }
+}
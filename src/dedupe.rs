@@ -0,0 +1,280 @@
+use chrono::Duration;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::parser::{Clipping, ClippingType};
+
+/// How to decide that a clipping duplicates another
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DedupeStrategy {
+    /// Same book, type and content, keeping the first
+    Exact,
+    /// A highlight whose location overlaps a longer highlight in the same book
+    SupersededByLongerOverlap,
+    /// Overlapping highlights in the same book made within the window, keeping the latest
+    TimeWindow(Duration),
+    /// Entries repeated by the file having been appended to itself, see
+    /// `concatenated`
+    Concatenated,
+}
+
+impl FromStr for DedupeStrategy {
+    type Err = String;
+
+    /// "exact", "superseded", `"window:<minutes>"` or "concatenated"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(DedupeStrategy::Exact),
+            "superseded" => Ok(DedupeStrategy::SupersededByLongerOverlap),
+            "concatenated" => Ok(DedupeStrategy::Concatenated),
+            _ => s
+                .strip_prefix("window:")
+                .and_then(|minutes| minutes.parse().ok())
+                .map(|minutes| DedupeStrategy::TimeWindow(Duration::minutes(minutes)))
+                .ok_or_else(|| format!("Invalid dedupe strategy: {}", s)),
+        }
+    }
+}
+
+/// Why a clipping was removed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DedupeReason {
+    Exact,
+    SupersededByLongerOverlap,
+    WithinTimeWindow,
+    Concatenated,
+}
+
+impl fmt::Display for DedupeReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            DedupeReason::Exact => "exact duplicate",
+            DedupeReason::SupersededByLongerOverlap => {
+                "superseded by a longer overlapping highlight"
+            }
+            DedupeReason::WithinTimeWindow => "re-highlighted within the time window",
+            DedupeReason::Concatenated => "repeated by the file being appended to itself",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// A clipping dropped as a duplicate of the kept clipping `kept_id`
+#[derive(Debug)]
+pub struct Removed {
+    pub clipping: Clipping,
+    pub reason: DedupeReason,
+    pub kept_id: String,
+}
+
+/// Split clippings into those kept and those removed as duplicates
+///
+/// Kept clippings stay in their original order.
+pub fn dedupe(clippings: Vec<Clipping>, strategy: DedupeStrategy) -> (Vec<Clipping>, Vec<Removed>) {
+    // Index of the clipping that replaces each removed one
+    let mut removed_by: Vec<Option<usize>> = vec![None; clippings.len()];
+
+    match strategy {
+        DedupeStrategy::Exact => {
+            let mut first = HashMap::new();
+            for (i, clipping) in clippings.iter().enumerate() {
+                let key = (
+                    clipping.clipping_type,
+                    &clipping.book_title,
+                    &clipping.author,
+                    clipping
+                        .content
+                        .as_deref()
+                        .map(|content| content.split_whitespace().collect::<Vec<_>>().join(" ")),
+                    // Bookmarks have no content, so tell them apart by location
+                    clipping
+                        .content
+                        .is_none()
+                        .then_some(clipping.location.start),
+                );
+
+                match first.get(&key) {
+                    Some(&kept) => removed_by[i] = Some(kept),
+                    None => {
+                        first.insert(key, i);
+                    }
+                }
+            }
+        }
+        DedupeStrategy::SupersededByLongerOverlap => {
+            let length = |clipping: &Clipping| {
+                clipping
+                    .content
+                    .as_deref()
+                    .map_or(0, |content| content.chars().count())
+            };
+
+            for (i, a) in clippings.iter().enumerate() {
+                removed_by[i] = overlapping_highlights(&clippings, i)
+                    .filter(|&j| length(&clippings[j]) > length(a))
+                    .max_by_key(|&j| length(&clippings[j]));
+            }
+        }
+        DedupeStrategy::Concatenated => {
+            for (i, original) in concatenated(&clippings) {
+                removed_by[i] = Some(original);
+            }
+        }
+        DedupeStrategy::TimeWindow(window) => {
+            for (i, a) in clippings.iter().enumerate() {
+                let Some(time) = a.timestamp() else { continue };
+
+                removed_by[i] = overlapping_highlights(&clippings, i)
+                    .filter_map(|j| Some((j, clippings[j].timestamp()?)))
+                    .filter(|&(j, other)| {
+                        (other > time || (other == time && j > i)) && other - time <= window
+                    })
+                    .max_by_key(|&(j, other)| (other, j))
+                    .map(|(j, _)| j);
+            }
+        }
+    }
+
+    let reason = match strategy {
+        DedupeStrategy::Exact => DedupeReason::Exact,
+        DedupeStrategy::SupersededByLongerOverlap => DedupeReason::SupersededByLongerOverlap,
+        DedupeStrategy::TimeWindow(_) => DedupeReason::WithinTimeWindow,
+        DedupeStrategy::Concatenated => DedupeReason::Concatenated,
+    };
+    let ids: Vec<String> = clippings.iter().map(Clipping::id).collect();
+
+    let mut kept = Vec::new();
+    let mut removed = Vec::new();
+    for (clipping, by) in clippings.into_iter().zip(removed_by) {
+        match by {
+            Some(j) => removed.push(Removed {
+                clipping,
+                reason,
+                kept_id: ids[j].clone(),
+            }),
+            None => kept.push(clipping),
+        }
+    }
+
+    (kept, removed)
+}
+
+/// Entries repeating an earlier one for at least this many in a row are
+/// taken for a copy of the file appended to itself
+pub const MIN_CONCATENATED_RUN: usize = 3;
+
+/// Indices of entries repeated by a file having been appended to itself,
+/// with the index of the entry each repeats
+///
+/// Backup tools sometimes concatenate My Clippings.txt onto itself. Entries
+/// with the id of an earlier entry count when they come in a run of at
+/// least `MIN_CONCATENATED_RUN`, or in a run ending the file, so a short
+/// file appended to itself is found too.
+pub fn concatenated(clippings: &[Clipping]) -> Vec<(usize, usize)> {
+    let mut first = HashMap::new();
+    let repeats: Vec<Option<usize>> = clippings
+        .iter()
+        .enumerate()
+        .map(|(i, clipping)| match first.get(&clipping.id()) {
+            Some(&original) => Some(original),
+            None => {
+                first.insert(clipping.id(), i);
+                None
+            }
+        })
+        .collect();
+
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < repeats.len() {
+        let start = i;
+        while let Some(Some(original)) = repeats.get(i) {
+            found.push((i, *original));
+            i += 1;
+        }
+        if i - start < MIN_CONCATENATED_RUN && i < repeats.len() {
+            found.truncate(found.len() - (i - start));
+        }
+        i += 1;
+    }
+
+    found
+}
+
+/// Indices of the other highlights in the same book whose locations overlap highlight `i`
+fn overlapping_highlights(clippings: &[Clipping], i: usize) -> impl Iterator<Item = usize> + '_ {
+    let a = &clippings[i];
+    let is_highlight = a.clipping_type == ClippingType::Highlight;
+
+    clippings
+        .iter()
+        .enumerate()
+        .filter(move |&(j, b)| {
+            is_highlight
+                && j != i
+                && b.clipping_type == ClippingType::Highlight
+                && b.book_title == a.book_title
+                && b.author == a.author
+                && b.location.overlaps(&a.location)
+        })
+        .map(|(j, _)| j)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    const CLIPPINGS: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-14 | Added on Monday, 1 January 2024 10:01:00
+
+Fear is the mind-killer. Fear is the little-death.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Tuesday, 2 January 2024 10:00:00
+
+Fear is the  mind-killer.
+==========";
+
+    #[test]
+    fn test_dedupe() {
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let ids: Vec<String> = clippings.iter().map(Clipping::id).collect();
+
+        let (kept, removed) = dedupe(clippings, DedupeStrategy::Exact);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(removed[0].clipping.id(), ids[2]);
+        assert_eq!(removed[0].kept_id, ids[0]);
+
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let (kept, removed) = dedupe(clippings, DedupeStrategy::SupersededByLongerOverlap);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].id(), ids[1]);
+        assert_eq!(removed.len(), 2);
+
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let (kept, removed) = dedupe(clippings, "window:5".parse().unwrap());
+        assert_eq!(kept.len(), 2);
+        assert_eq!(removed[0].clipping.id(), ids[0]);
+        assert_eq!(removed[0].reason, DedupeReason::WithinTimeWindow);
+
+        // The file appended to itself
+        let clippings = parse_clippings(&format!("{}\n{}", CLIPPINGS, CLIPPINGS)).unwrap();
+        assert_eq!(concatenated(&clippings), [(3, 0), (4, 1), (5, 2)]);
+        let (kept, removed) = dedupe(clippings, DedupeStrategy::Concatenated);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(removed[2].kept_id, ids[2]);
+
+        // A lone repeat in the middle of the file isn't taken for one
+        let mut clippings = parse_clippings(CLIPPINGS).unwrap();
+        clippings.insert(2, clippings[0].clone());
+        assert!(concatenated(&clippings).is_empty());
+    }
+}
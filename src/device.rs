@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+
+/// Serial number of the Kindle whose storage holds `path`, when it is
+/// mounted over USB
+///
+/// Only Linux exposes the serial without extra tools, through the names in
+/// `/dev/disk/by-id`; elsewhere, and for files copied off the device, this
+/// is `None` and `--device-label` names the device instead.
+pub fn identify(path: &Path) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        let path = path.canonicalize().ok()?;
+        let mounts = std::fs::read_to_string("/proc/self/mounts").ok()?;
+        let source = Path::new(mount_source(&mounts, &path)?)
+            .canonicalize()
+            .ok()?;
+
+        std::fs::read_dir("/dev/disk/by-id")
+            .ok()?
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().canonicalize().ok().as_ref() == Some(&source))
+            .find_map(|entry| serial_from_id(&entry.file_name().to_string_lossy()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Device mounted at the deepest mount point holding `path`, from the
+/// contents of `/proc/self/mounts`
+fn mount_source<'a>(mounts: &'a str, path: &Path) -> Option<&'a str> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let source = fields.next()?;
+            // Spaces in mount points are written as octal escapes
+            let mount_point = PathBuf::from(fields.next()?.replace("\\040", " "));
+            path.starts_with(&mount_point)
+                .then_some((mount_point.components().count(), source))
+        })
+        .max_by_key(|&(depth, _)| depth)
+        .map(|(_, source)| source)
+}
+
+/// The serial in a `/dev/disk/by-id` name such as
+/// `usb-Kindle_Internal_Storage_G000PP1234567890-0:0-part1`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn serial_from_id(name: &str) -> Option<String> {
+    let name = name.strip_prefix("usb-")?;
+    if !name.starts_with("Kindle") {
+        return None;
+    }
+
+    // Drop the logical unit and partition after the serial
+    let (model_and_serial, _) = name.rsplit_once("-0:")?;
+    let (_, serial) = model_and_serial.rsplit_once('_')?;
+    (!serial.is_empty()).then(|| serial.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identify_parts() {
+        let mounts = "\
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+/dev/sdb1 /media/reader/My\\040Kindle vfat rw,nosuid 0 0
+";
+        assert_eq!(
+            mount_source(
+                mounts,
+                Path::new("/media/reader/My Kindle/documents/My Clippings.txt")
+            ),
+            Some("/dev/sdb1")
+        );
+        assert_eq!(
+            mount_source(mounts, Path::new("/home/reader/clippings.txt")),
+            Some("/dev/nvme0n1p2")
+        );
+
+        assert_eq!(
+            serial_from_id("usb-Kindle_Internal_Storage_G000PP1234567890-0:0-part1").as_deref(),
+            Some("G000PP1234567890")
+        );
+        assert_eq!(serial_from_id("usb-SanDisk_Ultra_4C530001-0:0"), None);
+        assert_eq!(serial_from_id("ata-Samsung_SSD_870_S5Y1NX0R"), None);
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::parser::Clipping;
+
+/// A clipping present in both sets whose content differs
+#[derive(Debug)]
+pub struct Change<'a> {
+    pub old: &'a Clipping,
+    pub new: &'a Clipping,
+}
+
+/// Differences between two sets of clippings, matched on their ids
+#[derive(Debug, Default)]
+pub struct ClippingDiff<'a> {
+    /// In the new set only, in its order
+    pub added: Vec<&'a Clipping>,
+    /// In the old set only, in its order
+    pub removed: Vec<&'a Clipping>,
+    /// In both with different content, in the new set's order
+    pub changed: Vec<Change<'a>>,
+}
+
+impl ClippingDiff<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare two sets of clippings
+///
+/// Ids don't cover content, so an edited clipping shows up as changed rather
+/// than as removed and added.
+pub fn diff<'a>(old: &'a [Clipping], new: &'a [Clipping]) -> ClippingDiff<'a> {
+    let old_by_id: HashMap<String, &Clipping> = old
+        .iter()
+        .map(|clipping| (clipping.id(), clipping))
+        .collect();
+    let new_by_id: HashMap<String, &Clipping> = new
+        .iter()
+        .map(|clipping| (clipping.id(), clipping))
+        .collect();
+
+    let mut diff = ClippingDiff::default();
+    for clipping in new {
+        match old_by_id.get(&clipping.id()) {
+            None => diff.added.push(clipping),
+            Some(&previous) if previous.content != clipping.content => diff.changed.push(Change {
+                old: previous,
+                new: clipping,
+            }),
+            Some(_) => {}
+        }
+    }
+
+    diff.removed = old
+        .iter()
+        .filter(|clipping| !new_by_id.contains_key(&clipping.id()))
+        .collect();
+
+    diff
+}
+
+impl fmt::Display for ClippingDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = |f: &mut fmt::Formatter<'_>, sign: char, clipping: &Clipping| {
+            writeln!(
+                f,
+                "{} {} {} [{}]: {}",
+                sign,
+                clipping.id(),
+                clipping.book_title,
+                clipping.location,
+                clipping.content.as_deref().unwrap_or("N/A")
+            )
+        };
+
+        for clipping in &self.added {
+            line(f, '+', clipping)?;
+        }
+        for clipping in &self.removed {
+            line(f, '-', clipping)?;
+        }
+        for change in &self.changed {
+            line(f, '~', change.new)?;
+            writeln!(
+                f,
+                "    was: {}",
+                change.old.content.as_deref().unwrap_or("N/A")
+            )?;
+        }
+
+        write!(
+            f,
+            "{} added, {} removed, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    #[test]
+    fn test_diff() {
+        let old = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00
+
+Classic.
+==========",
+        )
+        .unwrap();
+        let new = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00
+
+Classic line.
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Sunday, 5 January 2025 09:00:00
+
+Be one.
+==========",
+        )
+        .unwrap();
+
+        let diff = diff(&old, &new);
+
+        assert_eq!(diff.added[0].book_title, "Meditations");
+        assert_eq!(diff.removed[0].location.start, 10);
+        assert_eq!(diff.changed[0].old.content.as_deref(), Some("Classic."));
+        assert_eq!(
+            diff.changed[0].new.content.as_deref(),
+            Some("Classic line.")
+        );
+        assert!(!diff.is_empty());
+        assert!(super::diff(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,204 @@
+use chrono::{Datelike, NaiveDate};
+
+use crate::KindlrError;
+#[cfg(feature = "push")]
+use crate::net;
+use crate::parser::Clipping;
+
+/// Somewhere a digest can be sent
+pub trait Channel {
+    /// Name the channel is selected by with `--channel`
+    fn name(&self) -> &str;
+
+    fn deliver(&self, text: &str) -> Result<(), KindlrError>;
+}
+
+/// Highlights with text, the only clippings worth quoting
+fn quotable(clippings: &[Clipping]) -> impl Iterator<Item = &Clipping> {
+    clippings.iter().filter(|clipping| {
+        clipping.clipping_type.is_highlight()
+            && clipping
+                .content
+                .as_deref()
+                .is_some_and(|content| !content.trim().is_empty())
+    })
+}
+
+/// The highlight for `date`, the same all day and a different one the next
+pub fn daily_quote(clippings: &[Clipping], date: NaiveDate) -> Option<&Clipping> {
+    let highlights: Vec<&Clipping> = quotable(clippings).collect();
+    if highlights.is_empty() {
+        return None;
+    }
+
+    // Spread consecutive days across the whole collection
+    let day = date.num_days_from_ce().unsigned_abs() as u64;
+    let index = day.wrapping_mul(2_654_435_761) % highlights.len() as u64;
+    Some(highlights[index as usize])
+}
+
+/// The `n` most recently added highlights, newest first
+pub fn latest(clippings: &[Clipping], n: usize) -> Vec<&Clipping> {
+    let mut highlights: Vec<&Clipping> = quotable(clippings).collect();
+    highlights.sort_by_key(|clipping| std::cmp::Reverse(clipping.timestamp()));
+    highlights.truncate(n);
+    highlights
+}
+
+/// Quotes with their book and author, separated by blank lines
+pub fn render(clippings: &[&Clipping]) -> String {
+    clippings
+        .iter()
+        .map(|clipping| {
+            format!(
+                "“{}”\n— {}, {}",
+                clipping.content.as_deref().unwrap_or_default().trim(),
+                clipping.book_title,
+                clipping.author
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Prints the digest
+pub struct Stdout;
+
+impl Channel for Stdout {
+    fn name(&self) -> &str {
+        "stdout"
+    }
+
+    fn deliver(&self, text: &str) -> Result<(), KindlrError> {
+        println!("{}", text);
+        Ok(())
+    }
+}
+
+/// Split `text` at paragraph breaks into messages of at most `max_len`
+/// characters, cutting paragraphs that are longer on their own
+#[cfg_attr(not(feature = "push"), allow(dead_code))]
+fn split_messages(text: &str, max_len: usize) -> Vec<String> {
+    let mut messages: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for paragraph in text.split("\n\n") {
+        let mut paragraph: Vec<char> = paragraph.chars().collect();
+        let joined = current.chars().count() + 2 + paragraph.len();
+
+        if !current.is_empty() && joined > max_len {
+            messages.push(std::mem::take(&mut current));
+        }
+        while paragraph.len() > max_len {
+            let rest = paragraph.split_off(max_len);
+            messages.push(paragraph.into_iter().collect());
+            paragraph = rest;
+        }
+
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.extend(paragraph);
+    }
+
+    if !current.is_empty() {
+        messages.push(current);
+    }
+    messages
+}
+
+/// A Telegram chat, messaged by a bot made with @BotFather
+#[cfg(feature = "push")]
+pub struct Telegram {
+    bot_token: String,
+    chat_id: String,
+    client: net::Client,
+}
+
+#[cfg(feature = "push")]
+impl Telegram {
+    /// Longest message Telegram accepts
+    const MAX_MESSAGE_LEN: usize = 4096;
+
+    pub fn new(bot_token: String, chat_id: String, client: net::Client) -> Self {
+        Telegram {
+            bot_token,
+            chat_id,
+            // Telegram allows a message a second in a chat
+            client: client.rate_limit("api.telegram.org", std::time::Duration::from_secs(1)),
+        }
+    }
+}
+
+#[cfg(feature = "push")]
+impl Channel for Telegram {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn deliver(&self, text: &str) -> Result<(), KindlrError> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token);
+
+        for message in split_messages(text, Self::MAX_MESSAGE_LEN) {
+            // Client errors leave out the URL, which holds the token
+            self.client
+                .post(&url)
+                .json(&serde_json::json!({ "chat_id": self.chat_id, "text": message }))
+                .call()
+                .map_err(|error| KindlrError::Network(format!("Telegram: {}", error)))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{ClippingType, parse_clippings};
+
+    const CLIPPINGS: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00
+
+Classic.
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Sunday, 5 January 2025 09:00:00
+
+Be one.
+==========";
+
+    #[test]
+    fn test_digest() {
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let today = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+
+        let quote = daily_quote(&clippings, today).unwrap();
+        assert_eq!(quote.clipping_type, ClippingType::Highlight);
+        assert_eq!(
+            daily_quote(&clippings, today).unwrap().id(),
+            quote.id(),
+            "the quote stays the same all day"
+        );
+
+        let latest = latest(&clippings, 5);
+        assert_eq!(latest.len(), 2);
+        assert_eq!(
+            render(&latest),
+            "“Be one.”\n— Meditations, Marcus Aurelius\n\n\
+             “Fear is the mind-killer.”\n— Dune, Frank Herbert"
+        );
+
+        assert_eq!(
+            split_messages("aaaa\n\nbb\n\ncc", 6),
+            vec!["aaaa", "bb\n\ncc"]
+        );
+        assert_eq!(split_messages("abcdefgh", 3), vec!["abc", "def", "gh"]);
+    }
+}
@@ -0,0 +1,519 @@
+use regex::Regex;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::KindlrError;
+use crate::analyze;
+use crate::import::unescape_html;
+use crate::parser::{Clipping, ClippingType};
+
+/// Text around a highlight, kept with the model in kindlr-core
+pub use kindlr_core::context::Context;
+
+/// Sentences of context taken either side of a highlight by default
+pub const DEFAULT_CONTEXT_SENTENCES: usize = 1;
+
+/// Similarity from which text in the book is taken for a highlight that
+/// isn't found word for word
+pub const MATCH_THRESHOLD: f64 = 0.6;
+
+/// The text of an EPUB, MOBI or AZW3 book
+///
+/// Runs of whitespace become a space, or a newline between paragraphs.
+/// Highlights are looked for in a folded copy of the text, lowercase and
+/// with curly quotes and dashes made plain, so they're found however the
+/// Kindle wrote them.
+#[derive(Debug)]
+pub struct Ebook {
+    pub title: Option<String>,
+    text: Vec<char>,
+    folded: String,
+    /// Offset in `folded` of each character of `text`
+    starts: Vec<usize>,
+}
+
+impl Ebook {
+    /// Read the book at `path`, an EPUB or a MOBI or AZW3 without DRM
+    pub fn open(path: &Path) -> Result<Self, KindlrError> {
+        let data = fs::read(path)?;
+        if data.starts_with(b"PK") {
+            epub(&data)
+        } else if data.get(60..68) == Some(b"BOOKMOBI") || data.get(60..68) == Some(b"TEXtREAd") {
+            mobi(&data)
+        } else {
+            Err(KindlrError::Config(format!(
+                "{} is not an EPUB, MOBI or AZW3 book",
+                path.display()
+            )))
+        }
+    }
+
+    /// A book of `text`, HTML already taken out
+    pub fn from_text(title: Option<String>, text: &str) -> Self {
+        let mut chars = Vec::new();
+        let mut space: Option<char> = None;
+        for c in text.chars() {
+            if c.is_whitespace() {
+                if space != Some('\n') {
+                    space = Some(if c == '\n' { '\n' } else { ' ' });
+                }
+                continue;
+            }
+            if let Some(space) = space.take()
+                && !chars.is_empty()
+            {
+                chars.push(space);
+            }
+            chars.push(c);
+        }
+
+        let mut folded = String::new();
+        let mut starts = Vec::with_capacity(chars.len());
+        for &c in &chars {
+            starts.push(folded.len());
+            folded.push(fold(c));
+        }
+        Ebook {
+            title,
+            text: chars,
+            folded,
+            starts,
+        }
+    }
+
+    /// Whether the book is the one titled `title`, going by its own title;
+    /// a book without one could be any
+    pub fn is_titled(&self, title: &str) -> bool {
+        let Some(own) = &self.title else {
+            return true;
+        };
+        let (own, title) = (own.to_lowercase(), title.to_lowercase());
+        own.contains(&title) || title.contains(&own)
+    }
+
+    /// Characters of the text `passage` was taken from, found word for word
+    /// or else at least `MATCH_THRESHOLD` similar
+    pub fn find(&self, passage: &str) -> Option<Range<usize>> {
+        let needle: String = Ebook::from_text(None, passage).folded;
+        if needle.is_empty() {
+            return None;
+        }
+        if let Some(offset) = self.folded.find(&needle) {
+            let start = self.starts.binary_search(&offset).ok()?;
+            return Some(start..start + needle.chars().count());
+        }
+
+        // Look around where the passage's first or last words are found
+        let length = needle.chars().count();
+        let words: Vec<&str> = needle.split(' ').collect();
+        let anchor = words.len().min(4);
+        let first = words[..anchor].join(" ");
+        let last = words[words.len() - anchor..].join(" ");
+        let starts = self
+            .folded
+            .match_indices(&first)
+            .filter_map(|(offset, _)| self.starts.binary_search(&offset).ok());
+        let ends = self
+            .folded
+            .match_indices(&last)
+            .filter_map(|(offset, _)| self.starts.binary_search(&offset).ok())
+            .map(|start| (start + last.chars().count()).saturating_sub(length));
+        starts
+            .chain(ends)
+            .map(|start| start..(start + length).min(self.text.len()))
+            .map(|range| {
+                let window: String = self.text[range.clone()].iter().collect();
+                (analyze::similarity(&window, &needle), range)
+            })
+            .filter(|(score, _)| *score >= MATCH_THRESHOLD)
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, range)| range)
+    }
+
+    /// The sentence `range` starts and ends in and `sentences` more either
+    /// side, never past a paragraph
+    pub fn context(&self, range: Range<usize>, sentences: usize) -> Context {
+        let mut start = range.start;
+        if !self.is_sentence_start(start) {
+            start = self.previous_sentence(start);
+        }
+        for _ in 0..sentences {
+            if start == 0 || self.text[start - 1] == '\n' {
+                break;
+            }
+            start = self.previous_sentence(start);
+        }
+
+        let mut end = self.next_sentence(range.end.saturating_sub(1));
+        for _ in 0..sentences {
+            if end >= self.text.len() || self.text[end - 1] == '\n' {
+                break;
+            }
+            end = self.next_sentence(end);
+        }
+
+        let text = |range: Range<usize>| -> String {
+            self.text[range]
+                .iter()
+                .collect::<String>()
+                .trim()
+                .to_string()
+        };
+        Context {
+            before: text(start..range.start),
+            after: text(range.end..end.max(range.end)),
+        }
+    }
+
+    /// Give the highlights of this book the text around them, and return how
+    /// many were found
+    pub fn attach_context(&self, clippings: &mut [Clipping], sentences: usize) -> usize {
+        let mut found = 0;
+        for clipping in clippings
+            .iter_mut()
+            .filter(|clipping| clipping.clipping_type == ClippingType::Highlight)
+            .filter(|clipping| self.is_titled(&clipping.book_title))
+        {
+            let Some(range) = clipping.content.as_deref().and_then(|text| self.find(text)) else {
+                continue;
+            };
+            clipping.context = Some(self.context(range, sentences));
+            found += 1;
+        }
+        found
+    }
+
+    fn is_sentence_start(&self, i: usize) -> bool {
+        if i == 0 || i >= self.text.len() {
+            return true;
+        }
+        match self.text[i - 1] {
+            '\n' => true,
+            ' ' => {
+                let before = self.text[..i - 1]
+                    .iter()
+                    .rev()
+                    .find(|c| !"\"')]”’»".contains(**c));
+                before.is_some_and(|c| ".!?…".contains(*c))
+            }
+            _ => false,
+        }
+    }
+
+    /// Start of the sentence before the one at `i`
+    fn previous_sentence(&self, i: usize) -> usize {
+        (0..i)
+            .rev()
+            .find(|&i| self.is_sentence_start(i))
+            .unwrap_or(0)
+    }
+
+    /// Start of the sentence after the one at `i`
+    fn next_sentence(&self, i: usize) -> usize {
+        (i + 1..self.text.len())
+            .find(|&i| self.is_sentence_start(i))
+            .unwrap_or(self.text.len())
+    }
+}
+
+/// `c` lowercase, with quotes, dashes and spaces made plain
+fn fold(c: char) -> char {
+    match c {
+        '‘' | '’' | '‚' | '′' => '\'',
+        '“' | '”' | '„' | '″' | '«' | '»' => '"',
+        '‐' | '‑' | '‒' | '–' | '—' | '―' => '-',
+        '\n' => ' ',
+        c => c.to_lowercase().next().unwrap_or(c),
+    }
+}
+
+/// The text of HTML, with paragraphs and line breaks as newlines
+fn html_text(html: &str) -> String {
+    let hidden = Regex::new(r"(?is)<(head|script|style)\b.*?</(head|script|style)\s*>")
+        .expect("valid regex");
+    let breaks = Regex::new(r"(?i)<(/p|/div|/h[1-6]|/li|/blockquote|br|mbp:pagebreak)\b[^>]*>")
+        .expect("valid regex");
+    let tags = Regex::new(r"<[^>]*>").expect("valid regex");
+
+    let html = hidden.replace_all(html, "");
+    let html = breaks.replace_all(&html, "\n");
+    unescape_html(&tags.replace_all(&html, ""))
+}
+
+/// Value of the attribute `name` of the first tag matched by `tag`
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    Regex::new(&format!(
+        r#"\b{}\s*=\s*["']([^"']*)["']"#,
+        regex::escape(name)
+    ))
+    .expect("valid regex")
+    .captures(tag)
+    .map(|caps| unescape_html(&caps[1]))
+}
+
+/// Text of an EPUB's spine, in reading order
+fn epub(data: &[u8]) -> Result<Ebook, KindlrError> {
+    let invalid =
+        |error: zip::result::ZipError| KindlrError::Config(format!("Invalid EPUB: {}", error));
+    let mut archive = zip::ZipArchive::new(Cursor::new(data)).map_err(invalid)?;
+    let mut read = |name: &str| -> Result<String, KindlrError> {
+        let mut text = String::new();
+        archive
+            .by_name(name)
+            .map_err(invalid)?
+            .read_to_string(&mut text)?;
+        Ok(text)
+    };
+
+    let container = read("META-INF/container.xml")?;
+    let rootfile = Regex::new(r"<rootfile\b[^>]*>")
+        .expect("valid regex")
+        .find(&container)
+        .and_then(|tag| attribute(tag.as_str(), "full-path"))
+        .ok_or_else(|| KindlrError::Config("Invalid EPUB: no package document".to_string()))?;
+    let package = read(&rootfile)?;
+    let dir = rootfile.rfind('/').map_or("", |slash| &rootfile[..=slash]);
+
+    let title = Regex::new(r"(?s)<dc:title\b[^>]*>(.*?)</dc:title>")
+        .expect("valid regex")
+        .captures(&package)
+        .map(|caps| html_text(&caps[1]).trim().to_string());
+    let items: Vec<(String, String)> = Regex::new(r"<item\b[^>]*>")
+        .expect("valid regex")
+        .find_iter(&package)
+        .filter_map(|tag| {
+            Some((
+                attribute(tag.as_str(), "id")?,
+                attribute(tag.as_str(), "href")?,
+            ))
+        })
+        .collect();
+
+    let mut text = String::new();
+    for itemref in Regex::new(r"<itemref\b[^>]*>")
+        .expect("valid regex")
+        .find_iter(&package)
+    {
+        let Some(idref) = attribute(itemref.as_str(), "idref") else {
+            continue;
+        };
+        let Some((_, href)) = items.iter().find(|(id, _)| *id == idref) else {
+            continue;
+        };
+        let href = href
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .replace("%20", " ");
+        text += &html_text(&read(&format!("{}{}", dir, href))?);
+        text.push('\n');
+    }
+
+    Ok(Ebook::from_text(title, &text))
+}
+
+/// Text of a MOBI or AZW3 book, which is a Palm database of compressed text
+/// records after a header record
+fn mobi(data: &[u8]) -> Result<Ebook, KindlrError> {
+    let invalid = || KindlrError::Config("Invalid MOBI: cut short".to_string());
+    let u16_at = |at: usize| -> Option<usize> {
+        data.get(at..at + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+    };
+    let u32_at = |at: usize| -> Option<usize> {
+        data.get(at..at + 4)
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    };
+
+    let records = u16_at(76).ok_or_else(invalid)?;
+    let offsets = (0..records)
+        .map(|i| u32_at(78 + i * 8))
+        .chain([Some(data.len())])
+        .collect::<Option<Vec<usize>>>()
+        .ok_or_else(invalid)?;
+    let record = |i: usize| -> Option<&[u8]> { data.get(*offsets.get(i)?..*offsets.get(i + 1)?) };
+
+    let header = record(0).ok_or_else(invalid)?;
+    let field = |at: usize, size: usize| -> Option<usize> {
+        header
+            .get(at..at + size)
+            .map(|bytes| bytes.iter().fold(0, |n, &byte| n << 8 | byte as usize))
+    };
+    let compression = field(0, 2).ok_or_else(invalid)?;
+    let text_records = field(8, 2).ok_or_else(invalid)?;
+    if field(12, 2) != Some(0) {
+        return Err(KindlrError::Config(
+            "The book is protected by DRM, which kindlr can't read".to_string(),
+        ));
+    }
+    if compression != 1 && compression != 2 {
+        return Err(KindlrError::Config(
+            "The book is compressed in a way kindlr can't read".to_string(),
+        ));
+    }
+
+    let is_mobi = header.get(16..20) == Some(b"MOBI");
+    let extra_flags = match is_mobi && field(20, 4).is_some_and(|length| length >= 0xE4) {
+        true => field(0xF2, 2).unwrap_or(0),
+        false => 0,
+    };
+    let utf8 = is_mobi && field(28, 4) == Some(65001);
+    let title = is_mobi
+        .then(|| {
+            let (offset, length) = (field(0x54, 4)?, field(0x58, 4)?);
+            header.get(offset..offset + length)
+        })
+        .flatten()
+        .map(|bytes| decode(bytes, utf8));
+
+    let mut text = Vec::new();
+    for i in 1..=text_records {
+        let data = trim_trailing_entries(record(i).ok_or_else(invalid)?, extra_flags);
+        match compression {
+            2 => text.extend(palmdoc(data)),
+            _ => text.extend_from_slice(data),
+        }
+    }
+
+    Ok(Ebook::from_text(title, &html_text(&decode(&text, utf8))))
+}
+
+/// `bytes` as UTF-8, or as Windows-1252 taken for Latin-1
+fn decode(bytes: &[u8], utf8: bool) -> String {
+    match utf8 {
+        true => String::from_utf8_lossy(bytes).into_owned(),
+        false => bytes.iter().map(|&byte| byte as char).collect(),
+    }
+}
+
+/// A text record without the entries `flags` says follow its text
+fn trim_trailing_entries(mut record: &[u8], flags: usize) -> &[u8] {
+    for _ in 0..(flags >> 1).count_ones() {
+        // Each entry ends with its size, written backwards in 7-bit bytes
+        let size = record[record.len().saturating_sub(4)..]
+            .iter()
+            .fold(0, |size, &byte| {
+                let size = if byte & 0x80 != 0 { 0 } else { size };
+                size << 7 | (byte & 0x7F) as usize
+            });
+        record = &record[..record.len().saturating_sub(size)];
+    }
+    if flags & 1 != 0
+        && let Some(&last) = record.last()
+    {
+        record = &record[..record.len().saturating_sub((last & 3) as usize + 1)];
+    }
+    record
+}
+
+/// Decompress a record compressed as PalmDOC, an LZ77 variant
+fn palmdoc(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() * 2);
+    let mut i = 0;
+    while i < data.len() {
+        let byte = data[i];
+        i += 1;
+        match byte {
+            1..=8 => {
+                let end = (i + byte as usize).min(data.len());
+                out.extend_from_slice(&data[i..end]);
+                i = end;
+            }
+            0x80..=0xBF => {
+                let Some(&next) = data.get(i) else { break };
+                i += 1;
+                let pair = (byte as usize) << 8 | next as usize;
+                let distance = (pair & 0x3FFF) >> 3;
+                if distance == 0 || distance > out.len() {
+                    continue;
+                }
+                for _ in 0..(pair & 7) + 3 {
+                    out.push(out[out.len() - distance]);
+                }
+            }
+            0xC0..=0xFF => out.extend_from_slice(&[b' ', byte ^ 0x80]),
+            _ => out.push(byte),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_find_and_context() {
+        let book = Ebook::from_text(
+            Some("Dune".to_string()),
+            "Chapter One\n\nI must not fear. Fear is the mind\u{2011}killer. Fear is the \
+             little-death that brings total obliteration. I will face my fear.\n\nThe end.",
+        );
+
+        let range = book.find("fear is the mind-killer.").unwrap();
+        let context = book.context(range, 1);
+        assert_eq!(context.before, "I must not fear.");
+        assert_eq!(
+            context.after,
+            "Fear is the little-death that brings total obliteration."
+        );
+
+        let range = book
+            .find("Fear is the little death that brings totall obliteration.")
+            .unwrap();
+        assert_eq!(book.context(range, 5).after, "I will face my fear.");
+        assert!(book.find("The spice must flow.").is_none());
+        assert!(book.is_titled("Dune: Deluxe Edition"));
+    }
+
+    #[test]
+    fn test_open() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("kindlr-ebook-{}.epub", std::process::id()));
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, text) in [
+            (
+                "META-INF/container.xml",
+                r#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                r#"<package><metadata><dc:title>Dune</dc:title></metadata>
+<manifest><item href="two.xhtml" id="two"/><item id="one" href="one.xhtml"/></manifest>
+<spine><itemref idref="one"/><itemref idref="two"/></spine></package>"#,
+            ),
+            (
+                "OEBPS/one.xhtml",
+                "<html><head><title>One</title></head><body><h1>Chapter One</h1></body></html>",
+            ),
+            (
+                "OEBPS/two.xhtml",
+                "<html><body><p>I must not fear. Fear is the mind&#8209;killer &amp; more.</p></body></html>",
+            ),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(text.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let book = Ebook::open(&path).unwrap();
+        assert_eq!(book.title.as_deref(), Some("Dune"));
+        assert!(
+            book.text
+                .iter()
+                .collect::<String>()
+                .starts_with("Chapter One\nI must not fear.")
+        );
+        let range = book.find("Fear is the mind-killer & more.").unwrap();
+        assert_eq!(book.context(range, 1).before, "I must not fear.");
+        fs::remove_file(&path).ok();
+
+        // "the mind" again, 8 bytes copied from 10 back, then " s" in a byte
+        assert_eq!(palmdoc(b"the mind, \x80\x55\xF3"), b"the mind, the mind s");
+        assert_eq!(palmdoc(b"\x02\x01\xC0"), b"\x01\xC0");
+    }
+}
@@ -0,0 +1,565 @@
+#[cfg(feature = "enrich")]
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::KindlrError;
+use crate::library::Library;
+pub use crate::library::Metadata;
+use crate::net::{self, RateLimiter};
+
+/// A catalogue books can be looked up in
+pub trait MetadataProvider: Send {
+    /// Name the provider is selected by and its cache is kept under
+    fn name(&self) -> &str;
+
+    /// Shortest time to leave between two lookups
+    fn min_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    /// The best match for the book, if the catalogue has one
+    fn lookup(&self, title: &str, author: &str) -> Result<Option<Metadata>, KindlrError>;
+}
+
+/// Fills `Book::metadata` from a provider, remembering every answer (including
+/// books the provider doesn't know) in `<cache_dir>/<provider>.json`
+pub struct Enricher {
+    provider: Box<dyn MetadataProvider>,
+    limiter: RateLimiter,
+    cache_path: PathBuf,
+    cache: BTreeMap<String, Option<Metadata>>,
+}
+
+impl Enricher {
+    pub fn new(provider: Box<dyn MetadataProvider>, cache_dir: &Path) -> Result<Self, KindlrError> {
+        let cache_path = cache_dir.join(format!("{}.json", provider.name()));
+        let cache = match fs::read_to_string(&cache_path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|error| {
+                KindlrError::Config(format!("Invalid {}: {}", cache_path.display(), error))
+            })?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(Enricher {
+            limiter: RateLimiter::new(provider.min_interval()),
+            provider,
+            cache_path,
+            cache,
+        })
+    }
+
+    /// Look up every book in `library`, returning how many were found
+    ///
+    /// The cache is saved even when a lookup fails, so a rerun picks up
+    /// where this one stopped.
+    pub fn enrich(&mut self, library: &mut Library) -> Result<usize, KindlrError> {
+        let result = self.lookup_all(library);
+        self.save()?;
+        result
+    }
+
+    /// Like `enrich`, looking books up on tokio's blocking thread pool so the
+    /// runtime isn't stalled by rate limits and slow catalogues
+    ///
+    /// `library` is handed back with its metadata filled in.
+    #[cfg(feature = "async")]
+    pub async fn enrich_async(
+        mut self,
+        mut library: Library,
+    ) -> Result<(Library, usize), KindlrError> {
+        tokio::task::spawn_blocking(move || {
+            let found = self.enrich(&mut library)?;
+            Ok((library, found))
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    fn lookup_all(&mut self, library: &mut Library) -> Result<usize, KindlrError> {
+        let mut found = 0;
+
+        for book in &mut library.books {
+            let key = format!("{}\t{}", book.title, book.author).to_lowercase();
+
+            let metadata = match self.cache.get(&key) {
+                Some(metadata) => metadata.clone(),
+                None => {
+                    self.limiter.wait();
+                    let metadata = self.provider.lookup(&book.title, &book.author)?;
+                    self.cache.insert(key, metadata.clone());
+                    metadata
+                }
+            };
+
+            found += usize::from(metadata.is_some());
+            book.metadata = metadata;
+        }
+
+        Ok(found)
+    }
+
+    fn save(&self) -> Result<(), KindlrError> {
+        if let Some(dir) = self.cache_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(&self.cache)
+            .map_err(|error| KindlrError::Io(error.into()))?;
+        fs::write(&self.cache_path, json)?;
+        Ok(())
+    }
+}
+
+/// Where downloaded covers are kept in the kindlr home directory
+pub fn covers_dir(home: &Path) -> PathBuf {
+    home.join("cache").join("covers")
+}
+
+/// Book covers downloaded once and kept in a directory
+pub struct CoverCache {
+    dir: PathBuf,
+    client: net::Client,
+}
+
+impl CoverCache {
+    /// Covers larger than this are skipped rather than downloaded
+    const MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+    pub fn new(dir: &Path, client: net::Client) -> Self {
+        CoverCache {
+            dir: dir.to_path_buf(),
+            client,
+        }
+    }
+
+    /// Point every book's `cover_path` at a local copy of its cover,
+    /// downloading those not cached yet, and return how many books have one
+    ///
+    /// A cover that fails to download is reported and skipped, as notes
+    /// without it are still worth exporting.
+    pub fn fetch(&self, library: &mut Library) -> usize {
+        let mut found = 0;
+
+        for book in &mut library.books {
+            let Some(metadata) = &mut book.metadata else {
+                continue;
+            };
+            let Some(url) = &metadata.cover_url else {
+                continue;
+            };
+
+            let path = self.path_for(url);
+            if !path.exists()
+                && let Err(error) = self.download(url, &path)
+            {
+                eprintln!("Skipped the cover of {}: {}", book.title, error);
+                continue;
+            }
+
+            metadata.cover_path = Some(path);
+            found += 1;
+        }
+
+        found
+    }
+
+    /// Like `fetch`, downloading covers on tokio's blocking thread pool
+    #[cfg(feature = "async")]
+    pub async fn fetch_async(self, mut library: Library) -> Result<(Library, usize), KindlrError> {
+        tokio::task::spawn_blocking(move || {
+            let found = self.fetch(&mut library);
+            (library, found)
+        })
+        .await
+        .map_err(|error| KindlrError::Io(io::Error::other(error)))
+    }
+
+    /// File the cover at `url` is kept in, named by a hash of the URL
+    fn path_for(&self, url: &str) -> PathBuf {
+        let extension = url
+            .split(['?', '#'])
+            .next()
+            .and_then(|path| path.rsplit_once('.'))
+            .map(|(_, extension)| extension.to_ascii_lowercase())
+            .filter(|extension| ["jpg", "jpeg", "png", "gif", "webp"].contains(&extension.as_str()))
+            .unwrap_or_else(|| "jpg".to_string());
+
+        self.dir
+            .join(format!("{:016x}.{}", net::hash(url), extension))
+    }
+
+    fn download(&self, url: &str, path: &Path) -> Result<(), KindlrError> {
+        // Kept here rather than in the client's cache
+        let image = self
+            .client
+            .get(url)
+            .uncached()
+            .max_bytes(Self::MAX_BYTES)
+            .call()
+            .map_err(|error| KindlrError::Network(error.to_string()))?
+            .into_bytes();
+
+        // Written aside and renamed, so an interrupted download isn't cached
+        fs::create_dir_all(&self.dir)?;
+        let partial = path.with_extension("part");
+        fs::write(&partial, image)?;
+        fs::rename(&partial, path)?;
+        Ok(())
+    }
+}
+
+/// Open Library's search API, <https://openlibrary.org/dev/docs/api/search>
+#[cfg(feature = "enrich")]
+pub struct OpenLibrary {
+    client: net::Client,
+}
+
+#[cfg(feature = "enrich")]
+impl OpenLibrary {
+    const SEARCH_URL: &str = "https://openlibrary.org/search.json";
+    const MAX_SUBJECTS: usize = 10;
+
+    pub fn new(client: net::Client) -> Self {
+        OpenLibrary { client }
+    }
+
+    fn parse_search(json: &str) -> Result<Option<Metadata>, KindlrError> {
+        #[derive(Deserialize)]
+        struct Search {
+            docs: Vec<Doc>,
+        }
+
+        #[derive(Deserialize)]
+        struct Doc {
+            #[serde(default)]
+            isbn: Vec<String>,
+            #[serde(default)]
+            id_amazon: Vec<String>,
+            cover_i: Option<u64>,
+            first_publish_year: Option<i32>,
+            #[serde(default)]
+            subject: Vec<String>,
+        }
+
+        let search: Search = serde_json::from_str(json).map_err(|error| {
+            KindlrError::Network(format!("Unexpected Open Library response: {}", error))
+        })?;
+
+        Ok(search.docs.into_iter().next().map(|doc| Metadata {
+            // Prefer ISBN-13, which every edition since 2007 has
+            isbn: doc
+                .isbn
+                .iter()
+                .find(|isbn| isbn.len() == 13)
+                .or(doc.isbn.first())
+                .cloned(),
+            // Kindle editions have ASINs starting with B, the rest are ISBN-10s
+            asin: doc.id_amazon.into_iter().find(|id| id.starts_with("B0")),
+            cover_url: doc
+                .cover_i
+                .map(|id| format!("https://covers.openlibrary.org/b/id/{}-L.jpg", id)),
+            cover_path: None,
+            year: doc.first_publish_year,
+            subjects: doc.subject.into_iter().take(Self::MAX_SUBJECTS).collect(),
+        }))
+    }
+}
+
+#[cfg(feature = "enrich")]
+impl MetadataProvider for OpenLibrary {
+    fn name(&self) -> &str {
+        "openlibrary"
+    }
+
+    fn lookup(&self, title: &str, author: &str) -> Result<Option<Metadata>, KindlrError> {
+        let network_error =
+            |error| KindlrError::Network(format!("Open Library lookup failed: {}", error));
+
+        let response = self
+            .client
+            .get(Self::SEARCH_URL)
+            .query("title", title)
+            .query("author", author)
+            .query(
+                "fields",
+                "isbn,id_amazon,cover_i,first_publish_year,subject",
+            )
+            .query("limit", "1")
+            .call()
+            .map_err(|error| network_error(error.to_string()))?;
+        let json = response
+            .into_string()
+            .map_err(|error| network_error(error.to_string()))?;
+
+        Self::parse_search(&json)
+    }
+}
+
+/// Google Books' volume search, <https://developers.google.com/books/docs/v1/using>
+#[cfg(feature = "enrich")]
+pub struct GoogleBooks {
+    api_key: Option<String>,
+    client: net::Client,
+}
+
+#[cfg(feature = "enrich")]
+impl GoogleBooks {
+    const SEARCH_URL: &str = "https://www.googleapis.com/books/v1/volumes";
+
+    /// Without a key requests share Google's anonymous quota
+    pub fn new(api_key: Option<String>, client: net::Client) -> Self {
+        GoogleBooks { api_key, client }
+    }
+
+    fn parse_search(json: &str) -> Result<Option<Metadata>, KindlrError> {
+        #[derive(Deserialize)]
+        struct Search {
+            #[serde(default)]
+            items: Vec<Volume>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Volume {
+            volume_info: VolumeInfo,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct VolumeInfo {
+            #[serde(default)]
+            industry_identifiers: Vec<Identifier>,
+            image_links: Option<ImageLinks>,
+            published_date: Option<String>,
+            #[serde(default)]
+            categories: Vec<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct Identifier {
+            #[serde(rename = "type")]
+            kind: String,
+            identifier: String,
+        }
+
+        #[derive(Deserialize)]
+        struct ImageLinks {
+            thumbnail: Option<String>,
+        }
+
+        let search: Search = serde_json::from_str(json).map_err(|error| {
+            KindlrError::Network(format!("Unexpected Google Books response: {}", error))
+        })?;
+
+        Ok(search.items.into_iter().next().map(|volume| {
+            let info = volume.volume_info;
+            let isbn = |kind: &str| {
+                info.industry_identifiers
+                    .iter()
+                    .find(|identifier| identifier.kind == kind)
+                    .map(|identifier| identifier.identifier.clone())
+            };
+
+            Metadata {
+                isbn: isbn("ISBN_13").or_else(|| isbn("ISBN_10")),
+                asin: None,
+                cover_url: info
+                    .image_links
+                    .and_then(|links| links.thumbnail)
+                    .map(|url| url.replacen("http://", "https://", 1)),
+                cover_path: None,
+                // Dates are "1965", "1965-08" or "1965-08-01"
+                year: info
+                    .published_date
+                    .and_then(|date| date.get(..4)?.parse().ok()),
+                subjects: info.categories,
+            }
+        }))
+    }
+}
+
+#[cfg(feature = "enrich")]
+impl MetadataProvider for GoogleBooks {
+    fn name(&self) -> &str {
+        "google-books"
+    }
+
+    fn lookup(&self, title: &str, author: &str) -> Result<Option<Metadata>, KindlrError> {
+        let network_error =
+            |error| KindlrError::Network(format!("Google Books lookup failed: {}", error));
+
+        let mut request = self
+            .client
+            .get(Self::SEARCH_URL)
+            .query("q", &format!("intitle:{} inauthor:{}", title, author))
+            .query("maxResults", "1");
+        if let Some(key) = &self.api_key {
+            request = request.query("key", key);
+        }
+
+        let response = request
+            .call()
+            .map_err(|error| network_error(error.to_string()))?;
+        let json = response
+            .into_string()
+            .map_err(|error| network_error(error.to_string()))?;
+
+        Self::parse_search(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct FakeProvider {
+        lookups: Arc<AtomicUsize>,
+    }
+
+    impl MetadataProvider for FakeProvider {
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        fn min_interval(&self) -> Duration {
+            Duration::ZERO
+        }
+
+        fn lookup(&self, title: &str, _author: &str) -> Result<Option<Metadata>, KindlrError> {
+            self.lookups.fetch_add(1, Ordering::Relaxed);
+            Ok((title == "Dune").then(|| Metadata {
+                year: Some(1965),
+                ..Metadata::default()
+            }))
+        }
+    }
+
+    #[test]
+    fn test_enricher_caches_lookups() {
+        let dir = std::env::temp_dir().join(format!("kindlr-enrich-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let clippings = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Sunday, 5 January 2025 09:00:00
+
+Be one.
+==========";
+
+        let lookups = Arc::new(AtomicUsize::new(0));
+        for _ in 0..2 {
+            let provider = FakeProvider {
+                lookups: Arc::clone(&lookups),
+            };
+            let mut library = Library::new(parse_clippings(clippings).unwrap());
+            let mut enricher = Enricher::new(Box::new(provider), &dir).unwrap();
+
+            assert_eq!(enricher.enrich(&mut library).unwrap(), 1);
+            assert_eq!(library.books[0].metadata.as_ref().unwrap().year, Some(1965));
+            assert!(library.books[1].metadata.is_none());
+        }
+
+        // The second run answers both books from the cache
+        assert_eq!(lookups.load(Ordering::Relaxed), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cover_cache() {
+        let dir = std::env::temp_dir().join(format!("kindlr-covers-{}", std::process::id()));
+        let cache = CoverCache::new(&dir, net::Client::new().offline(true));
+        let url = "https://covers.openlibrary.org/b/id/11481354-L.jpg";
+        let mut library = Library::new(
+            parse_clippings(
+                "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========",
+            )
+            .unwrap(),
+        );
+        library.books[0].metadata = Some(Metadata {
+            cover_url: Some(url.to_string()),
+            ..Metadata::default()
+        });
+
+        // Already cached, so nothing is downloaded
+        let path = cache.path_for(url);
+        assert_eq!(path.extension().unwrap(), "jpg");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, b"\xff\xd8\xff").unwrap();
+
+        assert_eq!(cache.fetch(&mut library), 1);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            library.books[0].metadata.as_ref().unwrap().cover_path,
+            Some(path)
+        );
+    }
+
+    #[cfg(feature = "enrich")]
+    #[test]
+    fn test_open_library_response() {
+        let metadata = OpenLibrary::parse_search(
+            r#"{"numFound": 1, "docs": [{"isbn": ["0441013597", "9780441013593"],
+                "id_amazon": ["0441013597", "B00B7NPRY8"],
+                "cover_i": 11481354, "first_publish_year": 1965,
+                "subject": ["Science fiction", "Dune (Imaginary place)"]}]}"#,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(metadata.isbn.as_deref(), Some("9780441013593"));
+        assert_eq!(metadata.asin.as_deref(), Some("B00B7NPRY8"));
+        assert_eq!(
+            metadata.cover_url.as_deref(),
+            Some("https://covers.openlibrary.org/b/id/11481354-L.jpg")
+        );
+        assert_eq!(metadata.subjects.len(), 2);
+
+        assert_eq!(
+            OpenLibrary::parse_search(r#"{"numFound": 0, "docs": []}"#).unwrap(),
+            None
+        );
+    }
+
+    #[cfg(feature = "enrich")]
+    #[test]
+    fn test_google_books_response() {
+        let metadata = GoogleBooks::parse_search(
+            r#"{"totalItems": 1, "items": [{"volumeInfo": {
+                "industryIdentifiers": [{"type": "ISBN_10", "identifier": "0441013597"},
+                                        {"type": "ISBN_13", "identifier": "9780441013593"}],
+                "imageLinks": {"thumbnail": "http://books.google.com/books/content?id=B1hSG45JCX4C"},
+                "publishedDate": "2005-08-02", "categories": ["Fiction"]}}]}"#,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(metadata.isbn.as_deref(), Some("9780441013593"));
+        assert_eq!(
+            metadata.cover_url.as_deref(),
+            Some("https://books.google.com/books/content?id=B1hSG45JCX4C")
+        );
+        assert_eq!(metadata.year, Some(2005));
+        assert_eq!(metadata.subjects, vec!["Fiction"]);
+
+        assert_eq!(
+            GoogleBooks::parse_search(r#"{"totalItems": 0}"#).unwrap(),
+            None
+        );
+    }
+}
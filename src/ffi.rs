@@ -0,0 +1,227 @@
+//! C interface for embedding the parser in other languages
+//!
+//! Parse a buffer into an opaque handle, read clippings from it by index and
+//! free it when done. Strings stay valid until the handle is freed. The header
+//! is `include/kindlr.h`, generated with `cbindgen --config cbindgen.toml`.
+
+use std::cell::RefCell;
+use std::ffi::{CString, c_char};
+use std::ptr;
+use std::slice;
+
+use crate::parser::{self, Clipping, ClippingType};
+
+/// Kind of clipping
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KindlrClippingType {
+    Highlight = 0,
+    Note = 1,
+    Bookmark = 2,
+    ArticleClip = 3,
+}
+
+/// A clipping whose strings are owned by its `KindlrClippings` handle
+///
+/// `page` and `location_end` are 0 when absent, `content` is null when absent.
+#[repr(C)]
+#[derive(Debug)]
+pub struct KindlrClipping {
+    pub id: *const c_char,
+    pub clipping_type: KindlrClippingType,
+    pub book_title: *const c_char,
+    pub author: *const c_char,
+    pub page: u32,
+    pub location_start: u32,
+    pub location_end: u32,
+    pub datetime: *const c_char,
+    pub content: *const c_char,
+}
+
+/// Opaque handle to parsed clippings
+pub struct KindlrClippings {
+    clippings: Vec<KindlrClipping>,
+    // Backing storage for the pointers in `clippings`
+    _strings: Vec<CString>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Copy into a C string, dropping any interior NUL bytes
+fn c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).unwrap_or_default()
+}
+
+fn set_last_error(message: String) {
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(c_string(&message)));
+}
+
+impl KindlrClippings {
+    fn new(clippings: Vec<Clipping>) -> Self {
+        let mut strings = Vec::new();
+        let mut keep = |s: &str| {
+            let s = c_string(s);
+            // Moving the CString doesn't move its heap buffer
+            let ptr = s.as_ptr();
+            strings.push(s);
+            ptr
+        };
+
+        let clippings = clippings
+            .iter()
+            .map(|clipping| KindlrClipping {
+                id: keep(&clipping.id()),
+                clipping_type: match clipping.clipping_type {
+                    ClippingType::Highlight => KindlrClippingType::Highlight,
+                    ClippingType::Note => KindlrClippingType::Note,
+                    ClippingType::Bookmark => KindlrClippingType::Bookmark,
+                    ClippingType::ArticleClip => KindlrClippingType::ArticleClip,
+                },
+                book_title: keep(&clipping.book_title),
+                author: keep(&clipping.author),
+                page: clipping.page.unwrap_or(0),
+                location_start: clipping.location.start,
+                location_end: clipping.location.end.unwrap_or(0),
+                datetime: keep(&clipping.datetime),
+                content: clipping.content.as_deref().map_or(ptr::null(), &mut keep),
+            })
+            .collect();
+
+        KindlrClippings {
+            clippings,
+            _strings: strings,
+        }
+    }
+}
+
+/// Parse `len` bytes of UTF-8 clippings text
+///
+/// Returns null on failure; `kindlr_last_error` then describes the problem.
+///
+/// # Safety
+///
+/// `buffer` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kindlr_parse(buffer: *const u8, len: usize) -> *mut KindlrClippings {
+    if buffer.is_null() {
+        set_last_error("Null buffer".to_string());
+        return ptr::null_mut();
+    }
+
+    // SAFETY: the caller guarantees `buffer` holds `len` bytes
+    let bytes = unsafe { slice::from_raw_parts(buffer, len) };
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(error) => {
+            set_last_error(format!("Invalid UTF-8: {}", error));
+            return ptr::null_mut();
+        }
+    };
+
+    match parser::parse_clippings(text) {
+        Ok(clippings) => Box::into_raw(Box::new(KindlrClippings::new(clippings))),
+        Err(error) => {
+            set_last_error(error.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Number of clippings behind the handle
+///
+/// # Safety
+///
+/// `handle` must come from `kindlr_parse` and not have been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kindlr_clippings_len(handle: *const KindlrClippings) -> usize {
+    // SAFETY: the caller guarantees `handle` is live or null
+    unsafe { handle.as_ref() }.map_or(0, |handle| handle.clippings.len())
+}
+
+/// Clipping at `index`, or null when out of range
+///
+/// # Safety
+///
+/// `handle` must come from `kindlr_parse` and not have been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kindlr_clippings_get(
+    handle: *const KindlrClippings,
+    index: usize,
+) -> *const KindlrClipping {
+    // SAFETY: the caller guarantees `handle` is live or null
+    unsafe { handle.as_ref() }
+        .and_then(|handle| handle.clippings.get(index))
+        .map_or(ptr::null(), ptr::from_ref)
+}
+
+/// Free a handle and every string read from it
+///
+/// # Safety
+///
+/// `handle` must come from `kindlr_parse` and is invalid afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kindlr_clippings_free(handle: *mut KindlrClippings) {
+    if !handle.is_null() {
+        // SAFETY: the caller hands back ownership of a handle from `kindlr_parse`
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
+
+/// Message of the last failure on this thread, or null
+///
+/// Valid until the next failing call on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn kindlr_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn test_ffi() {
+        let text = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Bookmark on page 2 | Location 20 | Added on Monday, 1 January 2024 10:05:00
+
+
+==========";
+
+        unsafe {
+            let handle = kindlr_parse(text.as_ptr(), text.len());
+            assert!(!handle.is_null());
+            assert_eq!(kindlr_clippings_len(handle), 2);
+
+            let highlight = &*kindlr_clippings_get(handle, 0);
+            assert_eq!(CStr::from_ptr(highlight.book_title).to_str(), Ok("Dune"));
+            assert_eq!(
+                CStr::from_ptr(highlight.content).to_str(),
+                Ok("Fear is the mind-killer.")
+            );
+            assert_eq!(highlight.location_end, 12);
+
+            let bookmark = &*kindlr_clippings_get(handle, 1);
+            assert_eq!(bookmark.clipping_type, KindlrClippingType::Bookmark);
+            assert!(kindlr_clippings_get(handle, 2).is_null());
+            kindlr_clippings_free(handle);
+
+            let broken = "Dune\n- nonsense\n\n==========";
+            assert!(kindlr_parse(broken.as_ptr(), broken.len()).is_null());
+            assert!(!kindlr_last_error().is_null());
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use chrono::Duration;
+use kindlr_core::samples::{AUTHORS, TITLES, WORDS, epoch};
+
+use crate::export::kindle_entry;
+use crate::parser::{Clipping, ClippingType, Language, Location};
+
+/// A My Clippings.txt as written by a Kindle set to English, with CRLF line
+/// endings, a highlight extended by a later one and notes beside highlights
+pub const ENGLISH: &str = include_str!("../fixtures/english.txt");
+pub const CZECH: &str = include_str!("../fixtures/czech.txt");
+pub const GREEK: &str = include_str!("../fixtures/greek.txt");
+pub const SWEDISH: &str = include_str!("../fixtures/swedish.txt");
+pub const NORWEGIAN: &str = include_str!("../fixtures/norwegian.txt");
+pub const DANISH: &str = include_str!("../fixtures/danish.txt");
+pub const FINNISH: &str = include_str!("../fixtures/finnish.txt");
+/// Times are written with 上午 and 下午, including midnight and noon
+pub const TRADITIONAL_CHINESE: &str = include_str!("../fixtures/traditional_chinese.txt");
+
+/// Every sample file and the language its metadata is written in
+pub const SAMPLES: [(Language, &str); 8] = [
+    (Language::English, ENGLISH),
+    (Language::Czech, CZECH),
+    (Language::Greek, GREEK),
+    (Language::Swedish, SWEDISH),
+    (Language::Norwegian, NORWEGIAN),
+    (Language::Danish, DANISH),
+    (Language::Finnish, FINNISH),
+    (Language::TraditionalChinese, TRADITIONAL_CHINESE),
+];
+
+/// A My Clippings.txt of `n` realistic entries with metadata in `language`
+///
+/// The same arguments always give the same file, so tests using it are
+/// repeatable without a seed.
+pub fn generate_clippings_file(n: usize, language: Language) -> String {
+    FileBuilder::new(n).language(language).build()
+}
+
+/// Builder for synthetic clippings files of any size, e.g.
+///
+/// ```
+/// use kindlr::fixtures::FileBuilder;
+/// use kindlr::parser::Language;
+///
+/// let file = FileBuilder::new(10_000).language(Language::Czech).crlf(true).build();
+/// ```
+///
+/// Entries are drawn from a fixed pool of titles, authors and words mixing
+/// scripts and punctuation, added a few days apart from 2015 on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileBuilder {
+    entries: usize,
+    language: Language,
+    seed: Option<u64>,
+    crlf: bool,
+}
+
+impl FileBuilder {
+    /// `entries` English entries with LF line endings
+    pub fn new(entries: usize) -> Self {
+        FileBuilder {
+            entries,
+            language: Language::English,
+            seed: None,
+            crlf: false,
+        }
+    }
+
+    /// Write metadata lines in `language`
+    pub fn language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Draw another file of the same size; the same seed gives the same file
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// End lines with CRLF, as Kindles do
+    pub fn crlf(mut self, crlf: bool) -> Self {
+        self.crlf = crlf;
+        self
+    }
+
+    /// The clippings the file holds
+    pub fn clippings(&self) -> Vec<Clipping> {
+        let seed = self
+            .seed
+            .unwrap_or(0x9e37_79b9_7f4a_7c15 ^ self.entries as u64);
+        let mut random = Random(seed);
+        let mut added = epoch();
+
+        (0..self.entries)
+            .map(|_| {
+                added += Duration::minutes(random.below(60 * 24 * 3) as i64);
+                let clipping_type = match random.below(10) {
+                    0 => ClippingType::Bookmark,
+                    1 | 2 => ClippingType::Note,
+                    _ => ClippingType::Highlight,
+                };
+                let start = 1 + random.below(20_000) as u32;
+                let words = 1 + random.below(40);
+                let content = (clipping_type != ClippingType::Bookmark).then(|| {
+                    (0..words)
+                        .map(|_| WORDS[random.below(WORDS.len() as u64) as usize])
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                });
+
+                Clipping::new(
+                    clipping_type,
+                    TITLES[random.below(TITLES.len() as u64) as usize].to_string(),
+                    AUTHORS[random.below(AUTHORS.len() as u64) as usize].to_string(),
+                    Some(1 + start / 15),
+                    Location {
+                        start,
+                        end: (clipping_type == ClippingType::Highlight)
+                            .then(|| start + random.below(12) as u32),
+                    },
+                    added,
+                    content,
+                )
+            })
+            .collect()
+    }
+
+    /// The file
+    pub fn build(&self) -> String {
+        let file: String = self
+            .clippings()
+            .iter()
+            .map(|clipping| kindle_entry(clipping, self.language))
+            .collect();
+        if self.crlf {
+            file
+        } else {
+            file.replace("\r\n", "\n")
+        }
+    }
+}
+
+/// SplitMix64, enough to vary fixtures without a dependency
+struct Random(u64);
+
+impl Random {
+    /// A number below `bound`
+    fn below(&mut self, bound: u64) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        (z ^ (z >> 31)) % bound.max(1)
+    }
+}
+
+/// proptest strategies for clippings, their metadata lines and whole files
+#[cfg(feature = "proptest")]
+pub mod strategies {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::export::kindle_metadata_line;
+
+    /// Any clipping a Kindle could write
+    pub fn clipping() -> impl Strategy<Value = Clipping> {
+        any::<Clipping>()
+    }
+
+    /// Metadata lines a Kindle set to `language` could write
+    pub fn metadata_line(language: Language) -> impl Strategy<Value = String> {
+        clipping().prop_map(move |clipping| kindle_metadata_line(&clipping, language))
+    }
+
+    /// Up to `max` clippings and the My Clippings.txt holding them
+    pub fn clippings_file(
+        max: usize,
+        language: Language,
+    ) -> impl Strategy<Value = (Vec<Clipping>, String)> {
+        proptest::collection::vec(clipping(), 0..=max).prop_map(move |clippings| {
+            let file = clippings
+                .iter()
+                .map(|clipping| kindle_entry(clipping, language))
+                .collect();
+            (clippings, file)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    #[test]
+    fn test_generate_clippings_file() {
+        let file = generate_clippings_file(200, Language::English);
+        assert_eq!(file, generate_clippings_file(200, Language::English));
+
+        let clippings = parse_clippings(&file).unwrap();
+        assert_eq!(clippings.len(), 200);
+        assert!(
+            clippings
+                .iter()
+                .all(|clipping| clipping.timestamp().is_some())
+        );
+
+        // The same clippings written in any language read back the same
+        for language in Language::ALL {
+            let translated = parse_clippings(&generate_clippings_file(200, language)).unwrap();
+            let ids = |clippings: &[Clipping]| -> Vec<String> {
+                clippings.iter().map(Clipping::id).collect()
+            };
+            assert_eq!(ids(&translated), ids(&clippings), "{:?}", language);
+        }
+
+        let builder = FileBuilder::new(50).seed(7).crlf(true);
+        assert_ne!(builder.build(), FileBuilder::new(50).build());
+        let ids = |clippings: Vec<Clipping>| -> Vec<String> {
+            clippings.iter().map(Clipping::id).collect()
+        };
+        assert_eq!(
+            ids(parse_clippings(&builder.build()).unwrap()),
+            ids(builder.clippings())
+        );
+    }
+
+    #[test]
+    fn test_samples() {
+        for (language, sample) in SAMPLES {
+            let clippings = parse_clippings(sample).unwrap();
+            assert!(clippings.len() >= 3, "{:?}", language);
+            assert!(
+                clippings
+                    .iter()
+                    .all(|clipping| clipping.timestamp().is_some())
+            );
+        }
+        assert!(ENGLISH.contains("\r\n"));
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn test_parse_generated((clippings, file) in strategies::clippings_file(20, Language::English)) {
+            let parsed = parse_clippings(&file).unwrap();
+            proptest::prop_assert_eq!(parsed.len(), clippings.len());
+            for (parsed, generated) in parsed.iter().zip(&clippings) {
+                proptest::prop_assert_eq!(parsed.id(), generated.id());
+                proptest::prop_assert_eq!(&parsed.content, &generated.content);
+            }
+        }
+    }
+}
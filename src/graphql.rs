@@ -0,0 +1,348 @@
+use async_graphql::{
+    EmptyMutation, EmptySubscription, Error, InputObject, Object, Result, Schema, SimpleObject,
+};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::parser::Clipping;
+use crate::serve::{self, BookEntry, ClippingEntry};
+use crate::stats::{self, Summary};
+
+/// Schema of `/graphql`: queries of the clippings `serve` answers from, the
+/// REST routes staying the way to change them
+pub type KindlrSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+/// A schema answering from `clippings`, of which `starred` are starred
+pub fn schema(clippings: Vec<Clipping>, starred: HashSet<String>) -> KindlrSchema {
+    let query = Query {
+        books: serve::books(&clippings),
+        clippings,
+        starred,
+    };
+    Schema::new(query, EmptyMutation, EmptySubscription)
+}
+
+/// What `schema` answers `request` with
+pub fn execute(schema: &KindlrSchema, request: async_graphql::Request) -> async_graphql::Response {
+    // Every resolver is ready at once, so nothing waits on a runtime
+    pollster::block_on(schema.execute(request))
+}
+
+/// A request from the `query`, `variables` and `operationName` parameters
+/// of a GET
+pub fn from_params(params: &BTreeMap<String, String>) -> Result<async_graphql::Request, String> {
+    let query = params
+        .get("query")
+        .ok_or_else(|| "Missing parameter: query".to_string())?;
+    let mut request = async_graphql::Request::new(query.as_str());
+    if let Some(variables) = params.get("variables") {
+        let variables = serde_json::from_str(variables).map_err(|error| error.to_string())?;
+        request = request.variables(async_graphql::Variables::from_json(variables));
+    }
+    if let Some(name) = params.get("operationName") {
+        request = request.operation_name(name.as_str());
+    }
+    Ok(request)
+}
+
+/// Filters of `clippings` and `stats`, as `/clippings` takes them
+#[derive(Debug, Default, InputObject)]
+pub struct ClippingFilter {
+    /// Query such as book:"dune" type:note added:>2024-01-01
+    query: Option<String>,
+    /// Book title contains text
+    book: Option<String>,
+    /// Author contains text
+    author: Option<String>,
+    /// Content contains text
+    contains: Option<String>,
+    /// Any of highlight, note, bookmark, article
+    types: Option<Vec<String>>,
+    /// Any of yellow, blue, pink, orange
+    colors: Option<Vec<String>>,
+    /// Added on or after yyyy-mm-dd
+    since: Option<String>,
+    /// Added on or before yyyy-mm-dd
+    until: Option<String>,
+    /// Content has at least this many characters
+    min_length: Option<usize>,
+    /// Content has at most this many characters
+    max_length: Option<usize>,
+    /// Read from a device named or numbered like text
+    device: Option<String>,
+    /// Only starred clippings
+    favorites_only: Option<bool>,
+}
+
+impl ClippingFilter {
+    /// The filter as `/clippings` parameters
+    fn params(&self) -> BTreeMap<String, String> {
+        let texts = [
+            ("query", &self.query),
+            ("book", &self.book),
+            ("author", &self.author),
+            ("contains", &self.contains),
+            ("since", &self.since),
+            ("until", &self.until),
+            ("device", &self.device),
+        ];
+        let lists = [("type", &self.types), ("color", &self.colors)];
+        let numbers = [
+            ("min-length", self.min_length),
+            ("max-length", self.max_length),
+        ];
+
+        let texts = texts
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value.clone()?)));
+        let lists = lists
+            .into_iter()
+            .filter_map(|(name, values)| Some((name, values.as_ref()?.join(","))));
+        let numbers = numbers
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value?.to_string())));
+        let favorites = self
+            .favorites_only
+            .map(|favorites| ("favorites-only", favorites.to_string()));
+
+        texts
+            .chain(lists)
+            .chain(numbers)
+            .chain(favorites)
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+}
+
+/// A page of `books`
+#[derive(SimpleObject)]
+pub struct BookPage {
+    items: Vec<BookEntry>,
+    /// `after` of the next page, if there is one
+    next_cursor: Option<String>,
+}
+
+/// A page of `clippings`
+#[derive(SimpleObject)]
+pub struct ClippingPage {
+    items: Vec<ClippingNode>,
+    /// `after` of the next page, if there is one
+    next_cursor: Option<String>,
+}
+
+/// A clipping as `/clippings` answers with it
+pub struct ClippingNode(ClippingEntry);
+
+#[Object(name = "Clipping")]
+impl ClippingNode {
+    /// What `clipping` finds it by, as `list` shows it
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    async fn favorite(&self) -> bool {
+        self.0.favorite
+    }
+
+    /// Highlight, note, bookmark or article clip
+    #[graphql(name = "type")]
+    async fn clipping_type(&self) -> String {
+        self.0.clipping.clipping_type.to_string()
+    }
+
+    async fn book_title(&self) -> &str {
+        &self.0.clipping.book_title
+    }
+
+    async fn author(&self) -> &str {
+        &self.0.clipping.author
+    }
+
+    async fn page(&self) -> Option<u32> {
+        self.0.clipping.page
+    }
+
+    async fn location(&self) -> String {
+        self.0.clipping.location.to_string()
+    }
+
+    /// As the Kindle wrote it, once any clock rule and timezone applied
+    async fn datetime(&self) -> &str {
+        &self.0.clipping.datetime
+    }
+
+    async fn content(&self) -> Option<&str> {
+        self.0.clipping.content.as_deref()
+    }
+
+    async fn color(&self) -> Option<String> {
+        self.0.clipping.color.map(|color| color.to_string())
+    }
+
+    async fn device(&self) -> Option<&str> {
+        self.0.clipping.device.as_deref()
+    }
+}
+
+/// Books, clippings and their counts
+pub struct Query {
+    clippings: Vec<Clipping>,
+    starred: HashSet<String>,
+    books: Vec<BookEntry>,
+}
+
+impl Query {
+    fn matching(&self, filter: Option<ClippingFilter>) -> Result<Vec<&Clipping>> {
+        let params = filter.unwrap_or_default().params();
+        let query = serve::filter(&params, &self.starred).map_err(Error::new)?;
+        Ok(query.filter(&self.clippings))
+    }
+
+    fn node(&self, clipping: &Clipping) -> ClippingNode {
+        let id = clipping.id();
+        ClippingNode(ClippingEntry {
+            favorite: self.starred.contains(&id),
+            id,
+            clipping: clipping.clone(),
+        })
+    }
+}
+
+/// `first` and `after` as the `limit` and `cursor` parameters of the REST
+/// routes
+fn paging(first: Option<usize>, after: Option<String>) -> BTreeMap<String, String> {
+    let first = first.map(|first| ("limit".to_string(), first.to_string()));
+    let after = after.map(|after| ("cursor".to_string(), after));
+    first.into_iter().chain(after).collect()
+}
+
+#[Object]
+impl Query {
+    /// Books and how many clippings each has
+    async fn books(&self, first: Option<usize>, after: Option<String>) -> Result<BookPage> {
+        let page = serve::paginate(&self.books, BookEntry::cursor, &paging(first, after))
+            .map_err(Error::new)?;
+
+        Ok(BookPage {
+            items: page.items.into_iter().cloned().collect(),
+            next_cursor: page.next_cursor,
+        })
+    }
+
+    /// Clippings matching every filter given
+    async fn clippings(
+        &self,
+        filter: Option<ClippingFilter>,
+        first: Option<usize>,
+        after: Option<String>,
+    ) -> Result<ClippingPage> {
+        let matching = self.matching(filter)?;
+        let page = serve::paginate(&matching, |clipping| clipping.id(), &paging(first, after))
+            .map_err(Error::new)?;
+
+        Ok(ClippingPage {
+            items: page
+                .items
+                .into_iter()
+                .map(|clipping| self.node(clipping))
+                .collect(),
+            next_cursor: page.next_cursor,
+        })
+    }
+
+    /// A clipping by the id list shows
+    async fn clipping(&self, id: String) -> Option<ClippingNode> {
+        self.clippings
+            .iter()
+            .find(|clipping| clipping.id() == id)
+            .map(|clipping| self.node(clipping))
+    }
+
+    /// Counts of the clippings matching every filter given, as stats shows
+    async fn stats(&self, filter: Option<ClippingFilter>) -> Result<Summary> {
+        let matching: Vec<Clipping> = self.matching(filter)?.into_iter().cloned().collect();
+        Ok(stats::summary(&matching))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+    use serde_json::json;
+
+    const CLIPPINGS: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00
+
+Classic.
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Sunday, 5 January 2025 09:00:00
+
+Waste no more time.
+==========";
+
+    #[test]
+    fn test_schema() {
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let starred = HashSet::from([clippings[2].id()]);
+        let schema = schema(clippings, starred);
+
+        let answer = execute(
+            &schema,
+            async_graphql::Request::new(
+                r#"{
+                    books(first: 1) { items { title clippings } nextCursor }
+                    clippings(filter: {types: ["highlight"], favoritesOnly: true}) {
+                        items { type bookTitle content favorite }
+                    }
+                    stats(filter: {book: "dune"}) { highlights notes books }
+                }"#,
+            ),
+        );
+        assert!(answer.errors.is_empty(), "{:?}", answer.errors);
+        assert_eq!(
+            answer.data.into_json().unwrap(),
+            json!({
+                "books": {
+                    "items": [{"title": "Dune", "clippings": 2}],
+                    "nextCursor": "Dune (Frank Herbert)",
+                },
+                "clippings": {
+                    "items": [{
+                        "type": "Highlight",
+                        "bookTitle": "Meditations",
+                        "content": "Waste no more time.",
+                        "favorite": true,
+                    }],
+                },
+                "stats": {"highlights": 1, "notes": 1, "books": 1},
+            })
+        );
+
+        // Filters are checked as /clippings checks them
+        let answer = execute(
+            &schema,
+            async_graphql::Request::new(
+                r#"{ clippings(filter: {colors: ["red"]}) { items { id } } }"#,
+            ),
+        );
+        assert_eq!(answer.errors.len(), 1);
+
+        let params = BTreeMap::from([
+            (
+                "query".to_string(),
+                "query Count($id: String!) { clipping(id: $id) { id } }".to_string(),
+            ),
+            ("variables".to_string(), r#"{"id": "0"}"#.to_string()),
+        ]);
+        let answer = execute(&schema, from_params(&params).unwrap());
+        assert_eq!(answer.data.into_json().unwrap(), json!({"clipping": null}));
+        assert!(from_params(&BTreeMap::new()).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::KindlrError;
+use crate::library::Library;
+use crate::parser::Clipping;
+use crate::settings::Hooks;
+
+type PostParseHook = Box<dyn Fn(Vec<Clipping>) -> Result<Vec<Clipping>, KindlrError>>;
+type TransformHook = Box<dyn Fn(Clipping) -> Result<Option<Clipping>, KindlrError>>;
+type PreExportHook = Box<dyn Fn(&mut Library) -> Result<(), KindlrError>>;
+
+/// Hooks run at each stage of processing clippings
+///
+/// - post-parse hooks see every clipping once the file has been read
+/// - transform hooks then see each clipping on its own and may drop it
+/// - pre-export hooks see the library about to be exported
+///
+/// Hooks of a stage run in the order they were added.
+#[derive(Default)]
+pub struct Pipeline {
+    post_parse: Vec<PostParseHook>,
+    transform: Vec<TransformHook>,
+    pre_export: Vec<PreExportHook>,
+}
+
+impl Pipeline {
+    /// A pipeline without any hooks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A pipeline running the external commands in `hooks`
+    ///
+    /// Each command is run by `sh -c` with JSON on stdin and must print the
+    /// same kind of JSON on stdout: an array of clippings after parsing, a
+    /// single clipping (or `null` to drop it) per transform and the library
+    /// before exporting.
+    pub fn from_hooks(hooks: &Hooks) -> Self {
+        let mut pipeline = Self::new();
+
+        for command in &hooks.post_parse {
+            let command = command.clone();
+            pipeline.on_post_parse(move |clippings| run_command(&command, &clippings));
+        }
+        for command in &hooks.transform {
+            let command = command.clone();
+            pipeline.on_transform(move |clipping| run_command(&command, &clipping));
+        }
+        for command in &hooks.pre_export {
+            let command = command.clone();
+            pipeline.on_pre_export(move |library| {
+                let mut hooked: Library = run_command(&command, &*library)?;
+                // How kindlr formats the export isn't sent to the command
+                hooked.date_format = library.date_format.clone();
+                hooked.section_gap = library.section_gap;
+                *library = hooked;
+                Ok(())
+            });
+        }
+
+        pipeline
+    }
+
+    pub fn on_post_parse<F>(&mut self, hook: F)
+    where
+        F: Fn(Vec<Clipping>) -> Result<Vec<Clipping>, KindlrError> + 'static,
+    {
+        self.post_parse.push(Box::new(hook));
+    }
+
+    pub fn on_transform<F>(&mut self, hook: F)
+    where
+        F: Fn(Clipping) -> Result<Option<Clipping>, KindlrError> + 'static,
+    {
+        self.transform.push(Box::new(hook));
+    }
+
+    pub fn on_pre_export<F>(&mut self, hook: F)
+    where
+        F: Fn(&mut Library) -> Result<(), KindlrError> + 'static,
+    {
+        self.pre_export.push(Box::new(hook));
+    }
+
+    /// Run the post-parse hooks, then the transform hooks on each clipping
+    pub fn process(&self, mut clippings: Vec<Clipping>) -> Result<Vec<Clipping>, KindlrError> {
+        for hook in &self.post_parse {
+            clippings = hook(clippings)?;
+        }
+
+        if self.transform.is_empty() {
+            return Ok(clippings);
+        }
+
+        let mut transformed = Vec::with_capacity(clippings.len());
+        'clippings: for mut clipping in clippings {
+            for hook in &self.transform {
+                match hook(clipping)? {
+                    Some(next) => clipping = next,
+                    None => continue 'clippings,
+                }
+            }
+            transformed.push(clipping);
+        }
+
+        Ok(transformed)
+    }
+
+    pub fn pre_export(&self, library: &mut Library) -> Result<(), KindlrError> {
+        for hook in &self.pre_export {
+            hook(library)?;
+        }
+        Ok(())
+    }
+}
+
+/// Pipe `input` as JSON through `command` and read back its JSON output
+fn run_command<T: DeserializeOwned>(
+    command: &str,
+    input: &impl Serialize,
+) -> Result<T, KindlrError> {
+    let input = serde_json::to_vec(input).map_err(|error| KindlrError::Io(error.into()))?;
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|error| KindlrError::Hook(format!("Could not run `{}`: {}", command, error)))?;
+
+    // Write from another thread so a command printing as it reads can't block us
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = thread::spawn(move || stdin.write_all(&input));
+
+    let output = child.wait_with_output()?;
+    // The command may exit without reading all of its input
+    let _ = writer.join();
+
+    if !output.status.success() {
+        return Err(KindlrError::Hook(format!(
+            "`{}` exited with {}",
+            command, output.status
+        )));
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .map_err(|error| KindlrError::Hook(format!("Invalid output from `{}`: {}", command, error)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dates::DateFormat;
+    use crate::parser::{ClippingType, parse_clippings};
+
+    const CLIPPINGS: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Bookmark on page 2 | Location 20 | Added on Monday, 1 January 2024 10:05:00
+
+
+==========";
+
+    #[test]
+    fn test_closures() {
+        let mut pipeline = Pipeline::new();
+        pipeline.on_post_parse(|mut clippings| {
+            clippings.reverse();
+            Ok(clippings)
+        });
+        pipeline.on_transform(|clipping| {
+            Ok((clipping.clipping_type != ClippingType::Bookmark).then_some(clipping))
+        });
+        pipeline.on_pre_export(|library| {
+            library.books[0].length = Some(100);
+            Ok(())
+        });
+
+        let clippings = pipeline
+            .process(parse_clippings(CLIPPINGS).unwrap())
+            .unwrap();
+        assert_eq!(clippings.len(), 1);
+
+        let mut library = Library::new(clippings);
+        pipeline.pre_export(&mut library).unwrap();
+        assert_eq!(library.books[0].length, Some(100));
+    }
+
+    #[test]
+    fn test_commands() {
+        let hooks = Hooks {
+            post_parse: vec!["cat".to_string()],
+            transform: vec!["sed 's/mind-killer/little-death/'".to_string()],
+            pre_export: vec!["cat".to_string()],
+        };
+        let pipeline = Pipeline::from_hooks(&hooks);
+
+        let clippings = pipeline
+            .process(parse_clippings(CLIPPINGS).unwrap())
+            .unwrap();
+        assert_eq!(clippings.len(), 2);
+        assert_eq!(
+            clippings[0].content.as_deref(),
+            Some("Fear is the little-death.")
+        );
+
+        let mut library = Library::new(clippings);
+        library.date_format = DateFormat::Date;
+        library.section_gap = Some(500);
+        pipeline.pre_export(&mut library).unwrap();
+        assert_eq!(library.books[0].clippings.len(), 2);
+        assert_eq!(library.date_format, DateFormat::Date);
+        assert_eq!(library.section_gap, Some(500));
+
+        let failing = Hooks {
+            post_parse: vec!["exit 3".to_string()],
+            ..Hooks::default()
+        };
+        let error = Pipeline::from_hooks(&failing)
+            .process(Vec::new())
+            .unwrap_err();
+        assert_eq!(error.code(), "hook");
+    }
+}
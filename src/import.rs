@@ -0,0 +1,905 @@
+use chrono::{DateTime, Local, NaiveDateTime};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+#[cfg(feature = "async")]
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+use crate::KindlrError;
+use crate::parser::{self, Clipping, ClippingType, Location};
+
+/// Bytes read from the start of a file to recognize its source
+const SNIFF_LEN: usize = 4096;
+
+/// A source of clippings other than, or including, My Clippings.txt
+///
+/// Implement this and add it to an `ImporterRegistry` to read a new kind of file.
+pub trait Importer {
+    /// Name the source is selected by, e.g. "kindle"
+    fn name(&self) -> &str;
+
+    /// Whether a file starting with `head` looks like this source
+    fn sniff(&self, head: &[u8]) -> bool;
+
+    fn import(&self, path: &Path) -> Result<Vec<Clipping>, KindlrError>;
+}
+
+/// Importers by name
+#[derive(Default)]
+pub struct ImporterRegistry {
+    importers: BTreeMap<String, Box<dyn Importer>>,
+}
+
+impl ImporterRegistry {
+    /// A registry without any sources
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry with the sources kindlr ships with
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::new();
+        for importer in builtin() {
+            registry.register(importer);
+        }
+        registry
+    }
+
+    /// Add a source, replacing any source of the same name
+    pub fn register(&mut self, importer: Box<dyn Importer>) {
+        self.importers.insert(importer.name().to_string(), importer);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Importer> {
+        self.importers.get(name).map(|importer| importer.as_ref())
+    }
+
+    /// The first importer recognizing the file at `path`
+    pub fn detect(&self, path: &Path) -> Option<&dyn Importer> {
+        let head = head(path)?;
+        self.importers
+            .values()
+            .map(|importer| importer.as_ref())
+            .find(|importer| importer.sniff(&head))
+    }
+}
+
+fn builtin() -> Vec<Box<dyn Importer>> {
+    vec![
+        Box::new(KindleImporter),
+        Box::new(KoboImporter),
+        Box::new(AmazonHtmlImporter),
+        Box::new(ReadwiseCsvImporter),
+        Box::new(OmnivoreImporter),
+        Box::new(MatterCsvImporter),
+        Box::new(PocketImporter),
+    ]
+}
+
+fn head(path: &Path) -> Option<Vec<u8>> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    fs::File::open(path)
+        .ok()?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)
+        .ok()?;
+    Some(head)
+}
+
+/// Pick a built-in importer by looking at the file's contents
+pub fn detect_source(path: &Path) -> Option<Box<dyn Importer>> {
+    let head = head(path)?;
+    builtin().into_iter().find(|importer| importer.sniff(&head))
+}
+
+/// Like `read`, memory-mapping My Clippings.txt so huge files are parsed an
+/// entry at a time instead of being read into memory
+pub fn read_mapped(path: &Path) -> Result<Vec<Clipping>, KindlrError> {
+    match detect_source(path) {
+        Some(importer) if importer.name() != KindleImporter.name() => importer.import(path),
+        _ => KindleImporter.import_mapped(path),
+    }
+}
+
+/// Read clippings from any recognized source, treating unrecognized files as
+/// My Clippings.txt so parse errors point at the problem
+pub fn read(path: &Path) -> Result<Vec<Clipping>, KindlrError> {
+    match detect_source(path) {
+        Some(importer) => importer.import(path),
+        None => KindleImporter.import(path),
+    }
+}
+
+/// Like `read`, importing on tokio's blocking thread pool so large files
+/// don't stall other tasks
+#[cfg(feature = "async")]
+pub async fn read_async(path: impl AsRef<Path>) -> Result<Vec<Clipping>, KindlrError> {
+    let path = path.as_ref().to_path_buf();
+    tokio::task::spawn_blocking(move || read(&path))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// Import `path` with `importer` on tokio's blocking thread pool
+#[cfg(feature = "async")]
+pub async fn import_async(
+    importer: Box<dyn Importer + Send>,
+    path: impl AsRef<Path>,
+) -> Result<Vec<Clipping>, KindlrError> {
+    let path = path.as_ref().to_path_buf();
+    tokio::task::spawn_blocking(move || importer.import(&path))
+        .await
+        .map_err(io::Error::other)?
+}
+
+fn text(head: &[u8]) -> String {
+    String::from_utf8_lossy(head)
+        .trim_start_matches('\u{feff}')
+        .to_string()
+}
+
+/// My Clippings.txt from a Kindle
+pub struct KindleImporter;
+
+impl Importer for KindleImporter {
+    fn name(&self) -> &str {
+        "kindle"
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        let head = text(head);
+        head.contains("==========")
+            || head
+                .lines()
+                .nth(1)
+                .is_some_and(|line| line.starts_with("- "))
+    }
+
+    fn import(&self, path: &Path) -> Result<Vec<Clipping>, KindlrError> {
+        let contents = fs::read_to_string(path)?;
+        Ok(parser::parse_clippings(&contents)?)
+    }
+}
+
+impl KindleImporter {
+    #[cfg(feature = "mmap")]
+    fn import_mapped(&self, path: &Path) -> Result<Vec<Clipping>, KindlrError> {
+        let file = fs::File::open(path)?;
+        // SAFETY: the map is only read while parsing; a Kindle appending to the
+        // file meanwhile can at worst cut the last entry short
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(parser::Parser::default().parse_bytes(&map)?)
+    }
+
+    #[cfg(not(feature = "mmap"))]
+    fn import_mapped(&self, _path: &Path) -> Result<Vec<Clipping>, KindlrError> {
+        Err(KindlrError::Config(
+            "--mmap needs kindlr built with the mmap feature".to_string(),
+        ))
+    }
+}
+
+/// KoboReader.sqlite from a Kobo e-reader
+///
+/// Kobo has no Kindle locations, so each book's annotations are numbered in
+/// the order they were made.
+pub struct KoboImporter;
+
+impl Importer for KoboImporter {
+    fn name(&self) -> &str {
+        "kobo"
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        head.starts_with(b"SQLite format 3\0")
+    }
+
+    #[cfg(feature = "kobo")]
+    fn import(&self, path: &Path) -> Result<Vec<Clipping>, KindlrError> {
+        let sqlite_error = |error: rusqlite::Error| {
+            KindlrError::Config(format!("Cannot read Kobo database: {}", error))
+        };
+
+        let connection =
+            rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .map_err(sqlite_error)?;
+        let mut statement = connection
+            .prepare(
+                "SELECT c.Title, c.Attribution, b.Text, b.Annotation, b.DateCreated
+                 FROM Bookmark b JOIN content c ON c.ContentID = b.VolumeID
+                 ORDER BY b.VolumeID, b.DateCreated",
+            )
+            .map_err(sqlite_error)?;
+
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            })
+            .map_err(sqlite_error)?;
+
+        let mut clippings = Vec::new();
+        let mut numbers = std::collections::HashMap::new();
+        for row in rows {
+            let (title, author, text, annotation, created) = row.map_err(sqlite_error)?;
+            let title = title.unwrap_or_default();
+            let author = author.unwrap_or_default();
+            let added = created
+                .as_deref()
+                .and_then(parse_iso_datetime)
+                .unwrap_or_default();
+
+            let number = numbers.entry(title.clone()).or_insert(0);
+            *number += 1;
+            let location = Location {
+                start: *number,
+                end: None,
+            };
+
+            let text = text.filter(|text| !text.trim().is_empty());
+            let annotation = annotation.filter(|annotation| !annotation.trim().is_empty());
+            let clipping_type = if text.is_some() {
+                ClippingType::Highlight
+            } else if annotation.is_some() {
+                ClippingType::Note
+            } else {
+                ClippingType::Bookmark
+            };
+
+            clippings.push(Clipping::new(
+                clipping_type,
+                title.clone(),
+                author.clone(),
+                None,
+                location,
+                added,
+                text.clone().or(annotation.clone()),
+            ));
+            if text.is_some() && annotation.is_some() {
+                clippings.push(Clipping::new(
+                    ClippingType::Note,
+                    title,
+                    author,
+                    None,
+                    location,
+                    added,
+                    annotation,
+                ));
+            }
+        }
+
+        Ok(clippings)
+    }
+
+    #[cfg(not(feature = "kobo"))]
+    fn import(&self, _path: &Path) -> Result<Vec<Clipping>, KindlrError> {
+        Err(KindlrError::Config(
+            "Reading Kobo databases needs kindlr built with the kobo feature".to_string(),
+        ))
+    }
+}
+
+/// "Export Notes" HTML from the Kindle app
+///
+/// The export has no dates, so clippings are dated when the file was last modified.
+pub struct AmazonHtmlImporter;
+
+impl Importer for AmazonHtmlImporter {
+    fn name(&self) -> &str {
+        "amazon-html"
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        let head = text(head).to_lowercase();
+        head.contains("<html") && (head.contains("notebookfor") || head.contains("noteheading"))
+            || head.contains("class='booktitle'")
+            || head.contains("class=\"booktitle\"")
+    }
+
+    fn import(&self, path: &Path) -> Result<Vec<Clipping>, KindlrError> {
+        let html = fs::read_to_string(path)?;
+        let added: NaiveDateTime = fs::metadata(path)?
+            .modified()
+            .map(|time| DateTime::<Local>::from(time).naive_local())
+            .unwrap_or_default();
+        Ok(parse_amazon_html(&html, added))
+    }
+}
+
+fn parse_amazon_html(html: &str, added: NaiveDateTime) -> Vec<Clipping> {
+    let field = |class: &str| {
+        Regex::new(&format!(r#"(?s)<div class=['"]{}['"]>(.*?)</div>"#, class))
+            .unwrap()
+            .captures(html)
+            .map(|caps| unescape_html(&strip_tags(&caps[1])))
+            .unwrap_or_default()
+    };
+    let title = field("bookTitle");
+    let author = field("authors");
+
+    let note = Regex::new(
+        r#"(?s)<div class=['"]noteHeading['"]>(.*?)</div>\s*<div class=['"]noteText['"]>(.*?)</(?:div|h3)>"#,
+    )
+    .unwrap();
+    let page = Regex::new(r"Page (\d+)").unwrap();
+    let location = Regex::new(r"Location (\d+)").unwrap();
+    let color = Regex::new(r"highlight_(\w+)").unwrap();
+
+    note.captures_iter(html)
+        .filter_map(|caps| {
+            let heading = strip_tags(&caps[1]);
+            let clipping_type = if heading.starts_with("Note") {
+                ClippingType::Note
+            } else if heading.starts_with("Bookmark") {
+                ClippingType::Bookmark
+            } else if heading.starts_with("Highlight") {
+                ClippingType::Highlight
+            } else {
+                return None;
+            };
+            let number = |re: &Regex| {
+                re.captures(&heading)
+                    .and_then(|caps| caps[1].parse::<u32>().ok())
+            };
+
+            let mut clipping = Clipping::new(
+                clipping_type,
+                title.clone(),
+                author.clone(),
+                number(&page),
+                Location {
+                    start: number(&location).unwrap_or(0),
+                    end: None,
+                },
+                added,
+                Some(unescape_html(strip_tags(&caps[2]).trim())),
+            );
+            // The color is in the class of a span around its name
+            clipping.color = color
+                .captures(&caps[1])
+                .and_then(|color| color[1].parse().ok());
+            Some(clipping)
+        })
+        .collect()
+}
+
+fn strip_tags(html: &str) -> String {
+    Regex::new(r"<[^>]*>")
+        .unwrap()
+        .replace_all(html, "")
+        .trim()
+        .to_string()
+}
+
+/// `text` with HTML's named entities for markup and numeric entities
+/// replaced by the characters they stand for
+pub(crate) fn unescape_html(text: &str) -> String {
+    let numeric = Regex::new(r"&#([xX][0-9a-fA-F]+|[0-9]+);").expect("valid regex");
+    let text = numeric.replace_all(text, |caps: &regex::Captures| {
+        let code = match caps[1].strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => caps[1].parse().ok(),
+        };
+        code.and_then(char::from_u32)
+            .map_or_else(|| caps[0].to_string(), String::from)
+    });
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// CSV export from Readwise
+pub struct ReadwiseCsvImporter;
+
+impl Importer for ReadwiseCsvImporter {
+    fn name(&self) -> &str {
+        "readwise-csv"
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        text(head).starts_with("Highlight,Book Title,Book Author")
+    }
+
+    fn import(&self, path: &Path) -> Result<Vec<Clipping>, KindlrError> {
+        let contents = fs::read_to_string(path)?;
+        parse_readwise_csv(contents.trim_start_matches('\u{feff}'))
+    }
+}
+
+fn parse_readwise_csv(contents: &str) -> Result<Vec<Clipping>, KindlrError> {
+    let csv_error =
+        |error: csv::Error| KindlrError::Config(format!("Invalid Readwise CSV: {}", error));
+
+    let mut reader = csv::Reader::from_reader(contents.as_bytes());
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let (highlight, title, author, note, color, location_type, location, highlighted_at) = (
+        column("Highlight"),
+        column("Book Title"),
+        column("Book Author"),
+        column("Note"),
+        column("Color"),
+        column("Location Type"),
+        column("Location"),
+        column("Highlighted at"),
+    );
+
+    let mut clippings = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(csv_error)?;
+        let get = |column: Option<usize>| {
+            column
+                .and_then(|i| record.get(i))
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+
+        let number = get(location).parse::<u32>().ok();
+        let (page, start) = match get(location_type).as_str() {
+            "page" => (number, 0),
+            _ => (None, number.unwrap_or(0)),
+        };
+        let added = parse_iso_datetime(&get(highlighted_at)).unwrap_or_default();
+
+        let mut clipping = Clipping::new(
+            ClippingType::Highlight,
+            get(title),
+            get(author),
+            page,
+            Location { start, end: None },
+            added,
+            Some(get(highlight)),
+        );
+        clipping.color = get(color).parse().ok();
+        clippings.push(clipping);
+
+        let note = get(note);
+        if !note.is_empty() {
+            clippings.push(Clipping::new(
+                ClippingType::Note,
+                get(title),
+                get(author),
+                page,
+                Location { start, end: None },
+                added,
+                Some(note),
+            ));
+        }
+    }
+
+    Ok(clippings)
+}
+
+/// A highlight in a web article, as read-it-later services export them
+struct ArticleHighlight {
+    quote: String,
+    note: Option<String>,
+    added: NaiveDateTime,
+}
+
+/// Article clips, and notes beside them, for an article's highlights
+///
+/// Articles have no locations, so highlights are numbered in the order they
+/// are listed. Articles without an author are credited to their site.
+fn article_clippings(
+    title: &str,
+    author: &str,
+    url: &str,
+    highlights: Vec<ArticleHighlight>,
+) -> Vec<Clipping> {
+    let author = if author.trim().is_empty() {
+        url_host(url)
+    } else {
+        author.trim().to_string()
+    };
+
+    let mut clippings = Vec::new();
+    for (i, highlight) in highlights.into_iter().enumerate() {
+        let location = Location {
+            start: i as u32 + 1,
+            end: None,
+        };
+
+        clippings.push(Clipping::new(
+            ClippingType::ArticleClip,
+            title.trim().to_string(),
+            author.clone(),
+            None,
+            location,
+            highlight.added,
+            Some(highlight.quote),
+        ));
+        if let Some(note) = highlight.note {
+            clippings.push(Clipping::new(
+                ClippingType::Note,
+                title.trim().to_string(),
+                author.clone(),
+                None,
+                location,
+                highlight.added,
+                Some(note),
+            ));
+        }
+    }
+
+    clippings
+}
+
+/// "example.com" for "https://www.example.com/some/article"
+fn url_host(url: &str) -> String {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let host = url.split(['/', '?', '#']).next().unwrap_or_default();
+    host.trim_start_matches("www.").to_string()
+}
+
+/// Trimmed text, or `None` when blank
+fn non_empty(text: Option<&str>) -> Option<String> {
+    text.map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+/// JSON export from Omnivore, an array of saved articles with their highlights
+pub struct OmnivoreImporter;
+
+impl Importer for OmnivoreImporter {
+    fn name(&self) -> &str {
+        "omnivore-json"
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        let head = text(head);
+        head.trim_start().starts_with('[') && head.contains("\"savedAt\"")
+    }
+
+    fn import(&self, path: &Path) -> Result<Vec<Clipping>, KindlrError> {
+        let contents = fs::read_to_string(path)?;
+        parse_omnivore_json(contents.trim_start_matches('\u{feff}'))
+    }
+}
+
+fn parse_omnivore_json(contents: &str) -> Result<Vec<Clipping>, KindlrError> {
+    let articles: Vec<serde_json::Value> = serde_json::from_str(contents)
+        .map_err(|error| KindlrError::Config(format!("Invalid Omnivore JSON: {}", error)))?;
+
+    let mut clippings = Vec::new();
+    for article in &articles {
+        let field = |name: &str| article[name].as_str().unwrap_or_default();
+        let saved = parse_iso_datetime(field("savedAt")).unwrap_or_default();
+
+        let highlights = article["highlights"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|highlight| {
+                let added = highlight["createdAt"]
+                    .as_str()
+                    .or(highlight["updatedAt"].as_str())
+                    .and_then(parse_iso_datetime)
+                    .unwrap_or(saved);
+                Some(ArticleHighlight {
+                    quote: non_empty(highlight["quote"].as_str())?,
+                    note: non_empty(highlight["annotation"].as_str()),
+                    added,
+                })
+            })
+            .collect();
+
+        clippings.extend(article_clippings(
+            field("title"),
+            field("author"),
+            field("url"),
+            highlights,
+        ));
+    }
+
+    Ok(clippings)
+}
+
+/// CSV export from Matter
+///
+/// Matter has changed its column names over time, so columns are looked up
+/// by any of the names it has used.
+pub struct MatterCsvImporter;
+
+impl MatterCsvImporter {
+    const TITLE: &[&str] = &["Title"];
+    const AUTHOR: &[&str] = &["Author", "Publisher"];
+    const URL: &[&str] = &["URL", "Url"];
+    const TEXT: &[&str] = &["Text", "Highlight"];
+    const NOTE: &[&str] = &["Note", "Annotation"];
+    const ADDED: &[&str] = &["Highlighted at", "Created At", "Date"];
+}
+
+impl Importer for MatterCsvImporter {
+    fn name(&self) -> &str {
+        "matter-csv"
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        let head = text(head);
+        let header: Vec<&str> = head
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .split(',')
+            .map(|column| column.trim().trim_matches('"'))
+            .collect();
+        let has = |names: &[&str]| names.iter().any(|name| header.contains(name));
+
+        has(Self::TITLE) && has(Self::URL) && has(Self::TEXT)
+    }
+
+    fn import(&self, path: &Path) -> Result<Vec<Clipping>, KindlrError> {
+        let contents = fs::read_to_string(path)?;
+        parse_matter_csv(contents.trim_start_matches('\u{feff}'))
+    }
+}
+
+fn parse_matter_csv(contents: &str) -> Result<Vec<Clipping>, KindlrError> {
+    let csv_error =
+        |error: csv::Error| KindlrError::Config(format!("Invalid Matter CSV: {}", error));
+
+    let mut reader = csv::Reader::from_reader(contents.as_bytes());
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let column = |names: &[&str]| headers.iter().position(|header| names.contains(&header));
+    let (title, author, url, text, note, added) = (
+        column(MatterCsvImporter::TITLE),
+        column(MatterCsvImporter::AUTHOR),
+        column(MatterCsvImporter::URL),
+        column(MatterCsvImporter::TEXT),
+        column(MatterCsvImporter::NOTE),
+        column(MatterCsvImporter::ADDED),
+    );
+
+    // Highlights come one per row; gather each article's in order
+    let mut articles: Vec<((String, String, String), Vec<ArticleHighlight>)> = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(csv_error)?;
+        let get = |column: Option<usize>| column.and_then(|i| record.get(i));
+
+        let Some(quote) = non_empty(get(text)) else {
+            continue;
+        };
+        let highlight = ArticleHighlight {
+            quote,
+            note: non_empty(get(note)),
+            added: get(added)
+                .and_then(|added| parse_iso_datetime(added.trim()))
+                .unwrap_or_default(),
+        };
+
+        let key = (
+            get(title).unwrap_or_default().to_string(),
+            get(author).unwrap_or_default().to_string(),
+            get(url).unwrap_or_default().to_string(),
+        );
+        match articles.iter_mut().find(|(article, _)| *article == key) {
+            Some((_, highlights)) => highlights.push(highlight),
+            None => articles.push((key, vec![highlight])),
+        }
+    }
+
+    Ok(articles
+        .into_iter()
+        .flat_map(|((title, author, url), highlights)| {
+            article_clippings(&title, &author, &url, highlights)
+        })
+        .collect())
+}
+
+/// Annotations JSON from a Pocket export, an array of articles with the
+/// highlights made on them
+pub struct PocketImporter;
+
+impl Importer for PocketImporter {
+    fn name(&self) -> &str {
+        "pocket-json"
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        let head = text(head);
+        head.trim_start().starts_with('[')
+            && head.contains("\"quote\"")
+            && head.contains("\"created_at\"")
+    }
+
+    fn import(&self, path: &Path) -> Result<Vec<Clipping>, KindlrError> {
+        let contents = fs::read_to_string(path)?;
+        parse_pocket_json(contents.trim_start_matches('\u{feff}'))
+    }
+}
+
+fn parse_pocket_json(contents: &str) -> Result<Vec<Clipping>, KindlrError> {
+    let articles: Vec<serde_json::Value> = serde_json::from_str(contents)
+        .map_err(|error| KindlrError::Config(format!("Invalid Pocket JSON: {}", error)))?;
+
+    let mut clippings = Vec::new();
+    for article in &articles {
+        let field = |name: &str| article[name].as_str().unwrap_or_default();
+
+        let highlights = article["highlights"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|highlight| {
+                // Unix seconds, written as a number or a string
+                let created_at = &highlight["created_at"];
+                let added = created_at
+                    .as_i64()
+                    .or_else(|| created_at.as_str()?.parse().ok())
+                    .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+                    .map(|datetime| datetime.with_timezone(&Local).naive_local())
+                    .unwrap_or_default();
+                Some(ArticleHighlight {
+                    quote: non_empty(highlight["quote"].as_str())?,
+                    note: None,
+                    added,
+                })
+            })
+            .collect();
+
+        clippings.extend(article_clippings(
+            field("title"),
+            "",
+            field("url"),
+            highlights,
+        ));
+    }
+
+    Ok(clippings)
+}
+
+/// Datetimes as written by Kobo, Readwise, Omnivore and Matter, with or without an offset
+fn parse_iso_datetime(text: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(text)
+        .or_else(|_| DateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%:z"))
+        .map(|datetime| datetime.naive_local())
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f").ok())
+        .or_else(|| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::HighlightColor;
+
+    #[test]
+    fn test_sniff() {
+        let kindle = "\u{feff}Dune (Frank Herbert)\n- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00\n\nFear.\n==========";
+        let readwise = "Highlight,Book Title,Book Author,Amazon Book ID,Note,Color,Tags,Location Type,Location,Highlighted at,Document tags\n";
+        let html = "<html><body><div class='bookTitle'>Dune</div>";
+        let omnivore = r#"[{"id":"1","title":"On Focus","url":"https://example.com/focus","savedAt":"2024-01-01T10:00:00.000Z"#;
+        let matter = "Title,Author,Publisher,URL,Text,Note\n";
+        let pocket = r#"[{"url":"https://example.com/focus","title":"On Focus","highlights":[{"quote":"Attend.","created_at":1704103200}]}]"#;
+
+        let detect = |head: &str| {
+            builtin()
+                .into_iter()
+                .find(|importer| importer.sniff(head.as_bytes()))
+                .map(|importer| importer.name().to_string())
+        };
+
+        assert_eq!(detect(kindle).as_deref(), Some("kindle"));
+        assert_eq!(detect(readwise).as_deref(), Some("readwise-csv"));
+        assert_eq!(detect(html).as_deref(), Some("amazon-html"));
+        assert_eq!(detect(omnivore).as_deref(), Some("omnivore-json"));
+        assert_eq!(detect(matter).as_deref(), Some("matter-csv"));
+        assert_eq!(detect(pocket).as_deref(), Some("pocket-json"));
+        assert_eq!(detect("SQLite format 3\0...").as_deref(), Some("kobo"));
+        assert_eq!(detect("just some notes"), None);
+    }
+
+    #[test]
+    fn test_readwise_csv() {
+        let clippings = parse_readwise_csv(
+            "\
+Highlight,Book Title,Book Author,Amazon Book ID,Note,Color,Tags,Location Type,Location,Highlighted at,Document tags
+\"Fear is the mind-killer, they say.\",Dune,Frank Herbert,B00B7NPRY8,Classic.,yellow,,location,100,2024-01-01 10:00:00+00:00,
+",
+        )
+        .unwrap();
+
+        assert_eq!(clippings.len(), 2);
+        assert_eq!(
+            clippings[0].content.as_deref(),
+            Some("Fear is the mind-killer, they say.")
+        );
+        assert_eq!(clippings[0].location.start, 100);
+        assert_eq!(clippings[0].datetime, "1 January 2024 10:00:00");
+        assert_eq!(clippings[0].color, Some(HighlightColor::Yellow));
+        assert_eq!(clippings[1].clipping_type, ClippingType::Note);
+        assert_eq!(clippings[1].color, None);
+    }
+
+    #[test]
+    fn test_article_exports() {
+        let omnivore = parse_omnivore_json(
+            r#"[{
+                "title": "On Focus",
+                "author": "",
+                "url": "https://www.example.com/focus",
+                "savedAt": "2024-01-01T10:00:00.000Z",
+                "highlights": [
+                    {"quote": "Attend.", "annotation": "Yes.", "createdAt": "2024-01-02T09:00:00.000Z"},
+                    {"quote": "Rest.", "annotation": null}
+                ]
+            }]"#,
+        )
+        .unwrap();
+
+        assert_eq!(omnivore.len(), 3);
+        assert_eq!(omnivore[0].clipping_type, ClippingType::ArticleClip);
+        assert_eq!(omnivore[0].author, "example.com");
+        assert_eq!(omnivore[0].datetime, "2 January 2024 09:00:00");
+        assert_eq!(omnivore[1].clipping_type, ClippingType::Note);
+        assert_eq!(omnivore[1].location, omnivore[0].location);
+        assert_eq!(omnivore[2].location.start, 2);
+        assert_eq!(omnivore[2].datetime, "1 January 2024 10:00:00");
+
+        let matter = parse_matter_csv(
+            "\
+Title,Author,Publisher,URL,Text,Note
+On Focus,Jane Doe,Example,https://example.com/focus,Attend.,
+Elsewhere,,Example,https://example.org/elsewhere,Go.,Later.
+On Focus,Jane Doe,Example,https://example.com/focus,Rest.,
+",
+        )
+        .unwrap();
+
+        assert_eq!(matter.len(), 4);
+        assert_eq!(matter[0].author, "Jane Doe");
+        assert_eq!(matter[1].content.as_deref(), Some("Rest."));
+        assert_eq!(matter[1].location.start, 2);
+        assert_eq!(matter[2].author, "example.org");
+        assert_eq!(matter[3].clipping_type, ClippingType::Note);
+
+        let pocket = parse_pocket_json(
+            r#"[{"url": "https://example.com/focus", "title": "On Focus",
+                 "highlights": [{"quote": "Attend.", "created_at": 1704103200}]}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(pocket.len(), 1);
+        assert_eq!(pocket[0].book_title, "On Focus");
+        assert_eq!(pocket[0].author, "example.com");
+    }
+
+    #[test]
+    fn test_amazon_html() {
+        let html = r#"<html><body>
+<div class='bookTitle'>Dune</div>
+<div class='authors'>Frank Herbert</div>
+<div class='noteHeading'>Highlight(<span class='highlight_yellow'>yellow</span>) - Page 12 &middot; Location 123</div>
+<div class='noteText'>Fear is the mind-killer &amp; more.</h3>
+<div class='noteHeading'>Note - Page 12 &middot; Location 124</div>
+<div class='noteText'>Classic.</div>
+</body></html>"#;
+
+        let clippings = parse_amazon_html(html, NaiveDateTime::default());
+
+        assert_eq!(clippings.len(), 2);
+        assert_eq!(clippings[0].book_title, "Dune");
+        assert_eq!(clippings[0].page, Some(12));
+        assert_eq!(clippings[0].location.start, 123);
+        assert_eq!(
+            clippings[0].content.as_deref(),
+            Some("Fear is the mind-killer & more.")
+        );
+        assert_eq!(clippings[0].color, Some(HighlightColor::Yellow));
+        assert_eq!(clippings[1].clipping_type, ClippingType::Note);
+        assert_eq!(clippings[1].color, None);
+    }
+}
@@ -1,74 +1,2672 @@
-use std::error::Error;
-use std::fmt;
+use chrono::Local;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::env;
 use std::fs;
-use std::io;
+use std::io::{self, IsTerminal, Write};
+use std::net::TcpListener;
+use std::path::{self, Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use thiserror::Error;
 
-pub mod parser;
+pub mod aliases;
+pub mod analyze;
+pub mod archive;
+pub mod backup;
+pub mod batch;
+pub mod cache;
+pub mod clock;
+pub mod count;
+pub mod dedupe;
+pub mod device;
+pub mod diff;
+pub mod digest;
+pub mod ebook;
+pub mod enrich;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod hooks;
+pub mod import;
+pub mod lint;
+pub mod man;
+pub mod merge;
+pub mod metrics;
+pub mod net;
+pub mod notes;
+pub mod pager;
+pub mod plan;
+pub mod push;
+pub mod query;
+pub mod redact;
+pub mod remote;
+pub mod report;
+pub mod roundtrip;
+pub mod schema;
+pub mod serve;
+pub mod service;
+pub mod set;
+pub mod settings;
+pub mod site;
+pub mod stats;
+pub mod tidy;
+pub mod timezone;
+pub mod unpack;
+pub mod watch;
+#[cfg(feature = "web-ui")]
+pub mod web;
+pub mod writeback;
 
-#[derive(Debug)]
+#[cfg(feature = "wasm")]
+pub use kindlr_core::wasm;
+/// Kept in kindlr-core, which embeds without the CLI, store or integrations
+pub use kindlr_core::{languages, parser};
+pub use kindlr_export::{dates, export, goodreads, group, library, sections};
+pub use kindlr_store::store;
+
+use export::ExporterRegistry;
+use library::Library;
+use plan::Plan;
+use query::ClippingQuery;
+use set::ClippingSet;
+use settings::Settings;
+use store::Store;
+
+/// Exit status of a failure no other status covers
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit status of bad arguments or settings
+pub const EXIT_USAGE: i32 = 2;
+/// Exit status of a clippings file or export that can't be parsed
+pub const EXIT_PARSE: i32 = 3;
+/// Exit status of a file that can't be read or written
+pub const EXIT_IO: i32 = 4;
+/// Exit status when nothing matched, or the file has no clippings yet
+pub const EXIT_NO_RESULTS: i32 = 5;
+/// Exit status of a request to an integration that failed
+pub const EXIT_NETWORK: i32 = 6;
+/// Exit status of lint findings of rules set to error
+pub const EXIT_LINT: i32 = 7;
+
+#[derive(Debug, Error)]
 pub enum KindlrError {
-    Io(io::Error),
-    Parse(parser::ParseError),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Parse error: {0}")]
+    Parse(#[from] parser::ParseError),
+    #[error("Configuration error: {0}")]
     Config(String),
+    #[error("Store error: {0}")]
+    Store(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Hook error: {0}")]
+    Hook(String),
+    #[error("Network error: {0}")]
+    Network(String),
+    #[error("Round trip error: {0}")]
+    Roundtrip(String),
+    #[error("Lint error: {0}")]
+    Lint(String),
 }
 
-impl fmt::Display for KindlrError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl KindlrError {
+    /// Stable identifier of the kind of failure, for tools to match on
+    pub fn code(&self) -> &'static str {
         match self {
-            KindlrError::Io(err) => write!(f, "IO error: {}", err),
-            KindlrError::Parse(msg) => write!(f, "Parse error: {}", msg),
-            KindlrError::Config(msg) => write!(f, "Configuration error: {}", msg),
+            KindlrError::Io(_) => "io",
+            KindlrError::Parse(error) => error.code(),
+            KindlrError::Config(_) => "config",
+            KindlrError::Store(_) => "store",
+            KindlrError::NotFound(_) => "not_found",
+            KindlrError::Hook(_) => "hook",
+            KindlrError::Network(_) => "network",
+            KindlrError::Roundtrip(_) => "roundtrip",
+            KindlrError::Lint(_) => "lint",
         }
     }
-}
 
-impl Error for KindlrError {}
+    /// Status to exit with, so scripts can tell kinds of failure apart
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            KindlrError::Io(_) => EXIT_IO,
+            KindlrError::Parse(parser::ParseError::EmptyFile(_)) => EXIT_NO_RESULTS,
+            KindlrError::Parse(_) => EXIT_PARSE,
+            KindlrError::Config(_) => EXIT_USAGE,
+            KindlrError::NotFound(_) => EXIT_NO_RESULTS,
+            KindlrError::Network(_) => EXIT_NETWORK,
+            KindlrError::Lint(_) => EXIT_LINT,
+            KindlrError::Store(_) | KindlrError::Hook(_) | KindlrError::Roundtrip(_) => {
+                EXIT_FAILURE
+            }
+        }
+    }
+
+    pub fn report(&self) -> ErrorReport {
+        let entry = match self {
+            KindlrError::Parse(parser::ParseError::Entry {
+                index,
+                line,
+                snippet,
+                ..
+            }) => Some((*index, *line, snippet.clone())),
+            _ => None,
+        };
+
+        ErrorReport {
+            code: self.code(),
+            message: self.to_string(),
+            entry: entry.as_ref().map(|entry| entry.0),
+            line: entry.as_ref().map(|entry| entry.1),
+            snippet: entry.map(|entry| entry.2),
+        }
+    }
+}
 
-impl From<io::Error> for KindlrError {
-    fn from(err: io::Error) -> Self {
-        KindlrError::Io(err)
+impl From<export::ExportError> for KindlrError {
+    fn from(error: export::ExportError) -> Self {
+        match error {
+            export::ExportError::Io(error) => KindlrError::Io(error),
+            error => KindlrError::Config(error.to_string()),
+        }
     }
 }
 
-impl From<parser::ParseError> for KindlrError {
-    fn from(err: parser::ParseError) -> Self {
-        KindlrError::Parse(err)
+impl From<store::StoreError> for KindlrError {
+    fn from(error: store::StoreError) -> Self {
+        match error {
+            store::StoreError::Io(error) => KindlrError::Io(error),
+            store::StoreError::NoHome => KindlrError::Config(error.to_string()),
+            store::StoreError::Invalid(message) => KindlrError::Store(message),
+        }
     }
 }
 
+/// Machine-readable description of an error
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    pub code: &'static str,
+    pub message: String,
+    /// Failing entry of the clippings file, from 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+/// Subcommands
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    List,
+    Edit {
+        id: String,
+    },
+    Star {
+        id: String,
+        favorite: bool,
+    },
+    Backup {
+        archive: String,
+    },
+    Restore {
+        archive: String,
+    },
+    Stats {
+        view: StatsView,
+    },
+    Analyze {
+        view: AnalyzeView,
+    },
+    Report {
+        year: Option<i32>,
+        format: report::ReportFormat,
+        stopwords: String,
+    },
+    /// List clippings from any supported source, naming the source found
+    Import,
+    Export {
+        format: String,
+        output: Option<String>,
+        /// Only clippings new since the last export to the same format and
+        /// output, appended to the output, or to standard output new or
+        /// edited ones
+        since_last: bool,
+        /// Static site to write a page per book and a data file into, at
+        /// `output`
+        site: Option<site::Generator>,
+    },
+    /// Compare the clippings file with another one
+    Diff {
+        other: String,
+    },
+    /// List a saved search, or every saved search without a name
+    Collection {
+        name: Option<String>,
+    },
+    /// Highlights holding most of a phrase remembered loosely, best first
+    FindSimilar {
+        phrase: String,
+        top: usize,
+    },
+    /// Look up each book's ISBN, cover, year and subjects
+    Enrich,
+    /// Send clippings not sent before to another service
+    Push {
+        destination: String,
+    },
+    /// Upload the clippings file and its JSON export to the `[s3]` bucket
+    Archive,
+    /// Today's quote, or the latest highlights, printed or sent to a channel
+    Digest {
+        channel: String,
+        latest: Option<usize>,
+    },
+    /// Books and their clipping counts, or titles likely to need an alias
+    /// when given a similarity threshold
+    Books {
+        suggest_aliases: Option<f64>,
+    },
+    /// Print a `[[clock]]` rule shifting the datetimes of a Kindle whose
+    /// clock was wrong, to add to config.toml
+    Adjust {
+        rule: settings::ClockRule,
+    },
+    /// Print how many clippings there are, streaming the file
+    Count {
+        by: count::CountBy,
+    },
+    /// Check clippings against the `[lint]` rules, skipping those disabled
+    Lint {
+        disabled: Vec<lint::Rule>,
+    },
+    /// Usage, or the usage and examples of one command
+    Help {
+        command: Option<String>,
+    },
+    /// Print the manual page
+    Man,
+    /// Print the JSON Schema of exports
+    Schema,
+    /// Answer HTTP requests for the clippings on `address`, refusing those
+    /// that would star or edit them when `read_only`
+    Serve {
+        address: String,
+        read_only: bool,
+    },
+    /// Import the clippings file again whenever it changes, looking every
+    /// `interval`, and answer `/metrics` on `address` if given one
+    Watch {
+        interval: Duration,
+        address: Option<String>,
+    },
+    /// Install and start a user service running `watch` on the file with
+    /// `interval` and `address`, and the kindlr home directory in use
+    InstallService {
+        interval: Duration,
+        address: Option<String>,
+    },
+}
+
+/// What the stats command reports
+#[derive(Debug, PartialEq)]
+pub enum StatsView {
+    Summary,
+    Heatmap {
+        year: Option<i32>,
+    },
+    Sessions {
+        gap_minutes: i64,
+    },
+    ByAuthor,
+    ByDevice,
+    ByRating,
+    Lengths,
+    /// The bookmarks of books whose title contains `book`, as a timeline
+    Progress {
+        book: String,
+    },
+}
+
+/// What the analyze command reports
+#[derive(Debug, PartialEq)]
+pub enum AnalyzeView {
+    Terms {
+        top: usize,
+        by_book: bool,
+        stopwords: String,
+    },
+    Duplicates {
+        threshold: f64,
+    },
+    /// Highlights grouped into topics, as many as asked for or a number
+    /// suiting how many there are
+    Topics {
+        count: Option<usize>,
+        stopwords: String,
+    },
+}
+
+const STATS_VIEWS: [&str; 7] = [
+    "--heatmap",
+    "--sessions",
+    "--by-author",
+    "--by-device",
+    "--by-rating",
+    "--lengths",
+    "--progress",
+];
+
+const COMMANDS: [&str; 25] = [
+    "list",
+    "edit",
+    "star",
+    "unstar",
+    "backup",
+    "restore",
+    "stats",
+    "analyze",
+    "find-similar",
+    "report",
+    "collection",
+    "diff",
+    "digest",
+    "enrich",
+    "export",
+    "import",
+    "push",
+    "archive",
+    "books",
+    "lint",
+    "adjust",
+    "count",
+    "serve",
+    "watch",
+    "service",
+];
+
+/// Commands that make changes, which `--dry-run` lists instead
+const PLANNED_COMMANDS: [&str; 8] = [
+    "edit", "star", "unstar", "import", "export", "push", "archive", "service",
+];
+
+/// Commands that don't read a clippings file
+const FILELESS_COMMANDS: [&str; 5] = ["backup", "restore", "help", "man", "schema"];
+
 /// Application configuration
 pub struct Config {
-    pub file_path: String,
+    pub command: Command,
+    pub file_path: Option<String>,
+    /// Show clippings as found in the file, ignoring edits
+    pub show_original: bool,
+    pub favorites_only: bool,
+    /// Filters given on the command line
+    pub query: ClippingQuery,
+    /// Keys to sort listed clippings by, most significant first
+    pub sort: Vec<group::SortKey>,
+    pub group_by: Option<group::GroupBy>,
+    /// Part of the sorted clippings to list
+    pub page: group::Page,
+    /// Print only how many clippings would be listed
+    pub count_only: bool,
+    /// Merge highlights split at page boundaries when made within this window
+    pub merge_window: Option<chrono::Duration>,
+    /// Drop duplicate clippings before anything else looks at them
+    pub dedupe: Option<dedupe::DedupeStrategy>,
+    /// Trim partial words and dangling punctuation from highlights
+    pub tidy: Option<tidy::TidyLevel>,
+    /// Print machine-readable JSON instead of text
+    pub json: bool,
+    /// Goodreads library export to take ratings, shelves and read dates from
+    pub goodreads: Option<String>,
+    /// Look up book metadata before exporting
+    pub enrich: bool,
+    /// Memory-map the clippings file instead of reading it into memory
+    pub mmap: bool,
+    /// More files or directories to read along with `file_path`
+    pub extra_paths: Vec<String>,
+    /// Parse the file again instead of using clippings cached from an earlier run
+    pub no_cache: bool,
+    /// Refuse to export My Clippings.txt that would read back differently
+    pub verify_roundtrip: bool,
+    /// Device to attribute every clipping read to, instead of the one found
+    pub device_label: Option<String>,
+    /// File of a zipped clippings file to read, the largest .txt unless given
+    pub member: Option<String>,
+    /// Leave out what the `[redact]` settings mark private before exporting
+    pub redact: bool,
+    /// Answer network requests only from the cache
+    pub offline: bool,
+    /// How dates are shown, instead of `date_format` in the settings
+    pub date_format: Option<dates::DateFormat>,
+    /// Print long listings straight to the terminal instead of through a pager
+    pub no_pager: bool,
+    /// Split each book of a Markdown export into guessed sections
+    pub sections: bool,
+    /// EPUB, MOBI or AZW3 to find exported highlights in
+    pub book_file: Option<String>,
+    /// Sentences of the book either side of a highlight to export with it
+    pub context: usize,
+    /// Names of files in a notes directory, instead of `filename_template`
+    /// in the settings
+    pub filename_template: Option<notes::FileTemplate>,
+    /// List the changes the command would make instead of making them
+    pub dry_run: bool,
 }
 
 impl Config {
     pub fn build(mut args: impl Iterator<Item = String>) -> Result<Self, KindlrError> {
         args.next();
 
-        let file_path = args
+        let mut positional = Vec::new();
+        // Query terms after `--`, which makes the positional arguments before
+        // it all files to read
+        let mut query_terms = None;
+        let mut show_original = false;
+        let mut favorites_only = false;
+        let mut query = ClippingQuery::new();
+        let mut sort = Vec::new();
+        let mut group_by = None;
+        let mut limit = None;
+        let mut page_number = None;
+        let mut count_only = false;
+        let mut dedupe = None;
+        let mut tidy = None;
+        let mut merge_adjacent = false;
+        let mut merge_window = merge::DEFAULT_MERGE_WINDOW_MINUTES;
+        let mut json = false;
+        let mut stats_view = None;
+        let mut year = None;
+        let mut gap_minutes = stats::DEFAULT_SESSION_GAP_MINUTES;
+        let mut top = 10;
+        let mut by_book = false;
+        let mut stopwords = "en".to_string();
+        let mut duplicates = false;
+        let mut cluster = false;
+        let mut topics = None;
+        let mut suggest_aliases = false;
+        let mut threshold = None;
+        let mut fuzzy = None;
+        let mut format = None;
+        let mut output = None;
+        let mut goodreads = None;
+        let mut progress = None;
+        let mut enrich = false;
+        let mut mmap = false;
+        let mut no_cache = false;
+        let mut verify_roundtrip = false;
+        let mut dry_run = false;
+        let mut redact = false;
+        let mut offline = false;
+        let mut date_format = None;
+        let mut count_by = count::CountBy::default();
+        let mut no_pager = false;
+        let mut sections = false;
+        let mut book_file = None;
+        let mut context = ebook::DEFAULT_CONTEXT_SENTENCES;
+        let mut filename_template = None;
+        let mut since_last = false;
+        let mut site = None;
+        let mut device_label = None;
+        let mut member = None;
+        let mut channel = None;
+        let mut listen = None;
+        let mut read_only = false;
+        let mut interval = watch::DEFAULT_INTERVAL_SECONDS;
+        let mut latest = None;
+        let mut disabled = Vec::new();
+        // A duration for `adjust`, a number of clippings to skip otherwise
+        let mut offset: Option<String> = None;
+        // Filters kept as given, for the rule `adjust` prints
+        let mut book = None;
+        let mut since = None;
+        let mut until = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--original" => show_original = true,
+                "--favorites-only" => favorites_only = true,
+                "--language" => {
+                    query = query.language(parse_flag_value::<String>(&mut args, "--language")?)
+                }
+                "--book" => {
+                    let text: String = parse_flag_value(&mut args, "--book")?;
+                    query = query.book_contains(text.clone());
+                    book = Some(text);
+                }
+                "--author" => {
+                    query =
+                        query.author_contains(parse_flag_value::<String>(&mut args, "--author")?)
+                }
+                "--contains" => {
+                    query =
+                        query.content_contains(parse_flag_value::<String>(&mut args, "--contains")?)
+                }
+                "--type" => {
+                    let types: String = parse_flag_value(&mut args, "--type")?;
+                    query = query.types(query::parse_types(&types).map_err(KindlrError::Config)?);
+                }
+                "--color" => {
+                    let colors: String = parse_flag_value(&mut args, "--color")?;
+                    query =
+                        query.colors(query::parse_colors(&colors).map_err(KindlrError::Config)?);
+                }
+                "--since" => {
+                    let date = parse_flag_value(&mut args, "--since")?;
+                    query = query.since(date);
+                    since = Some(date);
+                }
+                "--until" => {
+                    let date = parse_flag_value(&mut args, "--until")?;
+                    query = query.until(date);
+                    until = Some(date);
+                }
+                "--offset" => offset = Some(parse_flag_value(&mut args, "--offset")?),
+                "--min-length" => {
+                    query = query.min_length(parse_flag_value(&mut args, "--min-length")?)
+                }
+                "--max-length" => {
+                    query = query.max_length(parse_flag_value(&mut args, "--max-length")?)
+                }
+                "--query" => {
+                    let expression: String = parse_flag_value(&mut args, "--query")?;
+                    query = query.and(expression.parse().map_err(KindlrError::Config)?);
+                }
+                "--sort" => {
+                    let keys: String = parse_flag_value(&mut args, "--sort")?;
+                    sort = group::SortKey::parse_list(&keys).map_err(KindlrError::Config)?;
+                }
+                "--group-by" => group_by = Some(parse_flag_value(&mut args, "--group-by")?),
+                "--limit" => limit = Some(parse_flag_value(&mut args, "--limit")?),
+                "--page" => page_number = Some(parse_flag_value::<usize>(&mut args, "--page")?),
+                "--count-only" => count_only = true,
+                "--dedupe" => dedupe = Some(parse_flag_value(&mut args, "--dedupe")?),
+                "--tidy" => tidy = Some(parse_flag_value(&mut args, "--tidy")?),
+                "--merge-adjacent" => merge_adjacent = true,
+                "--merge-window" => merge_window = parse_flag_value(&mut args, "--merge-window")?,
+                "--json" => json = true,
+                // Also read by main, to report errors in the same format
+                "--output-format" => {
+                    let format: String = parse_flag_value(&mut args, "--output-format")?;
+                    match format.as_str() {
+                        "json" => json = true,
+                        "text" => json = false,
+                        _ => {
+                            return Err(KindlrError::Config(format!(
+                                "Invalid value for --output-format: {}",
+                                format
+                            )));
+                        }
+                    }
+                }
+                view if STATS_VIEWS.contains(&view) => {
+                    if let Some(previous) = stats_view.replace(arg.clone()) {
+                        return Err(KindlrError::Config(format!(
+                            "Choose only one of {} and {}",
+                            previous, arg
+                        )));
+                    }
+                    if view == "--progress" {
+                        progress = Some(parse_flag_value(&mut args, "--progress")?);
+                    }
+                }
+                "--year" => year = Some(parse_flag_value(&mut args, "--year")?),
+                "--gap" => gap_minutes = parse_flag_value(&mut args, "--gap")?,
+                "--top" => top = parse_flag_value(&mut args, "--top")?,
+                "--by-book" => by_book = true,
+                "--duplicates" => duplicates = true,
+                "--cluster" => cluster = true,
+                "--topics" => topics = Some(parse_flag_value(&mut args, "--topics")?),
+                "--suggest-aliases" => suggest_aliases = true,
+                "--threshold" => threshold = Some(parse_flag_value(&mut args, "--threshold")?),
+                "--fuzzy" => fuzzy = Some(parse_flag_value::<String>(&mut args, "--fuzzy")?),
+                "--stopwords" => stopwords = parse_flag_value(&mut args, "--stopwords")?,
+                "--format" => format = Some(parse_flag_value::<String>(&mut args, "--format")?),
+                "--output" => output = Some(parse_flag_value(&mut args, "--output")?),
+                "--enrich" => enrich = true,
+                "--mmap" => mmap = true,
+                "--no-cache" => no_cache = true,
+                "--verify-roundtrip" => verify_roundtrip = true,
+                "--dry-run" => dry_run = true,
+                "--redact" => redact = true,
+                "--offline" => offline = true,
+                "--date-format" => {
+                    date_format = Some(parse_flag_value(&mut args, "--date-format")?)
+                }
+                "--no-pager" => no_pager = true,
+                "--sections" => sections = true,
+                "--book-file" => book_file = Some(parse_flag_value(&mut args, "--book-file")?),
+                "--context" => context = parse_flag_value(&mut args, "--context")?,
+                "--filename-template" => {
+                    let template: String = parse_flag_value(&mut args, "--filename-template")?;
+                    filename_template = Some(template.parse().map_err(KindlrError::Config)?);
+                }
+                "--by" => count_by = parse_flag_value(&mut args, "--by")?,
+                "--relative" => {
+                    date_format = Some(dates::DateFormat::Relative(Some(
+                        dates::DEFAULT_RELATIVE_DAYS,
+                    )))
+                }
+                "--since-last" => since_last = true,
+                "--site" => {
+                    let generator: String = parse_flag_value(&mut args, "--site")?;
+                    site = Some(generator.parse().map_err(KindlrError::Config)?);
+                }
+                "--device" => {
+                    query =
+                        query.device_contains(parse_flag_value::<String>(&mut args, "--device")?)
+                }
+                "--device-label" => {
+                    device_label = Some(parse_flag_value(&mut args, "--device-label")?)
+                }
+                "--member" => member = Some(parse_flag_value(&mut args, "--member")?),
+                "--channel" => channel = Some(parse_flag_value(&mut args, "--channel")?),
+                "--listen" => listen = Some(parse_flag_value(&mut args, "--listen")?),
+                "--read-only" => read_only = true,
+                "--interval" => interval = parse_flag_value(&mut args, "--interval")?,
+                "--disable" => {
+                    let rules: String = parse_flag_value(&mut args, "--disable")?;
+                    for rule in rules.split(',') {
+                        disabled.push(rule.trim().parse().map_err(KindlrError::Config)?);
+                    }
+                }
+                "--latest" => latest = Some(parse_flag_value(&mut args, "--latest")?),
+                "--goodreads" => goodreads = Some(parse_flag_value(&mut args, "--goodreads")?),
+                "--" => query_terms = Some(args.by_ref().collect::<Vec<_>>()),
+                flag if flag.starts_with("--") => {
+                    return Err(KindlrError::Config(format!("Unknown flag: {}", flag)));
+                }
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+
+        // `kindlr <file_path>` is shorthand for `kindlr list <file_path>`
+        let first = positional
             .next()
             .ok_or_else(|| KindlrError::Config("Missing file path argument".to_string()))?;
+        // `kindlr push <destination> <file_path>`
+        let destination = if first == "push" {
+            Some(
+                positional
+                    .next()
+                    .ok_or_else(|| KindlrError::Config("Missing destination".to_string()))?,
+            )
+        } else {
+            None
+        };
+        // `kindlr service install <file_path>`
+        if first == "service" {
+            match positional.next() {
+                Some(action) if action == "install" => {}
+                Some(action) => {
+                    return Err(KindlrError::Config(format!(
+                        "Unknown service action: {}, expected install",
+                        action
+                    )));
+                }
+                None => {
+                    return Err(KindlrError::Config(
+                        "Missing service action, expected install".to_string(),
+                    ));
+                }
+            }
+        }
+        let (command_name, file_path) = if FILELESS_COMMANDS.contains(&first.as_str()) {
+            (first, None)
+        } else if COMMANDS.contains(&first.as_str()) {
+            let file_path = positional
+                .next()
+                .ok_or_else(|| KindlrError::Config("Missing file path argument".to_string()))?;
+            (first, Some(file_path))
+        } else {
+            ("list".to_string(), Some(first))
+        };
+
+        let mut arg = |name: &str| {
+            positional
+                .next()
+                .ok_or_else(|| KindlrError::Config(format!("Missing {}", name)))
+        };
+
+        let mut extra_paths = Vec::new();
+        let command = match command_name.as_str() {
+            "edit" => Command::Edit {
+                id: arg("clipping id")?,
+            },
+            "star" => Command::Star {
+                id: arg("clipping id")?,
+                favorite: true,
+            },
+            "unstar" => Command::Star {
+                id: arg("clipping id")?,
+                favorite: false,
+            },
+            "backup" => Command::Backup {
+                archive: arg("archive path")?,
+            },
+            "help" => Command::Help {
+                command: arg("command").ok(),
+            },
+            "man" => Command::Man,
+            "schema" => match format.as_deref() {
+                None | Some("json") => Command::Schema,
+                Some(format) => {
+                    return Err(KindlrError::Config(format!(
+                        "Invalid schema format: {}, expected json",
+                        format
+                    )));
+                }
+            },
+            "restore" => Command::Restore {
+                archive: arg("archive path")?,
+            },
+            "stats" => Command::Stats {
+                view: match stats_view.as_deref() {
+                    Some("--heatmap") => StatsView::Heatmap { year },
+                    Some("--sessions") => StatsView::Sessions { gap_minutes },
+                    Some("--by-author") => StatsView::ByAuthor,
+                    Some("--by-device") => StatsView::ByDevice,
+                    Some("--by-rating") if goodreads.is_none() => {
+                        return Err(KindlrError::Config(
+                            "--by-rating needs ratings from --goodreads <csv>".to_string(),
+                        ));
+                    }
+                    Some("--by-rating") => StatsView::ByRating,
+                    Some("--lengths") => StatsView::Lengths,
+                    Some("--progress") => StatsView::Progress {
+                        book: progress.unwrap_or_default(),
+                    },
+                    _ => StatsView::Summary,
+                },
+            },
+            "analyze" if duplicates => Command::Analyze {
+                view: AnalyzeView::Duplicates {
+                    threshold: threshold.unwrap_or(analyze::DEFAULT_SIMILARITY_THRESHOLD),
+                },
+            },
+            "find-similar" => Command::FindSimilar {
+                phrase: arg("phrase to look for")?,
+                top,
+            },
+            "analyze" if cluster => Command::Analyze {
+                view: AnalyzeView::Topics {
+                    count: topics,
+                    stopwords,
+                },
+            },
+            "analyze" => Command::Analyze {
+                view: AnalyzeView::Terms {
+                    top,
+                    by_book,
+                    stopwords,
+                },
+            },
+            "report" => Command::Report {
+                year,
+                format: match format {
+                    Some(format) => format.parse().map_err(|_| {
+                        KindlrError::Config(format!("Invalid value for --format: {}", format))
+                    })?,
+                    None => report::ReportFormat::Markdown,
+                },
+                stopwords,
+            },
+            "import" => Command::Import,
+            "enrich" => Command::Enrich,
+            "digest" => Command::Digest {
+                channel: channel.unwrap_or_else(|| "stdout".to_string()),
+                latest,
+            },
+            "push" => Command::Push {
+                destination: destination.unwrap_or_default(),
+            },
+            "archive" => Command::Archive,
+            "export" if verify_roundtrip && format.as_deref() != Some("kindle") => {
+                return Err(KindlrError::Config(
+                    "--verify-roundtrip only applies to --format kindle".to_string(),
+                ));
+            }
+            "export" => {
+                if site.is_some() && (output.is_none() || since_last) {
+                    return Err(KindlrError::Config(
+                        "--site needs --output <site directory> and can't be given --since-last"
+                            .to_string(),
+                    ));
+                }
+                Command::Export {
+                    format: format.unwrap_or_else(|| "markdown".to_string()),
+                    output,
+                    since_last,
+                    site,
+                }
+            }
+            "diff" => Command::Diff {
+                other: arg("file path to compare with")?,
+            },
+            "books" => Command::Books {
+                suggest_aliases: suggest_aliases
+                    .then(|| threshold.unwrap_or(aliases::DEFAULT_ALIAS_THRESHOLD)),
+            },
+            "collection" => Command::Collection {
+                name: positional.next(),
+            },
+            "lint" => Command::Lint { disabled },
+            "serve" => Command::Serve {
+                address: listen.unwrap_or_else(|| serve::DEFAULT_ADDRESS.to_string()),
+                read_only,
+            },
+            "watch" => Command::Watch {
+                interval: Duration::from_secs(interval.max(1)),
+                address: listen,
+            },
+            "service" => Command::InstallService {
+                interval: Duration::from_secs(interval.max(1)),
+                address: listen,
+            },
+            "count" => Command::Count { by: count_by },
+            "adjust" => Command::Adjust {
+                rule: settings::ClockRule {
+                    book,
+                    since,
+                    until,
+                    offset: offset
+                        .as_deref()
+                        .map(parse_offset)
+                        .transpose()?
+                        .ok_or_else(|| KindlrError::Config("Missing --offset".to_string()))?,
+                },
+            },
+            _ => {
+                // `kindlr list <file_path> '<query>'`, and with several files
+                // `kindlr list <file_path>... -- '<query>'`
+                if query_terms.is_none() {
+                    query_terms = Some(positional.by_ref().collect());
+                }
+                Command::List
+            }
+        };
+        extra_paths.extend(positional);
+        for term in query_terms.into_iter().flatten() {
+            query = query.and(term.parse().map_err(KindlrError::Config)?);
+        }
+
+        if let Some(text) = fuzzy {
+            if !analyze::is_searchable(&text) {
+                return Err(KindlrError::Config(format!(
+                    "Invalid value for --fuzzy: {}, expected at least 3 characters",
+                    text
+                )));
+            }
+            query = query.similar_to(text, threshold.unwrap_or(analyze::DEFAULT_FUZZY_THRESHOLD));
+        }
 
-        // let command = args
-        //     .next()
-        //     .ok_or_else(|| KindlrError::Config("Didn't get a command string".to_string()))?;
+        if dry_run && !PLANNED_COMMANDS.contains(&command_name.as_str()) {
+            return Err(KindlrError::Config(format!(
+                "--dry-run only applies to {}, which make changes",
+                PLANNED_COMMANDS.join(", ")
+            )));
+        }
+
+        if query.uses_language() && !cfg!(feature = "language-detection") {
+            return Err(KindlrError::Config(
+                "Filtering by language needs kindlr built with the language-detection feature"
+                    .to_string(),
+            ));
+        }
+
+        let offset = match &offset {
+            Some(offset) if !matches!(command, Command::Adjust { .. }) => {
+                Some(parse_offset(offset)?)
+            }
+            _ => None,
+        };
+        let page = match (page_number, offset) {
+            (Some(0), _) => {
+                return Err(KindlrError::Config("Pages are numbered from 1".to_string()));
+            }
+            (Some(_), Some(_)) => {
+                return Err(KindlrError::Config(
+                    "--page and --offset can't be given together".to_string(),
+                ));
+            }
+            (Some(number), None) => {
+                group::Page::numbered(number, limit.unwrap_or(group::DEFAULT_PAGE_SIZE))
+            }
+            (None, offset) => group::Page {
+                offset: offset.unwrap_or_default(),
+                limit,
+            },
+        };
+
+        Ok(Config {
+            command,
+            file_path,
+            show_original,
+            favorites_only,
+            query,
+            sort,
+            group_by,
+            page,
+            count_only,
+            merge_window: merge_adjacent.then(|| chrono::Duration::minutes(merge_window)),
+            dedupe,
+            tidy,
+            json,
+            goodreads,
+            enrich,
+            mmap,
+            extra_paths,
+            no_cache,
+            verify_roundtrip,
+            redact,
+            offline,
+            device_label,
+            member,
+            date_format,
+            no_pager,
+            sections,
+            book_file,
+            context,
+            filename_template,
+            dry_run,
+        })
+    }
+}
+
+/// Read and parse a clippings file
+///
+/// A file without entries fails with `ParseError::EmptyFile`, so a new
+/// Kindle's file can be told apart from one whose entries all failed.
+pub fn read_clippings(path: impl AsRef<Path>) -> Result<Vec<parser::Clipping>, KindlrError> {
+    let contents = fs::read_to_string(&path)?;
+    check_not_empty(path.as_ref(), &contents)?;
+    Ok(parser::parse_clippings(&contents)?)
+}
 
-        Ok(Config { file_path })
+/// Read and parse a clippings file without blocking the async runtime
+///
+/// Parsing runs on tokio's blocking thread pool, so large files don't stall other tasks.
+#[cfg(feature = "async")]
+pub async fn read_clippings_async(
+    path: impl AsRef<Path>,
+) -> Result<Vec<parser::Clipping>, KindlrError> {
+    let contents = tokio::fs::read_to_string(&path).await?;
+    check_not_empty(path.as_ref(), &contents)?;
+
+    tokio::task::spawn_blocking(move || parser::parse_clippings(&contents))
+        .await
+        .map_err(io::Error::other)?
+        .map_err(KindlrError::from)
+}
+
+/// `ParseError::EmptyFile` if `contents`, read from `path`, has no entries
+fn check_not_empty(path: &Path, contents: &str) -> Result<(), KindlrError> {
+    if parser::is_empty_file(contents) {
+        return Err(parser::ParseError::EmptyFile(path.display().to_string()).into());
     }
+    Ok(())
+}
+
+/// The value of `--offset`, a duration for `adjust` or a number of
+/// clippings for listings
+fn parse_offset<T: FromStr>(value: &str) -> Result<T, KindlrError> {
+    value
+        .parse()
+        .map_err(|_| KindlrError::Config(format!("Invalid value for --offset: {}", value)))
+}
+
+fn parse_flag_value<T: FromStr>(
+    args: &mut impl Iterator<Item = String>,
+    flag: &str,
+) -> Result<T, KindlrError> {
+    let value = args
+        .next()
+        .ok_or_else(|| KindlrError::Config(format!("Missing value for {}", flag)))?;
+
+    value
+        .parse()
+        .map_err(|_| KindlrError::Config(format!("Invalid value for {}: {}", flag, value)))
 }
 
 pub fn run(config: Config) -> Result<(), KindlrError> {
-    let contents = fs::read_to_string(config.file_path)?;
+    run_with_exporters(config, &ExporterRegistry::with_builtin())
+}
+
+/// Clippings from the file given, or from every file when given several or
+/// a directory, reporting what each of those held
+fn read_input(file_path: &str, config: &Config) -> Result<Vec<parser::Clipping>, KindlrError> {
+    let mut clippings = read_paths(file_path, config)?;
+
+    if let Some(label) = &config.device_label {
+        for clipping in &mut clippings {
+            clipping.device = Some(label.clone());
+        }
+    }
+
+    Ok(clippings)
+}
+
+fn read_paths(file_path: &str, config: &Config) -> Result<Vec<parser::Clipping>, KindlrError> {
+    if config.extra_paths.is_empty() && !Path::new(file_path).is_dir() {
+        let read = if config.mmap {
+            import::read_mapped
+        } else {
+            import::read
+        };
+        let mut clippings = if config.no_cache {
+            read(Path::new(file_path))?
+        } else {
+            let cache = cache::ParseCache::new(&cache::dir(&store::home_dir()?));
+            cache.read(Path::new(file_path), read)?
+        };
+
+        if clippings.is_empty() {
+            check_not_empty(
+                Path::new(file_path),
+                &String::from_utf8_lossy(&fs::read(file_path)?),
+            )?;
+        }
+
+        let concatenated = dedupe::concatenated(&clippings).len();
+        if concatenated > 0 && config.dedupe != Some(dedupe::DedupeStrategy::Concatenated) {
+            eprintln!(
+                "{}: file appears to contain {} duplicated entries from self-concatenation; \
+                 --dedupe concatenated skips them",
+                file_path, concatenated
+            );
+        }
+
+        // Identified on every read, as the same file may be on another Kindle
+        // than when it was cached
+        let serial = device::identify(Path::new(file_path));
+        for clipping in &mut clippings {
+            clipping.device.clone_from(&serial);
+        }
+        return Ok(clippings);
+    }
+
+    let paths: Vec<PathBuf> = std::iter::once(file_path)
+        .chain(config.extra_paths.iter().map(String::as_str))
+        .map(PathBuf::from)
+        .collect();
+    let batch = batch::read(&paths)?;
+    for file in &batch.files {
+        eprint!(
+            "{}: {} clippings, {} skipped",
+            file.path.display(),
+            file.clippings,
+            file.issues
+        );
+        match file.concatenated {
+            0 => eprintln!(),
+            n => eprintln!(", {} duplicated from self-concatenation kept once", n),
+        }
+    }
+
+    Ok(batch.clippings)
+}
+
+/// Like `run`, resolving `export --format` through `exporters`
+pub fn run_with_exporters(config: Config, exporters: &ExporterRegistry) -> Result<(), KindlrError> {
+    let json = config.json;
+    let mut plan = Plan::new(config.dry_run);
+    let result = run_planned(config, exporters, &mut plan);
+
+    // Whatever was listed before a failure, so it can be told what would have got through
+    if plan.is_dry_run() {
+        if json {
+            print_json(&plan.changes())?;
+        } else {
+            for change in plan.changes() {
+                println!("{}", change);
+            }
+        }
+        eprintln!(
+            "Dry run: {} changes listed, none made",
+            plan.changes().len()
+        );
+    }
+    result
+}
+
+/// Run the command, making every change through `plan`
+fn run_planned(
+    config: Config,
+    exporters: &ExporterRegistry,
+    plan: &mut Plan,
+) -> Result<(), KindlrError> {
+    match &config.command {
+        Command::Backup { archive } => {
+            let count = backup::create(&store::home_dir()?, Path::new(archive))?;
+            println!("Backed up {} files to {}", count, archive);
+            return Ok(());
+        }
+        Command::Restore { archive } => {
+            let home = store::home_dir()?;
+            let count = backup::restore(Path::new(archive), &home)?;
+            println!("Restored {} files into {}", count, home.display());
+            return Ok(());
+        }
+        Command::Help { command: None } => {
+            println!("{}", man::USAGE);
+            return Ok(());
+        }
+        Command::Help {
+            command: Some(command),
+        } => {
+            let help = man::help(command)
+                .ok_or_else(|| KindlrError::NotFound(format!("No command named {}", command)))?;
+            print!("{}", help);
+            return Ok(());
+        }
+        Command::Man => {
+            print!("{}", man::page());
+            return Ok(());
+        }
+        Command::Schema => {
+            println!("{}", schema::export_schema());
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let file_path = config
+        .file_path
+        .as_deref()
+        .ok_or_else(|| KindlrError::Config("Missing file path argument".to_string()))?;
+    if let Command::InstallService {
+        interval,
+        ref address,
+    } = config.command
+    {
+        return install_service(file_path, interval, address.as_deref(), plan);
+    }
+    let settings = Settings::load(&store::home_dir()?)?;
+
+    // Files in cloud storage are read from a downloaded copy
+    let downloaded;
+    let file_path = match remote::Remote::parse(file_path) {
+        Some(remote) => {
+            let dir = remote::dir(&store::home_dir()?);
+            downloaded = remote
+                .download(&http_client(&config)?, &settings, &dir)?
+                .to_string_lossy()
+                .into_owned();
+            downloaded.as_str()
+        }
+        None => file_path,
+    };
+    // Compressed files are read from an unpacked copy
+    let unpacked;
+    let file_path = match unpack::unpack(
+        Path::new(file_path),
+        config.member.as_deref(),
+        &unpack::dir(&store::home_dir()?),
+    )? {
+        Some(path) => {
+            unpacked = path.to_string_lossy().into_owned();
+            unpacked.as_str()
+        }
+        None => file_path,
+    };
+
+    // Counting streams the file instead of reading every clipping in
+    if let Command::Count { by } = config.command {
+        let counts = count::count_file(Path::new(file_path), by, &settings)?;
+        if config.json {
+            print_json(&counts)?;
+        } else {
+            println!("{}", counts);
+        }
+        return Ok(());
+    }
+
+    let pipeline = hooks::Pipeline::from_hooks(&settings.hooks);
+    if let Command::Watch {
+        interval,
+        ref address,
+    } = config.command
+    {
+        return watch(
+            file_path,
+            interval,
+            address.as_deref(),
+            &pipeline,
+            &settings,
+            &config,
+        );
+    }
+    let mut clippings = pipeline.process(read_input(file_path, &config)?)?;
+    let mut store = Store::open()?;
+
+    match config.command {
+        Command::List => {
+            let starred = select(&mut clippings, &store, &settings, &config);
+            print_list(&mut clippings, &starred, &config, &settings)?;
+            found_any(&clippings)?;
+        }
+        Command::Edit { id } => {
+            let clipping = find_clipping(&clippings, &id, &settings)?;
+
+            let current = store
+                .history(&clipping.id())
+                .and_then(|history| history.current())
+                .or(clipping.content.as_deref())
+                .unwrap_or_default()
+                .to_string();
+
+            let edited = edit_in_editor(&current)?;
+
+            if edited == current {
+                println!("No changes made");
+            } else {
+                let change = plan::Change::Store {
+                    id: clipping.id(),
+                    action: format!("edit to {:?}", edited),
+                };
+                let saved = plan.apply(change, || {
+                    store.record_edit(clipping, edited);
+                    Ok(store.save()?)
+                })?;
+                if saved.is_some() {
+                    println!("Saved edit of clipping {}", id);
+                }
+            }
+        }
+        Command::Star { id, favorite } => {
+            let clipping = find_clipping(&clippings, &id, &settings)?;
+
+            let action = if favorite { "Starred" } else { "Unstarred" };
+            if store.is_favorite(&clipping.id()) != favorite {
+                let change = plan::Change::Store {
+                    id: clipping.id(),
+                    action: action.to_lowercase(),
+                };
+                plan.apply(change, || {
+                    store.set_favorite(&clipping.id(), favorite);
+                    Ok(store.save()?)
+                })?;
+            }
+
+            if !plan.is_dry_run() {
+                println!("{} clipping {}", action, id);
+            }
+        }
+        Command::Stats { ref view } => {
+            select(&mut clippings, &store, &settings, &config);
+            let clippings = ClippingSet::new(clippings);
+
+            match *view {
+                StatsView::Summary => {
+                    let summary = clippings.summary();
+                    let cadence = stats::cadence(&clippings, Local::now().date_naive());
+
+                    if config.json {
+                        #[derive(Serialize)]
+                        struct Report<'a> {
+                            #[serde(flatten)]
+                            summary: &'a stats::Summary,
+                            cadence: stats::Cadence,
+                        }
+
+                        print_json(&Report { summary, cadence })?;
+                    } else {
+                        println!("{}\n\n{}", summary, cadence);
+                    }
+                }
+                StatsView::Heatmap { year } => {
+                    // Default to the most recent year with clippings
+                    let year = year
+                        .or_else(|| stats::latest_year(&clippings))
+                        .ok_or_else(|| KindlrError::NotFound("No dated clippings".to_string()))?;
+                    let heatmap = stats::heatmap(&clippings, year);
+
+                    if config.json {
+                        print_json(&heatmap)?;
+                    } else {
+                        println!("{}", heatmap.render(io::stdout().is_terminal()));
+                    }
+                }
+                StatsView::Sessions { gap_minutes } => {
+                    let sessions =
+                        stats::sessions(&clippings, chrono::Duration::minutes(gap_minutes));
+
+                    if config.json {
+                        print_json(&sessions)?;
+                    } else {
+                        for session in &sessions {
+                            println!("{}", session);
+                        }
+                        println!("Total sessions: {}", sessions.len());
+                    }
+                }
+                StatsView::ByAuthor => {
+                    let authors = stats::by_author(&clippings);
+
+                    if config.json {
+                        print_json(&authors)?;
+                    } else {
+                        for (rank, author) in authors.iter().enumerate() {
+                            println!("{}. {}", rank + 1, author);
+                        }
+                    }
+                }
+                StatsView::ByDevice => {
+                    let devices = stats::by_device(&clippings);
+
+                    if config.json {
+                        print_json(&devices)?;
+                    } else {
+                        for device in &devices {
+                            println!("{}", device);
+                        }
+                    }
+                }
+                StatsView::ByRating => {
+                    let library = library(clippings.into_vec(), &config, &settings)?;
+                    let books = stats::by_rating(&library);
+
+                    if config.json {
+                        print_json(&books)?;
+                    } else {
+                        for book in &books {
+                            println!("{}", book);
+                        }
+                        println!("Total rated books: {}", books.len());
+                    }
+                }
+                StatsView::Lengths => {
+                    let lengths = stats::lengths(&clippings);
+
+                    if config.json {
+                        print_json(&lengths)?;
+                    } else {
+                        println!("{}", lengths);
+                    }
+                }
+                StatsView::Progress { ref book } => {
+                    let progress = stats::progress(&clippings, book);
+                    if progress.is_empty() {
+                        return Err(KindlrError::NotFound(format!(
+                            "No bookmarks in a book matching \"{}\"",
+                            book
+                        )));
+                    }
+
+                    if config.json {
+                        print_json(&progress)?;
+                    } else {
+                        let timelines: Vec<String> =
+                            progress.iter().map(ToString::to_string).collect();
+                        print!("{}", timelines.join("\n"));
+                    }
+                }
+            }
+        }
+        Command::Analyze {
+            view:
+                AnalyzeView::Terms {
+                    top,
+                    by_book,
+                    ref stopwords,
+                },
+        } => {
+            select(&mut clippings, &store, &settings, &config);
+            let stopwords = analyze::Stopwords::load(&store::home_dir()?, stopwords)?;
+
+            if by_book {
+                let books = analyze::analyze_by_book(&clippings, &stopwords, top);
+
+                if config.json {
+                    print_json(&books)?;
+                } else {
+                    for book in &books {
+                        println!("{}\n", book);
+                    }
+                }
+            } else {
+                let analysis = analyze::analyze(&clippings, &stopwords, top);
+
+                if config.json {
+                    print_json(&analysis)?;
+                } else {
+                    println!("{}", analysis);
+                }
+            }
+        }
+        Command::Analyze {
+            view: AnalyzeView::Duplicates { threshold },
+        } => {
+            select(&mut clippings, &store, &settings, &config);
+            let clusters = analyze::duplicates(&clippings, threshold);
+
+            if config.json {
+                print_json(&clusters)?;
+            } else {
+                for cluster in &clusters {
+                    println!("{}\n", cluster);
+                }
+                println!("Duplicate clusters: {}", clusters.len());
+            }
+        }
+        Command::Analyze {
+            view:
+                AnalyzeView::Topics {
+                    count,
+                    ref stopwords,
+                },
+        } => {
+            select(&mut clippings, &store, &settings, &config);
+            let stopwords = analyze::Stopwords::load(&store::home_dir()?, stopwords)?;
+            let topics = analyze::topics(&clippings, &stopwords, count);
+
+            if config.json {
+                print_json(&topics)?;
+            } else {
+                for topic in &topics {
+                    println!("{}\n", topic);
+                }
+                println!("Topics: {}", topics.len());
+            }
+        }
+        Command::FindSimilar { ref phrase, top } => {
+            select(&mut clippings, &store, &settings, &config);
+            let found = analyze::find_similar(phrase, &clippings, top);
+
+            if config.json {
+                print_json(&found)?;
+            } else {
+                for quote in &found {
+                    println!("{}\n", quote);
+                }
+            }
+            if found.is_empty() {
+                return Err(KindlrError::NotFound(format!(
+                    "No highlight resembles \"{}\"",
+                    phrase
+                )));
+            }
+        }
+        Command::Report {
+            year,
+            format,
+            ref stopwords,
+        } => {
+            select(&mut clippings, &store, &settings, &config);
+
+            let year = year
+                .or_else(|| stats::latest_year(&clippings))
+                .ok_or_else(|| KindlrError::NotFound("No dated clippings".to_string()))?;
+            let stopwords = analyze::Stopwords::load(&store::home_dir()?, stopwords)?;
+            let report = report::year_report(&clippings, year, &stopwords);
+
+            if config.json {
+                print_json(&report)?;
+            } else {
+                match format {
+                    report::ReportFormat::Markdown => print!("{}", report.to_markdown()),
+                    report::ReportFormat::Html => print!("{}", report.to_html()),
+                }
+            }
+        }
+        Command::Import => {
+            let source = import::detect_source(Path::new(file_path))
+                .map_or("kindle".to_string(), |importer| importer.name().to_string());
+            println!("Source: {}\n", source);
+
+            let starred = select(&mut clippings, &store, &settings, &config);
+            print_list(&mut clippings, &starred, &config, &settings)?;
+            notify_webhooks(
+                &clippings,
+                &settings,
+                &mut store,
+                &http_client(&config)?,
+                plan,
+            )?;
+        }
+        Command::Export {
+            ref format,
+            ref output,
+            since_last,
+            site,
+        } => {
+            let exporter = exporters.get(format)?;
+            select(&mut clippings, &store, &settings, &config);
+            if config.redact {
+                redact::redact(&mut clippings, &settings.redact);
+            }
+
+            let destination = match output {
+                Some(path) => format!("{}:{}", format, path::absolute(path)?.display()),
+                None => format!("{}:-", format),
+            };
+            if since_last && output.is_some() && !exporter.appendable() {
+                return Err(KindlrError::Config(format!(
+                    "--since-last --output can only add to a file in {}, export {} without --output",
+                    exporters.appendable_names().collect::<Vec<_>>().join(", "),
+                    format
+                )));
+            }
+            if since_last {
+                // A file is only added to, so an edited clipping already in it
+                // would be there twice; elsewhere it's sent again as edited
+                if output.is_some() {
+                    clippings.retain(|clipping| !store.was_exported(&destination, clipping));
+                } else {
+                    clippings.retain(|clipping| !store.is_exported(&destination, clipping));
+                }
+                if clippings.is_empty() {
+                    eprintln!("No new clippings since the last export");
+                    return Ok(());
+                }
+                for clipping in &clippings {
+                    let change = plan::Change::Store {
+                        id: clipping.id(),
+                        action: format!("record export to {}", destination),
+                    };
+                    plan.apply(change, || {
+                        store.record_export(&destination, clipping);
+                        Ok(())
+                    })?;
+                }
+            }
+
+            let mut library = library(clippings, &config, &settings)?;
+            pipeline.pre_export(&mut library)?;
+
+            if config.verify_roundtrip {
+                let losses = roundtrip::verify(&library);
+                for loss in &losses {
+                    eprintln!("{}", loss);
+                }
+                if !losses.is_empty() {
+                    return Err(KindlrError::Roundtrip(format!(
+                        "{} fields would change when read back, nothing was exported",
+                        losses.len()
+                    )));
+                }
+            }
+
+            if let (Some(generator), Some(root)) = (site, output) {
+                let template = match &config.filename_template {
+                    Some(template) => template.clone(),
+                    None => site::DEFAULT_PAGE_TEMPLATE
+                        .parse()
+                        .map_err(KindlrError::Config)?,
+                };
+                let pages = site::write(Path::new(root), &library, generator, &template, plan)?;
+                if !plan.is_dry_run() {
+                    eprintln!("Wrote {} book pages and clippings.json to {}", pages, root);
+                }
+                return Ok(());
+            }
+
+            match output {
+                // A notes directory, a file per book updated in place
+                Some(path) if path.ends_with('/') || Path::new(path).is_dir() => {
+                    if exporter.name() != "markdown" {
+                        return Err(KindlrError::Config(
+                            "Only --format markdown exports to a directory".to_string(),
+                        ));
+                    }
+                    let template = config
+                        .filename_template
+                        .clone()
+                        .or_else(|| settings.filename_template.clone())
+                        .unwrap_or_default();
+                    let summary = notes::update_dir(Path::new(path), &library, &template, plan)?;
+                    if !plan.is_dry_run() {
+                        eprintln!(
+                            "Added {} clippings to {}: {} files created, {} updated",
+                            summary.added, path, summary.created, summary.updated
+                        );
+                    }
+                }
+                // My Clippings.txt, perhaps on a Kindle, replaced only once
+                // the new one reads back
+                Some(path) if exporter.name() == "kindle" && !since_last => {
+                    let mut contents = Vec::new();
+                    exporter.export(&library, &mut contents)?;
+                    let count = library.clippings().count();
+                    let change = plan::Change::File {
+                        path: path.clone(),
+                        action: if Path::new(path).is_file() {
+                            format!("replace with {} clippings, backing it up", count)
+                        } else {
+                            format!("write {} clippings", count)
+                        },
+                    };
+                    let backups = writeback::backups_dir(&store::home_dir()?);
+                    let written = plan.apply(change, || {
+                        writeback::write_clippings(
+                            Path::new(path),
+                            &contents,
+                            &export::kindle_order(&library),
+                            &backups,
+                            Local::now(),
+                        )
+                    })?;
+                    match written {
+                        None => {}
+                        Some(Some(backup)) => eprintln!(
+                            "Exported {} clippings to {}, keeping the file it replaced as {}",
+                            count,
+                            path,
+                            backup.display()
+                        ),
+                        Some(None) => eprintln!("Exported {} clippings to {}", count, path),
+                    }
+                }
+                Some(path) => {
+                    let count = library.clippings().count();
+                    let change = plan::Change::File {
+                        path: path.clone(),
+                        action: if since_last {
+                            format!("append {} clippings", count)
+                        } else {
+                            format!("write {} clippings", count)
+                        },
+                    };
+                    let written = plan.apply(change, || {
+                        Ok(export::write_file(
+                            exporter,
+                            &library,
+                            Path::new(path),
+                            since_last,
+                        )?)
+                    })?;
+                    if written.is_some() {
+                        eprintln!("Exported {} clippings to {}", count, path);
+                    }
+                }
+                None => exporter.export(&library, &mut io::stdout().lock())?,
+            }
+            if since_last {
+                plan.save(&store)?;
+            }
+        }
+        Command::Diff { ref other } => {
+            let mut other_clippings = pipeline.process(import::read(Path::new(other))?)?;
+
+            select(&mut clippings, &store, &settings, &config);
+            select(&mut other_clippings, &store, &settings, &config);
+
+            println!("{}", diff::diff(&clippings, &other_clippings));
+        }
+        Command::Collection { name: None } => {
+            for (name, collection) in &settings.collections {
+                match &collection.description {
+                    Some(description) => {
+                        println!("{}: {}\n  {}", name, description, collection.query)
+                    }
+                    None => println!("{}\n  {}", name, collection.query),
+                }
+            }
+
+            println!("Total collections: {}", settings.collections.len());
+        }
+        Command::Collection {
+            name: Some(ref name),
+        } => {
+            let query = settings.collection(name)?;
 
-    let clippings = parser::parse_clippings(&contents)?;
+            let starred = select(&mut clippings, &store, &settings, &config);
+            query.retain(&mut clippings);
+            print_list(&mut clippings, &starred, &config, &settings)?;
+            found_any(&clippings)?;
+        }
+        Command::Enrich => {
+            select(&mut clippings, &store, &settings, &config);
+            let mut library = Library::new(clippings);
+            let found = enrich_library(&mut library, &settings, &http_client(&config)?)?;
+
+            if config.json {
+                #[derive(Serialize)]
+                struct Entry<'a> {
+                    title: &'a str,
+                    author: &'a str,
+                    metadata: Option<&'a enrich::Metadata>,
+                }
+
+                let entries: Vec<Entry> = library
+                    .books
+                    .iter()
+                    .map(|book| Entry {
+                        title: &book.title,
+                        author: &book.author,
+                        metadata: book.metadata.as_ref(),
+                    })
+                    .collect();
+                print_json(&entries)?;
+            } else {
+                for book in &library.books {
+                    println!("{} by {}", book.title, book.author);
+                    match &book.metadata {
+                        Some(metadata) => println!("{}\n", metadata),
+                        None => println!("  Not found\n"),
+                    }
+                }
+                println!("Found {} of {} books", found, library.books.len());
+            }
+        }
+        Command::Push { ref destination } => {
+            select(&mut clippings, &store, &settings, &config);
+            let client = http_client(&config)?;
+            let destination = push_destination(destination, &settings, &clippings, &client)?;
+
+            // Keep track of whatever got through, even if a later batch fails
+            let result = push::push(destination.as_ref(), &clippings, &mut store, plan);
+            plan.save(&store)?;
+            let summary = result?;
+
+            // A dry run's output is the list of what it would have done
+            if plan.is_dry_run() {
+                return Ok(());
+            }
+            if config.json {
+                print_json(&summary)?;
+            } else {
+                println!(
+                    "Pushed {} clippings to {} ({} pushed before)",
+                    summary.pushed,
+                    destination.name(),
+                    summary.skipped
+                );
+            }
+        }
+        Command::Archive => {
+            select(&mut clippings, &store, &settings, &config);
+            let library = library(clippings, &config, &settings)?;
+            let mut dataset = Vec::new();
+            exporters.get("json")?.export(&library, &mut dataset)?;
+
+            // Files as they were read, under the names they were given by
+            let given = config.file_path.as_deref().unwrap_or(file_path);
+            let mut files = Vec::new();
+            for (name, path) in std::iter::once((given, file_path)).chain(
+                config
+                    .extra_paths
+                    .iter()
+                    .map(|path| (path.as_str(), path.as_str())),
+            ) {
+                if Path::new(path).is_file() {
+                    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+                    files.push((name.to_string(), fs::read(path)?));
+                }
+            }
+            files.push(("clippings.json".to_string(), dataset));
+
+            let bucket = archive::Bucket::from_settings(&settings.s3)?;
+            let keys = upload_archive(&bucket, files, &http_client(&config)?, plan)?;
+            // A dry run's output is the list of what it would have done
+            if plan.is_dry_run() {
+                return Ok(());
+            }
+            if config.json {
+                print_json(&keys)?;
+            } else {
+                for key in &keys {
+                    println!("{}", bucket.url(key));
+                }
+                println!("Archived {} files", keys.len());
+            }
+        }
+        Command::Digest {
+            ref channel,
+            latest,
+        } => {
+            select(&mut clippings, &store, &settings, &config);
+            let channel = digest_channel(channel, &settings, &http_client(&config)?)?;
+
+            let quotes = match latest {
+                Some(n) => digest::latest(&clippings, n),
+                None => digest::daily_quote(&clippings, Local::now().date_naive())
+                    .into_iter()
+                    .collect(),
+            };
+            if quotes.is_empty() {
+                return Err(KindlrError::NotFound("No highlights to quote".to_string()));
+            }
+
+            channel.deliver(&digest::render(&quotes))?;
+        }
+        Command::Books {
+            suggest_aliases: Some(threshold),
+        } => {
+            select(&mut clippings, &store, &settings, &config);
+            let suggestions = aliases::suggest(&clippings, threshold);
+
+            if config.json {
+                print_json(&suggestions)?;
+            } else if suggestions.is_empty() {
+                println!("No likely aliases");
+            } else {
+                println!("[aliases]");
+                for suggestion in &suggestions {
+                    println!("{}", suggestion);
+                }
+            }
+        }
+        Command::Books {
+            suggest_aliases: None,
+        } => {
+            select(&mut clippings, &store, &settings, &config);
+            let library = library(clippings, &config, &settings)?;
+
+            #[derive(Serialize)]
+            struct Entry<'a> {
+                title: &'a str,
+                author: &'a str,
+                clippings: usize,
+            }
+
+            let books: Vec<Entry> = library
+                .books
+                .iter()
+                .map(|book| Entry {
+                    title: &book.title,
+                    author: &book.author,
+                    clippings: book.clippings.len(),
+                })
+                .collect();
+
+            if config.json {
+                print_json(&books)?;
+            } else {
+                for book in &books {
+                    println!(
+                        "{} ({}): {} clippings",
+                        book.title, book.author, book.clippings
+                    );
+                }
+                println!("Total books: {}", books.len());
+            }
+        }
+        Command::Adjust { ref rule } => {
+            let matched = clippings
+                .iter()
+                .filter(|clipping| rule.matches(clipping))
+                .count();
+            eprintln!(
+                "Shifts {} clippings by {}; add this to config.toml:",
+                matched, rule.offset
+            );
+            println!("{}", rule);
+        }
+        Command::Lint { ref disabled } => {
+            select(&mut clippings, &store, &settings, &config);
+            let mut rules = lint::Rules::new(&settings.lint)?;
+            for &rule in disabled {
+                rules.disable(rule);
+            }
+            let findings = lint::lint(&clippings, &rules, Local::now().naive_local());
+
+            if config.json {
+                print_json(&findings)?;
+            } else {
+                for finding in &findings {
+                    println!("{}", finding);
+                }
+                let count = |severity| {
+                    findings
+                        .iter()
+                        .filter(|finding| finding.severity == severity)
+                        .count()
+                };
+                println!(
+                    "{} errors, {} warnings, {} infos",
+                    count(lint::Severity::Error),
+                    count(lint::Severity::Warning),
+                    count(lint::Severity::Info)
+                );
+            }
+
+            let errors = findings
+                .iter()
+                .filter(|finding| finding.severity == lint::Severity::Error)
+                .count();
+            if errors > 0 {
+                return Err(KindlrError::Lint(format!(
+                    "{} findings of rules set to error",
+                    errors
+                )));
+            }
+        }
+        Command::Serve {
+            ref address,
+            read_only,
+        } => {
+            let file = clippings.clone();
+            let starred = select(&mut clippings, &store, &settings, &config);
+            let listener = TcpListener::bind(address)?;
+            let local = listener.local_addr()?;
+            eprintln!("Serving {} clippings on http://{}", clippings.len(), local);
+
+            let mut api = serve::Api::new(clippings, starred);
+            let token = serve_token(&settings);
+            if !read_only {
+                // Stars and edits are stored under the id a clipping has in the file
+                let originals = file
+                    .into_iter()
+                    .map(|clipping| {
+                        let mut shown = clipping.clone();
+                        aliases::apply(&mut shown, &settings.aliases, &settings.author_aliases);
+                        (shown.id(), clipping)
+                    })
+                    .collect();
+                api = api.writable(store, originals);
+                if token.is_none() && !local.ip().is_loopback() {
+                    eprintln!(
+                        "Anyone who can reach {} can star and edit clippings; set KINDLR_SERVE_TOKEN or pass --read-only",
+                        local
+                    );
+                }
+            }
+            if let Some(token) = token {
+                api = api.token(token);
+            }
+            serve::serve(&listener, |request| api.handle(request));
+        }
+        Command::Backup { .. }
+        | Command::Restore { .. }
+        | Command::Help { .. }
+        | Command::Man
+        | Command::Schema
+        | Command::Count { .. }
+        | Command::Watch { .. }
+        | Command::InstallService { .. } => {
+            unreachable!()
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply stored edits and aliases, drop ignored books and apply the filters
+/// given on the command line, returning the ids starred clippings have once
+/// aliased
+fn select(
+    clippings: &mut Vec<parser::Clipping>,
+    store: &Store,
+    settings: &Settings,
+    config: &Config,
+) -> HashSet<String> {
+    if !config.show_original {
+        store.apply_edits(clippings);
+    }
+
+    // Stars are stored under the id a clipping has in the file
+    let mut starred = HashSet::new();
+    for clipping in clippings.iter_mut() {
+        let favorite = store.is_favorite(&clipping.id());
+        clock::adjust(clipping, &settings.clock);
+        timezone::annotate(clipping, &settings.timezone);
+        aliases::apply(clipping, &settings.aliases, &settings.author_aliases);
+        if let Some(label) = clipping
+            .device
+            .as_ref()
+            .and_then(|d| settings.devices.get(d))
+        {
+            clipping.device = Some(label.clone());
+        }
+        if favorite {
+            starred.insert(clipping.id());
+        }
+    }
+    clippings.retain(|clipping| !settings.is_ignored(&clipping.book_title));
 
-    for (i, clipping) in clippings.iter().enumerate() {
-        println!("Clipping #{}:", i + 1);
-        println!("{}", clipping);
-        println!();
+    if let Some(window) = config.merge_window {
+        *clippings = merge::merge_adjacent(std::mem::take(clippings), window);
     }
 
-    println!("Total clippings: {}", clippings.len());
+    if let Some(strategy) = config.dedupe {
+        let (kept, _) = dedupe::dedupe(std::mem::take(clippings), strategy);
+        *clippings = kept;
+    }
+
+    // Only ever what is shown: the file and the store keep the text as highlighted
+    if let Some(level) = config.tidy
+        && !config.show_original
+    {
+        tidy::tidy(clippings, level);
+    }
+
+    #[cfg(feature = "language-detection")]
+    if config.query.uses_language() {
+        for clipping in clippings.iter_mut() {
+            clipping.detect_language();
+        }
+    }
+
+    if config.favorites_only {
+        config
+            .query
+            .clone()
+            .ids(starred.iter().cloned())
+            .retain(clippings);
+    } else {
+        config.query.retain(clippings);
+    }
+
+    starred
+}
+
+/// The provider chosen in the `[enrich]` settings
+#[cfg_attr(not(feature = "enrich"), allow(unused_variables))]
+fn metadata_provider(
+    settings: &settings::Enrich,
+    client: &net::Client,
+) -> Result<Box<dyn enrich::MetadataProvider>, KindlrError> {
+    #[cfg(feature = "enrich")]
+    return match settings.provider.as_deref() {
+        None | Some("openlibrary") => Ok(Box::new(enrich::OpenLibrary::new(client.clone()))),
+        Some("google-books") => Ok(Box::new(enrich::GoogleBooks::new(
+            settings.api_key.clone(),
+            client.clone(),
+        ))),
+        Some(other) => Err(KindlrError::Config(format!(
+            "Unknown metadata provider: {}, expected openlibrary or google-books",
+            other
+        ))),
+    };
+
+    #[cfg(not(feature = "enrich"))]
+    Err(KindlrError::Config(
+        "Looking up book metadata needs kindlr built with the enrich feature".to_string(),
+    ))
+}
+
+/// The service called `name`, set up from settings and the environment
+#[cfg_attr(not(feature = "push"), allow(unused_variables))]
+fn push_destination<'a>(
+    name: &str,
+    settings: &Settings,
+    clippings: &'a [parser::Clipping],
+    client: &net::Client,
+) -> Result<Box<dyn push::Destination + 'a>, KindlrError> {
+    #[cfg(feature = "push")]
+    return match name {
+        "readwise" => {
+            let token = env::var("READWISE_TOKEN")
+                .ok()
+                .or_else(|| settings.readwise.token.clone())
+                .ok_or_else(|| {
+                    KindlrError::Config(
+                        "Set READWISE_TOKEN or token under [readwise] in config.toml".to_string(),
+                    )
+                })?;
+            Ok(Box::new(push::Readwise::new(
+                token,
+                clippings,
+                client.clone(),
+            )))
+        }
+        "notion" => {
+            let token = env::var("NOTION_TOKEN")
+                .ok()
+                .or_else(|| settings.notion.token.clone())
+                .ok_or_else(|| {
+                    KindlrError::Config(
+                        "Set NOTION_TOKEN or token under [notion] in config.toml".to_string(),
+                    )
+                })?;
+            let database_id = settings.notion.database_id.clone().ok_or_else(|| {
+                KindlrError::Config("Set database_id under [notion] in config.toml".to_string())
+            })?;
+            let title_property = settings
+                .notion
+                .title_property
+                .clone()
+                .unwrap_or_else(|| "Name".to_string());
+            Ok(Box::new(push::Notion::new(
+                token,
+                database_id,
+                title_property,
+                client.clone(),
+            )))
+        }
+        "hypothesis" => {
+            let token = env::var("HYPOTHESIS_TOKEN")
+                .ok()
+                .or_else(|| settings.hypothesis.token.clone())
+                .ok_or_else(|| {
+                    KindlrError::Config(
+                        "Set HYPOTHESIS_TOKEN or token under [hypothesis] in config.toml"
+                            .to_string(),
+                    )
+                })?;
+            let username = settings.hypothesis.username.as_deref().ok_or_else(|| {
+                KindlrError::Config("Set username under [hypothesis] in config.toml".to_string())
+            })?;
+            Ok(Box::new(push::Hypothesis::new(
+                token,
+                username,
+                clippings,
+                client.clone(),
+            )))
+        }
+        _ => Err(KindlrError::Config(format!(
+            "Unknown destination: {}, expected readwise, notion or hypothesis",
+            name
+        ))),
+    };
+
+    #[cfg(not(feature = "push"))]
+    Err(KindlrError::Config(
+        "Pushing clippings needs kindlr built with the push feature".to_string(),
+    ))
+}
+
+#[cfg_attr(not(feature = "push"), allow(unused_variables))]
+fn digest_channel(
+    name: &str,
+    settings: &Settings,
+    client: &net::Client,
+) -> Result<Box<dyn digest::Channel>, KindlrError> {
+    match name {
+        "stdout" => Ok(Box::new(digest::Stdout)),
+        #[cfg(feature = "push")]
+        "telegram" => {
+            let bot_token = env::var("TELEGRAM_BOT_TOKEN")
+                .ok()
+                .or_else(|| settings.telegram.bot_token.clone())
+                .ok_or_else(|| {
+                    KindlrError::Config(
+                        "Set TELEGRAM_BOT_TOKEN or bot_token under [telegram] in config.toml"
+                            .to_string(),
+                    )
+                })?;
+            let chat_id = settings.telegram.chat_id.clone().ok_or_else(|| {
+                KindlrError::Config("Set chat_id under [telegram] in config.toml".to_string())
+            })?;
+            Ok(Box::new(digest::Telegram::new(
+                bot_token,
+                chat_id,
+                client.clone(),
+            )))
+        }
+        #[cfg(not(feature = "push"))]
+        "telegram" => Err(KindlrError::Config(
+            "Sending to Telegram needs kindlr built with the push feature".to_string(),
+        )),
+        _ => Err(KindlrError::Config(format!(
+            "Unknown channel: {}, expected stdout or telegram",
+            name
+        ))),
+    }
+}
+
+/// Bearer token `serve` and `watch --listen` ask requests for
+fn serve_token(settings: &Settings) -> Option<String> {
+    env::var("KINDLR_SERVE_TOKEN")
+        .ok()
+        .or_else(|| settings.serve.token.clone())
+}
+
+/// Import the file at `file_path` as `import` does whenever it changes, for
+/// as long as kindlr runs, answering `/metrics` on `address` meanwhile
+fn watch(
+    file_path: &str,
+    interval: Duration,
+    address: Option<&str>,
+    pipeline: &hooks::Pipeline,
+    settings: &Settings,
+    config: &Config,
+) -> Result<(), KindlrError> {
+    let metrics = Arc::new(metrics::Metrics::default());
+    if let Some(address) = address {
+        let listener = TcpListener::bind(address)?;
+        eprintln!(
+            "Serving metrics on http://{}/metrics",
+            listener.local_addr()?
+        );
+        let metrics = Arc::clone(&metrics);
+        let token = serve_token(settings);
+        thread::spawn(move || {
+            serve::serve(&listener, |request| {
+                serve::metrics_only(request, &metrics, token.as_deref())
+            })
+        });
+    }
+
+    let client = http_client(config)?;
+    let mut watcher = watch::Watcher::new(file_path, metrics);
+    eprintln!("Watching {} every {}s", file_path, interval.as_secs());
+    loop {
+        if watcher.changed() {
+            // A file that fails to read is read again once it changes
+            match read_input(file_path, config).and_then(|clippings| pipeline.process(clippings)) {
+                Ok(mut clippings) => {
+                    let new = watcher.imported(&clippings);
+                    if new > 0 {
+                        eprintln!("{} new clippings in {}", new, file_path);
+                    }
+                    // Opened for each import, so stars and edits made
+                    // meanwhile aren't saved over
+                    let mut store = Store::open()?;
+                    select(&mut clippings, &store, settings, config);
+                    let notified = notify_webhooks(
+                        &clippings,
+                        settings,
+                        &mut store,
+                        &client,
+                        &mut Plan::new(false),
+                    );
+                    if let Err(error) = notified {
+                        eprintln!("{}", error);
+                    }
+                }
+                Err(error) => {
+                    watcher.failed(&error);
+                    eprintln!("{}", error);
+                }
+            }
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// Install a service running `watch` on `file_path` for the user, and start it
+fn install_service(
+    file_path: &str,
+    interval: Duration,
+    address: Option<&str>,
+    plan: &mut Plan,
+) -> Result<(), KindlrError> {
+    // The service runs from the home directory, not where this runs from
+    let file_path = match remote::Remote::parse(file_path) {
+        Some(_) => file_path.to_string(),
+        None => path::absolute(file_path)?.to_string_lossy().into_owned(),
+    };
+    let mut args = vec![
+        "watch".to_string(),
+        file_path,
+        "--interval".to_string(),
+        interval.as_secs().to_string(),
+    ];
+    if let Some(address) = address {
+        args.extend(["--listen".to_string(), address.to_string()]);
+    }
+    let service = service::Service {
+        program: env::current_exe()?.to_string_lossy().into_owned(),
+        args,
+        home: path::absolute(store::home_dir()?)?
+            .to_string_lossy()
+            .into_owned(),
+    };
+
+    let manager = service::Manager::current();
+    let path = manager.path()?;
+    let change = plan::Change::File {
+        path: path.display().to_string(),
+        action: "write".to_string(),
+    };
+    plan.apply(change, || {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(fs::write(&path, manager.definition(&service))?)
+    })?;
+
+    for step in manager.start(&path) {
+        let command = &step.args;
+        let change = plan::Change::Run {
+            command: command.join(" "),
+        };
+        plan.apply(change, || {
+            let mut process = process::Command::new(&command[0]);
+            process.args(&command[1..]);
+            if step.may_fail {
+                process.stderr(process::Stdio::null());
+            }
+            let status = process.status()?;
+            if status.success() || step.may_fail {
+                Ok(())
+            } else {
+                Err(KindlrError::Config(format!(
+                    "{} failed with {}",
+                    command.join(" "),
+                    status
+                )))
+            }
+        })?;
+    }
+
+    if !plan.is_dry_run() {
+        println!("Installed and started {}", path.display());
+    }
+    Ok(())
+}
+
+/// Send clippings each configured webhook hasn't seen yet
+fn notify_webhooks(
+    clippings: &[parser::Clipping],
+    settings: &Settings,
+    store: &mut Store,
+    client: &net::Client,
+    plan: &mut Plan,
+) -> Result<(), KindlrError> {
+    for webhook in &settings.webhooks {
+        let destination = webhook_destination(webhook, client)?;
+        let result = push::push(destination.as_ref(), clippings, store, plan);
+        plan.save(store)?;
+        let summary = result?;
+
+        if summary.pushed > 0 && !plan.is_dry_run() {
+            eprintln!("Sent {} new clippings to {}", summary.pushed, webhook.url);
+        }
+    }
 
     Ok(())
 }
+
+#[cfg_attr(not(feature = "push"), allow(unused_variables))]
+fn webhook_destination(
+    webhook: &settings::Webhook,
+    client: &net::Client,
+) -> Result<Box<dyn push::Destination>, KindlrError> {
+    #[cfg(feature = "push")]
+    return Ok(Box::new(push::Webhook::new(
+        webhook.url.clone(),
+        webhook.secret.clone(),
+        client.clone(),
+    )));
+
+    #[cfg(not(feature = "push"))]
+    Err(KindlrError::Config(format!(
+        "Calling webhook {} needs kindlr built with the push feature",
+        webhook.url
+    )))
+}
+
+/// Fill in book metadata and download covers, returning how many books were found
+fn enrich_library(
+    library: &mut Library,
+    settings: &Settings,
+    client: &net::Client,
+) -> Result<usize, KindlrError> {
+    let provider = metadata_provider(&settings.enrich, client)?;
+    let home = store::home_dir()?;
+    let found = enrich::Enricher::new(provider, &home.join("cache"))?.enrich(library)?;
+    enrich::CoverCache::new(&enrich::covers_dir(&home), client.clone()).fetch(library);
+    Ok(found)
+}
+
+/// Upload `files`, named and read, into a new folder of `bucket`, returning
+/// their keys
+#[cfg_attr(not(feature = "push"), allow(unused_variables))]
+fn upload_archive(
+    bucket: &archive::Bucket,
+    files: Vec<(String, Vec<u8>)>,
+    client: &net::Client,
+    plan: &mut Plan,
+) -> Result<Vec<String>, KindlrError> {
+    #[cfg(feature = "push")]
+    {
+        let now = chrono::Utc::now();
+        let folder = bucket.folder(now);
+        let mut keys = Vec::new();
+        for (name, body) in files {
+            let key = format!("{}{}", folder, name);
+            let change = plan::Change::Upload {
+                url: bucket.url(&key),
+                bytes: body.len(),
+            };
+            if plan
+                .apply(change, || bucket.put(client, &key, body, now))?
+                .is_some()
+            {
+                keys.push(key);
+            }
+        }
+        Ok(keys)
+    }
+
+    #[cfg(not(feature = "push"))]
+    Err(KindlrError::Config(
+        "Archiving needs kindlr built with the push feature".to_string(),
+    ))
+}
+
+/// The client integrations make requests with, caching in the kindlr home
+/// directory and offline with `--offline` or `KINDLR_OFFLINE` set
+fn http_client(config: &Config) -> Result<net::Client, KindlrError> {
+    let offline = config.offline
+        || env::var("KINDLR_OFFLINE").is_ok_and(|value| !value.is_empty() && value != "0");
+
+    Ok(net::Client::new()
+        .cache(&net::cache_dir(&store::home_dir()?), net::DEFAULT_CACHE_TTL)
+        .offline(offline))
+}
+
+/// Clippings by book, with Goodreads data and metadata when asked for
+fn library(
+    mut clippings: Vec<parser::Clipping>,
+    config: &Config,
+    settings: &Settings,
+) -> Result<Library, KindlrError> {
+    if let Some(path) = &config.book_file {
+        let ebook = ebook::Ebook::open(Path::new(path))?;
+        let found = ebook.attach_context(&mut clippings, config.context);
+        eprintln!("Found {} highlights in {}", found, path);
+    }
+    let mut library = Library::new(clippings);
+
+    if let Some(path) = &config.goodreads {
+        goodreads::correlate(&mut library, &goodreads::read(Path::new(path))?);
+    }
+
+    if config.enrich {
+        enrich_library(&mut library, settings, &http_client(config)?)?;
+    }
+
+    for book in &mut library.books {
+        book.asin = settings.asins.get(&book.title).cloned().or_else(|| {
+            book.metadata
+                .as_ref()
+                .and_then(|metadata| metadata.asin.clone())
+        });
+    }
+    library.set_lengths(&settings.lengths);
+    library.date_format = date_format(config, settings);
+    library.section_gap = config.sections.then(|| {
+        settings
+            .section_gap
+            .unwrap_or(sections::DEFAULT_SECTION_GAP)
+    });
+    library.sort(if config.sort.is_empty() {
+        &settings.export_sort
+    } else {
+        &config.sort
+    });
+
+    Ok(library)
+}
+
+/// How dates are shown, from `--date-format` or else the settings
+fn date_format(config: &Config, settings: &Settings) -> dates::DateFormat {
+    config
+        .date_format
+        .clone()
+        .or_else(|| settings.date_format.clone())
+        .unwrap_or_default()
+}
+
+fn print_list(
+    clippings: &mut [parser::Clipping],
+    starred: &HashSet<String>,
+    config: &Config,
+    settings: &Settings,
+) -> Result<(), KindlrError> {
+    if config.count_only {
+        println!("{}", clippings.len());
+        return Ok(());
+    }
+
+    group::sort(clippings, &config.sort);
+    let date_format = date_format(config, settings);
+    let now = Local::now().naive_local();
+    // Pages run through clippings in the order they're shown, groups and all
+    let shown = config.page.range(clippings.len());
+
+    let groups = match config.group_by {
+        Some(by) => group::group(clippings, by),
+        None => vec![group::Group {
+            key: String::new(),
+            clippings: clippings.iter().collect(),
+        }],
+    };
+
+    let mut out = String::new();
+    let mut n = 0;
+    for group in &groups {
+        let first = n;
+        n += group.clippings.len();
+        if shown.end <= first || n <= shown.start {
+            continue;
+        }
+        if config.group_by.is_some() {
+            out += &format!("== {} ({}) ==\n\n", group.key, group.clippings.len());
+        }
+
+        for (i, clipping) in (first + 1..).zip(&group.clippings) {
+            if !shown.contains(&(i - 1)) {
+                continue;
+            }
+            let id = clipping.id();
+            let star = if starred.contains(&id) { " *" } else { "" };
+            out += &format!("Clipping #{} ({}){}:\n", i, id, star);
+            if date_format == dates::DateFormat::Kindle {
+                out += &format!("{}\n\n", clipping);
+            } else {
+                let date = date_format.format(clipping, now);
+                out += &format!("{}\n\n", clipping.display_dated(&date));
+            }
+        }
+    }
+    if config.page.is_partial() && shown.is_empty() {
+        // A zero limit or a page past the end
+        out += &format!(
+            "Total clippings: {} (showing 0 of {})\n",
+            clippings.len(),
+            clippings.len()
+        );
+    } else if config.page.is_partial() {
+        out += &format!(
+            "Total clippings: {} (showing {}-{})\n",
+            clippings.len(),
+            shown.start + 1,
+            shown.end
+        );
+    } else {
+        out += &format!("Total clippings: {}\n", clippings.len());
+    }
+
+    pager::page(&out, !config.no_pager)?;
+    Ok(())
+}
+
+/// Fail with `NotFound`, exiting with `EXIT_NO_RESULTS`, when nothing is
+/// left to list
+fn found_any(clippings: &[parser::Clipping]) -> Result<(), KindlrError> {
+    if clippings.is_empty() {
+        return Err(KindlrError::NotFound("No clippings match".to_string()));
+    }
+    Ok(())
+}
+
+fn print_json(value: &impl Serialize) -> Result<(), KindlrError> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|error| KindlrError::Config(error.to_string()))?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// The clipping with `id` in the file, or once aliased as listed
+fn find_clipping<'a>(
+    clippings: &'a [parser::Clipping],
+    id: &str,
+    settings: &Settings,
+) -> Result<&'a parser::Clipping, KindlrError> {
+    clippings
+        .iter()
+        .find(|clipping| {
+            let mut aliased = parser::Clipping::clone(clipping);
+            clipping.id() == id
+                || aliases::apply(&mut aliased, &settings.aliases, &settings.author_aliases)
+                    && aliased.id() == id
+        })
+        .ok_or_else(|| KindlrError::NotFound(format!("No clipping with id {}", id)))
+}
+
+/// Open `text` in $VISUAL or $EDITOR and return what the user saved
+fn edit_in_editor(text: &str) -> Result<String, KindlrError> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut editor_args = editor.split_whitespace();
+    let program = editor_args
+        .next()
+        .ok_or_else(|| KindlrError::Config("EDITOR is empty".to_string()))?;
+
+    let (mut file, path) = create_edit_file()?;
+    let written = file.write_all(text.as_bytes());
+    drop(file);
+    if let Err(error) = written {
+        fs::remove_file(&path).ok();
+        return Err(error.into());
+    }
+
+    let status = process::Command::new(program)
+        .args(editor_args)
+        .arg(&path)
+        .status();
+    let edited = fs::read_to_string(&path);
+    fs::remove_file(&path).ok();
+
+    if !status?.success() {
+        return Err(KindlrError::Config(format!(
+            "Editor '{}' exited with an error",
+            editor
+        )));
+    }
+
+    Ok(edited?.trim_end().to_string())
+}
+
+/// A new file in the temporary directory only the current user can read
+///
+/// The file is created rather than opened, so a link planted at its name by
+/// another user on a shared `/tmp` is never followed; a taken name is
+/// retried with another random suffix.
+fn create_edit_file() -> Result<(fs::File, PathBuf), KindlrError> {
+    use std::hash::{BuildHasher, RandomState};
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut attempts = 0;
+    loop {
+        let suffix = RandomState::new().hash_one(attempts);
+        let path =
+            env::temp_dir().join(format!("kindlr-edit-{}-{:016x}.txt", process::id(), suffix));
+        match options.open(&path) {
+            Ok(file) => return Ok((file, path)),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists && attempts < 100 => {
+                attempts += 1;
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use chrono::{Datelike, Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::KindlrError;
+use crate::parser::{Clipping, ClippingType, Weekday};
+use crate::settings::Lint;
+
+/// Highlights shorter than this many characters are reported by default
+pub const DEFAULT_MIN_HIGHLIGHT_LENGTH: usize = 10;
+/// Bookmarks older than this many days are reported by default
+pub const DEFAULT_STALE_BOOKMARK_DAYS: i64 = 365;
+/// A highlight or note within this many locations keeps a bookmark from
+/// being stale by default
+pub const DEFAULT_NEARBY_LOCATIONS: u32 = 50;
+
+/// How much a rule's findings matter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The rule isn't checked
+    Off,
+    Info,
+    Warning,
+    /// Makes `kindlr lint` fail
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Off => "off",
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        f.pad(name)
+    }
+}
+
+/// Something about a clipping worth a second look
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    /// A highlight shorter than `min_highlight_length`, usually a slip
+    ShortHighlight,
+    /// A bookmark older than `stale_bookmark_days` with no highlight or note
+    /// near it
+    StaleBookmark,
+    /// A note with nothing in it
+    EmptyNote,
+    /// A clipping added after now, as made on a Kindle with a wrong clock
+    FutureDate,
+    /// A weekday other than the one the date fell on, a sign of a corrupted
+    /// entry or a misread date
+    WeekdayMismatch,
+    /// A highlight overlapping an earlier one in the same book
+    Overlap,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Rule::ShortHighlight,
+        Rule::StaleBookmark,
+        Rule::EmptyNote,
+        Rule::FutureDate,
+        Rule::WeekdayMismatch,
+        Rule::Overlap,
+    ];
+
+    /// Name of the rule in config.toml and on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::ShortHighlight => "short-highlight",
+            Rule::StaleBookmark => "stale-bookmark",
+            Rule::EmptyNote => "empty-note",
+            Rule::FutureDate => "future-date",
+            Rule::WeekdayMismatch => "weekday-mismatch",
+            Rule::Overlap => "overlap",
+        }
+    }
+
+    /// Severity unless configured otherwise
+    pub fn default_severity(self) -> Severity {
+        match self {
+            Rule::ShortHighlight | Rule::EmptyNote | Rule::WeekdayMismatch | Rule::Overlap => {
+                Severity::Warning
+            }
+            Rule::StaleBookmark => Severity::Info,
+            Rule::FutureDate => Severity::Error,
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Rule::ALL
+            .into_iter()
+            .find(|rule| rule.name() == s)
+            .ok_or_else(|| format!("Invalid lint rule: {}", s))
+    }
+}
+
+/// A clipping breaking a rule
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub rule: Rule,
+    pub severity: Severity,
+    /// Id of the clipping
+    pub id: String,
+    pub book: String,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<7} {:<16} {} {}: {}",
+            self.severity,
+            self.rule.name(),
+            self.id,
+            self.book,
+            self.message
+        )
+    }
+}
+
+/// Rules to check and their thresholds, from the `[lint]` settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+    severities: HashMap<Rule, Severity>,
+    min_highlight_length: usize,
+    stale_bookmark_days: i64,
+    nearby_locations: u32,
+}
+
+impl Rules {
+    /// Rules as configured in `settings`, failing on rules it names that
+    /// don't exist
+    pub fn new(settings: &Lint) -> Result<Self, KindlrError> {
+        let mut severities: HashMap<Rule, Severity> = Rule::ALL
+            .into_iter()
+            .map(|rule| (rule, rule.default_severity()))
+            .collect();
+        for (name, &severity) in &settings.rules {
+            let rule = name.parse().map_err(KindlrError::Config)?;
+            severities.insert(rule, severity);
+        }
+
+        Ok(Rules {
+            severities,
+            min_highlight_length: settings
+                .min_highlight_length
+                .unwrap_or(DEFAULT_MIN_HIGHLIGHT_LENGTH),
+            stale_bookmark_days: settings
+                .stale_bookmark_days
+                .unwrap_or(DEFAULT_STALE_BOOKMARK_DAYS),
+            nearby_locations: settings
+                .nearby_locations
+                .unwrap_or(DEFAULT_NEARBY_LOCATIONS),
+        })
+    }
+
+    /// Stop checking `rule`
+    pub fn disable(&mut self, rule: Rule) {
+        self.severities.insert(rule, Severity::Off);
+    }
+
+    fn severity(&self, rule: Rule) -> Severity {
+        self.severities[&rule]
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules::new(&Lint::default()).expect("no rules are named")
+    }
+}
+
+/// Findings of every rule not turned off, in the order of the clippings
+/// they're about
+///
+/// Dates are compared with `now`; clippings whose datetime can't be read
+/// are never stale, in the future or on the wrong weekday.
+pub fn lint(clippings: &[Clipping], rules: &Rules, now: NaiveDateTime) -> Vec<Finding> {
+    let mut findings: Vec<(usize, Finding)> = Vec::new();
+    let mut report = |i: usize, rule: Rule, message: String| {
+        let severity = rules.severity(rule);
+        if severity != Severity::Off {
+            findings.push((
+                i,
+                Finding {
+                    rule,
+                    severity,
+                    id: clippings[i].id(),
+                    book: clippings[i].book_title.clone(),
+                    message,
+                },
+            ));
+        }
+    };
+
+    let mut books: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+    for (i, clipping) in clippings.iter().enumerate() {
+        books
+            .entry((&clipping.book_title, &clipping.author))
+            .or_default()
+            .push(i);
+    }
+
+    for (i, clipping) in clippings.iter().enumerate() {
+        let content = clipping.content.as_deref().map_or("", str::trim);
+        let timestamp = clipping.timestamp();
+        let book = &books[&(clipping.book_title.as_str(), clipping.author.as_str())];
+
+        match clipping.clipping_type {
+            ClippingType::Highlight if content.chars().count() < rules.min_highlight_length => {
+                report(
+                    i,
+                    Rule::ShortHighlight,
+                    format!(
+                        "highlight of {} characters at location {}",
+                        content.chars().count(),
+                        clipping.location
+                    ),
+                );
+            }
+            ClippingType::Note if content.is_empty() => {
+                report(
+                    i,
+                    Rule::EmptyNote,
+                    format!("empty note at location {}", clipping.location),
+                );
+            }
+            ClippingType::Bookmark => {
+                let stale = timestamp
+                    .is_some_and(|added| now - added > Duration::days(rules.stale_bookmark_days));
+                let near = |other: &Clipping| {
+                    other.clipping_type != ClippingType::Bookmark
+                        && other.location.start.abs_diff(clipping.location.start)
+                            <= rules.nearby_locations
+                };
+                if stale && !book.iter().any(|&j| near(&clippings[j])) {
+                    report(
+                        i,
+                        Rule::StaleBookmark,
+                        format!(
+                            "bookmark at location {} from {} with nothing highlighted near it",
+                            clipping.location, clipping.datetime
+                        ),
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        if timestamp.is_some_and(|added| added > now) {
+            report(
+                i,
+                Rule::FutureDate,
+                format!("added {}, after now", clipping.datetime),
+            );
+        }
+
+        if let Some(added) = timestamp
+            && let weekday = Weekday::from(added.weekday())
+            && weekday != clipping.weekday
+        {
+            report(
+                i,
+                Rule::WeekdayMismatch,
+                format!(
+                    "added on a {}, but {} was a {}",
+                    clipping.weekday,
+                    added.format("%-d %B %Y"),
+                    weekday
+                ),
+            );
+        }
+
+        if clipping.clipping_type == ClippingType::Highlight
+            && let Some(&earlier) = book.iter().take_while(|&&j| j < i).find(|&&j| {
+                clippings[j].clipping_type == ClippingType::Highlight
+                    && clippings[j].location.overlaps(&clipping.location)
+            })
+        {
+            report(
+                i,
+                Rule::Overlap,
+                format!(
+                    "location {} overlaps highlight {} at location {}",
+                    clipping.location,
+                    clippings[earlier].id(),
+                    clippings[earlier].location
+                ),
+            );
+        }
+    }
+
+    findings.sort_by_key(|(i, _)| *i);
+    findings.into_iter().map(|(_, finding)| finding).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+    use chrono::NaiveDate;
+
+    const CLIPPINGS: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 11-13 | Added on Monday, 1 January 2024 10:01:00
+
+Fear.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 13 | Added on Monday, 1 January 2024 10:02:00
+
+Fear of what?
+==========
+Dune (Frank Herbert)
+- Your Bookmark on page 2 | Location 40 | Added on Monday, 1 January 2024 10:03:00
+
+
+==========
+Dune (Frank Herbert)
+- Your Bookmark on page 30 | Location 900 | Added on Monday, 1 January 2024 10:04:00
+
+
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 40 | Location 1200-1201 | Added on Monday, 1 January 2035 10:00:00
+
+The spice must flow.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 50 | Location 1500-1501 | Added on Wednesday, 2 January 2024 10:00:00
+
+He who controls the spice.
+==========";
+
+    #[test]
+    fn test_lint() {
+        let mut clippings = parse_clippings(CLIPPINGS).unwrap();
+        clippings[2].content = Some(" ".to_string());
+        let now = NaiveDate::from_ymd_opt(2025, 6, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .unwrap();
+
+        let mut rules = Rules::default();
+        let found = |rules: &Rules| -> Vec<(Rule, Severity, String)> {
+            lint(&clippings, rules, now)
+                .into_iter()
+                .map(|finding| (finding.rule, finding.severity, finding.id))
+                .collect()
+        };
+        assert_eq!(
+            found(&rules),
+            [
+                (Rule::ShortHighlight, Severity::Warning, clippings[1].id()),
+                (Rule::Overlap, Severity::Warning, clippings[1].id()),
+                (Rule::EmptyNote, Severity::Warning, clippings[2].id()),
+                (Rule::StaleBookmark, Severity::Info, clippings[4].id()),
+                (Rule::FutureDate, Severity::Error, clippings[5].id()),
+                (Rule::WeekdayMismatch, Severity::Warning, clippings[6].id()),
+            ]
+        );
+
+        rules.disable(Rule::Overlap);
+        let settings = Lint {
+            rules: [("future-date".to_string(), Severity::Off)].into(),
+            min_highlight_length: Some(3),
+            ..Lint::default()
+        };
+        let configured = Rules::new(&settings).unwrap();
+        assert_eq!(found(&rules).len(), 5);
+        assert_eq!(found(&configured).len(), 4);
+
+        let settings = Lint {
+            rules: [("shouting".to_string(), Severity::Error)].into(),
+            ..Lint::default()
+        };
+        assert!(Rules::new(&settings).is_err());
+    }
+}
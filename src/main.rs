@@ -1,17 +1,55 @@
 use std::env;
 use std::process;
 
-use kindlr::Config;
+use kindlr::man::USAGE;
+use kindlr::parser::ParseError;
+use kindlr::{Config, EXIT_NO_RESULTS, EXIT_USAGE, KindlrError};
 
 fn main() {
-    let config = Config::build(env::args()).unwrap_or_else(|err| {
-        eprintln!("Problem parsing arguments: {err}");
-        eprintln!("\nUsage: kindlr <file_path>");
-        process::exit(1);
+    let args: Vec<String> = env::args().collect();
+
+    // `kindlr <command> --help` is shorthand for `kindlr help <command>`
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        match args.get(1).and_then(|command| kindlr::man::help(command)) {
+            Some(help) => print!("{help}"),
+            None => println!("{USAGE}"),
+        }
+        return;
+    }
+    let json_errors = args
+        .windows(2)
+        .any(|pair| pair[0] == "--output-format" && pair[1] == "json");
+
+    let report = |context: &str, error: KindlrError| {
+        if json_errors {
+            let report = serde_json::json!({ "error": error.report() });
+            eprintln!("{}", report);
+        } else {
+            eprintln!("{context}: {error}");
+        }
+    };
+
+    let config = Config::build(args.clone().into_iter()).unwrap_or_else(|err| {
+        report("Problem parsing arguments", err);
+        if !json_errors {
+            eprintln!("\n{USAGE}");
+        }
+        process::exit(EXIT_USAGE);
     });
 
-    if let Err(e) = kindlr::run(config) {
-        eprintln!("Application error: {e}");
-        process::exit(1);
+    match kindlr::run(config) {
+        Ok(()) => {}
+        Err(KindlrError::Parse(ParseError::EmptyFile(path))) if !json_errors => {
+            eprintln!(
+                "{path} has no clippings yet. Highlight, note or bookmark something on \
+                 your Kindle, then run kindlr again."
+            );
+            process::exit(EXIT_NO_RESULTS);
+        }
+        Err(e) => {
+            let code = e.exit_code();
+            report("Application error", e);
+            process::exit(code);
+        }
     }
 }
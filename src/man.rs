@@ -0,0 +1,724 @@
+/// Command line usage, printed after a mistake in the arguments and by
+/// `kindlr help`
+pub const USAGE: &str = "\
+Usage: kindlr [list] <file_path> [<query>] [--original] [filters]
+       kindlr [list] <file_path>... [-- <query>] [--original] [filters]
+           [--sort date|book|location|length,...] [--group-by book|author|month]
+           [--relative | --date-format <format>] [--no-pager]
+           [--limit <n>] [--offset <n> | --page <n>] [--count-only]
+       kindlr edit <file_path> <id>
+       kindlr star|unstar <file_path> <id>
+       kindlr stats <file_path> [--json]
+           [--heatmap [--year <year>] | --sessions [--gap <minutes>] | --by-author | --by-device
+            | --lengths | --by-rating --goodreads <csv> | --progress <book>]
+       kindlr analyze <file_path> [--top <n>] [--by-book] [--stopwords <lang>] [--json]
+       kindlr analyze <file_path> --duplicates [--threshold <0-1>] [--json]
+       kindlr analyze <file_path> --cluster [--topics <n>] [--stopwords <lang>] [--json]
+       kindlr find-similar <file_path> <phrase> [--top <n>] [filters] [--json]
+       kindlr report <file_path> [--year <year>] [--format markdown|html]
+           [--stopwords <lang>] [--json]
+       kindlr import <file_path>
+       kindlr export <file_path> [--format json|markdown|marginalia|kindle|yaml]
+           [--output <path>] [--goodreads <csv>] [--enrich] [--verify-roundtrip]
+           [--redact] [--since-last] [--sort date|location|length,...] [--sections]
+           [--book-file <ebook> [--context <n>]] [--filename-template <template>]
+           [--site hugo|jekyll] [filters]
+       kindlr enrich <file_path> [--json]
+       kindlr digest <file_path> [--latest <n>] [--channel stdout|telegram]
+       kindlr push readwise|notion|hypothesis <file_path> [filters] [--json]
+       kindlr archive <file_path> [filters] [--json]
+       kindlr diff <old_file_path> <new_file_path>
+       kindlr collection <file_path> [<name>]
+       kindlr books <file_path> [--suggest-aliases [--threshold <0-1>]] [--json]
+       kindlr lint <file_path> [--disable <rules>] [filters] [--json]
+       kindlr count <file_path> [--by type|book] [--json]
+       kindlr adjust <file_path> --offset <duration> [--book <text>]
+           [--since <yyyy-mm-dd>] [--until <yyyy-mm-dd>]
+       kindlr serve <file_path> [--listen <address>] [--read-only] [filters]
+       kindlr watch <file_path> [--interval <seconds>] [--listen <address>]
+       kindlr service install <file_path> [--interval <seconds>] [--listen <address>]
+       kindlr backup|restore <archive_path>
+       kindlr schema [--format json]
+       kindlr help [<command>]
+       kindlr man
+
+My Clippings.txt may come from a Kindle set to English, Hungarian, Czech,
+Swedish, Norwegian, Danish, Finnish, Greek, Arabic, Hebrew or Traditional
+Chinese.
+Besides My Clippings.txt, <file_path> may be an Amazon notes HTML export,
+a Readwise CSV export, a Kobo database (kobo feature) or the article
+highlights exported from Omnivore (JSON), Matter (CSV) or Pocket (JSON).
+Give several paths, or a directory to read every *.txt file in it, to read
+them all at once; clippings found in more than one file are kept once. For
+list, put a query after -- when giving several paths.
+<file_path> may also be a file in cloud storage, webdav://host/path,
+webdavs://host/path over HTTPS or dropbox:/path, downloaded on every read
+(remote feature). Gzipped files are read as the file they hold, and zip
+files as their largest .txt, or the member named with --member.
+
+Exporting markdown to a directory writes a file per book, adding new
+highlights between <!-- kindlr:start --> and <!-- kindlr:end --> and
+leaving everything else in existing files as it was. Exporting kindle to
+a file replaces it only once the new file reads back, keeping the old one
+in backups/ in the kindlr home directory.
+
+Lint rules are short-highlight, stale-bookmark, empty-note, future-date,
+weekday-mismatch and overlap; lint fails when a rule set to error in
+[lint.rules] is broken.
+
+watch reads the file again whenever it changes, sending new clippings to
+any [[webhooks]] as import does. watch --listen and serve answer /metrics
+with counters of imports, new clippings, parse errors and API requests in
+the Prometheus text format. service install writes a user systemd unit,
+or a launchd agent on macOS, running watch with the kindlr home directory
+in use, and starts it.
+
+Integrations, configured in config.toml in the kindlr home directory:
+    enrich, --enrich       Open Library, or Google Books, and covers for markdown
+                           exports (enrich feature)
+    push readwise          Token from READWISE_TOKEN or [readwise] (push feature)
+    push notion            Token from NOTION_TOKEN or [notion] (push feature)
+    push hypothesis        Token from HYPOTHESIS_TOKEN or [hypothesis] (push feature)
+    digest --channel telegram
+                           Bot token from TELEGRAM_BOT_TOKEN or [telegram] (push feature)
+    import                 POSTs new clippings to any [[webhooks]] (push feature)
+    webdav://, webdavs://  User and password from the URL or [webdav], or the
+                           password from WEBDAV_PASSWORD (remote feature)
+    dropbox:               Token from DROPBOX_TOKEN or [dropbox] (remote feature)
+    serve                  Asks every request for the bearer token from
+                           KINDLR_SERVE_TOKEN or [serve], when one is set
+    serve                  Answers / with a web UI of books, search, quote cards
+                           and a daily review (web-ui feature)
+    serve                  Answers GraphQL queries of books, clippings and stats
+                           at /graphql (graphql feature)
+    archive                Uploads to the [s3] bucket, keys from AWS_ACCESS_KEY_ID
+                           and AWS_SECRET_ACCESS_KEY or [s3] (push feature)
+    [aliases]              Titles to show as another title, see books --suggest-aliases
+    [author-aliases]       Authors to show as another author
+    [asins]                Kindle edition ASINs by title, linking markdown quotes
+                           back to the Kindle app when enrich finds none
+    [lengths]              Locations in each book by title, for how far through
+                           the book markdown quotes are
+    [devices]              Names for Kindles by the serial found when mounted
+    ignore_books           Globs or /regexes/ of titles never shown, like manuals
+    export_sort            Order of each book's clippings in exports, such as
+                           [\"location\"] for the order they come in the book
+    [lint]                 Severity of each lint rule, off to disable, and thresholds
+    [[clock]]              Offsets for datetimes of a Kindle whose clock was wrong,
+                           see adjust
+    [timezone]             Zone of the Kindle's clock, zones while travelling,
+                           and convert_to local, utc or a zone for output
+    export --redact        Drops [redact] private_books, omits or hashes notes
+                           and cuts dates to the day
+
+Options:
+    --output-format text|json  Print results and errors as JSON
+    --mmap                 Memory-map huge clippings files (mmap feature)
+    --no-cache             Parse the file again instead of reusing the last parse
+    --device-label <name>  Attribute clippings to a device, for files copied off it
+    --member <name>        File of a zipped input to read instead of its largest .txt
+    --dry-run              List what edit, star, unstar, import, export, push,
+                           archive or service install would change, without
+                           changing anything
+    --offline              Answer integrations only from cached responses, as does
+                           setting KINDLR_OFFLINE
+    --date-format <format> Show dates in list and Markdown as kindle, iso, date,
+                           relative, relative:<days> for dates as written once
+                           older, or a strftime string such as %d/%m/%Y, as
+                           does date_format in config.toml
+    --no-pager             Print long listings instead of paging them through
+                           KINDLR_PAGER, PAGER or less when on a terminal
+    --relative             Show dates as \"3 days ago\" within 30 days, the same
+                           as --date-format relative:30
+    --limit <n>            List at most n clippings
+    --offset <n>           Skip the first n clippings listed
+    --page <n>             List page n, of --limit clippings or 20
+    --count-only           Print how many clippings would be listed
+    --sections             Split each book of a Markdown file export into sections
+                           at bookmarks and gaps of section_gap locations, 300
+                           unless set in config.toml
+    --book-file <ebook>    Find highlights in the book's EPUB, or MOBI or AZW3
+                           without DRM, and export the text around them
+    --context <n>          Sentences either side of a highlight, 1 by default
+    --filename-template <template>
+                           Name files exported to a directory from {title},
+                           {author}, {title_slug}, {author_slug} and {year},
+                           such as {author}/{title}.md, as does
+                           filename_template in config.toml
+    --listen <address>     Address serve answers on, 127.0.0.1:7878 by default, or
+                           watch answers /metrics on
+    --interval <seconds>   How often watch looks for changes to the file, 30 by
+                           default
+    --read-only            Refuse requests to serve that would star or edit clippings
+    --site hugo|jekyll     Export into the site directory given by --output, a page
+                           per book with front matter under content/books or
+                           _books and every book in data/ or _data/clippings.json
+
+Filters:
+    --query <query>        Query such as 'book:\"dune\" type:note added:>2024-01-01'
+    --book <text>          Book title contains text
+    --author <text>        Author contains text
+    --contains <text>      Content contains text
+    --type <types>         Comma-separated highlight, note, bookmark, article
+    --color <colors>       Comma-separated yellow, blue, pink, orange, for highlights
+                           from the Kindle app's HTML or Readwise CSV exports
+    --since <yyyy-mm-dd>   Added on or after date
+    --until <yyyy-mm-dd>   Added on or before date
+    --fuzzy <text>         Content is similar to text [--threshold <0-1>]
+    --min-length <n>       Content has at least n characters
+    --max-length <n>       Content has at most n characters
+    --dedupe <strategy>    Drop duplicates: exact, superseded, window:<minutes>
+                           or concatenated, for a file appended to itself
+    --tidy <level>         Trim sloppy highlights: punctuation, words or sentences
+    --merge-adjacent       Merge highlights split at a page boundary
+        [--merge-window <minutes>]
+    --favorites-only       Only starred clippings
+    --device <text>        Read from a device named or numbered like text
+    --language <iso639-3>  Content language (language-detection feature)
+
+Exit status:
+    0                      Success
+    1                      A failure no other status covers, such as a failing hook
+    2                      Bad arguments or settings
+    3                      A file that can't be parsed
+    4                      A file that can't be read or written
+    5                      Nothing matched, or the file has no clippings yet
+    6                      A request to an integration failed
+    7                      Lint found breaks of rules set to error";
+
+/// Examples for each command, as what they do and the command line
+const EXAMPLES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "list",
+        &[
+            (
+                "Notes on Dune added this year",
+                "kindlr 'My Clippings.txt' --book dune --type note --since 2024-01-01",
+            ),
+            (
+                "Highlights grouped by book, longest first",
+                "kindlr list 'My Clippings.txt' --group-by book --sort length",
+            ),
+            (
+                "A search in the query language",
+                "kindlr list 'My Clippings.txt' 'author:\"le guin\" darkness'",
+            ),
+        ],
+    ),
+    (
+        "edit",
+        &[(
+            "Fix a typo in a highlight, keeping the original",
+            "kindlr edit 'My Clippings.txt' 3f2a9c1e5b7d8a04",
+        )],
+    ),
+    (
+        "star",
+        &[(
+            "Mark a clipping as a favorite",
+            "kindlr star 'My Clippings.txt' 3f2a9c1e5b7d8a04",
+        )],
+    ),
+    (
+        "unstar",
+        &[(
+            "Unmark a favorite",
+            "kindlr unstar 'My Clippings.txt' 3f2a9c1e5b7d8a04",
+        )],
+    ),
+    (
+        "stats",
+        &[
+            ("Counts by type and book", "kindlr stats 'My Clippings.txt'"),
+            (
+                "A calendar of reading days in 2024",
+                "kindlr stats 'My Clippings.txt' --heatmap --year 2024",
+            ),
+            (
+                "How far into Dune each bookmark was left, over time",
+                "kindlr stats 'My Clippings.txt' --progress Dune",
+            ),
+        ],
+    ),
+    (
+        "analyze",
+        &[
+            (
+                "The 20 words highlighted most in each book",
+                "kindlr analyze 'My Clippings.txt' --top 20 --by-book",
+            ),
+            (
+                "Highlights that are nearly the same",
+                "kindlr analyze 'My Clippings.txt' --duplicates --threshold 0.8",
+            ),
+            (
+                "Highlights from every book gathered into 8 topics, for writing an essay",
+                "kindlr analyze 'My Clippings.txt' --cluster --topics 8",
+            ),
+        ],
+    ),
+    (
+        "find-similar",
+        &[(
+            "Find the exact words and source of a half-remembered quote",
+            "kindlr find-similar 'My Clippings.txt' 'fear kills the mind'",
+        )],
+    ),
+    (
+        "report",
+        &[(
+            "A year in reading as a web page",
+            "kindlr report 'My Clippings.txt' --year 2024 --format html > 2024.html",
+        )],
+    ),
+    (
+        "import",
+        &[
+            (
+                "Read a Kobo database",
+                "kindlr import /media/KOBOeReader/.kobo/KoboReader.sqlite",
+            ),
+            (
+                "Read the clippings a Kindle Scribe synced to a NAS",
+                "kindlr import 'webdavs://reader@nas.local/kindle/My Clippings.txt'",
+            ),
+        ],
+    ),
+    (
+        "export",
+        &[
+            (
+                "Every book as Markdown into a notes directory",
+                "kindlr export 'My Clippings.txt' --format markdown --output notes/",
+            ),
+            (
+                "Clippings new since the last export, appended to a file",
+                "kindlr export 'My Clippings.txt' --format markdown --output all.md --since-last",
+            ),
+            (
+                "Each book's highlights in the order they come in the book",
+                "kindlr export 'My Clippings.txt' --format markdown --sort location",
+            ),
+            (
+                "Books and clippings as a Hugo data file",
+                "kindlr export 'My Clippings.txt' --format yaml --output data/clippings.yaml",
+            ),
+            (
+                "Only your own notes, each above the passage it was made on",
+                "kindlr export 'My Clippings.txt' --format marginalia --output marginalia.md",
+            ),
+            (
+                "In a Kindle's documents folder, its clippings written back without duplicates",
+                "kindlr export 'My Clippings.txt' --dedupe exact --format kindle --output 'My Clippings.txt'",
+            ),
+            (
+                "A page per book and the data they're drawn from, into a Hugo site",
+                "kindlr export 'My Clippings.txt' --site hugo --output ~/blog",
+            ),
+        ],
+    ),
+    (
+        "enrich",
+        &[(
+            "Look up ISBNs and covers without going online",
+            "kindlr enrich 'My Clippings.txt' --offline",
+        )],
+    ),
+    (
+        "digest",
+        &[(
+            "Send the five latest highlights to Telegram",
+            "kindlr digest 'My Clippings.txt' --latest 5 --channel telegram",
+        )],
+    ),
+    (
+        "push",
+        &[
+            (
+                "Send highlights not sent before to Readwise",
+                "READWISE_TOKEN=... kindlr push readwise 'My Clippings.txt'",
+            ),
+            (
+                "Which highlights of Dune would go to Notion, without sending any",
+                "kindlr push notion 'My Clippings.txt' --book dune --dry-run",
+            ),
+        ],
+    ),
+    (
+        "archive",
+        &[(
+            "Keep a dated copy of the file and its JSON export in S3",
+            "kindlr archive /media/Kindle/documents/'My Clippings.txt'",
+        )],
+    ),
+    (
+        "diff",
+        &[(
+            "What changed since an older copy",
+            "kindlr diff old/'My Clippings.txt' 'My Clippings.txt'",
+        )],
+    ),
+    (
+        "collection",
+        &[(
+            "List a search saved under [collections]",
+            "kindlr collection 'My Clippings.txt' stoics",
+        )],
+    ),
+    (
+        "books",
+        &[(
+            "Titles that look like the same book",
+            "kindlr books 'My Clippings.txt' --suggest-aliases >> ~/.kindlr/config.toml",
+        )],
+    ),
+    (
+        "lint",
+        &[
+            (
+                "Check for slips, empty notes and wrong clocks",
+                "kindlr lint 'My Clippings.txt'",
+            ),
+            (
+                "Skip overlapping highlights and old bookmarks",
+                "kindlr lint 'My Clippings.txt' --disable overlap,stale-bookmark",
+            ),
+        ],
+    ),
+    (
+        "count",
+        &[
+            (
+                "Count highlights, notes and bookmarks",
+                "kindlr count 'My Clippings.txt'",
+            ),
+            (
+                "Show the number of highlights in a shell prompt",
+                "kindlr count 'My Clippings.txt' --json | jq .highlights",
+            ),
+            (
+                "Count clippings per book, most clipped first",
+                "kindlr count 'My Clippings.txt' --by book",
+            ),
+        ],
+    ),
+    (
+        "adjust",
+        &[(
+            "Move highlights made while the clock was a year behind",
+            "kindlr adjust 'My Clippings.txt' --book Dune --until 2023-06-30 --offset P365D >> ~/.kindlr/config.toml",
+        )],
+    ),
+    (
+        "serve",
+        &[
+            (
+                "Answer the JSON API and its OpenAPI document at /openapi.json on the LAN",
+                "kindlr serve 'My Clippings.txt' --listen 0.0.0.0:7878",
+            ),
+            (
+                "Let a dashboard on the LAN read, but not star or edit, with a token",
+                "KINDLR_SERVE_TOKEN=... kindlr serve 'My Clippings.txt' --listen 0.0.0.0:7878 --read-only",
+            ),
+        ],
+    ),
+    (
+        "watch",
+        &[(
+            "Send new highlights to webhooks as the Kindle syncs, graphed in Prometheus",
+            "kindlr watch ~/Dropbox/'My Clippings.txt' --interval 60 --listen 127.0.0.1:9187",
+        )],
+    ),
+    (
+        "service",
+        &[(
+            "Keep watching the Kindle's file, from login on, without a terminal open",
+            "kindlr service install /media/$USER/Kindle/documents/'My Clippings.txt' --listen 127.0.0.1:9187",
+        )],
+    ),
+    (
+        "backup",
+        &[(
+            "Archive edits, favorites and settings",
+            "kindlr backup kindlr-backup.tar.gz",
+        )],
+    ),
+    (
+        "restore",
+        &[(
+            "Bring them back on another machine",
+            "kindlr restore kindlr-backup.tar.gz",
+        )],
+    ),
+    (
+        "schema",
+        &[(
+            "Generate types for reading JSON exports",
+            "kindlr schema > kindlr-export.schema.json",
+        )],
+    ),
+    (
+        "help",
+        &[("Usage and examples of export", "kindlr help export")],
+    ),
+    (
+        "man",
+        &[(
+            "Install the manual page",
+            "kindlr man > /usr/local/share/man/man1/kindlr.1",
+        )],
+    ),
+];
+
+/// Environment variables read, and what for
+const ENVIRONMENT: &[(&str, &str)] = &[
+    (
+        "KINDLR_HOME",
+        "Directory of the store, config.toml and caches, ~/.kindlr by default",
+    ),
+    (
+        "KINDLR_OFFLINE",
+        "Answer integrations only from cached responses, as --offline does",
+    ),
+    ("READWISE_TOKEN", "Token for push readwise"),
+    ("NOTION_TOKEN", "Token for push notion"),
+    ("HYPOTHESIS_TOKEN", "Token for push hypothesis"),
+    (
+        "TELEGRAM_BOT_TOKEN",
+        "Bot token for digest --channel telegram",
+    ),
+    (
+        "WEBDAV_PASSWORD",
+        "Password for webdav:// and webdavs:// files",
+    ),
+    ("DROPBOX_TOKEN", "Token for dropbox: files"),
+    (
+        "KINDLR_SERVE_TOKEN",
+        "Bearer token serve asks every request for",
+    ),
+    (
+        "AWS_ACCESS_KEY_ID",
+        "Access key of the [s3] bucket for archive",
+    ),
+    (
+        "AWS_SECRET_ACCESS_KEY",
+        "Secret key of the [s3] bucket for archive",
+    ),
+];
+
+/// Files in the kindlr home directory
+const FILES: &[(&str, &str)] = &[
+    ("config.toml", "Settings, integrations and saved searches"),
+    (
+        "store.json",
+        "Edits, favorites and what was pushed or exported where",
+    ),
+    (
+        "cache/",
+        "Parsed clippings, metadata, covers, network responses, downloaded and unpacked files, safe to delete",
+    ),
+    (
+        "backups/",
+        "Files replaced by export --format kindle, named by when, left out of kindlr backup",
+    ),
+];
+
+/// Usage and examples of `command`, or `None` if there is no such command
+pub fn help(command: &str) -> Option<String> {
+    let synopses: Vec<String> = synopses()
+        .into_iter()
+        .filter(|(commands, _)| commands.contains(&command))
+        .map(|(_, synopsis)| synopsis)
+        .collect();
+    if synopses.is_empty() {
+        return None;
+    }
+
+    let mut text = format!("Usage: {}\n", synopses.join("\n       "));
+    if let Some((_, examples)) = EXAMPLES.iter().find(|(name, _)| *name == command) {
+        text += "\nExamples:\n";
+        for (what, line) in *examples {
+            text += &format!("    {}\n        {}\n", what, line);
+        }
+    }
+    Some(text)
+}
+
+/// The kindlr(1) manual page in roff, made from `USAGE` and the examples
+pub fn page() -> String {
+    let mut page = format!(
+        ".TH KINDLR 1 \"\" \"kindlr {}\" \"User Commands\"\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    page += ".SH NAME\nkindlr \\- search, export and sync Kindle highlights and notes\n";
+
+    page += ".SH SYNOPSIS\n.nf\n";
+    for (_, synopsis) in synopses() {
+        page += &format!("{}\n", roff(&synopsis));
+    }
+    page += ".fi\n";
+
+    let (_, rest) = USAGE.split_once("\n\n").unwrap_or_default();
+    let blocks: Vec<&str> = rest.split("\n\n").collect();
+    page += ".SH DESCRIPTION\n";
+    for block in blocks.iter().filter(|block| !is_list(block)) {
+        page += &format!(".PP\n{}\n", roff(&block.replace('\n', " ")));
+    }
+
+    for block in blocks.iter().filter(|block| is_list(block)) {
+        let (heading, items) = block.split_once('\n').unwrap_or_default();
+        let heading = heading.trim_end_matches(':');
+        let (name, intro) = heading.split_once(", ").unwrap_or((heading, ""));
+        page += &format!(".SH {}\n", name.to_uppercase());
+        if let Some(first) = intro.chars().next() {
+            page += &format!(
+                ".PP\n{}{}.\n",
+                first.to_uppercase(),
+                roff(&intro[first.len_utf8()..])
+            );
+        }
+        for (term, description) in list_items(items) {
+            page += &format!(".TP\n\\fB{}\\fR\n{}\n", roff(&term), roff(&description));
+        }
+    }
+
+    page += ".SH EXAMPLES\n";
+    for (command, examples) in EXAMPLES {
+        page += &format!(".SS {}\n", command);
+        for (what, line) in *examples {
+            page += &format!(
+                ".PP\n{}:\n.RS 4\n.nf\n{}\n.fi\n.RE\n",
+                roff(what),
+                roff(line)
+            );
+        }
+    }
+
+    page += ".SH ENVIRONMENT\n";
+    for (name, description) in ENVIRONMENT {
+        page += &format!(".TP\n\\fB{}\\fR\n{}\n", name, roff(description));
+    }
+    page += ".SH FILES\n";
+    for (name, description) in FILES {
+        page += &format!(
+            ".TP\n\\fI~/.kindlr/{}\\fR\n{}\n",
+            roff(name),
+            roff(description)
+        );
+    }
+
+    page
+}
+
+/// Each usage line, with the lines continuing it, and the commands it is for
+fn synopses() -> Vec<(Vec<&'static str>, String)> {
+    let mut synopses: Vec<(Vec<&str>, String)> = Vec::new();
+
+    for line in USAGE.lines().take_while(|line| !line.is_empty()) {
+        let line = line.strip_prefix("Usage:").unwrap_or(line).trim();
+        match (line.strip_prefix("kindlr "), synopses.last_mut()) {
+            (Some(rest), _) => {
+                let name = rest.split_whitespace().next().unwrap_or_default();
+                let commands = if name.starts_with('<') {
+                    Vec::new()
+                } else {
+                    name.trim_matches(['[', ']']).split('|').collect()
+                };
+                synopses.push((commands, line.to_string()));
+            }
+            (None, Some((_, synopsis))) => {
+                *synopsis += &format!("\n    {}", line);
+            }
+            (None, None) => {}
+        }
+    }
+
+    synopses
+}
+
+/// Whether `block` is a heading followed by indented terms and descriptions
+fn is_list(block: &str) -> bool {
+    block
+        .split_once('\n')
+        .is_some_and(|(heading, items)| heading.ends_with(':') && items.starts_with("    "))
+}
+
+/// Terms and their descriptions, from lines like `    --term  Description`
+/// that may continue on more indented lines
+fn list_items(lines: &str) -> Vec<(String, String)> {
+    let mut items: Vec<(String, String)> = Vec::new();
+
+    for line in lines.lines() {
+        let indent = line.len() - line.trim_start().len();
+        let line = line.trim();
+        match items.last_mut() {
+            Some((term, _)) if indent > 4 && indent <= 8 => *term += &format!(" {}", line),
+            Some((_, description)) if indent > 8 => {
+                if !description.is_empty() {
+                    description.push(' ');
+                }
+                *description += line;
+            }
+            _ => {
+                let (term, description) = line.split_once("  ").unwrap_or((line, ""));
+                items.push((term.to_string(), description.trim().to_string()));
+            }
+        }
+    }
+
+    items
+}
+
+/// `text` with what roff would take for requests or escapes escaped
+fn roff(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let line = line.replace('\\', "\\e").replace('-', "\\-");
+            if line.starts_with(['.', '\'']) {
+                format!("\\&{}", line)
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page() {
+        for command in crate::COMMANDS.iter().chain(&crate::FILELESS_COMMANDS) {
+            let help = help(command).unwrap();
+            assert!(help.contains("\nExamples:\n"), "{}", command);
+        }
+        assert!(help("export").unwrap().starts_with(
+            "Usage: kindlr export <file_path> [--format json|markdown|marginalia|kindle|yaml]\n    "
+        ));
+        assert_eq!(help("frobnicate"), None);
+
+        let page = page();
+        assert!(page.starts_with(".TH KINDLR 1"));
+        assert!(page.contains(".SH SYNOPSIS\n.nf\nkindlr [list] <file_path>"));
+        assert!(page.contains(".SH INTEGRATIONS\n.PP\nConfigured in config.toml"));
+        assert!(page.contains(
+            ".TP\n\\fB\\-\\-merge\\-adjacent [\\-\\-merge\\-window <minutes>]\\fR\nMerge highlights"
+        ));
+        assert!(page.contains(
+            ".TP\n\\fBdigest \\-\\-channel telegram\\fR\nBot token from TELEGRAM_BOT_TOKEN"
+        ));
+        // Text starting with a dot would be taken for a request
+        let requests = [
+            ".TH ", ".SH ", ".SS ", ".PP", ".TP", ".RS ", ".RE", ".nf", ".fi",
+        ];
+        for line in page.lines().filter(|line| line.starts_with(['.', '\''])) {
+            assert!(
+                requests.iter().any(|request| line.starts_with(request)),
+                "{}",
+                line
+            );
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::{Duration, NaiveDateTime};
+
+use crate::parser::{Clipping, ClippingType, Location};
+
+/// Highlights made this close together may be merged by default
+pub const DEFAULT_MERGE_WINDOW_MINUTES: i64 = 10;
+
+/// Merge highlights that Kindle split in two, usually at a page boundary
+///
+/// Highlights in the same book are merged when their locations touch or overlap
+/// and they were made within `window` of each other. The merged highlight spans
+/// both locations, keeps the earlier date and joins the content in location order.
+/// Its id differs from both originals.
+pub fn merge_adjacent(clippings: Vec<Clipping>, window: Duration) -> Vec<Clipping> {
+    let mut merged: Vec<Clipping> = Vec::new();
+    // Time of the latest highlight merged into each entry
+    let mut latest: Vec<Option<NaiveDateTime>> = Vec::new();
+
+    for clipping in clippings {
+        let time = clipping.timestamp();
+        let target = merged
+            .iter()
+            .rposition(|other| adjacent(other, &clipping))
+            .filter(|&i| match (latest[i], time) {
+                (Some(a), Some(b)) => (b - a).abs() <= window,
+                _ => false,
+            });
+
+        match target {
+            Some(i) => {
+                let first = &mut merged[i];
+                let earlier = clipping.location.start < first.location.start;
+                let (before, after) = if earlier {
+                    (clipping.content.as_deref(), first.content.as_deref())
+                } else {
+                    (first.content.as_deref(), clipping.content.as_deref())
+                };
+
+                first.content = Some(join(before.unwrap_or_default(), after.unwrap_or_default()));
+                first.location = Location {
+                    start: first.location.start.min(clipping.location.start),
+                    end: Some(end(&first.location).max(end(&clipping.location))),
+                };
+                if earlier {
+                    first.page = clipping.page.or(first.page);
+                }
+                if time < first.timestamp() {
+                    first.datetime = clipping.datetime;
+                    first.weekday = clipping.weekday;
+                }
+                latest[i] = latest[i].max(time);
+            }
+            None => {
+                latest.push(time);
+                merged.push(clipping);
+            }
+        }
+    }
+
+    merged
+}
+
+fn end(location: &Location) -> u32 {
+    location.end.unwrap_or(location.start)
+}
+
+fn adjacent(a: &Clipping, b: &Clipping) -> bool {
+    a.clipping_type == ClippingType::Highlight
+        && b.clipping_type == ClippingType::Highlight
+        && a.book_title == b.book_title
+        && a.author == b.author
+        && b.location.start <= end(&a.location) + 1
+        && a.location.start <= end(&b.location) + 1
+}
+
+/// Join two pieces of a passage, dropping text they share
+fn join(before: &str, after: &str) -> String {
+    let (before, after) = (before.trim_end(), after.trim_start());
+
+    // The end of `before` repeated at the start of `after`
+    let overlap = before
+        .char_indices()
+        .map(|(i, _)| &before[i..])
+        .find(|suffix| suffix.chars().count() > 3 && after.starts_with(suffix));
+    if let Some(overlap) = overlap {
+        return format!("{}{}", before, &after[overlap.len()..]);
+    }
+
+    if before.is_empty() || after.is_empty() {
+        return format!("{}{}", before, after);
+    }
+
+    // Words split at a hyphen or dash continue without a space
+    if before.ends_with(['-', '—', '–']) {
+        return format!("{}{}", before, after);
+    }
+
+    format!("{} {}", before, after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    #[test]
+    fn test_join() {
+        assert_eq!(
+            join("Fear is the", "mind-killer."),
+            "Fear is the mind-killer."
+        );
+        assert_eq!(
+            join("Fear is the mind-", "killer."),
+            "Fear is the mind-killer."
+        );
+        assert_eq!(
+            join("Fear is the mind", "the mind-killer."),
+            "Fear is the mind-killer."
+        );
+    }
+
+    #[test]
+    fn test_merge_adjacent() {
+        let clippings = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 2 | Location 13-15 | Added on Monday, 1 January 2024 10:01:00
+
+mind-killer. Fear is the little-death.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 2 | Location 16-17 | Added on Monday, 1 January 2024 12:00:00
+
+Too late to merge.
+==========
+Dune (Frank Herbert)
+- Your Note on page 2 | Location 15 | Added on Monday, 1 January 2024 10:01:00
+
+A note.
+==========",
+        )
+        .unwrap();
+
+        let merged = merge_adjacent(clippings, Duration::minutes(DEFAULT_MERGE_WINDOW_MINUTES));
+
+        assert_eq!(merged.len(), 3);
+        assert_eq!(
+            merged[0].content.as_deref(),
+            Some("Fear is the mind-killer. Fear is the little-death.")
+        );
+        assert_eq!(merged[0].location.to_string(), "10-15");
+        assert_eq!(merged[0].page, Some(1));
+        assert_eq!(merged[0].datetime, "1 January 2024 10:00:00");
+    }
+}
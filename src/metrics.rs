@@ -0,0 +1,89 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// What `/metrics` counts while `watch` or `serve` runs, shared between the
+/// thread doing the work and the one answering requests
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Times the clippings file was read
+    pub imports: AtomicU64,
+    /// Clippings the file gained since the first read
+    pub new_clippings: AtomicU64,
+    /// Reads that failed to parse the file
+    pub parse_errors: AtomicU64,
+    /// Requests answered by `serve`
+    pub api_requests: AtomicU64,
+    /// Clippings in the file when last read
+    pub clippings: AtomicU64,
+}
+
+impl Metrics {
+    /// The metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let metrics = [
+            (
+                "kindlr_imports_total",
+                "counter",
+                "Times the clippings file was read",
+                &self.imports,
+            ),
+            (
+                "kindlr_new_clippings_total",
+                "counter",
+                "Clippings the file gained since the first read",
+                &self.new_clippings,
+            ),
+            (
+                "kindlr_parse_errors_total",
+                "counter",
+                "Reads that failed to parse the clippings file",
+                &self.parse_errors,
+            ),
+            (
+                "kindlr_api_requests_total",
+                "counter",
+                "Requests answered by serve",
+                &self.api_requests,
+            ),
+            (
+                "kindlr_clippings",
+                "gauge",
+                "Clippings in the file when last read",
+                &self.clippings,
+            ),
+        ];
+
+        let mut text = String::new();
+        for (name, kind, help, value) in metrics {
+            let _ = write!(
+                text,
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {}\n",
+                value.load(Ordering::Relaxed)
+            );
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::default();
+        metrics.imports.fetch_add(2, Ordering::Relaxed);
+        metrics.new_clippings.fetch_add(5, Ordering::Relaxed);
+        metrics.clippings.store(40, Ordering::Relaxed);
+
+        let text = metrics.render();
+        assert!(text.starts_with(
+            "# HELP kindlr_imports_total Times the clippings file was read\n\
+             # TYPE kindlr_imports_total counter\n\
+             kindlr_imports_total 2\n"
+        ));
+        assert!(text.contains("\nkindlr_new_clippings_total 5\n"));
+        assert!(text.contains("\nkindlr_parse_errors_total 0\n"));
+        assert!(text.contains("# TYPE kindlr_clippings gauge\nkindlr_clippings 40\n"));
+    }
+}
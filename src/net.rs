@@ -0,0 +1,577 @@
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long a cached answer is used before asking again
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Largest answer read unless a request allows more
+pub const DEFAULT_MAX_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Longest wait between two attempts, whatever a server asks for
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Sent with every request, as services ask callers to identify themselves
+#[cfg(any(feature = "enrich", feature = "push", feature = "remote"))]
+const USER_AGENT: &str = concat!("kindlr/", env!("CARGO_PKG_VERSION"));
+
+/// Where answers are cached in the kindlr home directory
+pub fn cache_dir(home: &Path) -> PathBuf {
+    home.join("cache").join("http")
+}
+
+/// Waits so calls are at least `interval` apart
+pub struct RateLimiter {
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(interval: Duration) -> Self {
+        RateLimiter {
+            interval,
+            last: None,
+        }
+    }
+
+    pub fn wait(&mut self) {
+        thread::sleep(self.reserve());
+    }
+
+    /// Take the next turn, returning how long to wait for it
+    ///
+    /// Each call takes the turn after the one before, so callers sharing a
+    /// limiter behind a lock can sleep after letting go of it.
+    pub fn reserve(&mut self) -> Duration {
+        let now = Instant::now();
+        let turn = match self.last {
+            Some(last) => (last + self.interval).max(now),
+            None => now,
+        };
+        self.last = Some(turn);
+        turn - now
+    }
+}
+
+/// Why a request failed, without the URL, which may hold a token
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The HTTP client every integration makes its requests through
+///
+/// GET answers are kept in a cache directory and reused until they are
+/// older than its TTL. Requests to a host are spaced out as set with
+/// `rate_limit`, and those failing with 429, a 5xx status or a dropped
+/// connection are tried again after an exponentially growing wait; only 429
+/// is retried for POST and PATCH, as the others may have been applied.
+/// Offline, cached answers are used however old they are and anything else
+/// fails without touching the network.
+///
+/// Clones share their rate limits.
+#[derive(Clone)]
+pub struct Client {
+    cache_dir: Option<PathBuf>,
+    cache_ttl: Duration,
+    retries: u32,
+    backoff: Duration,
+    offline: bool,
+    limiters: Arc<Mutex<HashMap<String, RateLimiter>>>,
+    #[cfg(any(feature = "enrich", feature = "push", feature = "remote"))]
+    agent: ureq::Agent,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Client::new()
+    }
+}
+
+impl Client {
+    /// A client without a cache, trying failed requests 3 more times
+    pub fn new() -> Self {
+        Client {
+            cache_dir: None,
+            cache_ttl: DEFAULT_CACHE_TTL,
+            retries: 3,
+            backoff: Duration::from_millis(500),
+            offline: false,
+            limiters: Default::default(),
+            #[cfg(any(feature = "enrich", feature = "push", feature = "remote"))]
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .user_agent(USER_AGENT)
+                .build(),
+        }
+    }
+
+    /// Cache GET answers in `dir`, reusing them for `ttl`
+    pub fn cache(mut self, dir: &Path, ttl: Duration) -> Self {
+        self.cache_dir = Some(dir.to_path_buf());
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// Try a failed request up to `retries` more times, waiting `backoff`
+    /// before the first retry and twice as long before each next one
+    pub fn retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
+    /// Answer only from the cache
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Leave at least `interval` between two requests to `host`
+    pub fn rate_limit(self, host: &str, interval: Duration) -> Self {
+        self.limiters
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(host.to_string(), RateLimiter::new(interval));
+        self
+    }
+
+    pub fn get(&self, url: &str) -> Request<'_> {
+        self.request("GET", url)
+    }
+
+    pub fn post(&self, url: &str) -> Request<'_> {
+        self.request("POST", url)
+    }
+
+    pub fn request(&self, method: &str, url: &str) -> Request<'_> {
+        Request {
+            client: self,
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: None,
+            cached: true,
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+
+    /// Wait for the rate limit of `host`, if it has one, without keeping
+    /// requests to other hosts waiting too
+    fn wait_turn(&self, host: &str) {
+        let wait = self
+            .limiters
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get_mut(host)
+            .map(RateLimiter::reserve);
+        if let Some(wait) = wait {
+            thread::sleep(wait);
+        }
+    }
+
+    #[cfg(any(feature = "enrich", feature = "push", feature = "remote"))]
+    fn fetch(&self, request: &Request) -> Result<Response, Failure> {
+        use std::io::Read;
+
+        let mut call = self.agent.request(&request.method, &request.url);
+        for (name, value) in &request.headers {
+            call = call.set(name, value);
+        }
+        let result = match &request.body {
+            Some(body) => call.send_bytes(body),
+            None => call.call(),
+        };
+
+        let idempotent = request.is_idempotent();
+        let response = match result {
+            Ok(response) => response,
+            Err(ureq::Error::Status(status, response)) => {
+                return Err(Failure {
+                    error: Error(format!("status {} from {}", status, request.host())),
+                    retry: status == 429 || (status >= 500 && idempotent),
+                    retry_after: response
+                        .header("Retry-After")
+                        .and_then(|seconds| seconds.trim().parse().ok())
+                        .map(Duration::from_secs),
+                });
+            }
+            Err(ureq::Error::Transport(transport)) => {
+                let reason = match transport.message() {
+                    Some(message) => format!("{}: {}", transport.kind(), message),
+                    None => transport.kind().to_string(),
+                };
+                return Err(Failure {
+                    error: Error(format!("{} ({})", reason, request.host())),
+                    retry: idempotent,
+                    retry_after: None,
+                });
+            }
+        };
+
+        let status = response.status();
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .take(request.max_bytes + 1)
+            .read_to_end(&mut body)
+            .map_err(|error| Failure {
+                error: Error(format!("{} ({})", error, request.host())),
+                retry: idempotent,
+                retry_after: None,
+            })?;
+        if body.len() as u64 > request.max_bytes {
+            return Err(Failure {
+                error: Error(format!(
+                    "answer from {} is larger than {} bytes",
+                    request.host(),
+                    request.max_bytes
+                )),
+                retry: false,
+                retry_after: None,
+            });
+        }
+
+        Ok(Response { status, body })
+    }
+
+    #[cfg(not(any(feature = "enrich", feature = "push", feature = "remote")))]
+    fn fetch(&self, _request: &Request) -> Result<Response, Failure> {
+        Err(Failure {
+            error: Error(
+                "making requests needs kindlr built with the enrich, push or remote feature"
+                    .to_string(),
+            ),
+            retry: false,
+            retry_after: None,
+        })
+    }
+}
+
+/// An attempt that failed, and whether trying again may help
+#[cfg_attr(
+    not(any(feature = "enrich", feature = "push", feature = "remote")),
+    allow(dead_code)
+)]
+struct Failure {
+    error: Error,
+    retry: bool,
+    /// How long the server asked to be left alone for
+    retry_after: Option<Duration>,
+}
+
+/// A request being built, sent with `call`
+pub struct Request<'a> {
+    client: &'a Client,
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Option<Vec<u8>>,
+    cached: bool,
+    max_bytes: u64,
+}
+
+impl Request<'_> {
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Add `name=value` to the query string, percent-encoded
+    pub fn query(mut self, name: &str, value: &str) -> Self {
+        let separator = if self.url.contains('?') { '&' } else { '?' };
+        self.url = format!(
+            "{}{}{}={}",
+            self.url,
+            separator,
+            encode(name),
+            encode(value)
+        );
+        self
+    }
+
+    /// Send `body` as JSON
+    pub fn json(mut self, body: &serde_json::Value) -> Self {
+        self.body = Some(body.to_string().into_bytes());
+        self.header("Content-Type", "application/json")
+    }
+
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    /// Neither use nor keep a cached answer, for what is cached elsewhere
+    pub fn uncached(mut self) -> Self {
+        self.cached = false;
+        self
+    }
+
+    /// Fail rather than read an answer longer than `max_bytes`
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Like `call`, sending the request on tokio's blocking thread pool so
+    /// waits for rate limits and retries don't stall the runtime
+    #[cfg(feature = "async")]
+    pub async fn call_async(self) -> Result<Response, Error> {
+        let client = self.client.clone();
+        let Request {
+            method,
+            url,
+            headers,
+            body,
+            cached,
+            max_bytes,
+            ..
+        } = self;
+
+        tokio::task::spawn_blocking(move || {
+            Request {
+                client: &client,
+                method,
+                url,
+                headers,
+                body,
+                cached,
+                max_bytes,
+            }
+            .call()
+        })
+        .await
+        .map_err(|error| Error(error.to_string()))?
+    }
+
+    pub fn call(self) -> Result<Response, Error> {
+        let client = self.client;
+        let cache_path = self.cache_path();
+
+        if let Some(path) = &cache_path {
+            let ttl = (!client.offline).then_some(client.cache_ttl);
+            if let Some(body) = read_cached(path, ttl) {
+                return Ok(Response { status: 200, body });
+            }
+        }
+        if client.offline {
+            return Err(Error(format!(
+                "offline, and no answer from {} is cached",
+                self.host()
+            )));
+        }
+
+        let mut attempt = 0;
+        let response = loop {
+            client.wait_turn(self.host());
+            match client.fetch(&self) {
+                Ok(response) => break response,
+                Err(failure) if failure.retry && attempt < client.retries => {
+                    let wait = failure
+                        .retry_after
+                        .unwrap_or_else(|| backoff(client.backoff, attempt));
+                    thread::sleep(wait.min(MAX_BACKOFF));
+                    attempt += 1;
+                }
+                Err(failure) => return Err(failure.error),
+            }
+        };
+
+        // An answer that can't be cached is still an answer
+        if let Some(path) = &cache_path {
+            let _ = write_cached(path, &response.body);
+        }
+        Ok(response)
+    }
+
+    /// File the answer is cached in, for GET requests to a caching client
+    fn cache_path(&self) -> Option<PathBuf> {
+        let dir = self.client.cache_dir.as_ref()?;
+        (self.cached && self.method == "GET").then(|| dir.join(format!("{:016x}", hash(&self.url))))
+    }
+
+    #[cfg_attr(
+        not(any(feature = "enrich", feature = "push", feature = "remote")),
+        allow(dead_code)
+    )]
+    fn is_idempotent(&self) -> bool {
+        ["GET", "HEAD", "PUT", "DELETE", "OPTIONS"].contains(&self.method.as_str())
+    }
+
+    /// Host, and port if any, the request goes to
+    fn host(&self) -> &str {
+        let rest = self
+            .url
+            .split_once("://")
+            .map_or(self.url.as_str(), |(_, rest)| rest);
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+        authority
+            .rsplit_once('@')
+            .map_or(authority, |(_, host)| host)
+    }
+}
+
+/// A successful answer
+#[derive(Debug)]
+pub struct Response {
+    status: u16,
+    body: Vec<u8>,
+}
+
+impl Response {
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.body
+    }
+
+    pub fn into_string(self) -> Result<String, Error> {
+        String::from_utf8(self.body).map_err(|error| Error(error.to_string()))
+    }
+
+    pub fn into_json<T: DeserializeOwned>(self) -> Result<T, Error> {
+        serde_json::from_slice(&self.body).map_err(|error| Error(error.to_string()))
+    }
+}
+
+/// FNV-1a hash of `text`, naming cached files
+pub fn hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Wait before retry number `attempt`, counting from 0
+fn backoff(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+/// `text` with everything but unreserved characters percent-encoded
+pub(crate) fn encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// `text` with `%XX` escapes and, as in query strings, `+` decoded
+pub(crate) fn decode(text: &str) -> String {
+    let mut decoded = Vec::with_capacity(text.len());
+    let mut i = 0;
+    while i < text.len() {
+        let byte = text.as_bytes()[i];
+        let escaped = text
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.bytes().all(|byte| byte.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (byte, escaped) {
+            (b'%', Some(escaped)) => {
+                decoded.push(escaped);
+                i += 3;
+                continue;
+            }
+            (b'+', _) => decoded.push(b' '),
+            _ => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The answer cached at `path`, if it is younger than `ttl` or any age without one
+fn read_cached(path: &Path, ttl: Option<Duration>) -> Option<Vec<u8>> {
+    if let Some(ttl) = ttl {
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+        let age = SystemTime::now().duration_since(modified.ok()?).ok()?;
+        if age > ttl {
+            return None;
+        }
+    }
+    fs::read(path).ok()
+}
+
+/// Cache `body` at `path`, written aside and renamed so a reader never sees half
+fn write_cached(path: &Path, body: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = path.with_extension("part");
+    fs::write(&partial, body)?;
+    fs::rename(&partial, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_reserve() {
+        let minute = Duration::from_secs(60);
+        let mut limiter = RateLimiter::new(minute);
+        assert_eq!(limiter.reserve(), Duration::ZERO);
+
+        // Turns taken before anyone slept queue up one after another
+        let second = limiter.reserve();
+        assert!(second > minute - Duration::from_secs(1) && second <= minute);
+        assert!(limiter.reserve() > second + minute - Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_offline_cache() {
+        let dir = std::env::temp_dir().join(format!("kindlr-net-{}", std::process::id()));
+        let client = Client::new().cache(&dir, DEFAULT_CACHE_TTL).offline(true);
+
+        let request = client
+            .get("https://openlibrary.org/search.json")
+            .query("title", "Dune: Deluxe")
+            .query("limit", "1");
+        assert_eq!(
+            request.url,
+            "https://openlibrary.org/search.json?title=Dune%3A%20Deluxe&limit=1"
+        );
+        assert_eq!(request.host(), "openlibrary.org");
+        write_cached(&request.cache_path().unwrap(), br#"{"numFound": 0}"#).unwrap();
+
+        let cached: serde_json::Value = request.call().unwrap().into_json().unwrap();
+        assert_eq!(cached["numFound"], 0);
+
+        let missing = client
+            .get("https://openlibrary.org/search.json?title=Emma")
+            .call();
+        assert_eq!(
+            missing.unwrap_err().to_string(),
+            "offline, and no answer from openlibrary.org is cached"
+        );
+        assert!(
+            client
+                .post("https://readwise.io/api/v2/highlights/")
+                .call()
+                .is_err()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            backoff(Duration::from_millis(500), 2),
+            Duration::from_secs(2)
+        );
+        assert_eq!(backoff(Duration::from_secs(1), 30), MAX_BACKOFF);
+    }
+}
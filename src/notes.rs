@@ -0,0 +1,599 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+use crate::KindlrError;
+use crate::dates::DateFormat;
+use crate::export::{markdown_entry, markdown_heading};
+use crate::library::{Book, Library};
+use crate::plan::{Change, Plan};
+
+/// Start of the part of a notes file kindlr adds highlights to
+pub const REGION_START: &str = "<!-- kindlr:start -->";
+/// End of the part of a notes file kindlr adds highlights to
+pub const REGION_END: &str = "<!-- kindlr:end -->";
+
+/// What a filename template may fill in for a book
+const PLACEHOLDERS: [&str; 5] = ["title", "author", "title_slug", "author_slug", "year"];
+
+/// Longest file or directory name written, in bytes, well within the 255
+/// most file systems allow
+const MAX_NAME_BYTES: usize = 200;
+
+/// Path of a book's file in a notes directory, such as
+/// "{author} - {title}.md" or "{title_slug}/{year}.md"
+///
+/// `{title}` and `{author}` are filled in without characters file systems
+/// reject, `{title_slug}` and `{author_slug}` in lowercase words joined by
+/// hyphens, and `{year}` with the year of the book's first clipping. Slashes
+/// make directories.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct FileTemplate(String);
+
+impl Default for FileTemplate {
+    fn default() -> Self {
+        FileTemplate("{title}.md".to_string())
+    }
+}
+
+impl FromStr for FileTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |why: &str| Err(format!("Invalid filename template {}: {}", s, why));
+        let path = Path::new(s);
+        if s.is_empty() || s.ends_with('/') {
+            return invalid("it names no file");
+        }
+        if !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return invalid("it must stay within the notes directory");
+        }
+
+        let placeholder = Regex::new(r"\{([^{}]*)\}").expect("valid regex");
+        for caps in placeholder.captures_iter(s) {
+            if !PLACEHOLDERS.contains(&&caps[1]) {
+                return invalid(&format!(
+                    "unknown {{{}}}, expected one of {{{}}}",
+                    &caps[1],
+                    PLACEHOLDERS.join("}, {")
+                ));
+            }
+        }
+        if placeholder.replace_all(s, "").contains(['{', '}']) {
+            return invalid("unmatched brace");
+        }
+        Ok(FileTemplate(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for FileTemplate {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl FileTemplate {
+    /// Path of `book`'s file relative to the notes directory, with `suffix`
+    /// added to the file name before its extension
+    pub fn path(&self, book: &Book, suffix: &str) -> PathBuf {
+        let year = book
+            .clippings
+            .iter()
+            .filter_map(|clipping| clipping.timestamp())
+            .min()
+            .map_or("Undated".to_string(), |timestamp| {
+                timestamp.format("%Y").to_string()
+            });
+        let fill = |part: &str| {
+            part.replace("{title_slug}", &slug(&book.title))
+                .replace("{author_slug}", &slug(&book.author))
+                .replace("{title}", &file_name(&book.title))
+                .replace("{author}", &file_name(&book.author))
+                .replace("{year}", &year)
+        };
+
+        let parts: Vec<&str> = self.0.split('/').collect();
+        let (file, dirs) = parts.split_last().expect("split returns a part");
+        let mut path: PathBuf = dirs
+            .iter()
+            .map(|dir| shorten(&file_name(&fill(dir)), ""))
+            .collect();
+        let file = fill(file);
+        let (stem, extension) = match file.rfind('.') {
+            Some(dot) if dot > 0 => file.split_at(dot),
+            _ => (file.as_str(), ""),
+        };
+        let stem = file_name(&format!("{}{}", stem, suffix));
+        path.push(shorten(&stem, extension));
+        path
+    }
+
+    /// Path of `book`'s file not among `taken`, which it's added to
+    ///
+    /// A book the template would give a taken name gets its author added,
+    /// unless the template has the author already, and then a number.
+    pub fn unique_path(&self, book: &Book, taken: &mut HashSet<PathBuf>) -> PathBuf {
+        self.unique_path_where(book, taken, |_| true)
+    }
+
+    /// Like `unique_path`, also passing over names `free` refuses, such as
+    /// those of another book's file
+    pub fn unique_path_where(
+        &self,
+        book: &Book,
+        taken: &mut HashSet<PathBuf>,
+        free: impl Fn(&Path) -> bool,
+    ) -> PathBuf {
+        let author = format!(" ({})", book.author);
+        let path = [""]
+            .into_iter()
+            .chain((!self.0.contains("{author")).then_some(author.as_str()))
+            .map(str::to_string)
+            .chain((2..).map(|n| format!(" {}", n)))
+            .map(|suffix| self.path(book, &suffix))
+            .find(|path| !taken.contains(path) && free(path))
+            .expect("numbered names never run out");
+        taken.insert(path.clone());
+        path
+    }
+}
+
+/// What updating a notes directory changed
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NotesSummary {
+    pub created: usize,
+    pub updated: usize,
+    /// Highlights and notes added across all files
+    pub added: usize,
+}
+
+/// Write a Markdown file per book into `dir`, keeping whatever was written
+/// into existing files by hand
+///
+/// Each file has a region between `REGION_START` and `REGION_END` that new
+/// highlights are added to the end of, each after a comment holding its id.
+/// Highlights whose id is anywhere in the file are never added again, so
+/// they can be edited, moved or deleted freely. A file without a region gets
+/// one appended. Downloaded covers are copied into `covers` in `dir` for new
+/// files to show.
+///
+/// Files are named by `template`, see `FileTemplate::unique_path`, passing
+/// over files of other books, so a book keeps its file whichever books are
+/// exported alongside it. New files record their book in a comment after
+/// `REGION_START`; older ones are told apart by their heading. Files are
+/// written through `plan`.
+pub fn update_dir(
+    dir: &Path,
+    library: &Library,
+    template: &FileTemplate,
+    plan: &mut Plan,
+) -> Result<NotesSummary, KindlrError> {
+    let mut summary = NotesSummary::default();
+    let mut names = HashSet::new();
+    for book in &library.books {
+        let name = template.unique_path_where(book, &mut names, |name| {
+            fs::read_to_string(dir.join(name)).map_or(true, |text| is_notes_of(&text, book))
+        });
+        let path = dir.join(&name);
+        let existing = match fs::read_to_string(&path) {
+            Ok(text) => Some(text),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => return Err(error.into()),
+        };
+        // Covers are linked relative to files in subdirectories too
+        let up = "../".repeat(name.components().count() - 1);
+        let cover = match &existing {
+            None => copy_cover(dir, book, plan)?.map(|cover| format!("{}{}", up, cover)),
+            Some(_) => None,
+        };
+        let (text, added) = update(
+            existing.as_deref(),
+            book,
+            cover.as_deref(),
+            &library.date_format,
+        );
+
+        if added > 0 {
+            let action = match existing {
+                Some(_) => format!("add {} clippings", added),
+                None => format!("create with {} clippings", added),
+            };
+            let change = Change::File {
+                path: path.display().to_string(),
+                action,
+            };
+            plan.apply(change, || {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                Ok(fs::write(&path, text)?)
+            })?;
+            summary.added += added;
+            if existing.is_some() {
+                summary.updated += 1;
+            } else {
+                summary.created += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// The notes file of `book` with the highlights `existing` lacks, and how
+/// many were added, showing the image at `cover` if the file is new and
+/// dating highlights in `date_format`
+pub fn update(
+    existing: Option<&str>,
+    book: &Book,
+    cover: Option<&str>,
+    date_format: &DateFormat,
+) -> (String, usize) {
+    let marker = Regex::new(r"<!-- kindlr:([0-9a-f]{16}) -->").expect("valid regex");
+    let present: HashSet<&str> = existing
+        .into_iter()
+        .flat_map(|text| marker.captures_iter(text))
+        .filter_map(|captures| captures.get(1))
+        .map(|id| id.as_str())
+        .collect();
+
+    let mut entries = String::new();
+    let mut added = 0;
+    for clipping in &book.clippings {
+        let id = clipping.id();
+        if present.contains(id.as_str()) {
+            continue;
+        }
+        if let Some(entry) = markdown_entry(book, clipping, date_format) {
+            entries += &format!("<!-- kindlr:{} -->\n{}\n", id, entry);
+            added += 1;
+        }
+    }
+
+    let region = format!(
+        "{}\n{}{}{}\n",
+        REGION_START,
+        book_marker(book),
+        entries,
+        REGION_END
+    );
+    let text = match existing {
+        None => format!("{}\n{}", markdown_heading(book, cover), region),
+        Some(text) => match region_end(text) {
+            Some(end) => format!("{}{}{}", &text[..end], entries, &text[end..]),
+            None if added == 0 => text.to_string(),
+            None => format!("{}\n\n{}", text.trim_end(), region),
+        },
+    };
+
+    (text, added)
+}
+
+/// Comment naming the book a notes file is for
+fn book_marker(book: &Book) -> String {
+    // Keeps a title with "-->" in it from ending the comment
+    let name = format!("{} ({})", book.title, book.author).replace("-->", "--&gt;");
+    format!("<!-- kindlr:book {} -->\n", name)
+}
+
+/// Whether `text`, a file in a notes directory, holds the notes of `book`
+///
+/// A file without a book comment is the book's when its heading names the
+/// book's title and author, as `markdown_heading` writes them.
+fn is_notes_of(text: &str, book: &Book) -> bool {
+    if text.contains("<!-- kindlr:book ") {
+        return text.contains(&book_marker(book));
+    }
+
+    let mut lines = text.lines();
+    lines.any(|line| line == format!("# {}", book.title))
+        && lines.any(|line| line.starts_with(&format!("*{}*", book.author)))
+}
+
+/// Path relative to `dir` of a copy of the book's downloaded cover
+fn copy_cover(dir: &Path, book: &Book, plan: &mut Plan) -> Result<Option<String>, KindlrError> {
+    let Some(source) = book
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.cover_path.as_ref())
+    else {
+        return Ok(None);
+    };
+    let Some(name) = source.file_name() else {
+        return Ok(None);
+    };
+
+    let covers = dir.join("covers");
+    let change = Change::File {
+        path: covers.join(name).display().to_string(),
+        action: "copy cover".to_string(),
+    };
+    plan.apply(change, || {
+        fs::create_dir_all(&covers)?;
+        fs::copy(source, covers.join(name))?;
+        Ok(())
+    })?;
+    Ok(Some(format!("covers/{}", name.to_string_lossy())))
+}
+
+/// Offset of the `REGION_END` closing the first region of `text`
+fn region_end(text: &str) -> Option<usize> {
+    let start = text.find(REGION_START)? + REGION_START.len();
+    text[start..].find(REGION_END).map(|end| start + end)
+}
+
+/// `title` without characters file systems reject, shortened to a sensible
+/// length
+fn file_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|c| {
+            if c.is_control() || r#"/\:*?"<>|"#.contains(c) {
+                ' '
+            } else {
+                c
+            }
+        })
+        .collect();
+    let name = name
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches('.')
+        .chars()
+        .take(120)
+        .collect::<String>();
+
+    if name.is_empty() {
+        "Untitled".to_string()
+    } else {
+        name
+    }
+}
+
+/// `text` in lowercase letters and digits, with hyphens between words
+fn slug(text: &str) -> String {
+    let slug = text
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "untitled".to_string()
+    } else {
+        slug
+    }
+}
+
+/// `stem` followed by `extension`, the stem cut short on a character
+/// boundary to keep the name within `MAX_NAME_BYTES`
+fn shorten(stem: &str, extension: &str) -> String {
+    let mut end = MAX_NAME_BYTES
+        .saturating_sub(extension.len())
+        .min(stem.len());
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", stem[..end].trim_end(), extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    const CLIPPINGS: &str = "\
+Dune: Deluxe Edition (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune: Deluxe Edition (Frank Herbert)
+- Your Highlight on page 2 | Location 20-22 | Added on Monday, 1 January 2024 10:05:00
+
+The spice must flow.
+==========";
+
+    #[test]
+    fn test_update_dir() {
+        let dir = std::env::temp_dir().join(format!("kindlr-notes-{}", std::process::id()));
+        let path = dir.join("Dune Deluxe Edition.md");
+        let mut clippings = parse_clippings(CLIPPINGS).unwrap();
+        clippings.truncate(1);
+        let library = Library::new(clippings);
+
+        // A dry run lists the file it would create and leaves it uncreated
+        let mut dry_run = Plan::new(true);
+        let summary = update_dir(&dir, &library, &FileTemplate::default(), &mut dry_run).unwrap();
+        assert_eq!(summary.created, 1);
+        assert_eq!(dry_run.changes().len(), 1);
+        assert!(!dir.exists());
+
+        let summary = update_dir(
+            &dir,
+            &library,
+            &FileTemplate::default(),
+            &mut Plan::default(),
+        )
+        .unwrap();
+        assert_eq!(summary.created, 1);
+
+        // Prose written around and inside the region survives
+        let edited = fs::read_to_string(&path)
+            .unwrap()
+            .replace(REGION_START, &format!("My thoughts.\n\n{}", REGION_START))
+            .replace(REGION_END, &format!("Fear, again!\n{}", REGION_END))
+            .replace("mind-killer", "MIND-KILLER");
+        fs::write(&path, &edited).unwrap();
+
+        let library = Library::new(parse_clippings(CLIPPINGS).unwrap());
+        let summary = update_dir(
+            &dir,
+            &library,
+            &FileTemplate::default(),
+            &mut Plan::default(),
+        )
+        .unwrap();
+        let again = update_dir(
+            &dir,
+            &library,
+            &FileTemplate::default(),
+            &mut Plan::default(),
+        )
+        .unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            summary,
+            NotesSummary {
+                created: 0,
+                updated: 1,
+                added: 1
+            }
+        );
+        assert_eq!(again.added, 0);
+        assert!(text.starts_with("# Dune: Deluxe Edition\n\n*Frank Herbert*\n\nMy thoughts."));
+        assert!(text.contains("MIND-KILLER"));
+        assert!(!text.contains("mind-killer"));
+        assert!(text.contains("Fear, again!\n<!-- kindlr:"));
+        assert!(text.contains("> The spice must flow.\n"));
+        assert!(text.ends_with(":00\n\n<!-- kindlr:end -->\n"));
+    }
+
+    #[test]
+    fn test_file_template() {
+        let library = Library::new(
+            parse_clippings(&CLIPPINGS.replace("Dune: Deluxe", "Dune/Über: Deluxe")).unwrap(),
+        );
+        let book = &library.books[0];
+        let path = |template: &str| {
+            let template: FileTemplate = template.parse().unwrap();
+            template.path(book, "").display().to_string()
+        };
+
+        assert_eq!(path("{title}.md"), "Dune Über Deluxe Edition.md");
+        assert_eq!(
+            path("{author} - {title}.md"),
+            "Frank Herbert - Dune Über Deluxe Edition.md"
+        );
+        assert_eq!(
+            path("{author_slug}/{year}/{title_slug}.md"),
+            "frank-herbert/2024/dune-über-deluxe-edition.md"
+        );
+        assert_eq!(
+            FileTemplate::default()
+                .path(book, " 2")
+                .display()
+                .to_string(),
+            "Dune Über Deluxe Edition 2.md"
+        );
+        assert_eq!(shorten(&"é".repeat(150), ".md").len(), 199);
+        for template in [
+            "",
+            "notes/",
+            "/notes.md",
+            "../{title}.md",
+            "{isbn}.md",
+            "{title.md",
+        ] {
+            assert!(template.parse::<FileTemplate>().is_err(), "{}", template);
+        }
+
+        // Titles alike in name by different authors, then by the same one
+        let alike = format!(
+            "{}\n{}\n{}",
+            CLIPPINGS,
+            CLIPPINGS.replace("Frank Herbert", "Brian Herbert"),
+            CLIPPINGS.replace("Dune:", "Dune?")
+        );
+        let dir = std::env::temp_dir().join(format!("kindlr-template-{}", std::process::id()));
+        let library = Library::new(parse_clippings(&alike).unwrap());
+        update_dir(
+            &dir,
+            &library,
+            &FileTemplate::default(),
+            &mut Plan::default(),
+        )
+        .unwrap();
+        update_dir(
+            &dir,
+            &library,
+            &"{author} - {title_slug}.md".parse().unwrap(),
+            &mut Plan::default(),
+        )
+        .unwrap();
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            names,
+            [
+                "Brian Herbert - dune-deluxe-edition.md",
+                "Dune Deluxe Edition (Brian Herbert).md",
+                "Dune Deluxe Edition (Frank Herbert).md",
+                "Dune Deluxe Edition.md",
+                "Frank Herbert - dune-deluxe-edition 2.md",
+                "Frank Herbert - dune-deluxe-edition.md",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filtered_rerun_keeps_each_book_in_its_file() {
+        let dir = std::env::temp_dir().join(format!("kindlr-rerun-{}", std::process::id()));
+        let brian = CLIPPINGS
+            .replace("Frank Herbert", "Brian Herbert")
+            .replace("Fear is the mind-killer.", "The sleeper must awaken.");
+        let both = Library::new(parse_clippings(&format!("{}\n{}", CLIPPINGS, brian)).unwrap());
+        update_dir(&dir, &both, &FileTemplate::default(), &mut Plan::default()).unwrap();
+
+        // Only Brian's book, as --author brian or --since-last would export
+        let later = brian
+            .replace("Location 20-22", "Location 30-32")
+            .replace("The spice must flow.", "Long live the fighters.");
+        let only_brian = Library::new(parse_clippings(&later).unwrap());
+        let summary = update_dir(
+            &dir,
+            &only_brian,
+            &FileTemplate::default(),
+            &mut Plan::default(),
+        )
+        .unwrap();
+
+        let frank = fs::read_to_string(dir.join("Dune Deluxe Edition.md")).unwrap();
+        let brian = fs::read_to_string(dir.join("Dune Deluxe Edition (Brian Herbert).md")).unwrap();
+        assert_eq!(summary.updated, 1);
+        assert!(!frank.contains("Long live the fighters."));
+        assert!(brian.contains("Long live the fighters."));
+
+        // A file written before books were recorded is known by its heading
+        let legacy = frank.replace(&book_marker(&both.books[0]), "");
+        fs::write(dir.join("Dune Deluxe Edition.md"), &legacy).unwrap();
+        fs::remove_file(dir.join("Dune Deluxe Edition (Brian Herbert).md")).unwrap();
+        update_dir(
+            &dir,
+            &only_brian,
+            &FileTemplate::default(),
+            &mut Plan::default(),
+        )
+        .unwrap();
+        let frank = fs::read_to_string(dir.join("Dune Deluxe Edition.md")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(frank, legacy);
+    }
+}
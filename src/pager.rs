@@ -0,0 +1,74 @@
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// Pager used when neither `KINDLR_PAGER` nor `PAGER` is set
+pub const DEFAULT_PAGER: &str = "less";
+
+/// Print `text`, through a pager when stdout is a terminal it doesn't fit on
+///
+/// As with git, the pager is `KINDLR_PAGER`, `PAGER` or `less`, and `less`
+/// is told to quit at once when the text fits on the screen unless `LESS`
+/// says otherwise. A terminal height in `LINES` skips the pager for text
+/// that fits; an empty pager, `cat` or one that can't be started prints the
+/// text as is.
+pub fn page(text: &str, enabled: bool) -> io::Result<()> {
+    let height = env::var("LINES").ok().and_then(|lines| lines.parse().ok());
+    let pager = match command() {
+        Some(pager) if enabled && io::stdout().is_terminal() && !fits(text, height) => pager,
+        _ => return io::stdout().write_all(text.as_bytes()),
+    };
+
+    let mut args = pager.split_whitespace();
+    let program = args.next().unwrap_or(DEFAULT_PAGER);
+    let mut command = Command::new(program);
+    command.args(args).stdin(Stdio::piped());
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    let Ok(mut child) = command.spawn() else {
+        return io::stdout().write_all(text.as_bytes());
+    };
+
+    let written = child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(text.as_bytes());
+    child.wait()?;
+    match written {
+        // Quitting the pager early closes the pipe
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        written => written,
+    }
+}
+
+/// The pager command line, unless paging is turned off by setting it to
+/// nothing or `cat`
+fn command() -> Option<String> {
+    let pager = env::var("KINDLR_PAGER")
+        .or_else(|_| env::var("PAGER"))
+        .unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+    let pager = pager.trim();
+    (!pager.is_empty() && pager != "cat").then(|| pager.to_string())
+}
+
+/// Whether `text` fits on a terminal `height` lines high, leaving a line
+/// for the prompt; an unknown height never fits
+fn fits(text: &str, height: Option<usize>) -> bool {
+    height.is_some_and(|height| text.lines().count() < height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fits() {
+        let text = "a\nb\nc\n";
+        assert!(fits(text, Some(4)));
+        assert!(!fits(text, Some(3)));
+        assert!(!fits(text, None));
+        assert!(fits("", Some(1)));
+    }
+}
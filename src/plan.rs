@@ -0,0 +1,120 @@
+use serde::Serialize;
+use std::fmt;
+
+use crate::KindlrError;
+use crate::store::Store;
+
+/// A change an operation makes to the store, files or an integration
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Change {
+    /// What the store keeps for the clipping with `id`, such as an edit
+    Store { id: String, action: String },
+    /// A file written, created or added to
+    File { path: String, action: String },
+    /// Clippings sent to an integration, by id
+    Send {
+        destination: String,
+        ids: Vec<String>,
+    },
+    /// A file uploaded to cloud storage
+    Upload { url: String, bytes: usize },
+    /// A command run, such as to start a service
+    Run { command: String },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Store { id, action } => write!(f, "store {}: {}", id, action),
+            Change::File { path, action } => write!(f, "file {}: {}", path, action),
+            Change::Send { destination, ids } => write!(
+                f,
+                "send {} clippings to {}: {}",
+                ids.len(),
+                destination,
+                ids.join(", ")
+            ),
+            Change::Upload { url, bytes } => write!(f, "upload {} ({} bytes)", url, bytes),
+            Change::Run { command } => write!(f, "run {}", command),
+        }
+    }
+}
+
+/// The changes an operation makes, each carried out as it is added, or for
+/// `--dry-run` only listed
+///
+/// Operations make every change through `apply` and save the store through
+/// `save`, so a dry run can't leave anything behind whatever the operation.
+#[derive(Debug, Default)]
+pub struct Plan {
+    dry_run: bool,
+    changes: Vec<Change>,
+}
+
+impl Plan {
+    pub fn new(dry_run: bool) -> Self {
+        Plan {
+            dry_run,
+            changes: Vec::new(),
+        }
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Add `change`, made by `make` unless this is a dry run, which gives
+    /// `None` instead of what it returns
+    pub fn apply<T>(
+        &mut self,
+        change: Change,
+        make: impl FnOnce() -> Result<T, KindlrError>,
+    ) -> Result<Option<T>, KindlrError> {
+        self.changes.push(change);
+        if self.dry_run {
+            Ok(None)
+        } else {
+            make().map(Some)
+        }
+    }
+
+    /// Save what the operation recorded in `store`, unless this is a dry run
+    pub fn save(&self, store: &Store) -> Result<(), KindlrError> {
+        if self.dry_run {
+            return Ok(());
+        }
+        Ok(store.save()?)
+    }
+
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan() {
+        let change = Change::Send {
+            destination: "readwise".to_string(),
+            ids: vec!["a1".to_string(), "b2".to_string()],
+        };
+
+        let mut plan = Plan::new(false);
+        assert_eq!(plan.apply(change.clone(), || Ok(1)).unwrap(), Some(1));
+
+        let mut dry_run = Plan::new(true);
+        let made = dry_run.apply(change.clone(), || -> Result<(), KindlrError> {
+            panic!("a dry run makes no changes")
+        });
+        assert_eq!(made.unwrap(), None);
+        assert_eq!(dry_run.changes(), plan.changes());
+        assert_eq!(
+            dry_run.changes()[0].to_string(),
+            "send 2 clippings to readwise: a1, b2"
+        );
+    }
+}
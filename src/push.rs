@@ -0,0 +1,759 @@
+use serde::Serialize;
+#[cfg(feature = "push")]
+use std::time::Duration;
+
+use crate::KindlrError;
+use crate::library::annotated_highlight;
+#[cfg(feature = "push")]
+use crate::net;
+use crate::parser::{Clipping, ClippingType};
+use crate::plan::{Change, Plan};
+use crate::store::Store;
+
+/// A service clippings can be sent to
+pub trait Destination {
+    /// Name the destination is selected by and pushes are tracked under
+    fn name(&self) -> &str;
+
+    /// Most clippings to send in one request
+    fn batch_size(&self) -> usize {
+        100
+    }
+
+    /// Send clippings, returning an id the service gave each one, in order
+    fn send(&self, clippings: &[&Clipping]) -> Result<Vec<String>, KindlrError>;
+}
+
+/// What a push did, or would do for a dry run
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct PushSummary {
+    pub pushed: usize,
+    /// Clippings sent before and left alone
+    pub skipped: usize,
+}
+
+/// Send the clippings `destination` hasn't seen yet, batch by batch
+///
+/// Bookmarks carry no text and are never sent. Each batch is sent through
+/// `plan` and recorded in `store` as soon as it is accepted, so a failed
+/// push can be retried without sending anything twice; the caller saves the
+/// store either way.
+pub fn push(
+    destination: &dyn Destination,
+    clippings: &[Clipping],
+    store: &mut Store,
+    plan: &mut Plan,
+) -> Result<PushSummary, KindlrError> {
+    let name = destination.name();
+    let (new, sent): (Vec<&Clipping>, Vec<&Clipping>) = clippings
+        .iter()
+        .filter(|clipping| clipping.clipping_type != ClippingType::Bookmark)
+        .partition(|clipping| store.remote_id(name, &clipping.id()).is_none());
+
+    let mut summary = PushSummary {
+        pushed: 0,
+        skipped: sent.len(),
+    };
+
+    for batch in new.chunks(destination.batch_size().max(1)) {
+        let change = Change::Send {
+            destination: name.to_string(),
+            ids: batch.iter().map(|clipping| clipping.id()).collect(),
+        };
+        if let Some(remote_ids) = plan.apply(change, || destination.send(batch))? {
+            for (clipping, remote_id) in batch.iter().zip(remote_ids) {
+                store.record_push(name, &clipping.id(), remote_id);
+            }
+        }
+        summary.pushed += batch.len();
+    }
+
+    Ok(summary)
+}
+
+/// A highlight as the Readwise API takes it,
+/// <https://readwise.io/api_deets#create>
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ReadwiseHighlight {
+    pub text: String,
+    pub title: String,
+    pub author: String,
+    pub source_type: &'static str,
+    pub category: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlighted_at: Option<String>,
+}
+
+/// Readwise highlights for `clippings`, one per clipping
+///
+/// Readwise keeps notes on the highlight they belong to, so a note is sent as
+/// the highlight it was made on, with the note attached, when that highlight
+/// is among `context`; Readwise merges it into the highlight with the same
+/// text. Other notes are sent as highlights of their own.
+pub fn readwise_highlights(
+    clippings: &[&Clipping],
+    context: &[Clipping],
+) -> Vec<ReadwiseHighlight> {
+    clippings
+        .iter()
+        .map(|clipping| {
+            let content = clipping.content.clone().unwrap_or_default();
+            let (text, note) = match clipping.clipping_type {
+                ClippingType::Note => match annotated_highlight(clipping, context) {
+                    Some(highlight) => {
+                        (highlight.content.clone().unwrap_or_default(), Some(content))
+                    }
+                    None => (content, None),
+                },
+                _ => (content, None),
+            };
+
+            // Kindle locations start at 1; 0 means the source only had pages
+            let (location, location_type) = match (clipping.location.start, clipping.page) {
+                (0, Some(page)) => (Some(page), Some("page")),
+                (0, None) => (None, None),
+                (start, _) => (Some(start), Some("location")),
+            };
+
+            ReadwiseHighlight {
+                text,
+                title: clipping.book_title.clone(),
+                author: clipping.author.clone(),
+                source_type: "kindlr",
+                category: match clipping.clipping_type {
+                    ClippingType::ArticleClip => "articles",
+                    _ => "books",
+                },
+                location,
+                location_type,
+                note,
+                highlighted_at: clipping
+                    .timestamp()
+                    .map(|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Readwise's highlight API, authenticated with an access token from
+/// <https://readwise.io/access_token>
+#[cfg(feature = "push")]
+pub struct Readwise<'a> {
+    token: String,
+    /// Every selected clipping, for attaching notes to their highlights
+    context: &'a [Clipping],
+    client: net::Client,
+}
+
+#[cfg(feature = "push")]
+impl<'a> Readwise<'a> {
+    const HIGHLIGHTS_URL: &'static str = "https://readwise.io/api/v2/highlights/";
+
+    pub fn new(token: String, context: &'a [Clipping], client: net::Client) -> Self {
+        Readwise {
+            token,
+            context,
+            // Readwise allows 240 requests a minute
+            client: client.rate_limit("readwise.io", Duration::from_millis(250)),
+        }
+    }
+}
+
+#[cfg(feature = "push")]
+impl Destination for Readwise<'_> {
+    fn name(&self) -> &str {
+        "readwise"
+    }
+
+    fn send(&self, clippings: &[&Clipping]) -> Result<Vec<String>, KindlrError> {
+        #[derive(serde::Deserialize)]
+        struct Book {
+            id: u64,
+            title: String,
+        }
+
+        let network_error = |error: String| KindlrError::Network(format!("Readwise: {}", error));
+
+        let highlights = readwise_highlights(clippings, self.context);
+        let books: Vec<Book> = self
+            .client
+            .post(Self::HIGHLIGHTS_URL)
+            .header("Authorization", &format!("Token {}", self.token))
+            .json(&serde_json::json!({ "highlights": highlights }))
+            .call()
+            .and_then(net::Response::into_json)
+            .map_err(|error| network_error(error.to_string()))?;
+
+        // Readwise answers with the books the highlights went into
+        Ok(clippings
+            .iter()
+            .map(|clipping| {
+                books
+                    .iter()
+                    .find(|book| book.title == clipping.book_title)
+                    .map(|book| format!("book:{}", book.id))
+                    .unwrap_or_default()
+            })
+            .collect())
+    }
+}
+
+/// Notion blocks for `clippings`: highlights as quotes captioned with their
+/// location and notes as callouts
+pub fn notion_blocks(clippings: &[&Clipping]) -> Vec<serde_json::Value> {
+    clippings
+        .iter()
+        .map(|clipping| {
+            let content = clipping.content.as_deref().unwrap_or_default();
+            match clipping.clipping_type {
+                ClippingType::Note => serde_json::json!({
+                    "type": "callout",
+                    "callout": {
+                        "rich_text": notion_text(content),
+                        "icon": { "type": "emoji", "emoji": "📝" },
+                    },
+                }),
+                _ => {
+                    let mut rich_text = notion_text(content);
+                    rich_text.push(serde_json::json!({
+                        "type": "text",
+                        "text": {
+                            "content": format!("\n— Location {}, {}", clipping.location, clipping.datetime),
+                        },
+                        "annotations": { "italic": true, "color": "gray" },
+                    }));
+                    serde_json::json!({ "type": "quote", "quote": { "rich_text": rich_text } })
+                }
+            }
+        })
+        .collect()
+}
+
+/// Text split into the 2000 character pieces Notion accepts
+fn notion_text(text: &str) -> Vec<serde_json::Value> {
+    const MAX_LEN: usize = 2000;
+
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .chunks(MAX_LEN)
+        .map(|chunk| {
+            serde_json::json!({
+                "type": "text",
+                "text": { "content": chunk.iter().collect::<String>() },
+            })
+        })
+        .collect()
+}
+
+/// A Notion database with a page per book, clippings appended to the page as blocks
+///
+/// Pages are found by title, so books pushed before get new clippings added
+/// below the old ones. Notion has no place for custom block metadata, so
+/// which clippings a block holds is kept in the local store.
+#[cfg(feature = "push")]
+pub struct Notion {
+    token: String,
+    database_id: String,
+    title_property: String,
+    pages: std::cell::RefCell<std::collections::HashMap<String, String>>,
+    client: net::Client,
+}
+
+#[cfg(feature = "push")]
+impl Notion {
+    const API_URL: &'static str = "https://api.notion.com/v1";
+    const API_VERSION: &'static str = "2022-06-28";
+    /// Most blocks Notion takes in one request
+    const MAX_BLOCKS: usize = 100;
+
+    pub fn new(
+        token: String,
+        database_id: String,
+        title_property: String,
+        client: net::Client,
+    ) -> Self {
+        Notion {
+            token,
+            database_id,
+            title_property,
+            pages: Default::default(),
+            // Notion averages out at three requests a second
+            client: client.rate_limit("api.notion.com", Duration::from_millis(350)),
+        }
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: serde_json::Value,
+    ) -> Result<serde_json::Value, KindlrError> {
+        let network_error = |error: String| KindlrError::Network(format!("Notion: {}", error));
+
+        self.client
+            .request(method, &format!("{}{}", Self::API_URL, path))
+            .header("Authorization", &format!("Bearer {}", self.token))
+            .header("Notion-Version", Self::API_VERSION)
+            .json(&body)
+            .call()
+            .and_then(net::Response::into_json)
+            .map_err(|error| network_error(error.to_string()))
+    }
+
+    /// Id of the book's page, created if the database doesn't have one yet
+    fn page(&self, title: &str) -> Result<String, KindlrError> {
+        if let Some(id) = self.pages.borrow().get(title) {
+            return Ok(id.clone());
+        }
+
+        let found = self.request(
+            "POST",
+            &format!("/databases/{}/query", self.database_id),
+            serde_json::json!({
+                "filter": { "property": self.title_property, "title": { "equals": title } },
+                "page_size": 1,
+            }),
+        )?;
+        let id = match found["results"][0]["id"].as_str() {
+            Some(id) => id.to_string(),
+            None => {
+                let created = self.request(
+                    "POST",
+                    "/pages",
+                    serde_json::json!({
+                        "parent": { "database_id": self.database_id },
+                        "properties": {
+                            self.title_property.as_str(): { "title": notion_text(title) },
+                        },
+                    }),
+                )?;
+                created["id"]
+                    .as_str()
+                    .ok_or_else(|| {
+                        KindlrError::Network("Notion: page created without an id".to_string())
+                    })?
+                    .to_string()
+            }
+        };
+
+        self.pages
+            .borrow_mut()
+            .insert(title.to_string(), id.clone());
+        Ok(id)
+    }
+}
+
+#[cfg(feature = "push")]
+impl Destination for Notion {
+    fn name(&self) -> &str {
+        "notion"
+    }
+
+    fn batch_size(&self) -> usize {
+        Self::MAX_BLOCKS
+    }
+
+    fn send(&self, clippings: &[&Clipping]) -> Result<Vec<String>, KindlrError> {
+        let mut block_ids = vec![String::new(); clippings.len()];
+
+        // Append each book's clippings to its page, keeping track of where each ended up
+        let mut titles: Vec<&str> = Vec::new();
+        for clipping in clippings {
+            if !titles.contains(&clipping.book_title.as_str()) {
+                titles.push(&clipping.book_title);
+            }
+        }
+        for title in titles {
+            let (indexes, book): (Vec<usize>, Vec<&Clipping>) = clippings
+                .iter()
+                .enumerate()
+                .filter(|(_, clipping)| clipping.book_title == title)
+                .map(|(i, clipping)| (i, *clipping))
+                .unzip();
+            let page = self.page(title)?;
+
+            let appended = self.request(
+                "PATCH",
+                &format!("/blocks/{}/children", page),
+                serde_json::json!({ "children": notion_blocks(&book) }),
+            )?;
+            let results = appended["results"].as_array().cloned().unwrap_or_default();
+            for (i, block) in indexes.into_iter().zip(results) {
+                block_ids[i] = block["id"].as_str().unwrap_or_default().to_string();
+            }
+        }
+
+        Ok(block_ids)
+    }
+}
+
+/// Body POSTed to webhooks, each clipping with its id
+pub fn webhook_payload(clippings: &[&Clipping]) -> serde_json::Value {
+    #[derive(Serialize)]
+    struct Entry<'a> {
+        id: String,
+        #[serde(flatten)]
+        clipping: &'a Clipping,
+    }
+
+    let entries: Vec<Entry> = clippings
+        .iter()
+        .map(|clipping| Entry {
+            id: clipping.id(),
+            clipping,
+        })
+        .collect();
+
+    serde_json::json!({ "event": "clippings.imported", "clippings": entries })
+}
+
+/// Hex HMAC-SHA256 of `body` keyed with `secret`, as sent in `X-Kindlr-Signature`
+#[cfg(feature = "push")]
+pub fn webhook_signature(secret: &str, body: &[u8]) -> String {
+    use hmac::Mac;
+
+    let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC takes keys of any length");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// A URL new clippings are POSTed to as JSON
+///
+/// With a secret, requests carry `X-Kindlr-Signature: sha256=<hex>` so the
+/// receiver can check they came from kindlr; see `webhook_signature`.
+#[cfg(feature = "push")]
+pub struct Webhook {
+    name: String,
+    url: String,
+    secret: Option<String>,
+    client: net::Client,
+}
+
+#[cfg(feature = "push")]
+impl Webhook {
+    pub fn new(url: String, secret: Option<String>, client: net::Client) -> Self {
+        Webhook {
+            name: format!("webhook:{}", url),
+            url,
+            secret,
+            client,
+        }
+    }
+}
+
+#[cfg(feature = "push")]
+impl Destination for Webhook {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn send(&self, clippings: &[&Clipping]) -> Result<Vec<String>, KindlrError> {
+        let body = serde_json::to_vec(&webhook_payload(clippings))
+            .map_err(|error| KindlrError::Io(error.into()))?;
+
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", "application/json");
+        if let Some(secret) = &self.secret {
+            let signature = format!("sha256={}", webhook_signature(secret, &body));
+            request = request.header("X-Kindlr-Signature", &signature);
+        }
+        request
+            .body(body)
+            .call()
+            .map_err(|error| KindlrError::Network(format!("Webhook {}: {}", self.url, error)))?;
+
+        // Webhooks don't give anything back to refer to
+        Ok(vec![String::new(); clippings.len()])
+    }
+}
+
+/// Stand-in URI for a book that has no web address,
+/// e.g. "urn:x-kindlr:book:dune--frank-herbert"
+pub fn book_urn(title: &str, author: &str) -> String {
+    let slug = |text: &str| {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join("-")
+    };
+
+    format!("urn:x-kindlr:book:{}--{}", slug(title), slug(author))
+}
+
+/// A Hypothes.is annotation for `clipping`, readable only by `user`
+/// (e.g. "acct:alice@hypothes.is")
+///
+/// Highlights quote their text; notes quote the highlight they were made on,
+/// when it is among `context`, with the note as the annotation's body.
+pub fn hypothesis_annotation(
+    clipping: &Clipping,
+    context: &[Clipping],
+    user: &str,
+) -> serde_json::Value {
+    let content = clipping.content.clone().unwrap_or_default();
+    let (quote, text) = match clipping.clipping_type {
+        ClippingType::Note => match annotated_highlight(clipping, context) {
+            Some(highlight) => (highlight.content.clone(), content),
+            None => (None, content),
+        },
+        _ => (Some(content), String::new()),
+    };
+
+    let uri = book_urn(&clipping.book_title, &clipping.author);
+    let mut target = serde_json::json!({ "source": uri });
+    if let Some(quote) = quote {
+        target["selector"] = serde_json::json!([{ "type": "TextQuoteSelector", "exact": quote }]);
+    }
+
+    serde_json::json!({
+        "uri": uri,
+        "document": { "title": [clipping.book_title] },
+        "text": text,
+        "target": [target],
+        "tags": ["kindlr", format!("location:{}", clipping.location)],
+        "group": "__world__",
+        "permissions": { "read": [user] },
+    })
+}
+
+/// Private annotations on Hypothes.is, made with a developer token from
+/// <https://hypothes.is/account/developer>
+#[cfg(feature = "push")]
+pub struct Hypothesis<'a> {
+    token: String,
+    user: String,
+    /// Every selected clipping, for quoting the highlight a note was made on
+    context: &'a [Clipping],
+    client: net::Client,
+}
+
+#[cfg(feature = "push")]
+impl<'a> Hypothesis<'a> {
+    const ANNOTATIONS_URL: &'static str = "https://api.hypothes.is/api/annotations";
+
+    /// `username` is the Hypothes.is account the token belongs to
+    pub fn new(
+        token: String,
+        username: &str,
+        context: &'a [Clipping],
+        client: net::Client,
+    ) -> Self {
+        let user = if username.starts_with("acct:") {
+            username.to_string()
+        } else {
+            format!("acct:{}@hypothes.is", username)
+        };
+
+        Hypothesis {
+            token,
+            user,
+            context,
+            client,
+        }
+    }
+}
+
+#[cfg(feature = "push")]
+impl Destination for Hypothesis<'_> {
+    fn name(&self) -> &str {
+        "hypothesis"
+    }
+
+    /// The API takes one annotation per request; record each as it's made
+    fn batch_size(&self) -> usize {
+        1
+    }
+
+    fn send(&self, clippings: &[&Clipping]) -> Result<Vec<String>, KindlrError> {
+        let network_error = |error: String| KindlrError::Network(format!("Hypothes.is: {}", error));
+
+        clippings
+            .iter()
+            .map(|clipping| {
+                let annotation: serde_json::Value = self
+                    .client
+                    .post(Self::ANNOTATIONS_URL)
+                    .header("Authorization", &format!("Bearer {}", self.token))
+                    .json(&hypothesis_annotation(clipping, self.context, &self.user))
+                    .call()
+                    .and_then(net::Response::into_json)
+                    .map_err(|error| network_error(error.to_string()))?;
+
+                Ok(annotation["id"].as_str().unwrap_or_default().to_string())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+    use std::cell::RefCell;
+
+    const CLIPPINGS: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00
+
+Classic.
+==========
+Dune (Frank Herbert)
+- Your Note on page 3 | Location 30 | Added on Monday, 1 January 2024 10:09:00
+
+Spice.
+==========";
+
+    struct Recorder {
+        batches: RefCell<Vec<usize>>,
+    }
+
+    impl Destination for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        fn batch_size(&self) -> usize {
+            2
+        }
+
+        fn send(&self, clippings: &[&Clipping]) -> Result<Vec<String>, KindlrError> {
+            self.batches.borrow_mut().push(clippings.len());
+            Ok(clippings.iter().map(|clipping| clipping.id()).collect())
+        }
+    }
+
+    #[test]
+    fn test_push_skips_sent_clippings() {
+        let path = std::env::temp_dir().join(format!("kindlr-push-{}.json", std::process::id()));
+        let mut store = Store::open_at(&path).unwrap();
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let recorder = Recorder {
+            batches: RefCell::new(Vec::new()),
+        };
+
+        let mut plan = Plan::new(false);
+        let summary = push(&recorder, &clippings[..2], &mut store, &mut plan).unwrap();
+        assert_eq!(summary.pushed, 2);
+
+        // A dry run lists the batch it would send without sending it
+        let mut dry_run = Plan::new(true);
+        let summary = push(&recorder, &clippings, &mut store, &mut dry_run).unwrap();
+        assert_eq!(summary.pushed, 1);
+        assert_eq!(dry_run.changes().len(), 1);
+        assert_eq!(store.remote_id("recorder", &clippings[2].id()), None);
+
+        let summary = push(&recorder, &clippings, &mut store, &mut plan).unwrap();
+        assert_eq!(
+            summary,
+            PushSummary {
+                pushed: 1,
+                skipped: 2
+            }
+        );
+        assert_eq!(*recorder.batches.borrow(), vec![2, 1]);
+        assert_eq!(
+            store.remote_id("recorder", &clippings[2].id()),
+            Some(clippings[2].id().as_str())
+        );
+    }
+
+    #[test]
+    fn test_readwise_highlights() {
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let selected: Vec<&Clipping> = clippings.iter().collect();
+        let highlights = readwise_highlights(&selected, &clippings);
+
+        assert_eq!(highlights[0].text, "Fear is the mind-killer.");
+        assert_eq!(highlights[0].location, Some(10));
+        assert_eq!(
+            highlights[0].highlighted_at.as_deref(),
+            Some("2024-01-01T10:00:00")
+        );
+
+        // The note goes on the highlight it was made on
+        assert_eq!(highlights[1].text, "Fear is the mind-killer.");
+        assert_eq!(highlights[1].note.as_deref(), Some("Classic."));
+
+        assert_eq!(highlights[2].text, "Spice.");
+        assert_eq!(highlights[2].note, None);
+    }
+
+    #[test]
+    fn test_notion_blocks() {
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let selected: Vec<&Clipping> = clippings.iter().collect();
+        let blocks = notion_blocks(&selected);
+
+        assert_eq!(blocks[0]["type"], "quote");
+        assert_eq!(
+            blocks[0]["quote"]["rich_text"][0]["text"]["content"],
+            "Fear is the mind-killer."
+        );
+        assert_eq!(
+            blocks[0]["quote"]["rich_text"][1]["text"]["content"],
+            "\n— Location 10-12, 1 January 2024 10:00:00"
+        );
+        assert_eq!(blocks[1]["type"], "callout");
+
+        assert_eq!(notion_text(&"x".repeat(4500)).len(), 3);
+    }
+
+    #[test]
+    fn test_hypothesis_annotation() {
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let user = "acct:reader@hypothes.is";
+
+        let highlight = hypothesis_annotation(&clippings[0], &clippings, user);
+        assert_eq!(highlight["uri"], "urn:x-kindlr:book:dune--frank-herbert");
+        assert_eq!(
+            highlight["target"][0]["selector"][0]["exact"],
+            "Fear is the mind-killer."
+        );
+        assert_eq!(highlight["permissions"]["read"][0], user);
+
+        let note = hypothesis_annotation(&clippings[1], &clippings, user);
+        assert_eq!(note["text"], "Classic.");
+        assert_eq!(
+            note["target"][0]["selector"][0]["exact"],
+            "Fear is the mind-killer."
+        );
+
+        let loose_note = hypothesis_annotation(&clippings[2], &clippings, user);
+        assert!(loose_note["target"][0].get("selector").is_none());
+    }
+
+    #[test]
+    fn test_webhook_payload() {
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let payload = webhook_payload(&[&clippings[0]]);
+
+        assert_eq!(payload["event"], "clippings.imported");
+        assert_eq!(payload["clippings"][0]["id"], clippings[0].id());
+        assert_eq!(payload["clippings"][0]["book_title"], "Dune");
+
+        #[cfg(feature = "push")]
+        assert_eq!(
+            webhook_signature("key", b"The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+}
@@ -0,0 +1,575 @@
+use chrono::NaiveDate;
+use regex::{Regex, RegexBuilder};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use crate::analyze;
+use crate::parser::{Clipping, ClippingType, HighlightColor};
+
+/// Composable filter over clippings
+///
+/// ```
+/// use kindlr::parser::ClippingType;
+/// use kindlr::query::ClippingQuery;
+///
+/// let query = ClippingQuery::new()
+///     .book_contains("dune")
+///     .types([ClippingType::Highlight])
+///     .min_length(100);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ClippingQuery {
+    book: Vec<TextFilter>,
+    author: Vec<TextFilter>,
+    content: Vec<TextFilter>,
+    device: Vec<TextFilter>,
+    types: Option<Vec<ClippingType>>,
+    colors: Option<Vec<HighlightColor>>,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    language: Option<String>,
+    ids: Option<HashSet<String>>,
+    similar: Option<(String, f64)>,
+    all: Vec<ClippingQuery>,
+    any: Vec<ClippingQuery>,
+}
+
+/// Condition on a text field
+#[derive(Debug, Clone)]
+enum TextFilter {
+    /// Lowercased substring
+    Contains(String),
+    Matches(Regex),
+}
+
+impl TextFilter {
+    fn matches(&self, text: &str) -> bool {
+        match self {
+            TextFilter::Contains(needle) => text.to_lowercase().contains(needle),
+            TextFilter::Matches(regex) => regex.is_match(text),
+        }
+    }
+}
+
+impl ClippingQuery {
+    /// A query matching every clipping
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Book title contains `text`, ignoring case
+    pub fn book_contains(mut self, text: impl Into<String>) -> Self {
+        self.book
+            .push(TextFilter::Contains(text.into().to_lowercase()));
+        self
+    }
+
+    /// Book title matches `regex`
+    pub fn book_matches(mut self, regex: Regex) -> Self {
+        self.book.push(TextFilter::Matches(regex));
+        self
+    }
+
+    /// Author contains `text`, ignoring case
+    pub fn author_contains(mut self, text: impl Into<String>) -> Self {
+        self.author
+            .push(TextFilter::Contains(text.into().to_lowercase()));
+        self
+    }
+
+    /// Author matches `regex`
+    pub fn author_matches(mut self, regex: Regex) -> Self {
+        self.author.push(TextFilter::Matches(regex));
+        self
+    }
+
+    /// Content contains `text`, ignoring case
+    pub fn content_contains(mut self, text: impl Into<String>) -> Self {
+        self.content
+            .push(TextFilter::Contains(text.into().to_lowercase()));
+        self
+    }
+
+    /// Content matches `regex`
+    pub fn content_matches(mut self, regex: Regex) -> Self {
+        self.content.push(TextFilter::Matches(regex));
+        self
+    }
+
+    /// Device the clipping was read from contains `text`, ignoring case
+    pub fn device_contains(mut self, text: impl Into<String>) -> Self {
+        self.device
+            .push(TextFilter::Contains(text.into().to_lowercase()));
+        self
+    }
+
+    /// Clipping is one of `types`
+    pub fn types(mut self, types: impl IntoIterator<Item = ClippingType>) -> Self {
+        self.types = Some(types.into_iter().collect());
+        self
+    }
+
+    /// Highlighted in one of `colors`
+    pub fn colors(mut self, colors: impl IntoIterator<Item = HighlightColor>) -> Self {
+        self.colors = Some(colors.into_iter().collect());
+        self
+    }
+
+    /// Added on or after `date`
+    pub fn since(mut self, date: NaiveDate) -> Self {
+        self.since = Some(date);
+        self
+    }
+
+    /// Added on or before `date`
+    pub fn until(mut self, date: NaiveDate) -> Self {
+        self.until = Some(date);
+        self
+    }
+
+    /// Content is at least `length` characters long
+    pub fn min_length(mut self, length: usize) -> Self {
+        self.min_length = Some(length);
+        self
+    }
+
+    /// Content is at most `length` characters long
+    pub fn max_length(mut self, length: usize) -> Self {
+        self.max_length = Some(length);
+        self
+    }
+
+    /// Detected content language is `language` (ISO 639-3)
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// Clipping id is one of `ids`
+    pub fn ids(mut self, ids: impl IntoIterator<Item = String>) -> Self {
+        self.ids = Some(ids.into_iter().collect());
+        self
+    }
+
+    /// Content has at least `threshold` similarity to `text`, see
+    /// `analyze::search_similarity`
+    pub fn similar_to(mut self, text: impl Into<String>, threshold: f64) -> Self {
+        self.similar = Some((text.into(), threshold));
+        self
+    }
+
+    /// `other` matches as well
+    pub fn and(mut self, other: ClippingQuery) -> Self {
+        self.all.push(other);
+        self
+    }
+
+    /// Any one of `queries` matches
+    ///
+    /// Combined with the other conditions of this query, so
+    /// `ClippingQuery::any_of([a, b]).since(date)` means "(a or b) and since date".
+    pub fn any_of(queries: impl IntoIterator<Item = ClippingQuery>) -> Self {
+        ClippingQuery {
+            any: queries.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Whether matching depends on detected content languages
+    pub fn uses_language(&self) -> bool {
+        self.language.is_some()
+            || self
+                .all
+                .iter()
+                .chain(&self.any)
+                .any(ClippingQuery::uses_language)
+    }
+
+    pub fn matches(&self, clipping: &Clipping) -> bool {
+        let all = |filters: &[TextFilter], text: &str| filters.iter().all(|f| f.matches(text));
+        let content = clipping.content.as_deref().unwrap_or_default();
+        let length = content.chars().count();
+        let date = clipping.timestamp().map(|timestamp| timestamp.date());
+
+        all(&self.book, &clipping.book_title)
+            && all(&self.author, &clipping.author)
+            && all(&self.content, content)
+            && (self.device.is_empty()
+                || clipping
+                    .device
+                    .as_deref()
+                    .is_some_and(|device| all(&self.device, device)))
+            && self
+                .types
+                .as_ref()
+                .is_none_or(|types| types.contains(&clipping.clipping_type))
+            && self
+                .colors
+                .as_ref()
+                .is_none_or(|colors| clipping.color.is_some_and(|color| colors.contains(&color)))
+            && self
+                .since
+                .is_none_or(|since| date.is_some_and(|date| date >= since))
+            && self
+                .until
+                .is_none_or(|until| date.is_some_and(|date| date <= until))
+            && self.min_length.is_none_or(|min| length >= min)
+            && self.max_length.is_none_or(|max| length <= max)
+            && self
+                .language
+                .as_ref()
+                .is_none_or(|language| clipping.content_language.as_ref() == Some(language))
+            && self
+                .ids
+                .as_ref()
+                .is_none_or(|ids| ids.contains(&clipping.id()))
+            && self.similar.as_ref().is_none_or(|(text, threshold)| {
+                let score = analyze::search_similarity(text, content);
+                score > 0.0 && score >= *threshold
+            })
+            && self.all.iter().all(|query| query.matches(clipping))
+            && (self.any.is_empty() || self.any.iter().any(|query| query.matches(clipping)))
+    }
+
+    /// Matching clippings, in their original order
+    pub fn filter<'a>(&self, clippings: &'a [Clipping]) -> Vec<&'a Clipping> {
+        clippings
+            .iter()
+            .filter(|clipping| self.matches(clipping))
+            .collect()
+    }
+
+    /// Drop clippings that don't match
+    pub fn retain(&self, clippings: &mut Vec<Clipping>) {
+        clippings.retain(|clipping| self.matches(clipping));
+    }
+}
+
+/// Parse a query string such as `book:"dune" type:note added:>2024-01-01 content:/spice/`
+///
+/// Terms are separated by whitespace and must all match; `OR` between groups of
+/// terms matches either group. Supported terms:
+///
+/// - `book:`, `author:`, `content:` followed by text (quoted if it has spaces) or
+///   a `/regex/`, all matched ignoring case
+/// - `device:` followed by text or a `/regex/`, as above
+/// - `type:highlight,note`
+/// - `color:yellow,blue`, of highlights whose source records their color
+/// - `added:2024-01-01`, `added:>2024-01-01`, `added:>=`, `added:<`, `added:<=`
+/// - `length:>100`, `length:<=50` and the like, in characters
+/// - `lang:eng` (needs the language-detection feature)
+/// - a bare word or quoted phrase, searched for in the content
+impl FromStr for ClippingQuery {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut groups = vec![ClippingQuery::new()];
+
+        for token in tokenize(s)? {
+            if token == "OR" {
+                groups.push(ClippingQuery::new());
+                continue;
+            }
+
+            let query = groups.pop().unwrap_or_default();
+            groups.push(apply_term(query, &token)?);
+        }
+
+        if groups.len() > 1 && groups.iter().any(|group| group.is_empty()) {
+            return Err("OR needs a term on each side".to_string());
+        }
+
+        Ok(match groups.len() {
+            1 => groups.remove(0),
+            _ => ClippingQuery::any_of(groups),
+        })
+    }
+}
+
+impl ClippingQuery {
+    fn is_empty(&self) -> bool {
+        self.book.is_empty()
+            && self.author.is_empty()
+            && self.content.is_empty()
+            && self.device.is_empty()
+            && self.types.is_none()
+            && self.colors.is_none()
+            && self.since.is_none()
+            && self.until.is_none()
+            && self.min_length.is_none()
+            && self.max_length.is_none()
+            && self.language.is_none()
+            && self.ids.is_none()
+            && self.similar.is_none()
+            && self.all.is_empty()
+            && self.any.is_empty()
+    }
+}
+
+/// Split a query string on whitespace, keeping quoted text and regexes whole
+fn tokenize(s: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            '"' => {
+                token.push('"');
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => token.push(c),
+                        None => return Err(format!("Unclosed quote in query: {}", s)),
+                    }
+                }
+            }
+            // A regex starts right after a field name
+            '/' if token.ends_with(':') => {
+                token.push('/');
+                loop {
+                    match chars.next() {
+                        Some('\\') if chars.peek() == Some(&'/') => {
+                            token.push(chars.next().unwrap_or('/'));
+                        }
+                        Some('\\') => {
+                            token.push('\\');
+                            if let Some(c) = chars.next() {
+                                token.push(c);
+                            }
+                        }
+                        Some('/') => break,
+                        Some(c) => token.push(c),
+                        None => return Err(format!("Unclosed regex in query: {}", s)),
+                    }
+                }
+                token.push('/');
+            }
+            c => token.push(c),
+        }
+    }
+
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// Add one `field:value` term to `query`
+///
+/// Quoted values arrive with a leading `"`, regexes as `/pattern/`.
+fn apply_term(query: ClippingQuery, token: &str) -> Result<ClippingQuery, String> {
+    let Some((field, value)) = token.split_once(':').filter(|(field, _)| {
+        !field.starts_with('"') && field.chars().all(|c| c.is_ascii_alphabetic())
+    }) else {
+        return Ok(query.content_contains(token.trim_start_matches('"')));
+    };
+
+    match field {
+        "book" | "author" | "content" => {
+            let filter = text_filter(value)?;
+            Ok(match (field, filter) {
+                ("book", TextFilter::Contains(text)) => query.book_contains(text),
+                ("book", TextFilter::Matches(regex)) => query.book_matches(regex),
+                ("author", TextFilter::Contains(text)) => query.author_contains(text),
+                ("author", TextFilter::Matches(regex)) => query.author_matches(regex),
+                (_, TextFilter::Contains(text)) => query.content_contains(text),
+                (_, TextFilter::Matches(regex)) => query.content_matches(regex),
+            })
+        }
+        "device" => {
+            let mut query = query;
+            query.device.push(text_filter(value)?);
+            Ok(query)
+        }
+        "type" => Ok(query.types(parse_types(value)?)),
+        "color" => Ok(query.colors(parse_colors(value)?)),
+        "added" => {
+            let (op, date) = comparison(value);
+            let date: NaiveDate = date
+                .parse()
+                .map_err(|_| format!("Invalid date in query: {}", value))?;
+            let day = chrono::Days::new(1);
+            let out_of_range = || format!("Date out of range in query: {}", value);
+            Ok(match op {
+                ">" => query.since(date.checked_add_days(day).ok_or_else(out_of_range)?),
+                ">=" => query.since(date),
+                "<" => query.until(date.checked_sub_days(day).ok_or_else(out_of_range)?),
+                "<=" => query.until(date),
+                _ => query.since(date).until(date),
+            })
+        }
+        "length" => {
+            let (op, length) = comparison(value);
+            let length: usize = length
+                .parse()
+                .map_err(|_| format!("Invalid length in query: {}", value))?;
+            let out_of_range = || format!("Length out of range in query: {}", value);
+            Ok(match op {
+                ">" => query.min_length(length.checked_add(1).ok_or_else(out_of_range)?),
+                ">=" => query.min_length(length),
+                "<" => query.max_length(length.checked_sub(1).ok_or_else(out_of_range)?),
+                "<=" => query.max_length(length),
+                _ => query.min_length(length).max_length(length),
+            })
+        }
+        "lang" => Ok(query.language(value.to_string())),
+        _ => Err(format!("Unknown query field: {}", field)),
+    }
+}
+
+fn text_filter(value: &str) -> Result<TextFilter, String> {
+    if let Some(pattern) = value.strip_prefix('/') {
+        let pattern = pattern.strip_suffix('/').unwrap_or(pattern);
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|error| format!("Invalid regex in query: {}", error))?;
+        return Ok(TextFilter::Matches(regex));
+    }
+
+    Ok(TextFilter::Contains(
+        value.trim_start_matches('"').to_lowercase(),
+    ))
+}
+
+/// Split a leading comparison operator off a value
+fn comparison(value: &str) -> (&str, &str) {
+    for op in [">=", "<=", ">", "<"] {
+        if let Some(rest) = value.strip_prefix(op) {
+            return (op, rest);
+        }
+    }
+    ("", value)
+}
+
+/// Parse a comma-separated list of clipping types such as "highlight,note"
+pub fn parse_types(s: &str) -> Result<Vec<ClippingType>, String> {
+    s.split(',')
+        .map(|name| match name.trim().to_lowercase().as_str() {
+            "highlight" | "highlights" => Ok(ClippingType::Highlight),
+            "note" | "notes" => Ok(ClippingType::Note),
+            "bookmark" | "bookmarks" => Ok(ClippingType::Bookmark),
+            "article" | "articles" => Ok(ClippingType::ArticleClip),
+            _ => Err(format!("Invalid clipping type: {}", name)),
+        })
+        .collect()
+}
+
+/// Parse a comma-separated list of highlight colors such as "yellow,blue"
+pub fn parse_colors(s: &str) -> Result<Vec<HighlightColor>, String> {
+    s.split(',').map(str::parse).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    #[test]
+    fn test_query() {
+        let clippings = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00
+
+Classic.
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Sunday, 5 January 2025 09:00:00
+
+Waste no more time arguing about what a good man should be. Be one.
+==========",
+        )
+        .unwrap();
+
+        let dune_highlights = ClippingQuery::new()
+            .book_contains("DUNE")
+            .types([ClippingType::Highlight]);
+        assert_eq!(dune_highlights.filter(&clippings).len(), 1);
+
+        let recent = ClippingQuery::new().since(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap());
+        assert_eq!(recent.filter(&clippings)[0].book_title, "Meditations");
+
+        let long = ClippingQuery::new().min_length(30);
+        assert_eq!(long.filter(&clippings).len(), 1);
+
+        let by_id = ClippingQuery::new().ids([clippings[1].id()]);
+        assert_eq!(
+            by_id.filter(&clippings)[0].content.as_deref(),
+            Some("Classic.")
+        );
+
+        assert_eq!(ClippingQuery::new().filter(&clippings).len(), 3);
+        assert_eq!(
+            parse_types("Highlight, notes"),
+            Ok(vec![ClippingType::Highlight, ClippingType::Note])
+        );
+        assert!(parse_types("quote").is_err());
+    }
+
+    #[test]
+    fn test_query_language() {
+        let mut clippings = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+The spice must flow.
+==========
+Dune Messiah (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Tuesday, 2 January 2024 10:05:00
+
+More spice.
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Sunday, 5 January 2025 09:00:00
+
+Virtue is its own reward.
+==========",
+        )
+        .unwrap();
+        clippings[2].color = Some(HighlightColor::Blue);
+
+        let count = |query: &str| {
+            query
+                .parse::<ClippingQuery>()
+                .unwrap()
+                .filter(&clippings)
+                .len()
+        };
+
+        assert_eq!(count(r#"book:"dune messiah" type:note"#), 1);
+        assert_eq!(count("added:>2024-01-01 content:/spice/"), 1);
+        assert_eq!(count("added:2024-01-01"), 1);
+        assert_eq!(count("content:/^THE/"), 1);
+        assert_eq!(count("spice"), 2);
+        assert_eq!(count(r#"author:"aurelius" OR content:/virtue/"#), 1);
+        assert_eq!(count("book:dune type:note OR author:marcus"), 2);
+        assert_eq!(count("length:<20 added:<=2024-12-31"), 1);
+        assert_eq!(count("color:blue,pink"), 1);
+        assert!("color:red".parse::<ClippingQuery>().is_err());
+        assert!("book:\"dune".parse::<ClippingQuery>().is_err());
+        assert!("OR spice".parse::<ClippingQuery>().is_err());
+        for query in [
+            "length:<0",
+            "length:>18446744073709551615",
+            "added:>+262142-12-31",
+            "added:<-262143-01-01",
+        ] {
+            assert!(query.parse::<ClippingQuery>().is_err(), "{}", query);
+        }
+    }
+}
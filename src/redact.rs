@@ -0,0 +1,139 @@
+use regex::Regex;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::languages;
+use crate::parser::{Clipping, ClippingType};
+use crate::settings::Redact;
+
+/// What `--redact` does with notes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteRedaction {
+    /// Leave notes out
+    #[default]
+    Omit,
+    /// Replace each note with a hash of it, so identical notes can still be
+    /// told apart from different ones
+    Hash,
+}
+
+/// Make `clippings` fit to publish: drop books marked private, omit or hash
+/// notes, and keep only the day each clipping was added
+pub fn redact(clippings: &mut Vec<Clipping>, settings: &Redact) {
+    clippings.retain(|clipping| {
+        !settings
+            .private_books
+            .iter()
+            .any(|pattern| pattern.matches(&clipping.book_title))
+    });
+
+    if settings.notes == NoteRedaction::Omit {
+        clippings.retain(|clipping| clipping.clipping_type != ClippingType::Note);
+    }
+
+    let time_of_day =
+        Regex::new(r"\s*\d{1,2}:\d{2}(:\d{2})?(\s*[AaPp]\.?[Mm]\.?)?").expect("valid regex");
+
+    for clipping in clippings {
+        if clipping.clipping_type == ClippingType::Note
+            && let Some(content) = &clipping.content
+        {
+            clipping.content = Some(format!("[note {}]", hash(content)));
+        }
+
+        clipping.datetime = day_only(&clipping.datetime, &time_of_day);
+        clipping.original_datetime = clipping
+            .original_datetime
+            .as_deref()
+            .map(|datetime| day_only(datetime, &time_of_day));
+        // An offset gives away where the reader was, and means nothing
+        // without the time
+        clipping.utc_offset = None;
+        clipping.raw = None;
+    }
+}
+
+/// `datetime` at midnight, or without its time if it isn't in English
+fn day_only(datetime: &str, time_of_day: &Regex) -> String {
+    match languages::ENGLISH.timestamp(datetime) {
+        Some(timestamp) => timestamp
+            .date()
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight is valid")
+            .format("%-d %B %Y %H:%M:%S")
+            .to_string(),
+        // A datetime the parser kept as written
+        None => time_of_day.replace_all(datetime, "").into_owned(),
+    }
+}
+
+/// First 16 hex digits of the SHA-256 of `text`
+fn hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes())[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, ParserOptions};
+
+    const CLIPPINGS: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:01:00
+
+Reminds me of my divorce.
+==========
+Diary (Me)
+- Your Highlight on page 1 | Location 1-2 | Added on Monday, 1 January 2024 11:00:00
+
+Dear diary.
+==========";
+
+    #[test]
+    fn test_redact() {
+        let parser = Parser::new(ParserOptions {
+            strict: false,
+            ..ParserOptions::default()
+        });
+        let settings: Redact =
+            toml::from_str("private_books = [\"Diary\"]\nnotes = \"hash\"").unwrap();
+
+        let mut clippings = parser.parse(CLIPPINGS).unwrap();
+        clippings[1].datetime = "1. Januar 2024 10:01:00".to_string();
+        clippings[0].original_datetime = Some("1 January 2024 09:00:00".to_string());
+        clippings[0].utc_offset = Some("+01:00".to_string());
+        redact(&mut clippings, &settings);
+
+        let titles: Vec<&str> = clippings.iter().map(|c| c.book_title.as_str()).collect();
+        assert_eq!(titles, ["Dune", "Dune"]);
+        assert_eq!(clippings[0].datetime, "1 January 2024 00:00:00");
+        assert_eq!(
+            clippings[0].content.as_deref(),
+            Some("Fear is the mind-killer.")
+        );
+        assert!(
+            clippings[1]
+                .content
+                .as_deref()
+                .unwrap()
+                .starts_with("[note ")
+        );
+        assert_eq!(clippings[1].datetime, "1. Januar 2024");
+        let published = serde_json::to_string(&clippings).unwrap();
+        assert!(!published.contains("10:00") && !published.contains("09:00"));
+        assert!(published.contains("\"original_datetime\":\"1 January 2024 00:00:00\""));
+        assert!(!published.contains("utc_offset"));
+
+        redact(&mut clippings, &Redact::default());
+        assert_eq!(clippings.len(), 1, "notes are omitted by default");
+    }
+}
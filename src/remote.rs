@@ -0,0 +1,226 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::KindlrError;
+use crate::net;
+use crate::settings::Settings;
+
+/// Largest remote file downloaded, well above any My Clippings.txt
+const MAX_BYTES: u64 = 256 * 1024 * 1024;
+
+const DROPBOX_DOWNLOAD: &str = "https://content.dropboxapi.com/2/files/download";
+
+/// Where remote files are downloaded to in the kindlr home directory
+pub fn dir(home: &Path) -> PathBuf {
+    home.join("cache").join("remote")
+}
+
+/// A file in cloud storage given in place of a local path
+#[derive(Debug, PartialEq)]
+pub enum Remote {
+    /// `webdav://[user[:password]@]host/path`, or `webdavs://` over HTTPS
+    WebDav {
+        url: String,
+        username: Option<String>,
+        password: Option<String>,
+    },
+    /// `dropbox:/path` within the Dropbox of the token's account
+    Dropbox { path: String },
+}
+
+impl Remote {
+    /// The remote file `input` names, or `None` for a local path
+    pub fn parse(input: &str) -> Option<Remote> {
+        if let Some(path) = input.strip_prefix("dropbox:") {
+            let path = path.trim_start_matches('/');
+            return Some(Remote::Dropbox {
+                path: format!("/{}", path),
+            });
+        }
+
+        let (scheme, rest) = if let Some(rest) = input.strip_prefix("webdav://") {
+            ("http", rest)
+        } else if let Some(rest) = input.strip_prefix("webdavs://") {
+            ("https", rest)
+        } else {
+            return None;
+        };
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let (userinfo, host) = match authority.rsplit_once('@') {
+            Some((userinfo, host)) => (Some(userinfo), host),
+            None => (None, authority),
+        };
+        let (username, password) = match userinfo.map(|userinfo| userinfo.split_once(':')) {
+            Some(Some((username, password))) => {
+                (Some(username.to_string()), Some(password.to_string()))
+            }
+            Some(None) => (userinfo.map(str::to_string), None),
+            None => (None, None),
+        };
+
+        Some(Remote::WebDav {
+            url: format!("{}://{}/{}", scheme, host, path.replace(' ', "%20")),
+            username,
+            password,
+        })
+    }
+
+    /// Name of the file, which tells importers what it holds
+    fn file_name(&self) -> &str {
+        let path = match self {
+            Remote::WebDav { url, .. } => url.split(['?', '#']).next().unwrap_or(url),
+            Remote::Dropbox { path } => path,
+        };
+        match path.rsplit('/').next() {
+            Some(name) if !name.is_empty() => name,
+            _ => "My Clippings.txt",
+        }
+    }
+
+    /// Download the file into `dir`, returning where it was written
+    ///
+    /// The file is downloaded again on every read, as it changes whenever
+    /// the Kindle syncs, and written aside and renamed so a failed download
+    /// leaves the last one in place.
+    pub fn download(
+        &self,
+        client: &net::Client,
+        settings: &Settings,
+        dir: &Path,
+    ) -> Result<PathBuf, KindlrError> {
+        let request = match self {
+            Remote::WebDav {
+                url,
+                username,
+                password,
+            } => {
+                let request = client.get(url);
+                let username = username
+                    .clone()
+                    .or_else(|| settings.webdav.username.clone());
+                let password = password
+                    .clone()
+                    .or_else(|| env::var("WEBDAV_PASSWORD").ok())
+                    .or_else(|| settings.webdav.password.clone());
+                match username {
+                    Some(username) => request.header(
+                        "Authorization",
+                        &format!(
+                            "Basic {}",
+                            base64(&format!("{}:{}", username, password.unwrap_or_default()))
+                        ),
+                    ),
+                    None => request,
+                }
+            }
+            Remote::Dropbox { path } => {
+                let token = env::var("DROPBOX_TOKEN")
+                    .ok()
+                    .or_else(|| settings.dropbox.token.clone())
+                    .ok_or_else(|| {
+                        KindlrError::Config(
+                            "Set DROPBOX_TOKEN or token under [dropbox] in config.toml".to_string(),
+                        )
+                    })?;
+                client
+                    .post(DROPBOX_DOWNLOAD)
+                    .header("Authorization", &format!("Bearer {}", token))
+                    .header("Dropbox-API-Arg", &dropbox_arg(path))
+            }
+        };
+
+        let body = request
+            .uncached()
+            .max_bytes(MAX_BYTES)
+            .call()
+            .map_err(|error| KindlrError::Network(format!("{}: {}", self.file_name(), error)))?
+            .into_bytes();
+
+        let key = match self {
+            Remote::WebDav { url, .. } => url,
+            Remote::Dropbox { path } => path,
+        };
+        let path = dir.join(format!("{:016x}-{}", net::hash(key), self.file_name()));
+        fs::create_dir_all(dir)?;
+        let partial = path.with_extension("part");
+        fs::write(&partial, body)?;
+        fs::rename(&partial, &path)?;
+        Ok(path)
+    }
+}
+
+/// The argument of a Dropbox download, JSON with anything outside ASCII
+/// escaped as Dropbox asks of HTTP headers
+fn dropbox_arg(path: &str) -> String {
+    serde_json::json!({ "path": path })
+        .to_string()
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii() => c.to_string(),
+            c => c
+                .encode_utf16(&mut [0; 2])
+                .iter()
+                .map(|unit| format!("\\u{:04x}", unit))
+                .collect(),
+        })
+        .collect()
+}
+
+/// `text` in standard, padded base64, for HTTP basic authentication
+fn base64(text: &str) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in text.as_bytes().chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let remote = Remote::parse("webdavs://reader:p@ss@dav.example.com/kindle/My Clippings.txt");
+        assert_eq!(
+            remote,
+            Some(Remote::WebDav {
+                url: "https://dav.example.com/kindle/My%20Clippings.txt".to_string(),
+                username: Some("reader".to_string()),
+                password: Some("p@ss".to_string()),
+            })
+        );
+        assert_eq!(remote.unwrap().file_name(), "My%20Clippings.txt");
+
+        let remote = Remote::parse("dropbox:Apps/Kindle/clippings.txt").unwrap();
+        assert_eq!(
+            remote,
+            Remote::Dropbox {
+                path: "/Apps/Kindle/clippings.txt".to_string()
+            }
+        );
+        assert_eq!(
+            Remote::parse("webdav://nas.local/").unwrap().file_name(),
+            "My Clippings.txt"
+        );
+        assert_eq!(Remote::parse("My Clippings.txt"), None);
+
+        assert_eq!(base64("reader:p@ss"), "cmVhZGVyOnBAc3M=");
+        assert_eq!(base64("ab"), "YWI=");
+        assert_eq!(dropbox_arg("/Bücher"), r#"{"path":"/B\u00fccher"}"#);
+    }
+}
@@ -0,0 +1,404 @@
+use chrono::Datelike;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::analyze::{self, Stopwords, TermCount};
+use crate::parser::{Clipping, ClippingType, HighlightColor};
+
+const STANDOUT_QUOTES: usize = 5;
+const TOP_WORDS: usize = 10;
+
+/// Output format of a report
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            "html" => Ok(ReportFormat::Html),
+            _ => Err(format!("Invalid report format: {}", s)),
+        }
+    }
+}
+
+/// Highlights and notes made in one book
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BookCount {
+    pub book_title: String,
+    pub author: String,
+    pub highlights: usize,
+    pub notes: usize,
+}
+
+/// A highlight worth showing off, with the number of notes attached to it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Quote {
+    pub content: String,
+    pub book_title: String,
+    pub author: String,
+    pub notes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<HighlightColor>,
+}
+
+/// Clippings in a month
+#[derive(Debug, PartialEq, Serialize)]
+pub struct MonthCount {
+    pub month: u32,
+    pub count: usize,
+}
+
+/// Summary of a year of reading
+#[derive(Debug, PartialEq, Serialize)]
+pub struct YearReport {
+    pub year: i32,
+    pub books: Vec<BookCount>,
+    pub total_highlights: usize,
+    pub total_notes: usize,
+    pub longest_highlight: Option<Quote>,
+    pub busiest_month: Option<MonthCount>,
+    pub top_words: Vec<TermCount>,
+    pub standout_quotes: Vec<Quote>,
+}
+
+/// Build the report from the clippings added during `year`
+pub fn year_report(clippings: &[Clipping], year: i32, stopwords: &Stopwords) -> YearReport {
+    let clippings: Vec<&Clipping> = clippings
+        .iter()
+        .filter(|clipping| {
+            clipping
+                .timestamp()
+                .is_some_and(|timestamp| timestamp.year() == year)
+        })
+        .collect();
+
+    let mut books: BTreeMap<(&str, &str), BookCount> = BTreeMap::new();
+    let mut months: BTreeMap<u32, usize> = BTreeMap::new();
+    for clipping in &clippings {
+        let book = books
+            .entry((&clipping.book_title, &clipping.author))
+            .or_insert_with(|| BookCount {
+                book_title: clipping.book_title.clone(),
+                author: clipping.author.clone(),
+                highlights: 0,
+                notes: 0,
+            });
+
+        match clipping.clipping_type {
+            ClippingType::Highlight | ClippingType::ArticleClip => book.highlights += 1,
+            ClippingType::Note => book.notes += 1,
+            ClippingType::Bookmark => {}
+        }
+
+        if let Some(timestamp) = clipping.timestamp() {
+            *months.entry(timestamp.month()).or_insert(0) += 1;
+        }
+    }
+
+    let mut books: Vec<BookCount> = books
+        .into_values()
+        .filter(|book| book.highlights + book.notes > 0)
+        .collect();
+    books.sort_by(|a, b| {
+        (b.highlights + b.notes)
+            .cmp(&(a.highlights + a.notes))
+            .then_with(|| a.book_title.cmp(&b.book_title))
+    });
+
+    let mut quotes: Vec<Quote> = clippings
+        .iter()
+        .filter(|clipping| clipping.clipping_type.is_highlight())
+        .filter_map(|highlight| {
+            Some(Quote {
+                content: highlight.content.clone()?,
+                book_title: highlight.book_title.clone(),
+                author: highlight.author.clone(),
+                notes: attached_notes(highlight, &clippings),
+                color: highlight.color,
+            })
+        })
+        .collect();
+
+    let longest_highlight = quotes
+        .iter()
+        .max_by_key(|quote| quote.content.chars().count())
+        .cloned();
+
+    // Most-noted highlights first, then the longest ones
+    quotes.sort_by(|a, b| {
+        b.notes
+            .cmp(&a.notes)
+            .then_with(|| b.content.chars().count().cmp(&a.content.chars().count()))
+    });
+    quotes.truncate(STANDOUT_QUOTES);
+
+    YearReport {
+        year,
+        total_highlights: books.iter().map(|book| book.highlights).sum(),
+        total_notes: books.iter().map(|book| book.notes).sum(),
+        books,
+        longest_highlight,
+        busiest_month: months
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+            .map(|(month, count)| MonthCount { month, count }),
+        top_words: analyze::analyze(clippings.iter().copied(), stopwords, TOP_WORDS).terms,
+        standout_quotes: quotes,
+    }
+}
+
+/// Notes in the same book placed within the highlight's location range
+fn attached_notes(highlight: &Clipping, clippings: &[&Clipping]) -> usize {
+    let start = highlight.location.start;
+    let end = highlight.location.end.unwrap_or(start);
+
+    clippings
+        .iter()
+        .filter(|clipping| {
+            clipping.clipping_type == ClippingType::Note
+                && clipping.book_title == highlight.book_title
+                && clipping.author == highlight.author
+                && (start..=end).contains(&clipping.location.start)
+        })
+        .count()
+}
+
+fn month_name(month: u32) -> &'static str {
+    const MONTHS: [&str; 12] = [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ];
+
+    MONTHS[(month as usize).saturating_sub(1) % 12]
+}
+
+/// The Kindle app's shade of a highlight color, behind its badge
+fn background(color: HighlightColor) -> &'static str {
+    match color {
+        HighlightColor::Yellow => "#fff2a8",
+        HighlightColor::Blue => "#bde0fe",
+        HighlightColor::Pink => "#ffc8dd",
+        HighlightColor::Orange => "#ffd6a5",
+    }
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl YearReport {
+    pub fn to_markdown(&self) -> String {
+        let mut output = String::new();
+
+        let _ = writeln!(output, "# {} in Reading\n", self.year);
+        let _ = writeln!(output, "- Books annotated: {}", self.books.len());
+        let _ = writeln!(output, "- Highlights: {}", self.total_highlights);
+        let _ = writeln!(output, "- Notes: {}", self.total_notes);
+        if let Some(month) = &self.busiest_month {
+            let _ = writeln!(
+                output,
+                "- Busiest month: {} ({} clippings)",
+                month_name(month.month),
+                month.count
+            );
+        }
+
+        let _ = writeln!(output, "\n## Books\n");
+        for book in &self.books {
+            let _ = writeln!(
+                output,
+                "- *{}* by {}: {} highlights, {} notes",
+                book.book_title, book.author, book.highlights, book.notes
+            );
+        }
+
+        if !self.top_words.is_empty() {
+            let _ = writeln!(output, "\n## Top Words\n");
+            let words: Vec<String> = self
+                .top_words
+                .iter()
+                .map(|word| format!("{} ({})", word.term, word.count))
+                .collect();
+            let _ = writeln!(output, "{}", words.join(", "));
+        }
+
+        if let Some(quote) = &self.longest_highlight {
+            let _ = writeln!(output, "\n## Longest Highlight\n");
+            let _ = writeln!(
+                output,
+                "> {}\n\n— *{}*, {}",
+                quote.content, quote.book_title, quote.author
+            );
+        }
+
+        if !self.standout_quotes.is_empty() {
+            let _ = writeln!(output, "\n## Standout Quotes");
+            for quote in &self.standout_quotes {
+                let _ = writeln!(
+                    output,
+                    "\n> {}\n\n— *{}*, {}",
+                    quote.content, quote.book_title, quote.author
+                );
+            }
+        }
+
+        output
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut output = String::new();
+
+        let _ = writeln!(
+            output,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{} in Reading</title>\n</head>\n<body>",
+            self.year
+        );
+        let _ = writeln!(output, "<h1>{} in Reading</h1>\n<ul>", self.year);
+        let _ = writeln!(output, "<li>Books annotated: {}</li>", self.books.len());
+        let _ = writeln!(output, "<li>Highlights: {}</li>", self.total_highlights);
+        let _ = writeln!(output, "<li>Notes: {}</li>", self.total_notes);
+        if let Some(month) = &self.busiest_month {
+            let _ = writeln!(
+                output,
+                "<li>Busiest month: {} ({} clippings)</li>",
+                month_name(month.month),
+                month.count
+            );
+        }
+        let _ = writeln!(output, "</ul>");
+
+        let _ = writeln!(output, "<h2>Books</h2>\n<ul>");
+        for book in &self.books {
+            let _ = writeln!(
+                output,
+                "<li><cite>{}</cite> by {}: {} highlights, {} notes</li>",
+                escape_html(&book.book_title),
+                escape_html(&book.author),
+                book.highlights,
+                book.notes
+            );
+        }
+        let _ = writeln!(output, "</ul>");
+
+        if !self.top_words.is_empty() {
+            let words: Vec<String> = self
+                .top_words
+                .iter()
+                .map(|word| format!("{} ({})", escape_html(&word.term), word.count))
+                .collect();
+            let _ = writeln!(output, "<h2>Top Words</h2>\n<p>{}</p>", words.join(", "));
+        }
+
+        let quote_html = |quote: &Quote| {
+            let badge = quote
+                .color
+                .map(|color| {
+                    format!(
+                        " <span class=\"color\" style=\"background: {}\">{}</span>",
+                        background(color),
+                        color
+                    )
+                })
+                .unwrap_or_default();
+            format!(
+                "<blockquote>\n<p>{}</p>\n<footer><cite>{}</cite>, {}{}</footer>\n</blockquote>",
+                escape_html(&quote.content),
+                escape_html(&quote.book_title),
+                escape_html(&quote.author),
+                badge
+            )
+        };
+
+        if let Some(quote) = &self.longest_highlight {
+            let _ = writeln!(output, "<h2>Longest Highlight</h2>\n{}", quote_html(quote));
+        }
+
+        if !self.standout_quotes.is_empty() {
+            let _ = writeln!(output, "<h2>Standout Quotes</h2>");
+            for quote in &self.standout_quotes {
+                let _ = writeln!(output, "{}", quote_html(quote));
+            }
+        }
+
+        let _ = writeln!(output, "</body>\n</html>");
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    #[test]
+    fn test_year_report() {
+        let mut clippings = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 11 | Added on Monday, 1 January 2024 10:05:00
+
+Classic.
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Monday, 4 March 2024 09:00:00
+
+Waste no more time arguing about what a good man should be. Be one.
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 80-81 | Added on Sunday, 5 January 2025 09:00:00
+
+Not this year.
+==========",
+        )
+        .unwrap();
+        clippings[0].color = Some(HighlightColor::Orange);
+
+        let report = year_report(&clippings, 2024, &Stopwords::builtin("en").unwrap());
+
+        assert_eq!(report.books.len(), 2);
+        assert_eq!(report.total_highlights, 2);
+        assert_eq!(report.total_notes, 1);
+        assert_eq!(
+            report.busiest_month,
+            Some(MonthCount { month: 1, count: 2 })
+        );
+        assert_eq!(
+            report.longest_highlight.as_ref().unwrap().book_title,
+            "Meditations"
+        );
+        assert_eq!(
+            report.standout_quotes[0].content,
+            "Fear is the mind-killer."
+        );
+        assert_eq!(report.standout_quotes[0].notes, 1);
+        assert!(report.to_html().contains(
+            "<cite>Dune</cite>, Frank Herbert <span class=\"color\" style=\"background: #ffd6a5\">orange</span>"
+        ));
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+use std::ops::ControlFlow;
+
+use crate::export::{Exporter, KindleExporter, kindle_entry, kindle_order};
+use crate::library::Library;
+use crate::parser::{Clipping, Language, Parser};
+
+/// A field of a clipping that reads back differently once written as My Clippings.txt
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loss {
+    /// Id of the clipping as it was before writing
+    pub id: String,
+    pub field: &'static str,
+    pub before: String,
+    pub after: String,
+}
+
+impl fmt::Display for Loss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} {:?} reads back as {:?}",
+            self.id, self.field, self.before, self.after
+        )
+    }
+}
+
+/// Write the library as My Clippings.txt with the Kindle exporter, parse
+/// it back and report every field that changed, clipping by clipping
+///
+/// Nothing is lost for clippings read from My Clippings.txt; clippings from
+/// other sources or edited by hooks may have notes over several lines, no
+/// page or titles a Kindle can't write.
+pub fn verify(library: &Library) -> Vec<Loss> {
+    let mut text = Vec::new();
+    if let Err(error) = KindleExporter.export(library, &mut text) {
+        return vec![Loss {
+            id: String::new(),
+            field: "file",
+            before: format!("{} clippings", library.clippings().count()),
+            after: error.to_string(),
+        }];
+    }
+    let text = String::from_utf8_lossy(&text);
+
+    let mut parsed = Vec::new();
+    Parser::default().parse_with(&text, |result| {
+        parsed.push(result);
+        ControlFlow::Continue(())
+    });
+
+    let clippings = kindle_order(library);
+
+    let mut losses = Vec::new();
+    if parsed.len() != clippings.len() {
+        losses.push(Loss {
+            id: String::new(),
+            field: "file",
+            before: format!("{} clippings", clippings.len()),
+            after: format!("{} entries", parsed.len()),
+        });
+    }
+
+    for (clipping, parsed) in clippings.into_iter().zip(&parsed) {
+        let mut lose = |field: &'static str, before: String, after: String| {
+            losses.push(Loss {
+                id: clipping.id(),
+                field,
+                before,
+                after,
+            });
+        };
+
+        match parsed {
+            Ok(after) => {
+                let fields = [
+                    ("id", clipping.id(), after.id()),
+                    (
+                        "type",
+                        clipping.clipping_type.to_string(),
+                        after.clipping_type.to_string(),
+                    ),
+                    (
+                        "title",
+                        clipping.book_title.clone(),
+                        after.book_title.clone(),
+                    ),
+                    ("author", clipping.author.clone(), after.author.clone()),
+                    (
+                        "page",
+                        format!("{:?}", clipping.page),
+                        format!("{:?}", after.page),
+                    ),
+                    (
+                        "location",
+                        clipping.location.to_string(),
+                        after.location.to_string(),
+                    ),
+                    (
+                        "datetime",
+                        clipping.datetime.clone(),
+                        after.datetime.clone(),
+                    ),
+                    (
+                        "weekday",
+                        clipping.weekday.to_string(),
+                        after.weekday.to_string(),
+                    ),
+                    (
+                        "content",
+                        format!("{:?}", clipping.content),
+                        format!("{:?}", after.content),
+                    ),
+                ];
+                for (field, before, after) in fields {
+                    if before != after {
+                        lose(field, before, after);
+                    }
+                }
+            }
+            Err(issue) => lose(
+                "entry",
+                kindle_entry(clipping, Language::English),
+                issue.error.to_string(),
+            ),
+        }
+    }
+
+    losses
+}
+
+/// Panic listing every loss when the clippings don't survive being written
+/// as My Clippings.txt and read back, for tests of code producing clippings
+#[track_caller]
+pub fn assert_lossless<'a>(clippings: impl IntoIterator<Item = &'a Clipping>) {
+    let losses = verify(&Library::new(clippings.into_iter().cloned().collect()));
+    if !losses.is_empty() {
+        let losses: Vec<String> = losses.iter().map(Loss::to_string).collect();
+        panic!("clippings change when read back:\n{}", losses.join("\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::generate_clippings_file;
+    use crate::parser::{ClippingType, Location, parse_clippings};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_verify() {
+        let clippings = parse_clippings(&generate_clippings_file(50, Language::English)).unwrap();
+        assert_lossless(&clippings);
+
+        let added = NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        let article = Clipping::new(
+            ClippingType::ArticleClip,
+            "On Focus".to_string(),
+            "example.com".to_string(),
+            None,
+            Location {
+                start: 1,
+                end: None,
+            },
+            added,
+            Some("Attend.\nRest.".to_string()),
+        );
+
+        let fields: Vec<&str> = verify(&Library::new(vec![article]))
+            .iter()
+            .map(|loss| loss.field)
+            .collect();
+        assert_eq!(fields, vec!["id", "type", "page", "content"]);
+    }
+}
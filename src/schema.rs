@@ -0,0 +1,39 @@
+use schemars::schema_for;
+
+use crate::library::Library;
+
+/// JSON Schema of the document `export --format json` writes, which
+/// `--format yaml` writes in YAML, for validating exports and generating
+/// code against
+pub fn export_schema() -> String {
+    serde_json::to_string_pretty(&schema_for!(Library)).expect("schemas serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_export_schema() {
+        let schema: Value = serde_json::from_str(&export_schema()).unwrap();
+
+        assert_eq!(schema["title"], "Library");
+        let clipping = &schema["definitions"]["Clipping"];
+        assert!(
+            clipping["required"]
+                .as_array()
+                .unwrap()
+                .contains(&"book_title".into())
+        );
+        assert!(clipping["properties"]["context"].is_object());
+        assert!(
+            schema["definitions"]["Weekday"]["enum"]
+                .as_array()
+                .unwrap()
+                .contains(&"Monday".into())
+        );
+        // Settings for exporting aren't part of the document
+        assert!(schema["properties"]["date_format"].is_null());
+    }
+}
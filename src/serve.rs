@@ -0,0 +1,1066 @@
+use chrono::{Datelike, Local, NaiveDate};
+use schemars::JsonSchema;
+use schemars::r#gen::SchemaSettings;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use crate::digest;
+#[cfg(feature = "graphql")]
+use crate::graphql;
+use crate::library::Library;
+use crate::metrics::Metrics;
+use crate::net;
+use crate::parser::Clipping;
+use crate::query::{self, ClippingQuery};
+use crate::set::ClippingSet;
+use crate::store::Store;
+#[cfg(feature = "web-ui")]
+use crate::web;
+
+/// Address `serve` listens on unless given `--listen`
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+/// Items on a page of a list unless the request asks for another `limit`
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Most items on a page, whatever `limit` asks for
+pub const MAX_PAGE_SIZE: usize = 500;
+
+/// How long a client may take to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest request body read, in bytes
+const MAX_BODY_SIZE: usize = 1 << 20;
+
+/// Largest request line and headers read, in bytes
+const MAX_HEAD_SIZE: u64 = 16 << 10;
+
+/// Most headers read
+const MAX_HEADERS: usize = 100;
+
+/// Query parameters filtering `/clippings`, named as the command line's
+/// filters, and what they keep
+pub const FILTERS: [(&str, &str); 12] = [
+    (
+        "query",
+        "Query such as book:\"dune\" type:note added:>2024-01-01",
+    ),
+    ("book", "Book title contains text"),
+    ("author", "Author contains text"),
+    ("contains", "Content contains text"),
+    ("type", "Comma-separated highlight, note, bookmark, article"),
+    ("color", "Comma-separated yellow, blue, pink, orange"),
+    ("since", "Added on or after yyyy-mm-dd"),
+    ("until", "Added on or before yyyy-mm-dd"),
+    ("min-length", "Content has at least n characters"),
+    ("max-length", "Content has at most n characters"),
+    ("device", "Read from a device named or numbered like text"),
+    ("favorites-only", "true for only starred clippings"),
+];
+
+/// An HTTP request, as much of it as the API reads
+#[derive(Debug, Default, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Query string parameters, decoded
+    pub params: BTreeMap<String, String>,
+    /// Headers by lowercase name
+    pub headers: BTreeMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// A request of `method` for `target`, a path and any query string
+    pub fn new(method: &str, target: &str) -> Self {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let params = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (net::decode(name), net::decode(value))
+            })
+            .collect();
+
+        Request {
+            method: method.to_string(),
+            path: net::decode(path),
+            params,
+            headers: BTreeMap::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Read a request line, headers and any body of `Content-Length`, or
+    /// `None` if the client closed the connection without sending any
+    ///
+    /// Requests with more than `MAX_HEAD_SIZE` bytes before the body or
+    /// more than `MAX_HEADERS` headers are refused.
+    pub fn read(reader: &mut impl BufRead) -> io::Result<Option<Self>> {
+        let too_large = || io::Error::new(io::ErrorKind::InvalidData, "Request headers too large");
+        let mut head = reader.take(MAX_HEAD_SIZE);
+        let mut read_line = |line: &mut String| match head.read_line(line)? {
+            0 => Ok(0),
+            _ if !line.ends_with('\n') && head.limit() == 0 => Err(too_large()),
+            read => Ok(read),
+        };
+
+        let mut line = String::new();
+        if read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid request line: {}", line.trim_end()),
+            ));
+        };
+        let mut request = Request::new(method, target);
+
+        for count in 0.. {
+            let mut header = String::new();
+            if read_line(&mut header)? == 0 {
+                break;
+            }
+            let Some((name, value)) = header.trim_end().split_once(':') else {
+                break;
+            };
+            if count == MAX_HEADERS {
+                return Err(too_large());
+            }
+            request
+                .headers
+                .insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+
+        if let Some(length) = request.headers.get("content-length") {
+            let length = match length.parse::<usize>() {
+                Ok(length) if length <= MAX_BODY_SIZE => length,
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid Content-Length: {}", length),
+                    ));
+                }
+            };
+            request.body = vec![0; length];
+            reader.read_exact(&mut request.body)?;
+        }
+
+        Ok(Some(request))
+    }
+}
+
+/// An HTTP response
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json(status: u16, value: &impl Serialize) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_vec_pretty(value).expect("responses serialize"),
+        }
+    }
+
+    /// `metrics` in the Prometheus text format
+    pub fn metrics(metrics: &Metrics) -> Self {
+        Response {
+            status: 200,
+            content_type: "text/plain; version=0.0.4; charset=utf-8",
+            body: metrics.render().into_bytes(),
+        }
+    }
+
+    /// `{"error": message}`
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Response::json(
+            status,
+            &ErrorBody {
+                error: message.into(),
+            },
+        )
+    }
+
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(
+            out,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            reason(self.status),
+            self.content_type,
+            self.body.len()
+        )?;
+        // Every 401 names the scheme that would have been let in
+        if self.status == 401 {
+            write!(out, "WWW-Authenticate: Bearer\r\n")?;
+        }
+        write!(out, "\r\n")?;
+        out.write_all(&self.body)?;
+        out.flush()
+    }
+}
+
+/// The body of a failed request
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ErrorBody {
+    pub error: String,
+}
+
+/// Part of a list, and the cursor to ask for the rest with
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// `cursor` of the next page, if there is one
+    pub next_cursor: Option<String>,
+}
+
+/// A clipping as the API answers with it
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ClippingEntry {
+    /// What `/clippings/{id}` finds it by, as `list` shows it
+    pub id: String,
+    /// Whether it is starred
+    pub favorite: bool,
+    #[serde(flatten)]
+    pub clipping: Clipping,
+}
+
+/// What `/review` shows for a day
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Review {
+    /// The day's quote, as `digest` picks it
+    pub quote: Option<ClippingEntry>,
+    /// Highlights added on the same day of earlier years, newest first
+    pub on_this_day: Vec<ClippingEntry>,
+}
+
+/// New content for a clipping, as `edit` saves it
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct Edit {
+    pub content: String,
+}
+
+/// A book as `/books` lists it
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+pub struct BookEntry {
+    pub title: String,
+    pub author: String,
+    pub clippings: usize,
+}
+
+impl BookEntry {
+    /// What a page of books ending with this one has as its cursor
+    pub(crate) fn cursor(&self) -> String {
+        format!("{} ({})", self.title, self.author)
+    }
+}
+
+/// A change a request makes to a clipping
+enum Update {
+    Favorite(bool),
+    Content(String),
+}
+
+/// What `kindlr serve` answers requests from: the clippings as `list`
+/// shows them, and which are starred
+///
+/// Requests that star or edit clippings are refused unless the API is
+/// given the store to save them in, and every request without the bearer
+/// token once the API is given one.
+pub struct Api {
+    clippings: ClippingSet,
+    starred: HashSet<String>,
+    books: Vec<BookEntry>,
+    store: Option<Store>,
+    /// Clippings as in the file, by the id they are shown with
+    originals: HashMap<String, Clipping>,
+    token: Option<String>,
+    metrics: Metrics,
+    /// Schema answering `/graphql` from the clippings as they were when it
+    /// was made, dropped whenever one changes
+    #[cfg(feature = "graphql")]
+    graphql: Option<graphql::KindlrSchema>,
+}
+
+impl Api {
+    pub fn new(clippings: Vec<Clipping>, starred: HashSet<String>) -> Self {
+        let metrics = Metrics::default();
+        metrics.imports.store(1, Ordering::Relaxed);
+        metrics
+            .clippings
+            .store(clippings.len() as u64, Ordering::Relaxed);
+
+        Api {
+            books: books(&clippings),
+            clippings: ClippingSet::new(clippings),
+            starred,
+            store: None,
+            originals: HashMap::new(),
+            token: None,
+            metrics,
+            #[cfg(feature = "graphql")]
+            graphql: None,
+        }
+    }
+
+    /// Save stars and edits in `store`, under the id each clipping has in
+    /// `originals`, the clippings as in the file by the id they are shown with
+    pub fn writable(mut self, store: Store, originals: HashMap<String, Clipping>) -> Self {
+        self.store = Some(store);
+        self.originals = originals;
+        self
+    }
+
+    /// Answer only requests with `Authorization: Bearer <token>`
+    pub fn token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    pub fn handle(&mut self, request: &Request) -> Response {
+        self.metrics.api_requests.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "web-ui")]
+        if request.method == "GET"
+            && let Some(page) = web::asset(&request.path)
+        {
+            return page;
+        }
+        if let Some(token) = &self.token
+            && !authorized(request, token)
+        {
+            return Response::error(401, "Missing or wrong bearer token");
+        }
+
+        let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["openapi.json"]) => Response::json(200, &openapi()),
+            ("GET", ["metrics"]) => Response::metrics(&self.metrics),
+            ("GET", ["review"]) => match self.review(&request.params) {
+                Ok(review) => Response::json(200, &review),
+                Err(error) => Response::error(400, error),
+            },
+            ("GET", ["books"]) => match paginate(&self.books, BookEntry::cursor, &request.params) {
+                Ok(page) => Response::json(200, &page),
+                Err(error) => Response::error(400, error),
+            },
+            ("GET", ["clippings"]) => match self.list(&request.params) {
+                Ok(page) => Response::json(200, &page),
+                Err(error) => Response::error(400, error),
+            },
+            ("GET", ["clippings", id]) => match self.clippings.get(id) {
+                Some(clipping) => Response::json(200, &self.entry(clipping)),
+                None => Response::error(404, format!("No clipping with id {}", id)),
+            },
+            #[cfg(feature = "graphql")]
+            ("GET" | "POST", ["graphql"]) => self.graphql(request),
+            ("PUT", ["clippings", id, "favorite"]) => self.update(id, Update::Favorite(true)),
+            ("DELETE", ["clippings", id, "favorite"]) => self.update(id, Update::Favorite(false)),
+            ("PUT", ["clippings", id, "content"]) => {
+                match serde_json::from_slice::<Edit>(&request.body) {
+                    Ok(edit) => self.update(id, Update::Content(edit.content)),
+                    Err(error) => Response::error(400, format!("Invalid edit: {}", error)),
+                }
+            }
+            (_, ["openapi.json" | "metrics" | "review" | "books" | "clippings"])
+            | (_, ["clippings", _])
+            | (_, ["clippings", _, "favorite" | "content"]) => {
+                Response::error(405, format!("{} isn't allowed", request.method))
+            }
+            _ => Response::error(404, format!("Nothing at {}", request.path)),
+        }
+    }
+
+    /// Make `update` to the clipping shown with `id` and save it, as `star`,
+    /// `unstar` and `edit` do
+    fn update(&mut self, id: &str, update: Update) -> Response {
+        let Some(store) = self.store.as_mut() else {
+            return Response::error(403, "The server is read-only");
+        };
+        let Some(clipping) = self.clippings.get(id) else {
+            return Response::error(404, format!("No clipping with id {}", id));
+        };
+        let original = self
+            .originals
+            .get(id)
+            .cloned()
+            .unwrap_or_else(|| clipping.clone());
+
+        match update {
+            Update::Favorite(favorite) => {
+                store.set_favorite(&original.id(), favorite);
+                if favorite {
+                    self.starred.insert(id.to_string());
+                } else {
+                    self.starred.remove(id);
+                }
+            }
+            Update::Content(content) => {
+                let current = store
+                    .history(&original.id())
+                    .and_then(|history| history.current())
+                    .or(original.content.as_deref());
+                if current != Some(content.as_str()) {
+                    store.record_edit(&original, content.clone());
+                }
+                self.clippings.set_content(id, content);
+            }
+        }
+        if let Err(error) = store.save() {
+            return Response::error(500, error.to_string());
+        }
+        #[cfg(feature = "graphql")]
+        {
+            self.graphql = None;
+        }
+
+        let clipping = self.clippings.get(id).expect("found above");
+        Response::json(200, &self.entry(clipping))
+    }
+
+    /// The page of clippings matching the filters in `params`
+    fn list(&self, params: &BTreeMap<String, String>) -> Result<Page<ClippingEntry>, String> {
+        let query = filter(params, &self.starred)?;
+        let matching: Vec<&Clipping> = query.filter(&self.clippings);
+        let page = paginate(&matching, |clipping| clipping.id(), params)?;
+
+        Ok(Page {
+            items: page
+                .items
+                .into_iter()
+                .map(|clipping| self.entry(clipping))
+                .collect(),
+            next_cursor: page.next_cursor,
+        })
+    }
+
+    /// Answer a GraphQL query, from the body of a POST or the parameters
+    /// of a GET
+    #[cfg(feature = "graphql")]
+    fn graphql(&mut self, request: &Request) -> Response {
+        let query = if request.method == "POST" {
+            serde_json::from_slice(&request.body).map_err(|error| error.to_string())
+        } else {
+            graphql::from_params(&request.params)
+        };
+        let query = match query {
+            Ok(query) => query,
+            Err(error) => {
+                return Response::error(400, format!("Invalid GraphQL request: {}", error));
+            }
+        };
+
+        let schema = self
+            .graphql
+            .get_or_insert_with(|| graphql::schema(self.clippings.to_vec(), self.starred.clone()));
+        Response::json(200, &graphql::execute(schema, query))
+    }
+
+    /// The review of the `date` parameter, or of today
+    fn review(&self, params: &BTreeMap<String, String>) -> Result<Review, String> {
+        let date = match params.get("date") {
+            Some(date) => date
+                .parse::<NaiveDate>()
+                .map_err(|_| format!("Invalid value for date: {}", date))?,
+            None => Local::now().date_naive(),
+        };
+
+        let mut on_this_day: Vec<&Clipping> = self
+            .clippings
+            .iter()
+            .filter(|clipping| clipping.clipping_type.is_highlight())
+            .filter(|clipping| {
+                clipping.timestamp().is_some_and(|added| {
+                    (added.month(), added.day()) == (date.month(), date.day())
+                        && added.year() < date.year()
+                })
+            })
+            .collect();
+        on_this_day.sort_by_key(|clipping| std::cmp::Reverse(clipping.timestamp()));
+
+        Ok(Review {
+            quote: digest::daily_quote(&self.clippings, date).map(|clipping| self.entry(clipping)),
+            on_this_day: on_this_day
+                .into_iter()
+                .map(|clipping| self.entry(clipping))
+                .collect(),
+        })
+    }
+
+    fn entry(&self, clipping: &Clipping) -> ClippingEntry {
+        let id = clipping.id();
+        ClippingEntry {
+            favorite: self.starred.contains(&id),
+            id,
+            clipping: clipping.clone(),
+        }
+    }
+}
+
+/// The books of `clippings` as `/books` lists them
+pub(crate) fn books(clippings: &[Clipping]) -> Vec<BookEntry> {
+    Library::new(clippings.to_vec())
+        .books
+        .iter()
+        .map(|book| BookEntry {
+            title: book.title.clone(),
+            author: book.author.clone(),
+            clippings: book.clippings.len(),
+        })
+        .collect()
+}
+
+/// Whether `request` carries `token` as its bearer token
+fn authorized(request: &Request, token: &str) -> bool {
+    let Some(given) = request
+        .headers
+        .get("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    // Every byte is compared, so how long it takes doesn't tell how much
+    // of a guess was right
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |differ, (a, b)| differ | (a ^ b))
+            == 0
+}
+
+/// The query made from the filters in `params`; paging parameters are
+/// left alone and any other is an error
+pub(crate) fn filter(
+    params: &BTreeMap<String, String>,
+    starred: &HashSet<String>,
+) -> Result<ClippingQuery, String> {
+    let number = |name: &str, value: &str| {
+        value
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid value for {}: {}", name, value))
+    };
+    let date = |name: &str, value: &str| {
+        value
+            .parse::<NaiveDate>()
+            .map_err(|_| format!("Invalid value for {}: {}", name, value))
+    };
+
+    let mut query = ClippingQuery::new();
+    for (name, value) in params {
+        query = match name.as_str() {
+            "query" => query.and(value.parse()?),
+            "book" => query.book_contains(value.clone()),
+            "author" => query.author_contains(value.clone()),
+            "contains" => query.content_contains(value.clone()),
+            "type" => query.types(query::parse_types(value)?),
+            "color" => query.colors(query::parse_colors(value)?),
+            "since" => query.since(date(name, value)?),
+            "until" => query.until(date(name, value)?),
+            "min-length" => query.min_length(number(name, value)?),
+            "max-length" => query.max_length(number(name, value)?),
+            "device" => query.device_contains(value.clone()),
+            "favorites-only" if value == "true" => query.ids(starred.iter().cloned()),
+            "favorites-only" => query,
+            "limit" | "cursor" => query,
+            _ => return Err(format!("Unknown parameter: {}", name)),
+        };
+    }
+    Ok(query)
+}
+
+/// The items of `items` after the one whose `cursor` is the `cursor`
+/// parameter, as many as `limit`
+pub(crate) fn paginate<'a, T>(
+    items: &'a [T],
+    cursor: impl Fn(&T) -> String,
+    params: &BTreeMap<String, String>,
+) -> Result<Page<&'a T>, String> {
+    let limit = match params.get("limit") {
+        Some(limit) => match limit.parse::<usize>() {
+            Ok(limit) if limit > 0 => limit.min(MAX_PAGE_SIZE),
+            _ => return Err(format!("Invalid value for limit: {}", limit)),
+        },
+        None => DEFAULT_PAGE_SIZE,
+    };
+    let start = match params.get("cursor") {
+        Some(after) => {
+            items
+                .iter()
+                .position(|item| cursor(item) == *after)
+                .ok_or_else(|| format!("Unknown cursor: {}", after))?
+                + 1
+        }
+        None => 0,
+    };
+    let end = (start + limit).min(items.len());
+
+    Ok(Page {
+        items: items[start..end].iter().collect(),
+        next_cursor: (end < items.len()).then(|| cursor(&items[end - 1])),
+    })
+}
+
+/// OpenAPI description of the API, its schemas made from the types it
+/// answers with
+pub fn openapi() -> Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    let clipping = generator.subschema_for::<ClippingEntry>();
+    let clippings = generator.subschema_for::<Page<ClippingEntry>>();
+    let books = generator.subschema_for::<Page<BookEntry>>();
+    let error = generator.subschema_for::<ErrorBody>();
+    let content = generator.subschema_for::<Edit>();
+    let review = generator.subschema_for::<Review>();
+
+    let paging = [
+        json!({
+            "name": "limit",
+            "in": "query",
+            "description": format!("Items on the page, {} unless given, at most {}", DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE),
+            "schema": {"type": "integer", "minimum": 1},
+        }),
+        json!({
+            "name": "cursor",
+            "in": "query",
+            "description": "next_cursor of the page before",
+            "schema": {"type": "string"},
+        }),
+    ];
+    let filters = FILTERS.iter().map(|(name, description)| {
+        json!({
+            "name": name,
+            "in": "query",
+            "description": description,
+            "schema": {"type": "string"},
+        })
+    });
+    let answer = |description: &str, schema: &_| {
+        json!({
+            "description": description,
+            "content": {"application/json": {"schema": schema}},
+        })
+    };
+    let id = json!([{
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "schema": {"type": "string"},
+    }]);
+    let unauthorized = answer("No bearer token, or the wrong one", &error);
+    let change = |summary: &str| {
+        json!({
+            "summary": summary,
+            "parameters": id,
+            "responses": {
+                "200": answer("The clipping once changed", &clipping),
+                "401": unauthorized,
+                "403": answer("The server is read-only", &error),
+                "404": answer("No clipping has the id", &error),
+            },
+        })
+    };
+    let mut edit = change("Save new content for a clipping, as edit does");
+    edit["requestBody"] = json!({
+        "required": true,
+        "content": {"application/json": {"schema": content}},
+    });
+    edit["responses"]["400"] = answer("Content missing from the body", &error);
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "kindlr",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": {
+            "/metrics": {
+                "get": {
+                    "summary": "Counters of imports and requests, in the Prometheus text format",
+                    "responses": {
+                        "200": {
+                            "description": "The counters",
+                            "content": {"text/plain": {"schema": {"type": "string"}}},
+                        },
+                        "401": unauthorized,
+                    },
+                },
+            },
+            "/review": {
+                "get": {
+                    "summary": "The day's quote and highlights added on the day in earlier years",
+                    "parameters": [{
+                        "name": "date",
+                        "in": "query",
+                        "description": "Day to review, yyyy-mm-dd, today unless given",
+                        "schema": {"type": "string", "format": "date"},
+                    }],
+                    "responses": {
+                        "200": answer("The review", &review),
+                        "400": answer("An invalid date", &error),
+                        "401": unauthorized,
+                    },
+                },
+            },
+            "/books": {
+                "get": {
+                    "summary": "Books and how many clippings each has",
+                    "parameters": paging,
+                    "responses": {
+                        "200": answer("A page of books", &books),
+                        "400": answer("An invalid limit or cursor", &error),
+                        "401": unauthorized,
+                    },
+                },
+            },
+            "/clippings": {
+                "get": {
+                    "summary": "Clippings matching every filter given",
+                    "parameters": filters.chain(paging.clone()).collect::<Vec<_>>(),
+                    "responses": {
+                        "200": answer("A page of clippings", &clippings),
+                        "400": answer("An invalid filter, limit or cursor", &error),
+                        "401": unauthorized,
+                    },
+                },
+            },
+            "/clippings/{id}": {
+                "get": {
+                    "summary": "A clipping by the id list shows",
+                    "parameters": id,
+                    "responses": {
+                        "200": answer("The clipping", &clipping),
+                        "401": unauthorized,
+                        "404": answer("No clipping has the id", &error),
+                    },
+                },
+            },
+            "/clippings/{id}/favorite": {
+                "put": change("Star a clipping, as star does"),
+                "delete": change("Unstar a clipping, as unstar does"),
+            },
+            "/clippings/{id}/content": {
+                "put": edit,
+            },
+        },
+        "components": {
+            "schemas": generator.definitions(),
+            "securitySchemes": {
+                "bearer": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "Needed once the server is given a token, by KINDLR_SERVE_TOKEN or [serve] in config.toml",
+                },
+            },
+        },
+        "security": [{"bearer": []}, {}],
+    })
+}
+
+/// Answer requests on `listener` one after another, for as long as it is open
+pub fn serve(listener: &TcpListener, mut handle: impl FnMut(&Request) -> Response) {
+    for stream in listener.incoming() {
+        // A client that goes wrong doesn't stop the server
+        if let Err(error) = stream.and_then(|stream| respond(&stream, &mut handle)) {
+            eprintln!("{}", error);
+        }
+    }
+}
+
+/// Answer `/metrics` from `metrics` and nothing else, as `watch --listen`
+/// does, asking for `token` when there is one
+pub fn metrics_only(request: &Request, metrics: &Metrics, token: Option<&str>) -> Response {
+    if let Some(token) = token
+        && !authorized(request, token)
+    {
+        return Response::error(401, "Missing or wrong bearer token");
+    }
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/metrics") => Response::metrics(metrics),
+        (_, "/metrics") => Response::error(405, format!("{} isn't allowed", request.method)),
+        _ => Response::error(404, format!("Nothing at {}", request.path)),
+    }
+}
+
+/// A stream that fails reads once `deadline` has passed, however slowly
+/// the client trickles bytes in
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Client took too long to send its request",
+            ));
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        (&mut &*self.stream).read(buf)
+    }
+}
+
+fn respond(stream: &TcpStream, handle: &mut impl FnMut(&Request) -> Response) -> io::Result<()> {
+    let mut reader = BufReader::new(DeadlineReader {
+        stream,
+        deadline: Instant::now() + READ_TIMEOUT,
+    });
+    let response = match Request::read(&mut reader) {
+        Ok(Some(request)) => handle(&request),
+        Ok(None) => return Ok(()),
+        Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+            Response::error(400, error.to_string())
+        }
+        Err(error) => return Err(error),
+    };
+    response.write_to(&mut &*stream)
+}
+
+/// Reason phrase of `status`
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    const CLIPPINGS: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00
+
+Classic.
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Sunday, 5 January 2025 09:00:00
+
+Waste no more time.
+==========";
+
+    fn body(response: &Response) -> Value {
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_handle() {
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let starred = HashSet::from([clippings[2].id()]);
+        let mut api = Api::new(clippings.clone(), starred);
+
+        // Two pages of highlights, the second after the first's cursor
+        let first = api.handle(&Request::new("GET", "/clippings?type=highlight&limit=1"));
+        assert_eq!(first.status, 200);
+        let first = body(&first);
+        assert_eq!(first["items"][0]["content"], "Fear is the mind-killer.");
+        let cursor = first["next_cursor"].as_str().unwrap();
+        let second = body(&api.handle(&Request::new(
+            "GET",
+            &format!("/clippings?type=highlight&limit=1&cursor={}", cursor),
+        )));
+        assert_eq!(second["items"][0]["book_title"], "Meditations");
+        assert!(second["next_cursor"].is_null());
+
+        let starred = body(&api.handle(&Request::new("GET", "/clippings?favorites-only=true")));
+        assert_eq!(starred["items"].as_array().unwrap().len(), 1);
+        let dune = body(&api.handle(&Request::new(
+            "GET",
+            "/clippings?query=book%3Adune+type%3Anote",
+        )));
+        assert_eq!(dune["items"][0]["content"], "Classic.");
+
+        // The Dune highlight was added a year before, its note isn't a highlight
+        let review = body(&api.handle(&Request::new("GET", "/review?date=2025-01-01")));
+        assert!(review["quote"]["content"].is_string());
+        assert_eq!(review["on_this_day"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            review["on_this_day"][0]["content"],
+            "Fear is the mind-killer."
+        );
+        assert_eq!(
+            api.handle(&Request::new("GET", "/review?date=soon")).status,
+            400
+        );
+
+        let books = body(&api.handle(&Request::new("GET", "/books")));
+        assert_eq!(books["items"][0]["clippings"], 2);
+
+        let path = format!("/clippings/{}", clippings[2].id());
+        let meditations = body(&api.handle(&Request::new("GET", &path)));
+        assert_eq!(meditations["id"], clippings[2].id());
+        assert_eq!(meditations["favorite"], true);
+        assert_eq!(meditations["content"], "Waste no more time.");
+        assert_eq!(api.handle(&Request::new("GET", "/clippings/0")).status, 404);
+        assert_eq!(
+            api.handle(&Request::new("GET", "/clippings?color=red"))
+                .status,
+            400
+        );
+        assert_eq!(
+            api.handle(&Request::new("GET", "/clippings?cursor=gone"))
+                .status,
+            400
+        );
+        assert_eq!(api.handle(&Request::new("DELETE", "/books")).status, 405);
+
+        let metrics = api.handle(&Request::new("GET", "/metrics"));
+        let metrics = String::from_utf8(metrics.body).unwrap();
+        assert!(metrics.contains("\nkindlr_api_requests_total 13\n"));
+        assert!(metrics.contains("\nkindlr_clippings 3\n"));
+    }
+
+    #[test]
+    fn test_update() {
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let id = clippings[0].id();
+        let favorite = format!("/clippings/{}/favorite", id);
+        let mut edit = Request::new("PUT", &format!("/clippings/{}/content", id));
+        edit.body = br#"{"content": "Fear is the little-death."}"#.to_vec();
+
+        let mut read_only = Api::new(clippings.clone(), HashSet::new());
+        assert_eq!(
+            read_only.handle(&Request::new("PUT", &favorite)).status,
+            403
+        );
+        assert_eq!(read_only.handle(&edit).status, 403);
+
+        let path = std::env::temp_dir().join(format!("kindlr-serve-{}.json", std::process::id()));
+        let originals = HashMap::from([(id.clone(), clippings[0].clone())]);
+        let mut api = Api::new(clippings.clone(), HashSet::new())
+            .writable(Store::open_at(&path).unwrap(), originals)
+            .token("secret".to_string());
+
+        // Nothing is answered or changed without the token
+        assert_eq!(api.handle(&Request::new("PUT", &favorite)).status, 401);
+        let mut starring = Request::new("PUT", &favorite);
+        starring
+            .headers
+            .insert("authorization".to_string(), "Bearer wrong!".to_string());
+        assert_eq!(api.handle(&starring).status, 401);
+
+        starring
+            .headers
+            .insert("authorization".to_string(), "Bearer secret".to_string());
+        assert_eq!(body(&api.handle(&starring))["favorite"], true);
+        edit.headers = starring.headers.clone();
+        assert_eq!(
+            body(&api.handle(&edit))["content"],
+            "Fear is the little-death."
+        );
+        let store = Store::open_at(&path).unwrap();
+        assert!(store.is_favorite(&id));
+        assert_eq!(
+            store.history(&id).unwrap().original.as_deref(),
+            Some("Fear is the mind-killer.")
+        );
+
+        // GraphQL answers from the clippings as they are after a change
+        #[cfg(feature = "graphql")]
+        let graphql = |api: &mut Api| {
+            let mut query = Request::new("POST", "/graphql");
+            query.headers = starring.headers.clone();
+            query.body = br#"{"query": "{ clippings(first: 1) { items { favorite } } }"}"#.to_vec();
+            body(&api.handle(&query))["data"]["clippings"]["items"][0]["favorite"].clone()
+        };
+        #[cfg(feature = "graphql")]
+        assert_eq!(graphql(&mut api), true);
+
+        let mut unstarring = Request::new("DELETE", &favorite);
+        unstarring.headers = starring.headers.clone();
+        assert_eq!(body(&api.handle(&unstarring))["favorite"], false);
+        #[cfg(feature = "graphql")]
+        assert_eq!(graphql(&mut api), false);
+        assert!(!Store::open_at(&path).unwrap().is_favorite(&id));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_openapi() {
+        let document = openapi();
+        let parameters: Vec<&str> = document["paths"]["/clippings"]["get"]["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|parameter| parameter["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(parameters.len(), FILTERS.len() + 2);
+        assert!(parameters.contains(&"min-length") && parameters.contains(&"cursor"));
+
+        let schemas = &document["components"]["schemas"];
+        assert!(schemas["ClippingEntry"]["properties"]["book_title"].is_object());
+        assert_eq!(
+            document["paths"]["/clippings/{id}"]["get"]["responses"]["200"]["content"]["application/json"]
+                ["schema"]["$ref"],
+            "#/components/schemas/ClippingEntry"
+        );
+    }
+
+    #[test]
+    fn test_request() {
+        let mut reader =
+            "GET /clippings?book=dune%20messiah&limit=5 HTTP/1.1\r\nHost: localhost\r\n\r\n"
+                .as_bytes();
+        let request = Request::read(&mut reader).unwrap().unwrap();
+        assert_eq!(request.path, "/clippings");
+        assert_eq!(request.params["book"], "dune messiah");
+        assert_eq!(request.headers["host"], "localhost");
+        assert_eq!(Request::read(&mut "".as_bytes()).unwrap(), None);
+        assert!(Request::read(&mut "nonsense\r\n".as_bytes()).is_err());
+
+        // Headers past the limits are refused rather than read on
+        let long = format!("GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n", "a".repeat(1 << 20));
+        let error = Request::read(&mut long.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let many = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X: 1\r\n".repeat(MAX_HEADERS + 1)
+        );
+        let error = Request::read(&mut many.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut reader =
+            "PUT /clippings/1/content HTTP/1.1\r\nContent-Length: 4\r\n\r\n{}\r\n".as_bytes();
+        assert_eq!(Request::read(&mut reader).unwrap().unwrap().body, b"{}\r\n");
+
+        let mut out = Vec::new();
+        Response::error(404, "gone").write_to(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with(
+            "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: "
+        ));
+        let mut out = Vec::new();
+        Response::error(401, "who?").write_to(&mut out).unwrap();
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .contains("\r\nWWW-Authenticate: Bearer\r\n\r\n")
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::KindlrError;
+use crate::report::escape_html;
+
+/// Name of the systemd unit running `watch`
+const UNIT: &str = "kindlr-watch.service";
+
+/// Label of the launchd agent running `watch`
+const LABEL: &str = "com.kindlr.watch";
+
+/// `kindlr watch` as the service runs it: the program, its arguments, and
+/// the kindlr home directory it reads config.toml and the store from
+#[derive(Debug, PartialEq)]
+pub struct Service {
+    pub program: String,
+    pub args: Vec<String>,
+    pub home: String,
+}
+
+/// A command run to start the service
+#[derive(Debug, PartialEq)]
+pub struct Step {
+    pub args: Vec<String>,
+    /// Whether failing is expected, as when stopping a service that wasn't
+    /// running
+    pub may_fail: bool,
+}
+
+/// What keeps a user's services running
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Manager {
+    Systemd,
+    Launchd,
+}
+
+impl Manager {
+    /// launchd on macOS, systemd anywhere else
+    pub fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            Manager::Launchd
+        } else {
+            Manager::Systemd
+        }
+    }
+
+    /// Where the service is installed for the user
+    pub fn path(self) -> Result<PathBuf, KindlrError> {
+        let home = env::var_os("HOME").map(PathBuf::from).ok_or_else(|| {
+            KindlrError::Config("Cannot locate home directory to install into".to_string())
+        })?;
+
+        Ok(match self {
+            Manager::Systemd => env::var_os("XDG_CONFIG_HOME")
+                .map_or_else(|| home.join(".config"), PathBuf::from)
+                .join("systemd")
+                .join("user")
+                .join(UNIT),
+            Manager::Launchd => home
+                .join("Library")
+                .join("LaunchAgents")
+                .join(format!("{}.plist", LABEL)),
+        })
+    }
+
+    /// The systemd unit or launchd property list running `service`
+    pub fn definition(self, service: &Service) -> String {
+        match self {
+            Manager::Systemd => {
+                // Specifiers start with % anywhere, variables with $ in ExecStart
+                let quote = |text: &str| {
+                    let escaped = text
+                        .replace('\\', "\\\\")
+                        .replace('"', "\\\"")
+                        .replace('%', "%%");
+                    format!("\"{}\"", escaped)
+                };
+                let command: Vec<String> = std::iter::once(&service.program)
+                    .chain(&service.args)
+                    .map(|arg| quote(&arg.replace('$', "$$")))
+                    .collect();
+
+                format!(
+                    "[Unit]\n\
+                     Description=kindlr watch\n\
+                     \n\
+                     [Service]\n\
+                     ExecStart={}\n\
+                     Environment={}\n\
+                     Restart=on-failure\n\
+                     \n\
+                     [Install]\n\
+                     WantedBy=default.target\n",
+                    command.join(" "),
+                    quote(&format!("KINDLR_HOME={}", service.home))
+                )
+            }
+            Manager::Launchd => {
+                let arguments: String = std::iter::once(&service.program)
+                    .chain(&service.args)
+                    .map(|arg| format!("        <string>{}</string>\n", escape_html(arg)))
+                    .collect();
+                let home = escape_html(&service.home);
+
+                format!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                     <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+                     <plist version=\"1.0\">\n\
+                     <dict>\n\
+                     \x20   <key>Label</key>\n\
+                     \x20   <string>{LABEL}</string>\n\
+                     \x20   <key>ProgramArguments</key>\n\
+                     \x20   <array>\n\
+                     {arguments}\
+                     \x20   </array>\n\
+                     \x20   <key>EnvironmentVariables</key>\n\
+                     \x20   <dict>\n\
+                     \x20       <key>KINDLR_HOME</key>\n\
+                     \x20       <string>{home}</string>\n\
+                     \x20   </dict>\n\
+                     \x20   <key>RunAtLoad</key>\n\
+                     \x20   <true/>\n\
+                     \x20   <key>KeepAlive</key>\n\
+                     \x20   <true/>\n\
+                     \x20   <key>StandardErrorPath</key>\n\
+                     \x20   <string>{home}/watch.log</string>\n\
+                     </dict>\n\
+                     </plist>\n"
+                )
+            }
+        }
+    }
+
+    /// Commands that start the service installed at `path` now and at every
+    /// login, restarting it if it ran an earlier definition
+    pub fn start(self, path: &Path) -> Vec<Step> {
+        let step = |args: &[&str], may_fail: bool| Step {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            may_fail,
+        };
+        match self {
+            Manager::Systemd => vec![
+                step(&["systemctl", "--user", "daemon-reload"], false),
+                step(&["systemctl", "--user", "enable", UNIT], false),
+                step(&["systemctl", "--user", "restart", UNIT], false),
+            ],
+            Manager::Launchd => {
+                // launchd keeps a loaded agent on its old definition, so it's
+                // unloaded first, which fails when it wasn't loaded
+                let path = path.to_string_lossy();
+                vec![
+                    step(&["launchctl", "unload", &path], true),
+                    step(&["launchctl", "load", "-w", &path], false),
+                ]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_definition() {
+        let service = Service {
+            program: "/usr/local/bin/kindlr".to_string(),
+            args: vec![
+                "watch".to_string(),
+                "/media/Kindle/documents/My Clippings.txt".to_string(),
+                "--listen".to_string(),
+                "127.0.0.1:9187".to_string(),
+            ],
+            home: "/home/me/100% \"kindlr\"".to_string(),
+        };
+
+        let unit = Manager::Systemd.definition(&service);
+        assert!(unit.contains(
+            "\nExecStart=\"/usr/local/bin/kindlr\" \"watch\" \"/media/Kindle/documents/My Clippings.txt\" \"--listen\" \"127.0.0.1:9187\"\n"
+        ));
+        assert!(unit.contains("\nEnvironment=\"KINDLR_HOME=/home/me/100%% \\\"kindlr\\\"\"\n"));
+        assert!(unit.ends_with("[Install]\nWantedBy=default.target\n"));
+
+        let plist = Manager::Launchd.definition(&service);
+        assert!(
+            plist.contains("        <string>/media/Kindle/documents/My Clippings.txt</string>\n")
+        );
+        assert!(plist.contains("<string>/home/me/100% &quot;kindlr&quot;</string>"));
+        assert!(plist.contains("    <string>com.kindlr.watch</string>\n"));
+    }
+
+    #[test]
+    fn test_start_reloads_launchd_agent() {
+        let path = Path::new("/Users/me/Library/LaunchAgents/com.kindlr.watch.plist");
+        let steps = Manager::Launchd.start(path);
+        let commands: Vec<String> = steps.iter().map(|step| step.args.join(" ")).collect();
+
+        assert_eq!(
+            commands,
+            [
+                "launchctl unload /Users/me/Library/LaunchAgents/com.kindlr.watch.plist",
+                "launchctl load -w /Users/me/Library/LaunchAgents/com.kindlr.watch.plist",
+            ]
+        );
+        assert!(steps[0].may_fail);
+        assert!(!steps[1].may_fail);
+    }
+}
@@ -0,0 +1,242 @@
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use crate::group::{self, GroupBy, SortKey};
+use crate::parser::Clipping;
+use crate::query::ClippingQuery;
+use crate::stats::{self, Summary};
+
+/// Clippings together with what's derived from them, worked out once
+///
+/// A set can't be changed in place beyond a clipping's content, which none
+/// of it is derived from, so the ids, book index, date range and counts stay
+/// true to its clippings; filtering, sorting and grouping return new sets
+/// instead. It derefs to a slice of its clippings for everything else.
+#[derive(Debug)]
+pub struct ClippingSet {
+    clippings: Vec<Clipping>,
+    /// Where the first clipping with each id is
+    ids: HashMap<String, usize>,
+    /// Books by title and author, in the order they first appear
+    books: Vec<(String, String)>,
+    book_index: HashMap<(String, String), Vec<usize>>,
+    date_range: Option<(NaiveDateTime, NaiveDateTime)>,
+    summary: Summary,
+}
+
+impl ClippingSet {
+    pub fn new(clippings: Vec<Clipping>) -> Self {
+        let mut books = Vec::new();
+        let mut book_index: HashMap<(String, String), Vec<usize>> = HashMap::new();
+        for (i, clipping) in clippings.iter().enumerate() {
+            let key = (clipping.book_title.clone(), clipping.author.clone());
+            book_index
+                .entry(key)
+                .or_insert_with_key(|key| {
+                    books.push(key.clone());
+                    Vec::new()
+                })
+                .push(i);
+        }
+
+        let timestamps = clippings.iter().filter_map(Clipping::timestamp);
+        let date_range = timestamps.clone().min().zip(timestamps.max());
+
+        ClippingSet {
+            ids: ids(&clippings),
+            summary: stats::summary(&clippings),
+            books,
+            book_index,
+            date_range,
+            clippings,
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<Clipping> {
+        self.clippings
+    }
+
+    /// Whether a clipping with id `id` is in the set
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.contains_key(id)
+    }
+
+    /// The clipping with id `id`
+    pub fn get(&self, id: &str) -> Option<&Clipping> {
+        self.ids.get(id).map(|&i| &self.clippings[i])
+    }
+
+    /// Change the content of the clipping with id `id`, returning it, or
+    /// `None` if there's no such clipping
+    pub fn set_content(&mut self, id: &str, content: String) -> Option<&Clipping> {
+        let clipping = &mut self.clippings[*self.ids.get(id)?];
+        clipping.content = Some(content);
+        Some(clipping)
+    }
+
+    /// Titles and authors of the books clipped, in the order they first
+    /// appear
+    pub fn books(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.books
+            .iter()
+            .map(|(title, author)| (title.as_str(), author.as_str()))
+    }
+
+    /// Clippings of the book, in their order in the set
+    pub fn book(&self, title: &str, author: &str) -> Vec<&Clipping> {
+        self.book_index
+            .get(&(title.to_string(), author.to_string()))
+            .map_or_else(Vec::new, |indices| {
+                indices.iter().map(|&i| &self.clippings[i]).collect()
+            })
+    }
+
+    /// Earliest and latest datetimes, leaving out ones that can't be read
+    pub fn date_range(&self) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        self.date_range
+    }
+
+    /// Counts by type and of books
+    pub fn summary(&self) -> &Summary {
+        &self.summary
+    }
+
+    /// Clippings matching `query`
+    pub fn filter(&self, query: &ClippingQuery) -> ClippingSet {
+        query.filter(&self.clippings).into_iter().cloned().collect()
+    }
+
+    /// Clippings stably sorted by `keys`, see `group::sort`
+    pub fn sorted(&self, keys: &[SortKey]) -> ClippingSet {
+        let mut clippings = self.clippings.clone();
+        group::sort(&mut clippings, keys);
+        ClippingSet::new(clippings)
+    }
+
+    /// Sets of clippings sharing a book, author or month, ordered by key as
+    /// `group::group` orders them
+    pub fn group(&self, by: GroupBy) -> Vec<(String, ClippingSet)> {
+        group::group(&self.clippings, by)
+            .into_iter()
+            .map(|group| {
+                let set = group.clippings.into_iter().cloned().collect();
+                (group.key, set)
+            })
+            .collect()
+    }
+}
+
+/// Index of the first clipping with each id
+fn ids(clippings: &[Clipping]) -> HashMap<String, usize> {
+    let mut ids = HashMap::new();
+    for (i, clipping) in clippings.iter().enumerate() {
+        ids.entry(clipping.id()).or_insert(i);
+    }
+    ids
+}
+
+impl Default for ClippingSet {
+    fn default() -> Self {
+        ClippingSet::new(Vec::new())
+    }
+}
+
+impl Deref for ClippingSet {
+    type Target = [Clipping];
+
+    fn deref(&self) -> &[Clipping] {
+        &self.clippings
+    }
+}
+
+impl From<Vec<Clipping>> for ClippingSet {
+    fn from(clippings: Vec<Clipping>) -> Self {
+        ClippingSet::new(clippings)
+    }
+}
+
+impl FromIterator<Clipping> for ClippingSet {
+    fn from_iter<I: IntoIterator<Item = Clipping>>(iter: I) -> Self {
+        ClippingSet::new(iter.into_iter().collect())
+    }
+}
+
+impl<'a> IntoIterator for &'a ClippingSet {
+    type Item = &'a Clipping;
+    type IntoIter = std::slice::Iter<'a, Clipping>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.clippings.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{ClippingType, parse_clippings};
+
+    #[test]
+    fn test_clipping_set() {
+        let set = ClippingSet::new(
+            parse_clippings(
+                "\
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Sunday, 5 January 2025 09:00:00
+
+Waste no more time arguing about what a good man should be. Be one.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 2 | Location 20-21 | Added on Tuesday, 2 January 2024 10:00:00
+
+The spice must flow.
+==========
+Dune (Frank Herbert)
+- Your Bookmark on page 1 | Location 10 | Added on Wednesday, 3 January 2024 10:00:00
+
+
+==========",
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&set[1].id()));
+        assert_eq!(set.get(&set[1].id()).unwrap().location.start, 20);
+        assert!(set.get("0").is_none());
+        assert_eq!(
+            set.books().collect::<Vec<_>>(),
+            [
+                ("Meditations", "Marcus Aurelius"),
+                ("Dune", "Frank Herbert")
+            ]
+        );
+        assert_eq!(set.book("Dune", "Frank Herbert").len(), 2);
+        let (first, last) = set.date_range().unwrap();
+        assert_eq!(first.to_string(), "2024-01-02 10:00:00");
+        assert_eq!(last.to_string(), "2025-01-05 09:00:00");
+        assert_eq!((set.summary().highlights, set.summary().books), (2, 2));
+
+        let highlights = set.filter(&ClippingQuery::new().types([ClippingType::Highlight]));
+        assert_eq!(highlights.summary().bookmarks, 0);
+        assert!(!highlights.contains(&set[2].id()));
+
+        let sorted = set.sorted(&[SortKey::Location]);
+        assert_eq!(sorted[0].location.start, 10);
+        assert_eq!(set[0].location.start, 70);
+
+        let groups = set.group(GroupBy::Book);
+        assert_eq!(groups[0].0, "Dune");
+        assert_eq!(groups[0].1.date_range().unwrap().0, first);
+
+        let mut set = set;
+        let id = set[1].id();
+        let edited = set.set_content(&id, "He who controls the spice".to_string());
+        assert_eq!(
+            edited.unwrap().content.as_deref(),
+            Some("He who controls the spice")
+        );
+        assert!(set.contains(&id));
+        assert!(set.set_content("0", String::new()).is_none());
+    }
+}
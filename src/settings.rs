@@ -0,0 +1,596 @@
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::KindlrError;
+use crate::clock::Offset;
+use crate::dates::DateFormat;
+use crate::group::SortKey;
+use crate::lint::Severity;
+use crate::notes::FileTemplate;
+use crate::query::ClippingQuery;
+use crate::redact::NoteRedaction;
+use crate::timezone::Target;
+
+const SETTINGS_FILE: &str = "config.toml";
+
+/// User settings read from `<home>/config.toml`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    /// Saved searches by name
+    #[serde(default)]
+    pub collections: BTreeMap<String, Collection>,
+    /// External commands run at pipeline stages
+    #[serde(default)]
+    pub hooks: Hooks,
+    /// Where the enrich command looks books up
+    #[serde(default)]
+    pub enrich: Enrich,
+    #[serde(default)]
+    pub readwise: Readwise,
+    #[serde(default)]
+    pub notion: Notion,
+    /// URLs told about new clippings on import
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
+    #[serde(default)]
+    pub telegram: Telegram,
+    #[serde(default)]
+    pub hypothesis: Hypothesis,
+    #[serde(default)]
+    pub webdav: WebDav,
+    #[serde(default)]
+    pub dropbox: Dropbox,
+    #[serde(default)]
+    pub s3: S3,
+    #[serde(default)]
+    pub serve: Serve,
+    /// Book titles to show as another title, e.g.
+    ///
+    /// ```toml
+    /// [aliases]
+    /// "Dune: Deluxe Edition" = "Dune"
+    /// ```
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    /// Authors to show as another author, as `aliases` does for titles
+    #[serde(default, rename = "author-aliases")]
+    pub author_aliases: BTreeMap<String, String>,
+    /// Books never shown, such as manuals and dictionaries, e.g.
+    ///
+    /// ```toml
+    /// ignore_books = ["*User Guide*", "Dictionary*", "/^sample: /"]
+    /// ```
+    #[serde(default)]
+    pub ignore_books: Vec<BookPattern>,
+    /// How `list` and Markdown show dates, e.g.
+    ///
+    /// ```toml
+    /// date_format = "%d/%m/%Y"
+    /// ```
+    ///
+    /// See `dates::DateFormat` for the presets.
+    pub date_format: Option<DateFormat>,
+    /// Order of each book's clippings in exports unless `--sort` is given,
+    /// e.g. the order passages come in the book:
+    ///
+    /// ```toml
+    /// export_sort = ["location"]
+    /// ```
+    #[serde(default)]
+    pub export_sort: Vec<SortKey>,
+    /// Locations without a highlight that start a new section in
+    /// `export --sections`
+    pub section_gap: Option<u32>,
+    /// Names of the files exporting Markdown to a directory writes, e.g.
+    ///
+    /// ```toml
+    /// filename_template = "{author} - {title}.md"
+    /// ```
+    ///
+    /// See `notes::FileTemplate`.
+    pub filename_template: Option<FileTemplate>,
+    /// What `export --redact` leaves out
+    #[serde(default)]
+    pub redact: Redact,
+    /// Names for Kindles by serial number, e.g.
+    ///
+    /// ```toml
+    /// [devices]
+    /// G000PP1234567890 = "Paperwhite"
+    /// ```
+    #[serde(default)]
+    pub devices: BTreeMap<String, String>,
+    /// Kindle edition ASINs by book title, for links back to the book when
+    /// enrichment doesn't find them, e.g.
+    ///
+    /// ```toml
+    /// [asins]
+    /// Dune = "B00B7NPRY8"
+    /// ```
+    #[serde(default)]
+    pub asins: BTreeMap<String, String>,
+    /// Length of books in locations by title, for how far through the book
+    /// a highlight is, which is otherwise guessed from the furthest clipped
+    ///
+    /// ```toml
+    /// [lengths]
+    /// Dune = 10240
+    /// ```
+    #[serde(default)]
+    pub lengths: BTreeMap<String, u32>,
+    /// What `kindlr lint` checks
+    #[serde(default)]
+    pub lint: Lint,
+    /// Shifts for datetimes written while a Kindle's clock was wrong, see
+    /// `ClockRule`
+    #[serde(default)]
+    pub clock: Vec<ClockRule>,
+    /// Zone the Kindle's clock was set to, see `Timezone`
+    #[serde(default)]
+    pub timezone: Timezone,
+}
+
+/// A glob with `*` and `?`, or a regex between slashes, matching whole book
+/// titles regardless of case
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct BookPattern(Regex);
+
+impl BookPattern {
+    pub fn matches(&self, title: &str) -> bool {
+        self.0.is_match(title)
+    }
+}
+
+impl TryFrom<String> for BookPattern {
+    type Error = String;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        let regex = match pattern
+            .strip_prefix('/')
+            .and_then(|pattern| pattern.strip_suffix('/'))
+        {
+            Some(regex) => regex.to_string(),
+            None => {
+                let glob: String = pattern
+                    .split_inclusive(['*', '?'])
+                    .map(|part| match part.char_indices().last() {
+                        Some((i, '*')) => format!("{}.*", regex::escape(&part[..i])),
+                        Some((i, '?')) => format!("{}.", regex::escape(&part[..i])),
+                        _ => regex::escape(part),
+                    })
+                    .collect();
+                format!("^{}$", glob)
+            }
+        };
+
+        RegexBuilder::new(&regex)
+            .case_insensitive(true)
+            .build()
+            .map(BookPattern)
+            .map_err(|error| format!("invalid ignore_books pattern {:?}: {}", pattern, error))
+    }
+}
+
+/// A named query, e.g.
+///
+/// ```toml
+/// [collections.stoicism]
+/// query = 'author:"aurelius" OR content:/virtue/'
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Collection {
+    pub query: String,
+    pub description: Option<String>,
+}
+
+/// Shell commands to run at each stage, in order, e.g.
+///
+/// ```toml
+/// [hooks]
+/// post_parse = ["./enrich-isbn.py"]
+/// transform = ["jq '.content |= ascii_downcase'"]
+/// pre_export = ["./add-covers.sh"]
+/// ```
+///
+/// See `hooks::Pipeline` for what each command reads and writes.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    #[serde(default)]
+    pub post_parse: Vec<String>,
+    #[serde(default)]
+    pub transform: Vec<String>,
+    #[serde(default)]
+    pub pre_export: Vec<String>,
+}
+
+/// Metadata provider settings, e.g.
+///
+/// ```toml
+/// [enrich]
+/// provider = "google-books"
+/// api_key = "..."
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Enrich {
+    /// "openlibrary" (the default) or "google-books"
+    pub provider: Option<String>,
+    /// Sent to providers that take one, for a higher quota
+    pub api_key: Option<String>,
+}
+
+/// Readwise account for `kindlr push readwise`, e.g.
+///
+/// ```toml
+/// [readwise]
+/// token = "..."
+/// ```
+///
+/// The `READWISE_TOKEN` environment variable takes precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Readwise {
+    pub token: Option<String>,
+}
+
+/// Bearer token `kindlr serve` asks every request for, e.g.
+///
+/// ```toml
+/// [serve]
+/// token = "..."
+/// ```
+///
+/// The `KINDLR_SERVE_TOKEN` environment variable takes precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Serve {
+    pub token: Option<String>,
+}
+
+/// Notion integration for `kindlr push notion`, e.g.
+///
+/// ```toml
+/// [notion]
+/// token = "secret_..."
+/// database_id = "8f2b..."
+/// title_property = "Name"
+/// ```
+///
+/// The database must be shared with the integration. The `NOTION_TOKEN`
+/// environment variable takes precedence over `token`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Notion {
+    pub token: Option<String>,
+    pub database_id: Option<String>,
+    /// Title property of the database, "Name" unless renamed
+    pub title_property: Option<String>,
+}
+
+/// A URL new clippings are POSTed to, e.g.
+///
+/// ```toml
+/// [[webhooks]]
+/// url = "https://example.com/kindlr"
+/// secret = "..."
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    pub url: String,
+    /// Key for the HMAC-SHA256 signature in `X-Kindlr-Signature`
+    pub secret: Option<String>,
+}
+
+/// Telegram chat for `kindlr digest --channel telegram`, e.g.
+///
+/// ```toml
+/// [telegram]
+/// bot_token = "123456:ABC..."
+/// chat_id = "-1001234567890"
+/// ```
+///
+/// The `TELEGRAM_BOT_TOKEN` environment variable takes precedence over `bot_token`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Telegram {
+    pub bot_token: Option<String>,
+    pub chat_id: Option<String>,
+}
+
+/// Hypothes.is account for `kindlr push hypothesis`, e.g.
+///
+/// ```toml
+/// [hypothesis]
+/// token = "6879-..."
+/// username = "reader"
+/// ```
+///
+/// The `HYPOTHESIS_TOKEN` environment variable takes precedence over `token`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hypothesis {
+    pub token: Option<String>,
+    /// Account the annotations are kept private to
+    pub username: Option<String>,
+}
+
+/// Account for reading `webdav://` and `webdavs://` files, e.g.
+///
+/// ```toml
+/// [webdav]
+/// username = "reader"
+/// password = "..."
+/// ```
+///
+/// A user and password in the URL take precedence, and the `WEBDAV_PASSWORD`
+/// environment variable over `password`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebDav {
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Dropbox app for reading `dropbox:` files, e.g.
+///
+/// ```toml
+/// [dropbox]
+/// token = "sl.B..."
+/// ```
+///
+/// The `DROPBOX_TOKEN` environment variable takes precedence over `token`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dropbox {
+    pub token: Option<String>,
+}
+
+/// S3-compatible bucket for `kindlr archive`, e.g.
+///
+/// ```toml
+/// [s3]
+/// endpoint = "https://s3.eu-west-1.amazonaws.com"
+/// bucket = "reading"
+/// region = "eu-west-1"
+/// access_key_id = "AKIA..."
+/// secret_access_key = "..."
+/// ```
+///
+/// The `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY` environment variables
+/// take precedence over the keys.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct S3 {
+    pub endpoint: Option<String>,
+    pub bucket: Option<String>,
+    /// "us-east-1" unless set
+    pub region: Option<String>,
+    /// Folder archives go into, "kindlr/" unless set
+    pub prefix: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+}
+
+/// What `export --redact` leaves out, e.g.
+///
+/// ```toml
+/// [redact]
+/// private_books = ["Journal*"]
+/// notes = "hash"
+/// ```
+///
+/// Added datetimes are always cut to the day.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Redact {
+    /// Books left out entirely, as in `ignore_books`
+    #[serde(default)]
+    pub private_books: Vec<BookPattern>,
+    /// "omit" (the default) or "hash"
+    #[serde(default)]
+    pub notes: NoteRedaction,
+}
+
+/// Rules and thresholds for `kindlr lint`, e.g.
+///
+/// ```toml
+/// [lint]
+/// min_highlight_length = 20
+///
+/// [lint.rules]
+/// stale-bookmark = "off"
+/// overlap = "error"
+/// ```
+///
+/// Each rule is "off", "info", "warning" or "error"; see `lint::Rule` for
+/// the rules and their default severities.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lint {
+    #[serde(default)]
+    pub rules: BTreeMap<String, Severity>,
+    /// Highlights with fewer characters are reported
+    pub min_highlight_length: Option<usize>,
+    /// Bookmarks older than this are reported when nothing is highlighted
+    /// near them
+    pub stale_bookmark_days: Option<i64>,
+    /// How many locations away a highlight or note counts as near a bookmark
+    pub nearby_locations: Option<u32>,
+}
+
+/// Datetimes to shift, as written by a Kindle whose clock was off, e.g.
+///
+/// ```toml
+/// [[clock]]
+/// book = "Dune"
+/// since = "2023-01-01"
+/// until = "2023-06-30"
+/// offset = "P365D"
+/// ```
+///
+/// `book` is contained in the title, ignoring case, and `since` and `until`
+/// bound the dates as the Kindle wrote them; each is optional. The first
+/// rule about a clipping shifts it; see `clock::adjust`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClockRule {
+    pub book: Option<String>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    pub offset: Offset,
+}
+
+/// Zone the Kindle's clock was set to, and what to convert datetimes to,
+/// e.g.
+///
+/// ```toml
+/// [timezone]
+/// device = "Europe/Berlin"
+/// convert_to = "utc"
+///
+/// [[timezone.travel]]
+/// since = "2024-07-08"
+/// until = "2024-07-14"
+/// zone = "Asia/Tokyo"
+/// ```
+///
+/// Zones are IANA names; `convert_to` may also be "local" or "utc". Without
+/// `device`, datetimes outside any trip are left as the Kindle wrote them;
+/// see `timezone::annotate`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Timezone {
+    pub device: Option<Tz>,
+    /// Zones the Kindle was set to for a while instead of `device`
+    #[serde(default)]
+    pub travel: Vec<Trip>,
+    pub convert_to: Option<Target>,
+}
+
+/// Days, as the Kindle wrote them, it was set to `zone`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Trip {
+    pub since: NaiveDate,
+    pub until: NaiveDate,
+    pub zone: Tz,
+}
+
+impl Settings {
+    /// Settings in `home`, or the defaults when there is no config file
+    pub fn load(home: &Path) -> Result<Self, KindlrError> {
+        let path = home.join(SETTINGS_FILE);
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).map_err(|error| {
+                KindlrError::Config(format!("Invalid {}: {}", path.display(), error))
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Whether `title` matches any of `ignore_books`
+    pub fn is_ignored(&self, title: &str) -> bool {
+        self.ignore_books
+            .iter()
+            .any(|pattern| pattern.matches(title))
+    }
+
+    /// Compiled query of the collection called `name`
+    pub fn collection(&self, name: &str) -> Result<ClippingQuery, KindlrError> {
+        let collection = self
+            .collections
+            .get(name)
+            .ok_or_else(|| KindlrError::NotFound(format!("No collection named {}", name)))?;
+
+        collection.query.parse().map_err(|error| {
+            KindlrError::Config(format!("Invalid query for collection {}: {}", name, error))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    #[test]
+    fn test_collections() {
+        let home = std::env::temp_dir().join(format!("kindlr-settings-{}", std::process::id()));
+        fs::create_dir_all(&home).unwrap();
+        assert!(Settings::load(&home).unwrap().collections.is_empty());
+
+        fs::write(
+            home.join(SETTINGS_FILE),
+            r#"
+[collections.stoicism]
+query = 'author:"aurelius" OR content:/virtue/'
+description = "Stoic reading"
+
+[collections.broken]
+query = 'color:red'
+"#,
+        )
+        .unwrap();
+        let settings = Settings::load(&home).unwrap();
+        fs::remove_dir_all(&home).unwrap();
+
+        let clippings = parse_clippings(
+            "\
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Sunday, 5 January 2025 09:00:00
+
+Waste no more time arguing about what a good man should be. Be one.
+==========
+Ethics (Aristotle)
+- Your Highlight on page 9 | Location 90-91 | Added on Sunday, 5 January 2025 10:00:00
+
+Virtue is a state of character.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========",
+        )
+        .unwrap();
+
+        let stoicism = settings.collection("stoicism").unwrap();
+        assert_eq!(stoicism.filter(&clippings).len(), 2);
+        assert!(matches!(
+            settings.collection("broken"),
+            Err(KindlrError::Config(_))
+        ));
+        assert!(matches!(
+            settings.collection("missing"),
+            Err(KindlrError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_ignore_books() {
+        let settings: Settings =
+            toml::from_str(r#"ignore_books = ["*User Guide*", "Dictionary?", "/^sample: /"]"#)
+                .unwrap();
+
+        assert!(settings.is_ignored("Kindle User Guide (5th edition)"));
+        assert!(settings.is_ignored("dictionary1"));
+        assert!(settings.is_ignored("Sample: Dune"));
+        assert!(!settings.is_ignored("Oxford Dictionary of English"));
+        assert!(!settings.is_ignored("Dune"));
+
+        assert!(toml::from_str::<Settings>(r#"ignore_books = ["/(/"]"#).is_err());
+    }
+}
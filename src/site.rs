@@ -0,0 +1,222 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::KindlrError;
+use crate::export::{Exporter, JsonExporter, markdown_entry};
+use crate::library::{Book, Library};
+use crate::notes::FileTemplate;
+use crate::parser::ClippingType;
+use crate::plan::{Change, Plan};
+
+/// Names of book pages unless `--filename-template` is given
+pub const DEFAULT_PAGE_TEMPLATE: &str = "{title_slug}.md";
+
+/// Static site generator an export is laid out for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Generator {
+    Hugo,
+    Jekyll,
+}
+
+impl FromStr for Generator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hugo" => Ok(Generator::Hugo),
+            "jekyll" => Ok(Generator::Jekyll),
+            _ => Err(format!("Invalid site: {}, expected hugo or jekyll", s)),
+        }
+    }
+}
+
+impl Generator {
+    /// Directory of the book pages within the site
+    fn content_dir(self) -> &'static str {
+        match self {
+            Generator::Hugo => "content/books",
+            Generator::Jekyll => "_books",
+        }
+    }
+
+    /// Directory of data files within the site
+    fn data_dir(self) -> &'static str {
+        match self {
+            Generator::Hugo => "data",
+            Generator::Jekyll => "_data",
+        }
+    }
+}
+
+/// What a book's page tells the theme about it
+#[derive(Debug, Serialize)]
+struct FrontMatter<'a> {
+    title: &'a str,
+    author: &'a str,
+    /// When the first clipping was made
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date: Option<String>,
+    /// When the last clipping was made, named as Hugo names it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lastmod: Option<String>,
+    /// When the last clipping was made, named as Jekyll names it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_modified_at: Option<String>,
+    quotes: usize,
+    notes: usize,
+    /// Jekyll's layout for pages of the books collection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    layout: Option<&'a str>,
+}
+
+/// Write a page per book into the content directory of the site at `root`,
+/// named by `template`, and every book as `clippings.json` into its data
+/// directory through `plan`, returning how many pages were written
+///
+/// Pages are written over on every export, so they're best left to kindlr
+/// and styled by the theme.
+pub fn write(
+    root: &Path,
+    library: &Library,
+    generator: Generator,
+    template: &FileTemplate,
+    plan: &mut Plan,
+) -> Result<usize, KindlrError> {
+    let content = root.join(generator.content_dir());
+    let mut taken = HashSet::new();
+    for book in &library.books {
+        let path = content.join(template.unique_path(book, &mut taken));
+        let page = page(book, library, generator)?;
+        let change = Change::File {
+            path: path.display().to_string(),
+            action: format!("write page of {} clippings", book.clippings.len()),
+        };
+        plan.apply(change, || {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            Ok(fs::write(&path, page)?)
+        })?;
+    }
+
+    let data = root.join(generator.data_dir());
+    let change = Change::File {
+        path: data.join("clippings.json").display().to_string(),
+        action: format!("write {} books", library.books.len()),
+    };
+    plan.apply(change, || {
+        fs::create_dir_all(&data)?;
+        let mut file = BufWriter::new(fs::File::create(data.join("clippings.json"))?);
+        JsonExporter.export(library, &mut file)?;
+        Ok(file.flush()?)
+    })?;
+    Ok(library.books.len())
+}
+
+/// The page of `book`, its front matter and then its highlights and notes
+fn page(book: &Book, library: &Library, generator: Generator) -> Result<String, KindlrError> {
+    let timestamps = || {
+        book.clippings
+            .iter()
+            .filter_map(|clipping| clipping.timestamp())
+    };
+    let format =
+        |timestamp: chrono::NaiveDateTime| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string();
+    let (first, last) = (
+        timestamps().min().map(format),
+        timestamps().max().map(format),
+    );
+    let count = |clipping_type: ClippingType| {
+        book.clippings
+            .iter()
+            .filter(|clipping| clipping.clipping_type == clipping_type)
+            .count()
+    };
+
+    let hugo = generator == Generator::Hugo;
+    let front_matter = FrontMatter {
+        title: &book.title,
+        author: &book.author,
+        date: first,
+        lastmod: last.clone().filter(|_| hugo),
+        last_modified_at: last.filter(|_| !hugo),
+        quotes: count(ClippingType::Highlight) + count(ClippingType::ArticleClip),
+        notes: count(ClippingType::Note),
+        layout: (!hugo).then_some("book"),
+    };
+    let yaml = serde_yaml::to_string(&front_matter)
+        .map_err(|error| KindlrError::Io(std::io::Error::other(error)))?;
+
+    let entries: Vec<String> = book
+        .clippings
+        .iter()
+        .filter_map(|clipping| markdown_entry(book, clipping, &library.date_format))
+        .collect();
+    Ok(format!("---\n{}---\n\n{}", yaml, entries.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    #[test]
+    fn test_write() {
+        let library = Library::new(
+            parse_clippings(
+                "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Tuesday, 2 January 2024 10:05:00
+
+Classic.
+==========",
+            )
+            .unwrap(),
+        );
+        let root = std::env::temp_dir().join(format!("kindlr-site-{}", std::process::id()));
+        let template = DEFAULT_PAGE_TEMPLATE.parse().unwrap();
+
+        assert_eq!(
+            write(
+                &root,
+                &library,
+                Generator::Hugo,
+                &template,
+                &mut Plan::default()
+            )
+            .unwrap(),
+            1
+        );
+        write(
+            &root,
+            &library,
+            Generator::Jekyll,
+            &template,
+            &mut Plan::default(),
+        )
+        .unwrap();
+        let hugo = fs::read_to_string(root.join("content/books/dune.md")).unwrap();
+        let jekyll = fs::read_to_string(root.join("_books/dune.md")).unwrap();
+        let data = fs::read_to_string(root.join("_data/clippings.json")).unwrap();
+        assert!(root.join("data/clippings.json").exists());
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(hugo.starts_with(
+            "---\ntitle: Dune\nauthor: Frank Herbert\ndate: 2024-01-01T10:00:00\n\
+             lastmod: 2024-01-02T10:05:00\nquotes: 1\nnotes: 1\n---\n\n> Fear is the mind-killer.\n"
+        ));
+        assert!(hugo.ends_with("\n**Note:** Classic.\n"));
+        assert!(jekyll.contains("\nlast_modified_at: 2024-01-02T10:05:00\n"));
+        assert!(jekyll.contains("\nlayout: book\n---\n"));
+        assert!(data.contains("\"title\": \"Dune\""));
+    }
+}
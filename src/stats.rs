@@ -0,0 +1,1038 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+
+use crate::goodreads;
+use crate::languages::ENGLISH;
+use crate::library::Library;
+use crate::parser::{self, Clipping, ClippingType};
+
+/// Overall counts for a set of clippings
+#[derive(Debug, PartialEq, Serialize)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+pub struct Summary {
+    pub total: usize,
+    pub highlights: usize,
+    pub notes: usize,
+    pub bookmarks: usize,
+    pub books: usize,
+}
+
+pub fn summary(clippings: &[Clipping]) -> Summary {
+    let count = |clipping_type: ClippingType| {
+        clippings
+            .iter()
+            .filter(|clipping| clipping.clipping_type == clipping_type)
+            .count()
+    };
+
+    Summary {
+        total: clippings.len(),
+        highlights: count(ClippingType::Highlight) + count(ClippingType::ArticleClip),
+        notes: count(ClippingType::Note),
+        bookmarks: count(ClippingType::Bookmark),
+        books: clippings
+            .iter()
+            .map(|clipping| (&clipping.book_title, &clipping.author))
+            .collect::<BTreeSet<_>>()
+            .len(),
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Clippings: {}\nHighlights: {}\nNotes: {}\nBookmarks: {}\nBooks: {}",
+            self.total, self.highlights, self.notes, self.bookmarks, self.books
+        )
+    }
+}
+
+/// How regularly clippings are made
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Cadence {
+    /// Consecutive days with clippings ending today or yesterday
+    pub current_streak: usize,
+    pub longest_streak: usize,
+    pub active_days: usize,
+    pub average_per_active_day: f64,
+    /// Clippings per weekday, Monday first
+    pub by_weekday: [usize; 7],
+    /// Clippings per hour of the day
+    pub by_hour: [usize; 24],
+}
+
+pub fn cadence(clippings: &[Clipping], today: NaiveDate) -> Cadence {
+    let timestamps: Vec<NaiveDateTime> = clippings
+        .iter()
+        .filter_map(|clipping| clipping.timestamp())
+        .collect();
+    let dates: BTreeSet<NaiveDate> = timestamps
+        .iter()
+        .map(|timestamp| timestamp.date())
+        .collect();
+
+    let mut by_weekday = [0; 7];
+    let mut by_hour = [0; 24];
+    for timestamp in &timestamps {
+        by_weekday[timestamp.weekday().num_days_from_monday() as usize] += 1;
+        by_hour[timestamp.hour() as usize] += 1;
+    }
+
+    let mut longest_streak = 0;
+    let mut streak = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &date in &dates {
+        streak = match previous {
+            Some(previous) if previous.succ_opt() == Some(date) => streak + 1,
+            _ => 1,
+        };
+        longest_streak = longest_streak.max(streak);
+        previous = Some(date);
+    }
+
+    // A streak is still current if the last active day was today or yesterday
+    let current_streak = match previous {
+        Some(last) if last == today || last.succ_opt() == Some(today) => streak,
+        _ => 0,
+    };
+
+    Cadence {
+        current_streak,
+        longest_streak,
+        active_days: dates.len(),
+        average_per_active_day: if dates.is_empty() {
+            0.0
+        } else {
+            timestamps.len() as f64 / dates.len() as f64
+        },
+        by_weekday,
+        by_hour,
+    }
+}
+
+impl fmt::Display for Cadence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Active days: {}", self.active_days)?;
+        writeln!(
+            f,
+            "Clippings per active day: {:.1}",
+            self.average_per_active_day
+        )?;
+        writeln!(f, "Current streak: {}", days(self.current_streak))?;
+        write!(f, "Longest streak: {}", days(self.longest_streak))?;
+
+        let max = self.by_weekday.iter().max().copied().unwrap_or(0).max(1);
+        writeln!(f, "\n\nBy weekday:")?;
+        for (names, count) in ENGLISH.weekdays.iter().zip(self.by_weekday) {
+            let line = format!(
+                "{:<10} {:>4} {}",
+                names[0],
+                count,
+                "#".repeat(count * 30 / max)
+            );
+            writeln!(f, "{}", line.trim_end())?;
+        }
+
+        let max = self.by_hour.iter().max().copied().unwrap_or(0).max(1);
+        write!(f, "\nBy hour:")?;
+        for (hour, count) in self.by_hour.iter().enumerate() {
+            if *count > 0 {
+                write!(
+                    f,
+                    "\n{:02}:00      {:>4} {}",
+                    hour,
+                    count,
+                    "#".repeat(count * 30 / max)
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Clippings added on a single day
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Day {
+    pub date: NaiveDate,
+    pub count: usize,
+}
+
+/// Clippings per day over a calendar year
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Heatmap {
+    pub year: i32,
+    pub max: usize,
+    pub days: Vec<Day>,
+}
+
+/// Count clippings for every day of `year`, including days without any
+pub fn heatmap(clippings: &[Clipping], year: i32) -> Heatmap {
+    let mut counts = BTreeMap::new();
+    for date in clippings
+        .iter()
+        .filter_map(|clipping| clipping.timestamp())
+        .map(|timestamp| timestamp.date())
+        .filter(|date| date.year() == year)
+    {
+        *counts.entry(date).or_insert(0) += 1;
+    }
+
+    let days: Vec<Day> = NaiveDate::from_ymd_opt(year, 1, 1)
+        .into_iter()
+        .flat_map(|first| first.iter_days())
+        .take_while(|date| date.year() == year)
+        .map(|date| Day {
+            date,
+            count: counts.get(&date).copied().unwrap_or(0),
+        })
+        .collect();
+
+    Heatmap {
+        year,
+        max: days.iter().map(|day| day.count).max().unwrap_or(0),
+        days,
+    }
+}
+
+/// Most recent year that has clippings
+pub fn latest_year(clippings: &[Clipping]) -> Option<i32> {
+    clippings
+        .iter()
+        .filter_map(|clipping| clipping.timestamp())
+        .map(|timestamp| timestamp.year())
+        .max()
+}
+
+impl Heatmap {
+    /// Intensity from 0 (no clippings) to 4 (busiest day)
+    pub fn level(&self, count: usize) -> usize {
+        if count == 0 || self.max == 0 {
+            0
+        } else {
+            (count * 4).div_ceil(self.max)
+        }
+    }
+
+    /// Render as a week-per-column calendar, in ANSI colors when `color`
+    /// and otherwise in shades of plain characters
+    pub fn render(&self, color: bool) -> String {
+        const COLORS: [u8; 5] = [237, 22, 28, 34, 40];
+        const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+        const LABELS: [&str; 7] = ["Mon", "", "Wed", "", "Fri", "", "Sun"];
+
+        let Some(first) = self.days.first() else {
+            return String::new();
+        };
+        let offset = first.date.weekday().num_days_from_monday() as usize;
+        let weeks = (self.days.len() + offset).div_ceil(7);
+
+        let mut grid = vec![vec![None; weeks]; 7];
+        let mut months = vec![' '; weeks * 2];
+        for (i, day) in self.days.iter().enumerate() {
+            let week = (i + offset) / 7;
+            grid[(i + offset) % 7][week] = Some(self.level(day.count));
+
+            if day.date.day() == 1 {
+                let name = day.date.format("%b").to_string();
+                for (j, c) in name.chars().enumerate() {
+                    if let Some(slot) = months.get_mut(week * 2 + j) {
+                        *slot = c;
+                    }
+                }
+            }
+        }
+
+        let mut output = String::new();
+        let _ = writeln!(
+            output,
+            "    {}",
+            months.iter().collect::<String>().trim_end()
+        );
+        for (row, label) in grid.iter().zip(LABELS) {
+            let _ = write!(output, "{:<4}", label);
+            for cell in row {
+                match cell {
+                    Some(level) if color => {
+                        let _ = write!(output, "\x1b[38;5;{}m■\x1b[0m ", COLORS[*level]);
+                    }
+                    Some(level) => {
+                        let _ = write!(output, "{} ", SHADES[*level]);
+                    }
+                    None => output.push_str("  "),
+                }
+            }
+            output.truncate(output.trim_end_matches(' ').len());
+            output.push('\n');
+        }
+
+        let active = self.days.iter().filter(|day| day.count > 0).count();
+        let total: usize = self.days.iter().map(|day| day.count).sum();
+        let _ = write!(
+            output,
+            "{} clippings on {} in {}",
+            total,
+            days(active),
+            self.year
+        );
+
+        output
+    }
+}
+
+/// `count` days, as "1 day" or "3 days"
+fn days(count: usize) -> String {
+    counted(count, "day")
+}
+
+/// `count` of `noun`, as "1 highlight" or "3 highlights"
+fn counted(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+/// Clippings aggregated across every book by one author
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AuthorStats {
+    pub author: String,
+    pub books: usize,
+    pub clippings: usize,
+    pub highlights: usize,
+    pub notes: usize,
+    pub bookmarks: usize,
+    pub first: Option<NaiveDateTime>,
+    pub last: Option<NaiveDateTime>,
+}
+
+impl fmt::Display for AuthorStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} clippings in {} books ({} highlights, {} notes, {} bookmarks)",
+            self.author, self.clippings, self.books, self.highlights, self.notes, self.bookmarks
+        )?;
+
+        if let (Some(first), Some(last)) = (self.first, self.last) {
+            write!(
+                f,
+                ", {} to {}",
+                first.format("%Y-%m-%d"),
+                last.format("%Y-%m-%d")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Aggregate clippings by normalized author, most clipped author first
+pub fn by_author(clippings: &[Clipping]) -> Vec<AuthorStats> {
+    let mut authors: BTreeMap<String, (AuthorStats, BTreeSet<&str>)> = BTreeMap::new();
+
+    for clipping in clippings {
+        let author = parser::normalize_author(&clipping.author);
+        let (stats, books) = authors.entry(author.to_lowercase()).or_insert_with(|| {
+            (
+                AuthorStats {
+                    author,
+                    books: 0,
+                    clippings: 0,
+                    highlights: 0,
+                    notes: 0,
+                    bookmarks: 0,
+                    first: None,
+                    last: None,
+                },
+                BTreeSet::new(),
+            )
+        });
+
+        books.insert(&clipping.book_title);
+        stats.clippings += 1;
+        match clipping.clipping_type {
+            ClippingType::Highlight | ClippingType::ArticleClip => stats.highlights += 1,
+            ClippingType::Note => stats.notes += 1,
+            ClippingType::Bookmark => stats.bookmarks += 1,
+        }
+
+        if let Some(timestamp) = clipping.timestamp() {
+            stats.first = Some(stats.first.map_or(timestamp, |first| first.min(timestamp)));
+            stats.last = Some(stats.last.map_or(timestamp, |last| last.max(timestamp)));
+        }
+    }
+
+    let mut authors: Vec<AuthorStats> = authors
+        .into_values()
+        .map(|(mut stats, books)| {
+            stats.books = books.len();
+            stats
+        })
+        .collect();
+
+    authors.sort_by(|a, b| {
+        b.clippings
+            .cmp(&a.clippings)
+            .then_with(|| a.author.cmp(&b.author))
+    });
+    authors
+}
+
+/// Clippings read from one device
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DeviceStats {
+    /// Label or serial, "Unknown" for clippings from no known device
+    pub device: String,
+    pub clippings: usize,
+    pub books: usize,
+    pub first: Option<NaiveDateTime>,
+    pub last: Option<NaiveDateTime>,
+}
+
+impl fmt::Display for DeviceStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} clippings in {} books",
+            self.device, self.clippings, self.books
+        )?;
+
+        if let (Some(first), Some(last)) = (self.first, self.last) {
+            write!(
+                f,
+                ", {} to {}",
+                first.format("%Y-%m-%d"),
+                last.format("%Y-%m-%d")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Aggregate clippings by the device they were read from, most clipped first
+pub fn by_device(clippings: &[Clipping]) -> Vec<DeviceStats> {
+    let mut devices: BTreeMap<&str, (DeviceStats, BTreeSet<&str>)> = BTreeMap::new();
+
+    for clipping in clippings {
+        let device = clipping.device.as_deref().unwrap_or("Unknown");
+        let (stats, books) = devices.entry(device).or_insert_with(|| {
+            (
+                DeviceStats {
+                    device: device.to_string(),
+                    clippings: 0,
+                    books: 0,
+                    first: None,
+                    last: None,
+                },
+                BTreeSet::new(),
+            )
+        });
+
+        books.insert(&clipping.book_title);
+        stats.clippings += 1;
+        if let Some(timestamp) = clipping.timestamp() {
+            stats.first = Some(stats.first.map_or(timestamp, |first| first.min(timestamp)));
+            stats.last = Some(stats.last.map_or(timestamp, |last| last.max(timestamp)));
+        }
+    }
+
+    let mut devices: Vec<DeviceStats> = devices
+        .into_values()
+        .map(|(mut stats, books)| {
+            stats.books = books.len();
+            stats
+        })
+        .collect();
+
+    devices.sort_by_key(|device| std::cmp::Reverse(device.clippings));
+    devices
+}
+
+/// A rated book and how much of it was clipped
+#[derive(Debug, PartialEq, Serialize)]
+pub struct RatedBook {
+    pub title: String,
+    pub author: String,
+    pub rating: u8,
+    pub clippings: usize,
+    pub highlights: usize,
+}
+
+impl fmt::Display for RatedBook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} by {}: {} ({})",
+            goodreads::stars(self.rating),
+            self.title,
+            self.author,
+            counted(self.clippings, "clipping"),
+            counted(self.highlights, "highlight")
+        )
+    }
+}
+
+/// Rated books, highest rated first and most clipped first within a rating
+pub fn by_rating(library: &Library) -> Vec<RatedBook> {
+    let mut books: Vec<RatedBook> = library
+        .books
+        .iter()
+        .filter_map(|book| {
+            Some(RatedBook {
+                title: book.title.clone(),
+                author: book.author.clone(),
+                rating: book.rating?,
+                clippings: book.clippings.len(),
+                highlights: book
+                    .clippings
+                    .iter()
+                    .filter(|clipping| clipping.clipping_type.is_highlight())
+                    .count(),
+            })
+        })
+        .collect();
+
+    books.sort_by(|a, b| {
+        b.rating
+            .cmp(&a.rating)
+            .then_with(|| b.clippings.cmp(&a.clippings))
+            .then_with(|| a.title.cmp(&b.title))
+    });
+    books
+}
+
+/// Upper bounds, in characters, of the highlight length histogram buckets
+const LENGTH_BUCKETS: [usize; 5] = [50, 100, 200, 400, 800];
+
+/// Text Kindle puts in place of content past the publisher's clipping limit
+const CLIPPING_LIMIT_MESSAGE: &str = "You have reached the clipping limit";
+
+/// Highlights of at most `up_to` characters (unbounded for the last bucket)
+#[derive(Debug, PartialEq, Serialize)]
+pub struct LengthBucket {
+    pub up_to: Option<usize>,
+    pub count: usize,
+}
+
+/// A highlight that appears to have been cut off
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TruncatedHighlight {
+    pub id: String,
+    pub book_title: String,
+    pub location: String,
+    pub content: String,
+}
+
+/// Distribution of highlight lengths in characters
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Lengths {
+    pub count: usize,
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    pub median: usize,
+    pub buckets: Vec<LengthBucket>,
+    pub truncated: Vec<TruncatedHighlight>,
+}
+
+/// Whether highlight content looks cut off by the Kindle: it hit the
+/// clipping limit or stops mid-word or mid-sentence
+pub fn looks_truncated(content: &str) -> bool {
+    let content = content.trim_end();
+
+    if content.contains(CLIPPING_LIMIT_MESSAGE) {
+        return true;
+    }
+
+    match content.chars().last() {
+        Some(last) => last.is_alphanumeric() || matches!(last, ',' | ';' | ':' | '-' | '('),
+        None => false,
+    }
+}
+
+pub fn lengths(clippings: &[Clipping]) -> Lengths {
+    let highlights: Vec<(&Clipping, usize)> = clippings
+        .iter()
+        .filter(|clipping| clipping.clipping_type.is_highlight())
+        .filter_map(|clipping| {
+            let content = clipping.content.as_deref()?;
+            Some((clipping, content.chars().count()))
+        })
+        .collect();
+
+    let mut sorted: Vec<usize> = highlights.iter().map(|(_, length)| *length).collect();
+    sorted.sort_unstable();
+
+    let mut buckets: Vec<LengthBucket> = LENGTH_BUCKETS
+        .iter()
+        .map(|&up_to| Some(up_to))
+        .chain([None])
+        .map(|up_to| LengthBucket { up_to, count: 0 })
+        .collect();
+    for length in &sorted {
+        let index = LENGTH_BUCKETS
+            .iter()
+            .position(|up_to| length <= up_to)
+            .unwrap_or(LENGTH_BUCKETS.len());
+        buckets[index].count += 1;
+    }
+
+    Lengths {
+        count: sorted.len(),
+        min: sorted.first().copied().unwrap_or(0),
+        max: sorted.last().copied().unwrap_or(0),
+        mean: if sorted.is_empty() {
+            0.0
+        } else {
+            sorted.iter().sum::<usize>() as f64 / sorted.len() as f64
+        },
+        median: sorted.get(sorted.len() / 2).copied().unwrap_or(0),
+        buckets,
+        truncated: highlights
+            .iter()
+            .filter_map(|(clipping, _)| {
+                let content = clipping.content.as_deref()?;
+                looks_truncated(content).then(|| TruncatedHighlight {
+                    id: clipping.id(),
+                    book_title: clipping.book_title.clone(),
+                    location: clipping.location.to_string(),
+                    content: content.to_string(),
+                })
+            })
+            .collect(),
+    }
+}
+
+impl fmt::Display for Lengths {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Highlights: {}", self.count)?;
+        writeln!(
+            f,
+            "Length: min {}, median {}, mean {:.0}, max {}",
+            self.min, self.median, self.mean, self.max
+        )?;
+
+        let max = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.count)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        writeln!(f)?;
+        for bucket in &self.buckets {
+            let label = match bucket.up_to {
+                Some(up_to) => format!("<= {}", up_to),
+                None => format!("> {}", LENGTH_BUCKETS[LENGTH_BUCKETS.len() - 1]),
+            };
+            let line = format!(
+                "{:<10} {:>4} {}",
+                label,
+                bucket.count,
+                "#".repeat(bucket.count * 30 / max)
+            );
+            writeln!(f, "{}", line.trim_end())?;
+        }
+
+        write!(f, "\nPossibly truncated: {}", self.truncated.len())?;
+        for highlight in &self.truncated {
+            write!(
+                f,
+                "\n  {} {} [{}]: {}",
+                highlight.id, highlight.book_title, highlight.location, highlight.content
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Clippings further apart than this start a new reading session by default
+pub const DEFAULT_SESSION_GAP_MINUTES: i64 = 45;
+
+/// A stretch of reading in one book, inferred from clipping timestamps
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Session {
+    pub book_title: String,
+    pub author: String,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub clippings: usize,
+    pub location_start: u32,
+    pub location_end: u32,
+}
+
+impl Session {
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} - {} ({} min) {}: {} clippings, locations {}-{}",
+            self.start.format("%Y-%m-%d %H:%M"),
+            self.end.format("%H:%M"),
+            self.duration().num_minutes(),
+            self.book_title,
+            self.clippings,
+            self.location_start,
+            self.location_end
+        )
+    }
+}
+
+/// Group each book's clippings into sessions split wherever consecutive
+/// clippings are more than `gap` apart, ordered by start time
+pub fn sessions(clippings: &[Clipping], gap: Duration) -> Vec<Session> {
+    let mut by_book: BTreeMap<(&str, &str), Vec<(NaiveDateTime, &Clipping)>> = BTreeMap::new();
+    for clipping in clippings {
+        if let Some(timestamp) = clipping.timestamp() {
+            by_book
+                .entry((&clipping.book_title, &clipping.author))
+                .or_default()
+                .push((timestamp, clipping));
+        }
+    }
+
+    let mut sessions: Vec<Session> = Vec::new();
+    for ((book_title, author), mut entries) in by_book {
+        entries.sort_by_key(|(timestamp, _)| *timestamp);
+
+        let mut current: Option<Session> = None;
+        for (timestamp, clipping) in entries {
+            let location_end = clipping.location.end.unwrap_or(clipping.location.start);
+
+            match current.as_mut() {
+                Some(session) if timestamp - session.end <= gap => {
+                    session.end = timestamp;
+                    session.clippings += 1;
+                    session.location_start = session.location_start.min(clipping.location.start);
+                    session.location_end = session.location_end.max(location_end);
+                }
+                _ => {
+                    sessions.extend(current.take());
+                    current = Some(Session {
+                        book_title: book_title.to_string(),
+                        author: author.to_string(),
+                        start: timestamp,
+                        end: timestamp,
+                        clippings: 1,
+                        location_start: clipping.location.start,
+                        location_end,
+                    });
+                }
+            }
+        }
+        sessions.extend(current);
+    }
+
+    sessions.sort_by_key(|session| session.start);
+    sessions
+}
+
+/// Where a bookmark was left in a book, and when
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Mark {
+    pub added: NaiveDateTime,
+    pub location: u32,
+}
+
+/// A book's bookmarks in the order they were left, tracing how far into
+/// it reading had got over time
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Progress {
+    pub book_title: String,
+    pub author: String,
+    pub marks: Vec<Mark>,
+}
+
+impl Progress {
+    /// One bar per bookmark, as high as its location is far into the
+    /// furthest one
+    pub fn sparkline(&self) -> String {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+        let furthest = self
+            .marks
+            .iter()
+            .map(|mark| mark.location)
+            .max()
+            .unwrap_or(0);
+        self.marks
+            .iter()
+            .map(|mark| match furthest {
+                0 => BARS[0],
+                furthest => {
+                    BARS[(mark.location as usize * (BARS.len() - 1)).div_ceil(furthest as usize)]
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (Some(first), Some(last)) = (self.marks.first(), self.marks.last()) else {
+            return write!(f, "{} ({}): no bookmarks", self.book_title, self.author);
+        };
+        writeln!(
+            f,
+            "{} ({}): {} bookmarks over {}",
+            self.book_title,
+            self.author,
+            self.marks.len(),
+            days((last.added.date() - first.added.date()).num_days() as usize + 1)
+        )?;
+        writeln!(f, "{}", self.sparkline())?;
+        for mark in &self.marks {
+            writeln!(
+                f,
+                "{}  location {}",
+                mark.added.format("%Y-%m-%d %H:%M"),
+                mark.location
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The bookmarks of each book whose title contains `book`, ignoring case,
+/// ordered by when they were left; bookmarks without a date are left out
+pub fn progress(clippings: &[Clipping], book: &str) -> Vec<Progress> {
+    let book = book.to_lowercase();
+    let mut by_book: BTreeMap<(&str, &str), Vec<Mark>> = BTreeMap::new();
+    for clipping in clippings {
+        if clipping.clipping_type != ClippingType::Bookmark
+            || !clipping.book_title.to_lowercase().contains(&book)
+        {
+            continue;
+        }
+        if let Some(added) = clipping.timestamp() {
+            by_book
+                .entry((&clipping.book_title, &clipping.author))
+                .or_default()
+                .push(Mark {
+                    added,
+                    location: clipping.location.start,
+                });
+        }
+    }
+
+    by_book
+        .into_iter()
+        .map(|((book_title, author), mut marks)| {
+            marks.sort_by_key(|mark| mark.added);
+            Progress {
+                book_title: book_title.to_string(),
+                author: author.to_string(),
+                marks,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    const CLIPPINGS: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Note on page 1 | Location 12 | Added on Monday, 1 January 2024 10:05:00
+
+Classic.
+==========
+Meditations (Marcus Aurelius)
+- Your Bookmark on page 4 | Location 60 | Added on Tuesday, 31 December 2024 22:00:00
+
+==========
+Meditations (Marcus Aurelius)
+- Your Highlight on page 5 | Location 70-71 | Added on Sunday, 5 January 2025 09:00:00
+
+Waste no more time.
+==========";
+
+    #[test]
+    fn test_summary() {
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+
+        assert_eq!(
+            summary(&clippings),
+            Summary {
+                total: 4,
+                highlights: 2,
+                notes: 1,
+                bookmarks: 1,
+                books: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_cadence() {
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+
+        let today = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let cadence = cadence(&clippings, today);
+
+        assert_eq!(cadence.active_days, 3);
+        assert_eq!(cadence.current_streak, 1);
+        assert_eq!(cadence.longest_streak, 1);
+        assert!((cadence.average_per_active_day - 4.0 / 3.0).abs() < 1e-9);
+        assert_eq!(cadence.by_weekday, [2, 1, 0, 0, 0, 0, 1]);
+        assert_eq!(cadence.by_hour[10], 2);
+        assert!(cadence.to_string().contains("Current streak: 1 day\n"));
+
+        let later = NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+        assert_eq!(super::cadence(&clippings, later).current_streak, 0);
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let heatmap = heatmap(&clippings, 2024);
+
+        // 2024 is a leap year
+        assert_eq!(heatmap.days.len(), 366);
+        assert_eq!(heatmap.max, 2);
+        assert_eq!(heatmap.days[0].count, 2);
+        assert_eq!(heatmap.days[365].count, 1);
+        assert_eq!(heatmap.level(1), 2);
+        assert_eq!(heatmap.level(2), 4);
+        assert_eq!(latest_year(&clippings), Some(2025));
+
+        // Colors only for a terminal, shades otherwise
+        assert!(heatmap.render(true).contains("\x1b[38;5;40m■"));
+        let plain = heatmap.render(false);
+        assert!(!plain.contains('\x1b'));
+        assert!(plain.contains("█ "));
+        assert!(plain.ends_with("3 clippings on 2 days in 2024"));
+    }
+
+    #[test]
+    fn test_by_author() {
+        let mut clippings = parse_clippings(CLIPPINGS).unwrap();
+        clippings[1].author = "Herbert, Frank".to_string();
+        let authors = by_author(&clippings);
+
+        assert_eq!(authors.len(), 2);
+        assert_eq!(authors[0].author, "Frank Herbert");
+        assert_eq!(authors[0].clippings, 2);
+        assert_eq!(authors[0].books, 1);
+        assert_eq!(authors[1].author, "Marcus Aurelius");
+        assert_eq!(authors[1].last.unwrap().to_string(), "2025-01-05 09:00:00");
+    }
+
+    #[test]
+    fn test_by_device() {
+        let mut clippings = parse_clippings(CLIPPINGS).unwrap();
+        for clipping in &mut clippings[1..] {
+            clipping.device = Some("Paperwhite".to_string());
+        }
+        let devices = by_device(&clippings);
+
+        assert_eq!(devices.len(), 2);
+        assert_eq!(
+            devices[0].to_string(),
+            "Paperwhite: 3 clippings in 2 books, 2024-01-01 to 2025-01-05"
+        );
+        assert_eq!(devices[1].device, "Unknown");
+    }
+
+    #[test]
+    fn test_by_rating() {
+        let mut library = Library::new(parse_clippings(CLIPPINGS).unwrap());
+        library.books[0].rating = Some(4);
+        library.books[1].rating = Some(5);
+
+        let books = by_rating(&library);
+        assert_eq!(books[0].title, "Meditations");
+        assert_eq!(
+            books[1].to_string(),
+            "★★★★☆ Dune by Frank Herbert: 2 clippings (1 highlight)"
+        );
+    }
+
+    #[test]
+    fn test_lengths() {
+        assert!(looks_truncated("brings total oblit"));
+        assert!(looks_truncated("and so,"));
+        assert!(!looks_truncated("A complete sentence."));
+        assert!(!looks_truncated("“Quoted.”"));
+        assert!(looks_truncated(
+            "<You have reached the clipping limit for this item>"
+        ));
+
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let lengths = lengths(&clippings);
+
+        assert_eq!(lengths.count, 2);
+        assert_eq!((lengths.min, lengths.max), (19, 24));
+        assert_eq!(lengths.buckets[0].count, 2);
+        assert_eq!(lengths.buckets.last().unwrap().up_to, None);
+        assert!(lengths.truncated.is_empty());
+    }
+
+    #[test]
+    fn test_sessions() {
+        let clippings = parse_clippings(CLIPPINGS).unwrap();
+        let sessions = sessions(&clippings, Duration::minutes(DEFAULT_SESSION_GAP_MINUTES));
+
+        assert_eq!(sessions.len(), 3);
+        assert_eq!(sessions[0].book_title, "Dune");
+        assert_eq!(sessions[0].clippings, 2);
+        assert_eq!(sessions[0].duration(), Duration::minutes(5));
+        assert_eq!(
+            (sessions[0].location_start, sessions[0].location_end),
+            (10, 12)
+        );
+        assert_eq!(sessions[1].book_title, "Meditations");
+        assert_eq!(sessions[2].clippings, 1);
+    }
+
+    #[test]
+    fn test_progress() {
+        let clippings = parse_clippings(&format!(
+            "{}
+Meditations (Marcus Aurelius)
+- Your Bookmark on page 20 | Location 300 | Added on Saturday, 4 January 2025 21:30:00
+
+==========
+Meditations (Marcus Aurelius)
+- Your Bookmark on page 10 | Location 150 | Added on Thursday, 2 January 2025 23:00:00
+
+==========",
+            CLIPPINGS
+        ))
+        .unwrap();
+        let progress = progress(&clippings, "meditations");
+
+        assert_eq!(progress.len(), 1);
+        assert_eq!(
+            progress[0]
+                .marks
+                .iter()
+                .map(|mark| mark.location)
+                .collect::<Vec<_>>(),
+            vec![60, 150, 300]
+        );
+        assert_eq!(progress[0].sparkline(), "▃▅█");
+        assert!(progress[0].to_string().starts_with(
+            "Meditations (Marcus Aurelius): 3 bookmarks over 5 days\n▃▅█\n2024-12-31 22:00  location 60\n"
+        ));
+    }
+}
@@ -0,0 +1,165 @@
+use std::str::FromStr;
+
+use crate::parser::Clipping;
+
+/// How much of a sloppy highlight `--tidy` trims, each level doing what the
+/// previous ones do too
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TidyLevel {
+    /// Whitespace and punctuation left dangling at either end
+    Punctuation,
+    /// Also the word a highlight starts in the middle of, when it starts in
+    /// lowercase, and the word it ends in the middle of, when it ends
+    /// without punctuation
+    Words,
+    /// Also whole partial sentences at either end, when a full sentence
+    /// remains
+    Sentences,
+}
+
+impl FromStr for TidyLevel {
+    type Err = String;
+
+    /// "punctuation", "words" or "sentences"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "punctuation" => Ok(TidyLevel::Punctuation),
+            "words" => Ok(TidyLevel::Words),
+            "sentences" => Ok(TidyLevel::Sentences),
+            _ => Err(format!("Invalid tidy level: {}", s)),
+        }
+    }
+}
+
+/// Punctuation a quote can't sensibly start with
+const DANGLING_START: &[char] = &[
+    ',', ';', ':', '.', ')', ']', '}', '»', '”', '’', '-', '–', '—',
+];
+
+/// Punctuation a quote can't sensibly end with
+const DANGLING_END: &[char] = &[',', ';', ':', '(', '[', '{', '«', '“', '‘', '-', '–', '—'];
+
+/// Punctuation ending a sentence, possibly followed by closing quotes
+const SENTENCE_END: &[char] = &['.', '!', '?', '…'];
+const CLOSING: &[char] = &['"', '\'', '”', '’', ')', ']', '»'];
+
+/// Tidy the content of every highlight, leaving notes as they were typed
+pub fn tidy(clippings: &mut [Clipping], level: TidyLevel) {
+    for clipping in clippings {
+        if clipping.clipping_type.is_highlight()
+            && let Some(content) = &clipping.content
+        {
+            clipping.content = Some(tidy_text(content, level));
+        }
+    }
+}
+
+/// `text` trimmed at `level`, never trimmed away entirely
+pub fn tidy_text(text: &str, level: TidyLevel) -> String {
+    let trimmed = trim_punctuation(text);
+    let mut text = trimmed;
+
+    if level >= TidyLevel::Sentences {
+        text = trim_sentences(text);
+    }
+    if level >= TidyLevel::Words {
+        text = trim_words(text);
+    }
+
+    if text.is_empty() { trimmed } else { text }.to_string()
+}
+
+fn trim_punctuation(text: &str) -> &str {
+    text.trim_start_matches(|c: char| c.is_whitespace() || DANGLING_START.contains(&c))
+        .trim_end_matches(|c: char| c.is_whitespace() || DANGLING_END.contains(&c))
+}
+
+fn starts_mid_sentence(text: &str) -> bool {
+    text.chars().next().is_some_and(char::is_lowercase)
+}
+
+fn ends_mid_sentence(text: &str) -> bool {
+    !text
+        .trim_end_matches(CLOSING)
+        .ends_with(|c: char| SENTENCE_END.contains(&c))
+}
+
+/// Drop a partial word from either end, keeping at least one word
+fn trim_words(mut text: &str) -> &str {
+    if starts_mid_sentence(text)
+        && let Some((_, rest)) = text.split_once(char::is_whitespace)
+    {
+        text = trim_punctuation(rest);
+    }
+    if ends_mid_sentence(text)
+        && text.ends_with(char::is_alphanumeric)
+        && let Some((rest, _)) = text.rsplit_once(char::is_whitespace)
+    {
+        text = trim_punctuation(rest);
+    }
+    text
+}
+
+/// Drop a partial sentence from either end, keeping at least one sentence
+fn trim_sentences(mut text: &str) -> &str {
+    let boundaries: Vec<usize> = sentence_boundaries(text).collect();
+
+    if starts_mid_sentence(text)
+        && let Some(&start) = boundaries.first()
+    {
+        text = text[start..].trim_start();
+    }
+    if ends_mid_sentence(text)
+        && let Some(end) = sentence_boundaries(text).last()
+    {
+        text = text[..end].trim_end();
+    }
+    text
+}
+
+/// Byte offsets just past each sentence end that has text after it
+fn sentence_boundaries(text: &str) -> impl Iterator<Item = usize> + '_ {
+    text.char_indices().filter_map(move |(i, c)| {
+        if !SENTENCE_END.contains(&c) {
+            return None;
+        }
+        let after = &text[i + c.len_utf8()..];
+        let closing = after.len() - after.trim_start_matches(CLOSING).len();
+        let rest = &after[closing..];
+
+        let next = rest.trim_start();
+        (rest.starts_with(char::is_whitespace) && next.starts_with(char::is_uppercase))
+            .then_some(text.len() - after.len() + closing)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tidy_text() {
+        let sloppy = "ar is the mind-killer. Fear is the little-death that brings total obliteration. I will fa";
+
+        assert_eq!(
+            tidy_text(" , Fear is the mind-killer; ", TidyLevel::Punctuation),
+            "Fear is the mind-killer"
+        );
+        assert_eq!(
+            tidy_text(sloppy, TidyLevel::Words),
+            "is the mind-killer. Fear is the little-death that brings total obliteration. I will"
+        );
+        assert_eq!(
+            tidy_text(sloppy, TidyLevel::Sentences),
+            "Fear is the little-death that brings total obliteration."
+        );
+        assert_eq!(
+            tidy_text(
+                "“Fear is the mind-killer.” Fear is the lit",
+                TidyLevel::Sentences
+            ),
+            "“Fear is the mind-killer.”"
+        );
+        assert_eq!(tidy_text("killer", TidyLevel::Sentences), "killer");
+    }
+}
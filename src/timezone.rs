@@ -0,0 +1,148 @@
+use chrono::{Local, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::str::FromStr;
+
+use crate::clock;
+use crate::parser::Clipping;
+use crate::settings::Timezone;
+
+/// Zone datetimes are converted to for showing and exporting
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Target {
+    /// The zone of the computer kindlr runs on
+    Local,
+    Utc,
+    Zone(Tz),
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    /// "local", "utc" or an IANA name such as "Europe/Berlin"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "local" => Ok(Target::Local),
+            "utc" | "UTC" => Ok(Target::Utc),
+            _ => s
+                .parse()
+                .map(Target::Zone)
+                .map_err(|_| format!("Invalid time zone: {}", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for Target {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl Timezone {
+    /// Zone the Kindle was set to when it wrote `datetime`, going by the
+    /// trips in `travel` before `device`
+    pub fn zone_at(&self, datetime: NaiveDateTime) -> Option<Tz> {
+        let date = datetime.date();
+        self.travel
+            .iter()
+            .find(|trip| trip.since <= date && date <= trip.until)
+            .map(|trip| trip.zone)
+            .or(self.device)
+    }
+}
+
+/// Give `clipping` the UTC offset of the zone its Kindle was set to,
+/// converting its datetime to `settings.convert_to` if set, and return
+/// whether it had a zone
+///
+/// A datetime skipped by a change to summer time has no zone; one repeated
+/// by the change back is taken for the first. A converted clipping keeps
+/// the datetime the Kindle wrote in `original_datetime`, and so its id.
+pub fn annotate(clipping: &mut Clipping, settings: &Timezone) -> bool {
+    let Some(written) = clipping.timestamp() else {
+        return false;
+    };
+    let Some(zoned) = settings
+        .zone_at(written)
+        .and_then(|zone| zone.from_local_datetime(&written).earliest())
+    else {
+        return false;
+    };
+
+    let converted = match settings.convert_to {
+        None => zoned.fixed_offset(),
+        Some(Target::Local) => zoned.with_timezone(&Local).fixed_offset(),
+        Some(Target::Utc) => zoned.with_timezone(&Utc).fixed_offset(),
+        Some(Target::Zone(zone)) => zoned.with_timezone(&zone).fixed_offset(),
+    };
+    if converted.naive_local() != written {
+        clock::redate(clipping, converted.naive_local());
+    }
+    clipping.utc_offset = Some(converted.offset().fix().to_string());
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+    use crate::settings::Trip;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_annotate() {
+        let mut clippings = parse_clippings(
+            "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 July 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 2 | Location 20-22 | Added on Wednesday, 10 July 2024 23:30:00
+
+The spice must flow.
+==========
+Dune (Frank Herbert)
+- Your Highlight on page 3 | Location 30-32 | Added on Sunday, 31 March 2024 02:30:00
+
+Skipped by summer time.
+==========",
+        )
+        .unwrap();
+        let ids: Vec<String> = clippings.iter().map(Clipping::id).collect();
+        let mut settings = Timezone {
+            device: Some(chrono_tz::Europe::Berlin),
+            travel: vec![Trip {
+                since: NaiveDate::from_ymd_opt(2024, 7, 8).unwrap(),
+                until: NaiveDate::from_ymd_opt(2024, 7, 14).unwrap(),
+                zone: chrono_tz::Asia::Tokyo,
+            }],
+            convert_to: None,
+        };
+
+        let mut first = clippings[0].clone();
+        assert!(annotate(&mut first, &settings));
+        assert_eq!(first.utc_offset.as_deref(), Some("+02:00"));
+        assert_eq!(first.datetime, "1 July 2024 10:00:00");
+        assert_eq!(first.original_datetime, None);
+        assert!(!annotate(&mut clippings[2], &settings));
+
+        settings.convert_to = Some("utc".parse().unwrap());
+        for clipping in &mut clippings[..2] {
+            assert!(annotate(clipping, &settings));
+        }
+        assert_eq!(clippings[0].datetime, "1 July 2024 08:00:00");
+        assert_eq!(clippings[1].datetime, "10 July 2024 14:30:00");
+        assert_eq!(clippings[1].utc_offset.as_deref(), Some("+00:00"));
+        assert_eq!(
+            clippings[1].original_datetime.as_deref(),
+            Some("10 July 2024 23:30:00")
+        );
+        assert_eq!(clippings.iter().map(Clipping::id).collect::<Vec<_>>(), ids);
+        assert!("Mars/Olympus_Mons".parse::<Target>().is_err());
+    }
+}
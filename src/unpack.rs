@@ -0,0 +1,182 @@
+use flate2::read::MultiGzDecoder;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::KindlrError;
+use crate::net;
+
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// Where compressed files are unpacked to in the kindlr home directory
+pub fn dir(home: &Path) -> PathBuf {
+    home.join("cache").join("unpacked")
+}
+
+/// The file `path` holds when it is gzipped or zipped, unpacked into `dir`,
+/// or `None` when it isn't compressed
+///
+/// A zip's largest `.txt` is taken unless `member` names another, by its
+/// path in the archive or its file name. What was unpacked is reused until
+/// the compressed file changes.
+pub fn unpack(
+    path: &Path,
+    member: Option<&str>,
+    dir: &Path,
+) -> Result<Option<PathBuf>, KindlrError> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let mut magic = Vec::with_capacity(ZIP_MAGIC.len());
+    File::open(path)?
+        .take(ZIP_MAGIC.len() as u64)
+        .read_to_end(&mut magic)?;
+    let name = path
+        .file_name()
+        .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+
+    if magic.starts_with(ZIP_MAGIC) {
+        let invalid = |error: zip::result::ZipError| {
+            KindlrError::Config(format!("Invalid zip {}: {}", name, error))
+        };
+        let mut archive = zip::ZipArchive::new(File::open(path)?).map_err(invalid)?;
+        let index = pick_member(&mut archive, member).ok_or_else(|| match member {
+            Some(member) => {
+                KindlrError::NotFound(format!("No member named {} in {}", member, name))
+            }
+            None => KindlrError::NotFound(format!(
+                "No .txt file in {}; pick a member with --member",
+                name
+            )),
+        })?;
+        let mut entry = archive.by_index(index).map_err(invalid)?;
+        let entry_name = entry.name().to_string();
+        let file_name = entry_name.rsplit('/').next().unwrap_or(&entry_name);
+        let target = target(path, dir, &entry_name, file_name)?;
+        if !is_fresh(path, &target) {
+            write(&target, &mut entry)?;
+        }
+        return Ok(Some(target));
+    }
+
+    if member.is_some() {
+        return Err(KindlrError::Config(
+            "--member only applies to zip files".to_string(),
+        ));
+    }
+    if magic.starts_with(GZIP_MAGIC) {
+        let file_name = name.strip_suffix(".gz").unwrap_or(&name);
+        let target = target(path, dir, "", file_name)?;
+        if !is_fresh(path, &target) {
+            write(&target, &mut MultiGzDecoder::new(File::open(path)?))?;
+        }
+        return Ok(Some(target));
+    }
+    Ok(None)
+}
+
+/// Index of the member to read: the one named `member`, or else the
+/// largest `.txt`
+fn pick_member<R: Read + io::Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    member: Option<&str>,
+) -> Option<usize> {
+    let mut largest: Option<(usize, u64)> = None;
+    for index in 0..archive.len() {
+        let Ok(entry) = archive.by_index(index) else {
+            continue;
+        };
+        if !entry.is_file() {
+            continue;
+        }
+        let name = entry.name();
+        let file_name = name.rsplit('/').next().unwrap_or(name);
+        match member {
+            Some(member) if name == member || file_name == member => return Some(index),
+            Some(_) => {}
+            None if file_name.to_lowercase().ends_with(".txt")
+                && largest.is_none_or(|(_, size)| entry.size() > size) =>
+            {
+                largest = Some((index, entry.size()));
+            }
+            None => {}
+        }
+    }
+    largest.map(|(index, _)| index)
+}
+
+/// File in `dir` that `member` of `path` is unpacked to, named `file_name`
+/// after a hash telling apart files of the same name
+fn target(path: &Path, dir: &Path, member: &str, file_name: &str) -> io::Result<PathBuf> {
+    let key = format!("{}\n{}", fs::canonicalize(path)?.display(), member);
+    Ok(dir.join(format!("{:016x}-{}", net::hash(&key), file_name)))
+}
+
+/// Whether `target` was unpacked since `path` last changed
+fn is_fresh(path: &Path, target: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    matches!((modified(path), modified(target)), (Ok(source), Ok(unpacked)) if unpacked >= source)
+}
+
+/// Write what `reader` holds to `target`, aside and renamed so a failed
+/// unpack isn't taken for a finished one
+fn write(target: &Path, reader: &mut impl Read) -> io::Result<()> {
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = target.with_extension("part");
+    io::copy(reader, &mut File::create(&partial)?)?;
+    fs::rename(&partial, target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    #[test]
+    fn test_unpack() {
+        let root = std::env::temp_dir().join(format!("kindlr-unpack-{}", std::process::id()));
+        let dir = root.join("unpacked");
+        fs::create_dir_all(&root).unwrap();
+
+        let gz = root.join("My Clippings.txt.gz");
+        let mut encoder = GzEncoder::new(File::create(&gz).unwrap(), Compression::default());
+        encoder.write_all(b"clippings").unwrap();
+        encoder.finish().unwrap();
+
+        let zipped = root.join("backup.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&zipped).unwrap());
+        for (name, contents) in [
+            ("notes.txt", "short"),
+            ("documents/My Clippings.txt", "the longest text"),
+            ("export.json", "{\"the longest file\": \"of all\"}"),
+        ] {
+            writer
+                .start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let unpacked = unpack(&gz, None, &dir).unwrap().unwrap();
+        assert!(unpacked.to_string_lossy().ends_with("-My Clippings.txt"));
+        assert_eq!(fs::read_to_string(&unpacked).unwrap(), "clippings");
+
+        let largest = unpack(&zipped, None, &dir).unwrap().unwrap();
+        assert_eq!(fs::read_to_string(&largest).unwrap(), "the longest text");
+        let member = unpack(&zipped, Some("export.json"), &dir).unwrap().unwrap();
+        assert_eq!(
+            fs::read_to_string(&member).unwrap(),
+            "{\"the longest file\": \"of all\"}"
+        );
+        assert!(unpack(&zipped, Some("missing.txt"), &dir).is_err());
+        assert!(unpack(&gz, Some("notes.txt"), &dir).is_err());
+        assert_eq!(unpack(&largest, None, &dir).unwrap(), None);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::SystemTime;
+
+use crate::KindlrError;
+use crate::metrics::Metrics;
+use crate::parser::{Clipping, ParseError};
+
+/// Seconds between looks at the watched file unless given `--interval`
+pub const DEFAULT_INTERVAL_SECONDS: u64 = 30;
+
+/// A clippings file `watch` reads again whenever it changes, and what was
+/// read from it so far
+pub struct Watcher {
+    path: PathBuf,
+    /// Modification time and length of the file when last looked at
+    stamp: Option<(SystemTime, u64)>,
+    /// Ids of every clipping read, `None` before the first read
+    seen: Option<HashSet<String>>,
+    metrics: Arc<Metrics>,
+}
+
+impl Watcher {
+    pub fn new(path: impl Into<PathBuf>, metrics: Arc<Metrics>) -> Self {
+        Watcher {
+            path: path.into(),
+            stamp: None,
+            seen: None,
+            metrics,
+        }
+    }
+
+    /// Whether the file changed since the last look, as it has on the first
+    ///
+    /// A file that isn't there, such as on a Kindle not plugged in, hasn't
+    /// changed, so it is read again only once it is back with other contents.
+    pub fn changed(&mut self) -> bool {
+        let Some(stamp) = fs::metadata(&self.path)
+            .ok()
+            .and_then(|metadata| Some((metadata.modified().ok()?, metadata.len())))
+        else {
+            return false;
+        };
+        self.stamp.replace(stamp) != Some(stamp)
+    }
+
+    /// Count a read of `clippings`, returning how many weren't read before,
+    /// none on the first read
+    pub fn imported(&mut self, clippings: &[Clipping]) -> usize {
+        let ids = clippings.iter().map(Clipping::id);
+        let new = match &mut self.seen {
+            Some(seen) => ids.filter(|id| seen.insert(id.clone())).count(),
+            None => {
+                self.seen = Some(ids.collect());
+                0
+            }
+        };
+
+        self.metrics.imports.fetch_add(1, Ordering::Relaxed);
+        self.metrics
+            .new_clippings
+            .fetch_add(new as u64, Ordering::Relaxed);
+        self.metrics
+            .clippings
+            .store(clippings.len() as u64, Ordering::Relaxed);
+        new
+    }
+
+    /// Count a read that failed with `error`; a file without clippings yet
+    /// didn't fail to parse
+    pub fn failed(&self, error: &KindlrError) {
+        match error {
+            KindlrError::Parse(ParseError::EmptyFile(_)) => {}
+            KindlrError::Parse(_) => {
+                self.metrics.parse_errors.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_clippings;
+
+    const ENTRY: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+";
+
+    #[test]
+    fn test_watcher() {
+        let path = std::env::temp_dir().join(format!("kindlr-watch-{}.txt", std::process::id()));
+        let metrics = Arc::new(Metrics::default());
+        let mut watcher = Watcher::new(&path, Arc::clone(&metrics));
+        assert!(!watcher.changed());
+
+        fs::write(&path, ENTRY).unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+        let first = parse_clippings(ENTRY).unwrap();
+        assert_eq!(watcher.imported(&first), 0);
+
+        let more = ENTRY.replace("10-12", "20-22");
+        fs::write(&path, format!("{}{}", ENTRY, more)).unwrap();
+        assert!(watcher.changed());
+        let second = parse_clippings(&format!("{}{}", ENTRY, more)).unwrap();
+        assert_eq!(watcher.imported(&second), 1);
+        assert_eq!(watcher.imported(&second), 0);
+        watcher.failed(&KindlrError::Parse(ParseError::EmptyFile(
+            path.display().to_string(),
+        )));
+        watcher.failed(&KindlrError::Parse(ParseError::InvalidFormat(
+            "no title".to_string(),
+        )));
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(metrics.imports.load(Ordering::Relaxed), 3);
+        assert_eq!(metrics.new_clippings.load(Ordering::Relaxed), 1);
+        assert_eq!(metrics.parse_errors.load(Ordering::Relaxed), 1);
+        assert_eq!(metrics.clippings.load(Ordering::Relaxed), 2);
+    }
+}
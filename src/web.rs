@@ -0,0 +1,51 @@
+use crate::serve::Response;
+
+/// Files of the web UI `serve` answers with, by path, with their content type
+const ASSETS: [(&str, &str, &str); 3] = [
+    (
+        "/",
+        "text/html; charset=utf-8",
+        include_str!("web/index.html"),
+    ),
+    (
+        "/app.js",
+        "text/javascript; charset=utf-8",
+        include_str!("web/app.js"),
+    ),
+    (
+        "/style.css",
+        "text/css; charset=utf-8",
+        include_str!("web/style.css"),
+    ),
+];
+
+/// The web UI's file at `path`
+///
+/// The UI holds no clippings, only the page that asks the API for them,
+/// so it is served without the bearer token and asks for it itself.
+pub fn asset(path: &str) -> Option<Response> {
+    ASSETS
+        .iter()
+        .find(|(at, ..)| *at == path)
+        .map(|(_, content_type, body)| Response {
+            status: 200,
+            content_type,
+            body: body.as_bytes().to_vec(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset() {
+        let page = asset("/").unwrap();
+        assert_eq!(page.content_type, "text/html; charset=utf-8");
+        let page = String::from_utf8(page.body).unwrap();
+        for (path, ..) in &ASSETS[1..] {
+            assert!(page.contains(&format!("\"{}\"", path)), "{}", path);
+        }
+        assert_eq!(asset("/books"), None);
+    }
+}
@@ -0,0 +1,173 @@
+"use strict";
+
+// What the clippings page shows: the book picked in the sidebar, the
+// search, and where the next page starts
+const state = { book: null, search: "", cursor: null };
+
+const TOKEN_KEY = "kindlr-token";
+
+// Answer of the API to a request, asking for the server's token when it
+// wants one and keeping it for the next visit
+async function api(method, path, body) {
+  const headers = {};
+  const token = localStorage.getItem(TOKEN_KEY);
+  if (token) {
+    headers.Authorization = `Bearer ${token}`;
+  }
+  if (body !== undefined) {
+    headers["Content-Type"] = "application/json";
+  }
+
+  const response = await fetch(path, {
+    method,
+    headers,
+    body: body === undefined ? undefined : JSON.stringify(body),
+  });
+  if (response.status === 401) {
+    const given = prompt("Token for this kindlr server");
+    if (given) {
+      localStorage.setItem(TOKEN_KEY, given);
+      return api(method, path, body);
+    }
+  }
+  const answer = await response.json();
+  if (!response.ok) {
+    throw new Error(answer.error);
+  }
+  return answer;
+}
+
+function element(tag, className, text) {
+  const node = document.createElement(tag);
+  if (className) {
+    node.className = className;
+  }
+  if (text !== undefined) {
+    node.textContent = text;
+  }
+  return node;
+}
+
+function status(message) {
+  document.getElementById("status").textContent = message;
+}
+
+// A quote card, starred and unstarred with its button
+function card(entry) {
+  const article = element("article", `card ${entry.clipping_type.toLowerCase()}`);
+  article.append(
+    element("blockquote", "", entry.content ?? ""),
+    element("footer", "", `${entry.book_title} — ${entry.author}, ${entry.datetime}`),
+  );
+
+  const star = element("button", "star", entry.favorite ? "★" : "☆");
+  star.type = "button";
+  star.title = entry.favorite ? "Unstar" : "Star";
+  star.addEventListener("click", () => {
+    api(entry.favorite ? "DELETE" : "PUT", `/clippings/${entry.id}/favorite`)
+      .then((changed) => article.replaceWith(card(changed)))
+      .catch((error) => status(error.message));
+  });
+  article.append(star);
+  return article;
+}
+
+async function loadBooks() {
+  const list = document.getElementById("books");
+  const item = (label, book) => {
+    const entry = element("li");
+    const link = element("a", "", label);
+    link.href = "#";
+    link.addEventListener("click", (event) => {
+      event.preventDefault();
+      state.book = book;
+      for (const other of list.querySelectorAll("a")) {
+        other.classList.toggle("selected", other === link);
+      }
+      location.hash = "";
+      loadClippings(false).catch((error) => status(error.message));
+    });
+    entry.append(link);
+    return entry;
+  };
+
+  list.replaceChildren(item("All books", null));
+  let cursor = null;
+  do {
+    const params = new URLSearchParams({ limit: "500" });
+    if (cursor) {
+      params.set("cursor", cursor);
+    }
+    const page = await api("GET", `/books?${params}`);
+    list.append(...page.items.map((book) => item(`${book.title} (${book.clippings})`, book)));
+    cursor = page.next_cursor;
+  } while (cursor);
+}
+
+// The first page of clippings, or the next one when `more`
+async function loadClippings(more) {
+  const params = new URLSearchParams({ type: "highlight,note", limit: "50" });
+  if (state.book) {
+    params.set("book", state.book.title);
+    params.set("author", state.book.author);
+  }
+  if (state.search) {
+    params.set("contains", state.search);
+  }
+  if (more && state.cursor) {
+    params.set("cursor", state.cursor);
+  }
+
+  const page = await api("GET", `/clippings?${params}`);
+  const cards = document.getElementById("cards");
+  if (!more) {
+    cards.replaceChildren();
+  }
+  cards.append(...page.items.map(card));
+  state.cursor = page.next_cursor;
+  document.getElementById("more").hidden = !state.cursor;
+  status(cards.children.length ? "" : "No clippings match");
+}
+
+async function loadReview() {
+  const review = await api("GET", "/review");
+  const cards = document.getElementById("cards");
+  cards.replaceChildren(element("h2", "", "Today's quote"));
+  cards.append(review.quote ? card(review.quote) : element("p", "", "No highlights yet"));
+  cards.append(element("h2", "", "On this day"));
+  if (review.on_this_day.length) {
+    cards.append(...review.on_this_day.map(card));
+  } else {
+    cards.append(element("p", "", "Nothing was highlighted on this day in earlier years"));
+  }
+  document.getElementById("more").hidden = true;
+  status("");
+}
+
+function route() {
+  const page = location.hash === "#review" ? loadReview() : loadClippings(false);
+  page.catch((error) => status(error.message));
+}
+
+document.addEventListener("DOMContentLoaded", () => {
+  let typing;
+  document.getElementById("search").addEventListener("input", (event) => {
+    clearTimeout(typing);
+    typing = setTimeout(() => {
+      state.search = event.target.value.trim();
+      if (location.hash === "#review") {
+        location.hash = "";
+      } else {
+        route();
+      }
+    }, 300);
+  });
+  document.getElementById("more").addEventListener("click", () => {
+    loadClippings(true).catch((error) => status(error.message));
+  });
+  window.addEventListener("hashchange", route);
+
+  loadBooks()
+    .then(route)
+    .catch((error) => status(error.message));
+});
@@ -0,0 +1,177 @@
+use chrono::{DateTime, Local};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::KindlrError;
+use crate::parser::{Clipping, Parser};
+
+/// Where files replaced by writing clippings back are kept, in the kindlr
+/// home directory
+pub fn backups_dir(home: &Path) -> PathBuf {
+    home.join("backups")
+}
+
+/// Replace the My Clippings.txt at `path` with `contents`, which hold the
+/// `expected` clippings in order, returning the backup of the file it replaced
+///
+/// The file on a Kindle is never left half-written or wrong: `contents` go
+/// into a file beside `path`, so on the same volume, which is synced to disk
+/// and read back to check it parses into the clippings meant, with the same
+/// ids and content. Only
+/// then is whatever `path` held copied into `backups`, named by `time` and
+/// numbered when that name is taken, and the new file renamed over it.
+pub fn write_clippings(
+    path: &Path,
+    contents: &[u8],
+    expected: &[&Clipping],
+    backups: &Path,
+    time: DateTime<Local>,
+) -> Result<Option<PathBuf>, KindlrError> {
+    let name = path
+        .file_name()
+        .map_or("My Clippings.txt".into(), |name| name.to_string_lossy());
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let partial = dir.join(format!(".{}.kindlr-part", name));
+
+    let written = write_synced(&partial, contents).and_then(|()| verify(&partial, expected));
+    if let Err(error) = written {
+        let _ = fs::remove_file(&partial);
+        return Err(error);
+    }
+
+    let backup = if path.is_file() {
+        let stem = Path::new(name.as_ref())
+            .file_stem()
+            .map_or(name.to_string(), |stem| stem.to_string_lossy().into_owned());
+        let stamp = time.format("%Y%m%dT%H%M%S");
+        let mut backup = backups.join(format!("{}-{}.txt", stem, stamp));
+        // Another write in the same second keeps its own backup
+        let mut count = 1;
+        while backup.exists() {
+            count += 1;
+            backup = backups.join(format!("{}-{}-{}.txt", stem, stamp, count));
+        }
+        fs::create_dir_all(backups)?;
+        fs::copy(path, &backup)?;
+        File::open(&backup)?.sync_all()?;
+        Some(backup)
+    } else {
+        None
+    };
+
+    fs::rename(&partial, path)?;
+    // The rename itself only lasts once the directory is synced
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    Ok(backup)
+}
+
+fn write_synced(path: &Path, contents: &[u8]) -> Result<(), KindlrError> {
+    let mut file = File::create(path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Check the file at `path` reads back as the `expected` clippings
+fn verify(path: &Path, expected: &[&Clipping]) -> Result<(), KindlrError> {
+    let text = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+    let parsed = match Parser::default().parse(&text) {
+        Ok(clippings) => clippings,
+        Err(_) if expected.is_empty() && text.trim().is_empty() => Vec::new(),
+        Err(error) => {
+            return Err(KindlrError::Roundtrip(format!(
+                "{} failed to read back ({}), nothing was replaced",
+                path.display(),
+                error
+            )));
+        }
+    };
+    if parsed.len() != expected.len() {
+        return Err(KindlrError::Roundtrip(format!(
+            "{} read back as {} clippings instead of {}, nothing was replaced",
+            path.display(),
+            parsed.len(),
+            expected.len()
+        )));
+    }
+    for (n, (parsed, expected)) in parsed.iter().zip(expected).enumerate() {
+        if parsed.id() != expected.id() || parsed.content != expected.content {
+            return Err(KindlrError::Roundtrip(format!(
+                "{} read back clipping {} ({}) differently, nothing was replaced",
+                path.display(),
+                n + 1,
+                expected.id()
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const ENTRY: &str = "\
+Dune (Frank Herbert)
+- Your Highlight on page 1 | Location 10-12 | Added on Monday, 1 January 2024 10:00:00
+
+Fear is the mind-killer.
+==========
+";
+
+    fn clippings(text: &str) -> Vec<Clipping> {
+        Parser::default().parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_write_clippings() {
+        let root = std::env::temp_dir().join(format!("kindlr-writeback-{}", std::process::id()));
+        let backups = root.join("backups");
+        let path = root.join("documents").join("My Clippings.txt");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let time = Local.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+
+        let one = clippings(ENTRY);
+        let one: Vec<&Clipping> = one.iter().collect();
+        let two = clippings(&ENTRY.repeat(2));
+        let two: Vec<&Clipping> = two.iter().collect();
+
+        assert_eq!(
+            write_clippings(&path, ENTRY.repeat(2).as_bytes(), &two, &backups, time).unwrap(),
+            None
+        );
+
+        let backup = write_clippings(&path, ENTRY.as_bytes(), &one, &backups, time)
+            .unwrap()
+            .unwrap();
+        assert_eq!(backup, backups.join("My Clippings-20240102T030405.txt"));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), ENTRY.repeat(2));
+        assert_eq!(fs::read_to_string(&path).unwrap(), ENTRY);
+
+        // Writing again in the same second keeps both backups
+        let again = write_clippings(&path, ENTRY.as_bytes(), &one, &backups, time)
+            .unwrap()
+            .unwrap();
+        assert_eq!(again, backups.join("My Clippings-20240102T030405-2.txt"));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), ENTRY.repeat(2));
+        assert_eq!(fs::read_to_string(&again).unwrap(), ENTRY);
+
+        // Contents that don't read back as meant leave the file as it was
+        assert!(write_clippings(&path, b"not clippings", &one, &backups, time).is_err());
+        assert!(write_clippings(&path, ENTRY.as_bytes(), &two, &backups, time).is_err());
+        let altered = ENTRY.replace("mind-killer", "little-death");
+        assert!(write_clippings(&path, altered.as_bytes(), &one, &backups, time).is_err());
+        let moved = ENTRY.replace("Location 10-12", "Location 11-12");
+        assert!(write_clippings(&path, moved.as_bytes(), &one, &backups, time).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), ENTRY);
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}